@@ -2,6 +2,11 @@ use std::{
     fmt,
     io::{self, Read, Write},
     str,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
 };
 
 use crate::{
@@ -20,30 +25,54 @@ pub struct Chunk {
 
 impl Chunk {
     /// Reads and decodes a `Chunk` from the given reader.
-    pub fn decode<R: Read>(mut reader: R) -> io::Result<Chunk> {
+    pub fn decode<R: Read>(reader: R) -> io::Result<Chunk> {
+        RawChunk::read(reader)?.decompress()
+    }
+}
+
+/// A chunk as it's stored in the file, before decompression. Chunks are
+/// compressed independently, so these can be decompressed in any order.
+#[derive(Debug)]
+pub struct RawChunk {
+    header: ChunkHeader,
+    data: Vec<u8>,
+}
+
+impl RawChunk {
+    /// Reads a chunk's header and its still-compressed contents.
+    pub fn read<R: Read>(mut reader: R) -> io::Result<RawChunk> {
         let header = decode_chunk_header(&mut reader)?;
 
         log::trace!("{header}");
 
+        let stored_len = if header.compressed_len == 0 {
+            header.len
+        } else {
+            header.compressed_len
+        };
+        let mut data = Vec::with_capacity(stored_len as usize);
+        reader.take(stored_len as u64).read_to_end(&mut data)?;
+
+        Ok(RawChunk { header, data })
+    }
+
+    pub fn name(&self) -> [u8; 4] {
+        self.header.name
+    }
+
+    pub fn decompress(self) -> io::Result<Chunk> {
+        let header = self.header;
+
         let data = if header.compressed_len == 0 {
             log::trace!("No compression");
-            let mut data = Vec::with_capacity(header.len as usize);
-            reader.take(header.len as u64).read_to_end(&mut data)?;
-            data
+            self.data
+        } else if &self.data[0..4] == ZSTD_MAGIC_NUMBER {
+            log::trace!("ZSTD compression");
+            zstd::bulk::decompress(&self.data, header.len as usize)?
         } else {
-            let mut compressed_data = Vec::with_capacity(header.compressed_len as usize);
-            reader
-                .take(header.compressed_len as u64)
-                .read_to_end(&mut compressed_data)?;
-
-            if &compressed_data[0..4] == ZSTD_MAGIC_NUMBER {
-                log::trace!("ZSTD compression");
-                zstd::bulk::decompress(&compressed_data, header.len as usize)?
-            } else {
-                log::trace!("LZ4 compression");
-                lz4_flex::block::decompress(&compressed_data, header.len as usize)
-                    .map_err(io::Error::other)?
-            }
+            log::trace!("LZ4 compression");
+            lz4_flex::block::decompress(&self.data, header.len as usize)
+                .map_err(io::Error::other)?
         };
 
         assert_eq!(data.len(), header.len as usize);
@@ -55,6 +84,36 @@ impl Chunk {
     }
 }
 
+/// Decompresses chunks on up to `threads` threads, keeping their order.
+pub fn decompress_parallel(chunks: Vec<RawChunk>, threads: usize) -> io::Result<Vec<Chunk>> {
+    let next = AtomicUsize::new(0);
+    let pending: Vec<Mutex<Option<RawChunk>>> = chunks
+        .into_iter()
+        .map(|chunk| Mutex::new(Some(chunk)))
+        .collect();
+    let decoded: Vec<Mutex<Option<io::Result<Chunk>>>> =
+        pending.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, pending.len().max(1)) {
+            scope.spawn(|| loop {
+                // chunk sizes vary a lot, so threads take the next one as they go
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(slot) = pending.get(index) else {
+                    break;
+                };
+                let chunk = slot.lock().unwrap().take().unwrap();
+                *decoded[index].lock().unwrap() = Some(chunk.decompress());
+            });
+        }
+    });
+
+    decoded
+        .into_iter()
+        .map(|slot| slot.into_inner().unwrap().unwrap())
+        .collect()
+}
+
 /// Holds a chunk that is currently being written.
 ///
 /// This type intended to be written into via io::Write and then dumped into the
@@ -7,6 +7,8 @@ use std::{io::Read, str};
 use rbx_dom_weak::WeakDom;
 use rbx_reflection::ReflectionDatabase;
 
+use crate::chunk::Chunk;
+
 use self::state::DeserializerState;
 
 
@@ -47,6 +49,7 @@ pub use self::error::Error;
 /// [reflection_database]: Deserializer#method.reflection_database
 pub struct Deserializer<'db> {
     database: &'db ReflectionDatabase<'db>,
+    decompression_threads: usize,
 }
 
 impl<'db> Deserializer<'db> {
@@ -54,13 +57,25 @@ impl<'db> Deserializer<'db> {
     pub fn new() -> Self {
         Self {
             database: rbx_reflection_database::get().unwrap(),
+            decompression_threads: 1,
         }
     }
 
     /// Sets what reflection database for the deserializer to use.
     #[inline]
     pub fn reflection_database(self, database: &'db ReflectionDatabase<'db>) -> Self {
-        Self { database }
+        Self { database, ..self }
+    }
+
+    /// Sets how many threads decompress chunks. With more than one, every
+    /// chunk is read and decompressed up front before any are decoded.
+    /// Decoding itself stays on the calling thread.
+    #[inline]
+    pub fn decompression_threads(self, decompression_threads: usize) -> Self {
+        Self {
+            decompression_threads,
+            ..self
+        }
     }
 
     /// Deserialize a Roblox binary model or place from the given stream using
@@ -70,23 +85,18 @@ impl<'db> Deserializer<'db> {
 
         let mut deserializer = DeserializerState::new(self, reader)?;
 
-        loop {
-            let chunk = deserializer.next_chunk()?;
-
-            match &chunk.name {
-                b"META" => deserializer.decode_meta_chunk(&chunk.data)?,
-                b"SSTR" => deserializer.decode_sstr_chunk(&chunk.data)?,
-                b"INST" => deserializer.decode_inst_chunk(&chunk.data)?,
-                b"PROP" => deserializer.decode_prop_chunk(&chunk.data)?,
-                b"PRNT" => deserializer.decode_prnt_chunk(&chunk.data)?,
-                b"END\0" => {
-                    deserializer.decode_end_chunk(&chunk.data)?;
+        if self.decompression_threads > 1 {
+            for chunk in deserializer.read_chunks_parallel(self.decompression_threads)? {
+                if decode_chunk(&mut deserializer, &chunk)? {
+                    break;
+                }
+            }
+        } else {
+            loop {
+                let chunk = deserializer.next_chunk()?;
+                if decode_chunk(&mut deserializer, &chunk)? {
                     break;
                 }
-                _ => match str::from_utf8(&chunk.name) {
-                    Ok(name) => log::info!("Unknown binary chunk name {name}"),
-                    Err(_) => log::info!("Unknown binary chunk name {:?}", chunk.name),
-                },
             }
         }
 
@@ -94,6 +104,26 @@ impl<'db> Deserializer<'db> {
     }
 }
 
+/// Decodes one chunk, returning whether it was the last one.
+fn decode_chunk<R: Read>(deserializer: &mut DeserializerState<'_, R>, chunk: &Chunk) -> Result<bool, Error> {
+    match &chunk.name {
+        b"META" => deserializer.decode_meta_chunk(&chunk.data)?,
+        b"SSTR" => deserializer.decode_sstr_chunk(&chunk.data)?,
+        b"INST" => deserializer.decode_inst_chunk(&chunk.data)?,
+        b"PROP" => deserializer.decode_prop_chunk(&chunk.data)?,
+        b"PRNT" => deserializer.decode_prnt_chunk(&chunk.data)?,
+        b"END\0" => {
+            deserializer.decode_end_chunk(&chunk.data)?;
+            return Ok(true);
+        }
+        _ => match str::from_utf8(&chunk.name) {
+            Ok(name) => log::info!("Unknown binary chunk name {name}"),
+            Err(_) => log::info!("Unknown binary chunk name {:?}", chunk.name),
+        },
+    }
+    Ok(false)
+}
+
 impl Default for Deserializer<'_> {
     fn default() -> Self {
         Self::new()
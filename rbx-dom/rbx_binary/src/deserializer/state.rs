@@ -15,7 +15,7 @@ use rbx_dom_weak::{
 use rbx_reflection::{DataType, PropertyKind, PropertySerialization, ReflectionDatabase};
 
 use crate::{
-    chunk::Chunk,
+    chunk::{decompress_parallel, Chunk, RawChunk},
     core::{find_property_descriptors, RbxReadExt},
     types::Type,
 };
@@ -233,6 +233,20 @@ impl<'db, R: Read> DeserializerState<'db, R> {
         Ok(Chunk::decode(&mut self.input)?)
     }
 
+    /// Reads every remaining chunk and decompresses them on `threads` threads.
+    pub(super) fn read_chunks_parallel(&mut self, threads: usize) -> Result<Vec<Chunk>, InnerError> {
+        let mut raw = Vec::new();
+        loop {
+            let chunk = RawChunk::read(&mut self.input)?;
+            let is_end = &chunk.name() == b"END\0";
+            raw.push(chunk);
+            if is_end {
+                break;
+            }
+        }
+        Ok(decompress_parallel(raw, threads)?)
+    }
+
     #[profiling::function]
     pub(super) fn decode_meta_chunk(&mut self, mut chunk: &[u8]) -> Result<(), InnerError> {
         let len = chunk.read_le_u32()?;
@@ -450,6 +464,14 @@ This may cause unexpected or broken behavior in your final results if you rely o
                         add_property(instance, &property, value.into());
                     }
                 }
+                // written inline by files that predate the SSTR chunk
+                VariantType::SharedString => {
+                    for referent in &type_info.referents {
+                        let instance = self.instances_by_ref.get_mut(referent).unwrap();
+                        let value = SharedString::new(chunk.read_binary_string()?);
+                        add_property(instance, &property, value.into());
+                    }
+                }
                 VariantType::Tags => {
                     for referent in &type_info.referents {
                         let instance = self.instances_by_ref.get_mut(referent).unwrap();
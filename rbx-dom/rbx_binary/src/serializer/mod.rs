@@ -47,6 +47,8 @@ pub use self::error::Error;
 pub struct Serializer<'db> {
     database: &'db ReflectionDatabase<'db>,
     compression: CompressionType,
+    inline_shared_strings: bool,
+    legacy_content: bool,
 }
 
 impl<'db> Serializer<'db> {
@@ -55,6 +57,8 @@ impl<'db> Serializer<'db> {
         Serializer {
             database: rbx_reflection_database::get().unwrap(),
             compression: CompressionType::default(),
+            inline_shared_strings: false,
+            legacy_content: false,
         }
     }
 
@@ -73,6 +77,26 @@ impl<'db> Serializer<'db> {
         }
     }
 
+    /// Sets whether SharedString properties are written inline as strings
+    /// instead of through the SSTR chunk, for readers that predate it.
+    #[inline]
+    pub fn inline_shared_strings(self, inline_shared_strings: bool) -> Self {
+        Self {
+            inline_shared_strings,
+            ..self
+        }
+    }
+
+    /// Sets whether Content properties are written as plain strings, the way
+    /// files did before the Content property type existed.
+    #[inline]
+    pub fn legacy_content(self, legacy_content: bool) -> Self {
+        Self {
+            legacy_content,
+            ..self
+        }
+    }
+
     /// Serialize a Roblox binary model or place into the given stream using
     /// this serializer.
     pub fn serialize<W: Write>(&self, writer: W, dom: &WeakDom, refs: &[Ref]) -> Result<(), Error> {
@@ -322,7 +322,9 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
         for (prop_name, prop_value) in &instance.properties {
             // Discover and track any shared strings we come across.
             if let Variant::SharedString(shared_string) = prop_value {
-                if !self.shared_string_ids.contains_key(shared_string) {
+                if !self.serializer.inline_shared_strings
+                    && !self.shared_string_ids.contains_key(shared_string)
+                {
                     // We insert it with a dummy id of 0 so that we can check for contains_key.
                     // The actual id is set in `add_instances`
                     self.shared_string_ids.insert(shared_string.clone(), 0);
@@ -428,6 +430,14 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                 serialized_ty = VariantType::Color3;
             }
 
+            if serialized_ty == VariantType::SharedString && self.serializer.inline_shared_strings {
+                serialized_ty = VariantType::BinaryString;
+            }
+
+            if serialized_ty == VariantType::Content && self.serializer.legacy_content {
+                serialized_ty = VariantType::String;
+            }
+
             if !type_info.properties.contains_key(&canonical_name) {
                 let default_value = type_info
                     .class_descriptor
@@ -451,7 +461,9 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                 // will actually get serialized inside of the SSTR chunk, so we
                 // check here just to make sure.
                 if let Variant::SharedString(sstr) = default_value.borrow() {
-                    if !self.shared_string_ids.contains_key(sstr) {
+                    if !self.serializer.inline_shared_strings
+                        && !self.shared_string_ids.contains_key(sstr)
+                    {
                         self.shared_string_ids.insert(sstr.clone(), 0);
                         self.shared_strings.push(sstr.clone());
                     }
@@ -714,6 +726,9 @@ impl<'dom, 'db, W: Write> SerializerState<'dom, 'db, W> {
                                 Variant::BinaryString(value) => {
                                     chunk.write_binary_string(value.as_ref())?;
                                 }
+                                Variant::SharedString(value) => {
+                                    chunk.write_binary_string(value.data())?;
+                                }
                                 Variant::Tags(value) => {
                                     let buf = value.encode();
                                     chunk.write_binary_string(&buf)?;
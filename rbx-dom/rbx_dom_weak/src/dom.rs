@@ -360,6 +360,27 @@ impl WeakDom {
         dest_parent.children.push(referent);
     }
 
+    /// Sort the children of the instance with the given `referent` using the
+    /// given comparison function. The sort is stable.
+    ///
+    /// ## Panics
+    /// Panics if `referent` does not refer to an instance in `self`.
+    pub fn sort_children_by<F>(&mut self, referent: Ref, mut compare: F)
+    where
+        F: FnMut(&Instance, &Instance) -> std::cmp::Ordering,
+    {
+        let mut children = std::mem::take(
+            &mut self
+                .instances
+                .get_mut(&referent)
+                .unwrap_or_else(|| panic!("cannot sort children of an instance that does not exist"))
+                .children,
+        );
+
+        children.sort_by(|a, b| compare(&self.instances[a], &self.instances[b]));
+        self.instances.get_mut(&referent).unwrap().children = children;
+    }
+
     /// Clone the instance with the given `referent` and all its descendants
     /// (i.e. the entire subtree) into the same WeakDom.
     ///
@@ -0,0 +1,457 @@
+// KeyframeSequence -> gltf animation, so roblox animations can be looked at and edited in
+// blender and friends
+//
+// the sequence only stores a CFrame per joint per keyframe (a Pose named after the moving part),
+// what that means depends on the rig. the rig is a model whose parts are joined by Motor6Ds, the
+// same thing the animation editor works with. each part becomes a node under its Part0 with
+//   local = C0 * pose * C1:Inverse()
+// and gets a box child the size of the part so there's something to look at.
+//
+// easing styles aren't carried over, constant poses become STEP and everything else LINEAR.
+//
+// going back the other way the gltf animation is sampled at a fixed rate, nodes are matched to
+// rig parts by name and each pose is recovered as C0:Inverse() * local * C1.
+use crate::gltf::{GlbBuilder, Transform};
+use crate::importer::{read_accessor, read_gltf};
+use rbx_dom_weak::types::{CFrame, Matrix3, Ref, Vector3};
+use rbx_dom_weak::{Instance, InstanceBuilder, WeakDom};
+use rbx_types::{Enum, Variant};
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::error::Error;
+
+pub(crate) fn transform_of(cframe: &CFrame) -> Transform {
+    let (x, y, z) = (cframe.orientation.x, cframe.orientation.y, cframe.orientation.z);
+    Transform {
+        rotation: [x.x, x.y, x.z, y.x, y.y, y.z, z.x, z.y, z.z],
+        position: [cframe.position.x, cframe.position.y, cframe.position.z],
+    }
+}
+
+pub(crate) fn cframe_of(transform: &Transform) -> CFrame {
+    let (r, p) = (transform.rotation, transform.position);
+    CFrame::new(
+        Vector3::new(p[0], p[1], p[2]),
+        Matrix3::new(
+            Vector3::new(r[0], r[1], r[2]),
+            Vector3::new(r[3], r[4], r[5]),
+            Vector3::new(r[6], r[7], r[8]),
+        ),
+    )
+}
+
+fn cframe_prop(instance: &Instance, name: &str) -> Option<CFrame> {
+    match instance.properties.get(&name.into()) {
+        Some(Variant::CFrame(cframe)) => Some(*cframe),
+        _ => None,
+    }
+}
+
+fn ref_prop(instance: &Instance, name: &str) -> Option<Ref> {
+    match instance.properties.get(&name.into()) {
+        Some(Variant::Ref(referent)) if referent.is_some() => Some(*referent),
+        _ => None,
+    }
+}
+
+pub(crate) struct Joint {
+    pub part0: Ref,
+    pub part1: Ref,
+    pub c0: Transform,
+    pub c1: Transform,
+}
+
+// every Motor6D (or old style Motor) in the rig
+pub(crate) fn rig_joints(rig: &WeakDom) -> Vec<Joint> {
+    rig.descendants()
+        .filter(|instance| instance.class == "Motor6D" || instance.class == "Motor")
+        .filter_map(|motor| {
+            Some(Joint {
+                part0: ref_prop(motor, "Part0")?,
+                part1: ref_prop(motor, "Part1")?,
+                c0: cframe_prop(motor, "C0").map_or(Transform::IDENTITY, |c0| transform_of(&c0)),
+                c1: cframe_prop(motor, "C1").map_or(Transform::IDENTITY, |c1| transform_of(&c1)),
+            })
+        })
+        .collect()
+}
+
+// the part everything hangs off, HumanoidRootPart or the PrimaryPart if there is one, otherwise
+// the first Part0 that isn't also some joint's Part1
+pub(crate) fn root_part(rig: &WeakDom, joints: &[Joint]) -> Option<Ref> {
+    let named = rig
+        .descendants()
+        .find(|instance| instance.name == "HumanoidRootPart" && instance.properties.contains_key(&"Size".into()));
+    if let Some(part) = named {
+        return Some(part.referent());
+    }
+    let primary = rig.descendants().find_map(|instance| ref_prop(instance, "PrimaryPart"));
+    if primary.is_some() {
+        return primary;
+    }
+    joints
+        .iter()
+        .map(|joint| joint.part0)
+        .find(|&part| !joints.iter().any(|joint| joint.part1 == part))
+}
+
+struct Key {
+    time: f32,
+    cframe: CFrame,
+    constant: bool,
+}
+
+// poses can nest, every one of them is keyed by the name of the part it moves
+fn collect_poses(dom: &WeakDom, parent: &Instance, time: f32, keys: &mut HashMap<String, Vec<Key>>) {
+    for &child in parent.children() {
+        let Some(pose) = dom.get_by_ref(child) else { continue };
+        if pose.class != "Pose" {
+            continue;
+        }
+        if let Some(cframe) = cframe_prop(pose, "CFrame") {
+            let constant = matches!(pose.properties.get(&"EasingStyle".into()), Some(Variant::Enum(style)) if style.to_u32() == 1);
+            keys.entry(pose.name.to_string()).or_default().push(Key { time, cframe, constant });
+        }
+        collect_poses(dom, pose, time, keys);
+    }
+}
+
+// a 1x1x1 box, scaled per part
+fn unit_box(glb: &mut GlbBuilder) -> Value {
+    let mut positions = Vec::new();
+    let mut normals = Vec::new();
+    let mut indices = Vec::new();
+    for axis in 0..3 {
+        for sign in [-1.0f32, 1.0] {
+            let mut normal = [0.0; 3];
+            normal[axis] = sign;
+            let (u, v) = ((axis + 1) % 3, (axis + 2) % 3);
+            let base = (positions.len() / 3) as u32;
+            for (a, b) in [(-0.5, -0.5), (0.5, -0.5), (0.5, 0.5), (-0.5, 0.5)] {
+                let mut corner = [0.0; 3];
+                corner[axis] = 0.5 * sign;
+                corner[u] = a;
+                corner[v] = b;
+                positions.extend(corner);
+                normals.extend(normal);
+            }
+            if sign > 0.0 {
+                indices.extend([base, base + 1, base + 2, base, base + 2, base + 3]);
+            } else {
+                indices.extend([base, base + 2, base + 1, base, base + 3, base + 2]);
+            }
+        }
+    }
+    let position = glb.push_floats(&positions, "VEC3", 3, true, None);
+    let normal = glb.push_floats(&normals, "VEC3", 3, false, None);
+    let index = glb.push_indices(&indices);
+    json!({
+        "name": "PartBox",
+        "primitives": [{ "attributes": { "POSITION": position, "NORMAL": normal }, "indices": index, "mode": 4 }],
+    })
+}
+
+struct Exporter<'a> {
+    rig: &'a WeakDom,
+    joints: Vec<Joint>,
+    keys: HashMap<String, Vec<Key>>,
+    glb: GlbBuilder,
+    nodes: Vec<Value>,
+    channels: Vec<Value>,
+    samplers: Vec<Value>,
+}
+
+impl Exporter<'_> {
+    // adds the part and everything jointed to it, returns its node index
+    fn add_part(&mut self, part: Ref, joint: Option<usize>, visited: &mut Vec<Ref>) -> usize {
+        visited.push(part);
+        let instance = self.rig.get_by_ref(part);
+        let name = instance.map_or_else(String::new, |instance| instance.name.to_string());
+        let rest = match joint {
+            Some(joint) => self.joints[joint].c0.then(&self.joints[joint].c1.inverse()),
+            None => Transform::IDENTITY,
+        };
+        let node = self.nodes.len();
+        self.nodes.push(json!({ "name": name, "translation": rest.position, "rotation": rest.quaternion() }));
+        let mut children = Vec::new();
+
+        if let Some(Variant::Vector3(size)) = instance.and_then(|instance| instance.properties.get(&"Size".into())) {
+            children.push(self.nodes.len());
+            self.nodes.push(json!({ "name": format!("{} Box", name), "mesh": 0, "scale": [size.x, size.y, size.z] }));
+        }
+
+        // the root can't move relative to anything, its poses are only there to hold the others
+        if let Some(keys) = self.keys.remove(&name)
+            && let Some(joint) = joint
+        {
+            self.animate(node, joint, &keys);
+        }
+
+        let jointed: Vec<usize> = (0..self.joints.len())
+            .filter(|&index| self.joints[index].part0 == part)
+            .collect();
+        for index in jointed {
+            let child = self.joints[index].part1;
+            if !visited.contains(&child) {
+                children.push(self.add_part(child, Some(index), visited));
+            }
+        }
+        if !children.is_empty() {
+            self.nodes[node]["children"] = json!(children);
+        }
+        node
+    }
+
+    fn animate(&mut self, node: usize, joint: usize, keys: &[Key]) {
+        let Joint { c0, c1, .. } = &self.joints[joint];
+        let c1_inverse = c1.inverse();
+        let times: Vec<f32> = keys.iter().map(|key| key.time).collect();
+        let mut translations = Vec::with_capacity(keys.len() * 3);
+        let mut rotations: Vec<f32> = Vec::with_capacity(keys.len() * 4);
+        let mut previous = [0.0, 0.0, 0.0, 1.0];
+        for key in keys {
+            let local = c0.then(&transform_of(&key.cframe)).then(&c1_inverse);
+            translations.extend(local.position);
+            // stay on the same side as the last key so interpolation takes the short way round
+            let mut rotation = local.quaternion();
+            if rotation.iter().zip(previous).map(|(a, b)| a * b).sum::<f32>() < 0.0 {
+                rotation = rotation.map(|v| -v);
+            }
+            rotations.extend(rotation);
+            previous = rotation;
+        }
+        let interpolation = if keys.iter().all(|key| key.constant) { "STEP" } else { "LINEAR" };
+        let input = self.glb.push_floats(&times, "SCALAR", 1, true, None);
+        let translation_output = self.glb.push_floats(&translations, "VEC3", 3, false, None);
+        let rotation_output = self.glb.push_floats(&rotations, "VEC4", 4, false, None);
+        for (path, output) in [("translation", translation_output), ("rotation", rotation_output)] {
+            self.channels.push(json!({ "sampler": self.samplers.len(), "target": { "node": node, "path": path } }));
+            self.samplers.push(json!({ "input": input, "output": output, "interpolation": interpolation }));
+        }
+    }
+}
+
+pub fn keyframe_sequence_to_glb(animation: &WeakDom, rig: &WeakDom) -> Result<Vec<u8>, Box<dyn Error>> {
+    let sequence = animation
+        .descendants()
+        .find(|instance| instance.class == "KeyframeSequence")
+        .ok_or("no KeyframeSequence in the animation file")?;
+    let mut keys: HashMap<String, Vec<Key>> = HashMap::new();
+    for &child in sequence.children() {
+        let Some(keyframe) = animation.get_by_ref(child).filter(|instance| instance.class == "Keyframe") else {
+            continue;
+        };
+        let time = match keyframe.properties.get(&"Time".into()) {
+            Some(Variant::Float32(time)) => *time,
+            _ => 0.0,
+        };
+        collect_poses(animation, keyframe, time, &mut keys);
+    }
+    for part_keys in keys.values_mut() {
+        part_keys.sort_by(|a, b| a.time.total_cmp(&b.time));
+        part_keys.dedup_by(|a, b| a.time == b.time);
+    }
+
+    let joints = rig_joints(rig);
+    if joints.is_empty() {
+        return Err("the rig has no Motor6D joints".into());
+    }
+    let root = root_part(rig, &joints).ok_or("couldn't find the rig's root part")?;
+
+    let mut exporter = Exporter {
+        rig,
+        joints,
+        keys,
+        glb: GlbBuilder::default(),
+        nodes: Vec::new(),
+        channels: Vec::new(),
+        samplers: Vec::new(),
+    };
+    let part_box = unit_box(&mut exporter.glb);
+    let root_node = exporter.add_part(root, None, &mut Vec::new());
+    for name in exporter.keys.keys() {
+        println!("[legacy_place::anim] no jointed part named '{}' in the rig, its poses are skipped", name);
+    }
+
+    let mut document = json!({
+        "meshes": [part_box],
+        "nodes": exporter.nodes,
+        "scenes": [{ "nodes": [root_node] }],
+        "scene": 0,
+    });
+    if !exporter.channels.is_empty() {
+        document["animations"] = json!([{
+            "name": sequence.name.as_str(),
+            "channels": exporter.channels,
+            "samplers": exporter.samplers,
+        }]);
+    }
+    Ok(exporter.glb.finish(document)?)
+}
+
+// one animated property of one node
+struct Track {
+    times: Vec<f32>,
+    values: Vec<f32>,
+    components: usize,
+    interpolation: String,
+}
+
+impl Track {
+    fn value(&self, key: usize) -> &[f32] {
+        // cubic spline keys are stored as in tangent, value, out tangent
+        let (stride, offset) = if self.interpolation == "CUBICSPLINE" { (3, 1) } else { (1, 0) };
+        let start = (key * stride + offset) * self.components;
+        self.values.get(start..start + self.components).unwrap_or_default()
+    }
+
+    // cubic splines are treated as linear between their keys
+    fn sample(&self, time: f32) -> Vec<f32> {
+        let last = self.times.len().saturating_sub(1);
+        let next = self.times.partition_point(|&key_time| key_time <= time);
+        if next == 0 || next > last {
+            return self.value(next.min(last)).to_vec();
+        }
+        let (a, b) = (self.value(next - 1), self.value(next));
+        if self.interpolation == "STEP" {
+            return a.to_vec();
+        }
+        let span = self.times[next] - self.times[next - 1];
+        let t = if span > 0.0 { (time - self.times[next - 1]) / span } else { 0.0 };
+        // quaternions, flip to the near side and renormalize
+        let sign = if self.components == 4 && a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>() < 0.0 { -1.0 } else { 1.0 };
+        let mut value: Vec<f32> = a.iter().zip(b).map(|(x, y)| x + (y * sign - x) * t).collect();
+        if self.components == 4 {
+            let length = value.iter().map(|v| v * v).sum::<f32>().sqrt();
+            if length > 0.0 {
+                value.iter_mut().for_each(|v| *v /= length);
+            }
+        }
+        value
+    }
+}
+
+fn new_pose(name: &str, cframe: CFrame) -> InstanceBuilder {
+    InstanceBuilder::new("Pose")
+        .with_name(name)
+        .with_property("CFrame", Variant::CFrame(cframe))
+        .with_property("Weight", Variant::Float32(1.0))
+        .with_property("EasingStyle", Variant::Enum(Enum::from_u32(0)))
+        .with_property("EasingDirection", Variant::Enum(Enum::from_u32(0)))
+}
+
+// samples the first animation in the glb every 1/fps seconds into a KeyframeSequence, returned as
+// the only child of the dom's root
+pub fn glb_to_keyframe_sequence(glb_data: &[u8], rig: &WeakDom, fps: f32) -> Result<WeakDom, Box<dyn Error>> {
+    if fps.is_nan() || fps <= 0.0 {
+        return Err("the sample rate has to be above 0".into());
+    }
+    let (document, buffers) = read_gltf(glb_data, None)?;
+    let animation = document["animations"]
+        .get(0)
+        .ok_or("the gltf has no animations")?;
+    let nodes = document["nodes"].as_array().map(Vec::as_slice).unwrap_or_default();
+
+    // node name -> (translation, rotation)
+    let mut tracks: HashMap<String, (Option<Track>, Option<Track>)> = HashMap::new();
+    let samplers = animation["samplers"].as_array().map(Vec::as_slice).unwrap_or_default();
+    for channel in animation["channels"].as_array().into_iter().flatten() {
+        let target = &channel["target"];
+        let (Some(node), Some(path)) = (target["node"].as_u64(), target["path"].as_str()) else { continue };
+        let Some(name) = nodes.get(node as usize).and_then(|node| node["name"].as_str()) else { continue };
+        let Some(sampler) = channel["sampler"].as_u64().and_then(|sampler| samplers.get(sampler as usize)) else {
+            continue;
+        };
+        let (Some(input), Some(output)) = (sampler["input"].as_u64(), sampler["output"].as_u64()) else { continue };
+        let (times, _) = read_accessor(&document, &buffers, input as usize)?;
+        let (values, components) = read_accessor(&document, &buffers, output as usize)?;
+        let track = Track {
+            times,
+            values,
+            components,
+            interpolation: sampler["interpolation"].as_str().unwrap_or("LINEAR").to_owned(),
+        };
+        let stride = if track.interpolation == "CUBICSPLINE" { 3 } else { 1 };
+        if track.times.is_empty() || track.values.len() < track.times.len() * stride * components {
+            continue;
+        }
+        let entry = tracks.entry(name.to_owned()).or_default();
+        match path {
+            "translation" if components == 3 => entry.0 = Some(track),
+            "rotation" if components == 4 => entry.1 = Some(track),
+            _ => {}
+        }
+    }
+    let duration = tracks
+        .values()
+        .flat_map(|(translation, rotation)| [translation, rotation])
+        .flatten()
+        .filter_map(|track| track.times.last().copied())
+        .fold(0.0f32, f32::max);
+
+    let joints = rig_joints(rig);
+    let root = root_part(rig, &joints).ok_or("couldn't find the rig's root part")?;
+    let part_name = |part: Ref| rig.get_by_ref(part).map_or_else(String::new, |instance| instance.name.to_string());
+    // (joint, index of the parent in this list), parents first
+    let mut order: Vec<(usize, Option<usize>)> = Vec::new();
+    let mut visited = vec![root];
+    let mut frontier = vec![(root, None)];
+    while let Some((part, parent)) = frontier.pop() {
+        for (index, joint) in joints.iter().enumerate() {
+            if joint.part0 == part && !visited.contains(&joint.part1) {
+                visited.push(joint.part1);
+                order.push((index, parent));
+                frontier.push((joint.part1, Some(order.len() - 1)));
+            }
+        }
+    }
+    for name in tracks.keys() {
+        if !order.iter().any(|&(joint, _)| part_name(joints[joint].part1) == *name) && *name != part_name(root) {
+            println!("[legacy_place::anim] no jointed part named '{}' in the rig, its animation is skipped", name);
+        }
+    }
+
+    let animation_name = animation["name"].as_str().unwrap_or("Animation");
+    let mut dom = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let sequence = dom.insert(
+        dom.root_ref(),
+        InstanceBuilder::new("KeyframeSequence")
+            .with_name(animation_name)
+            .with_property("Loop", Variant::Bool(false)),
+    );
+    let frames = (duration * fps).ceil().max(0.0) as usize;
+    for frame in 0..=frames {
+        let time = (frame as f32 / fps).min(duration);
+        let keyframe = dom.insert(
+            sequence,
+            InstanceBuilder::new("Keyframe").with_property("Time", Variant::Float32(time)),
+        );
+        let root_pose = dom.insert(keyframe, new_pose(&part_name(root), cframe_of(&Transform::IDENTITY)));
+        let mut poses = Vec::with_capacity(order.len());
+        for &(index, parent) in &order {
+            let joint = &joints[index];
+            let name = part_name(joint.part1);
+            let node = nodes.iter().find(|node| node["name"].as_str() == Some(&name));
+            // parts the animation doesn't touch stay at rest
+            let rest = node.map_or(joint.c0.then(&joint.c1.inverse()), Transform::of_node);
+            let local = match tracks.get(&name) {
+                Some((translation, rotation)) => {
+                    let position = translation.as_ref().map_or(rest.position, |track| {
+                        let v = track.sample(time);
+                        [v[0], v[1], v[2]]
+                    });
+                    let rotation = rotation.as_ref().map_or(rest.quaternion(), |track| {
+                        let v = track.sample(time);
+                        [v[0], v[1], v[2], v[3]]
+                    });
+                    Transform::from_quaternion(rotation, position)
+                }
+                None => rest,
+            };
+            let pose = joint.c0.inverse().then(&local).then(&joint.c1);
+            let parent_pose = parent.map_or(root_pose, |parent| poses[parent]);
+            poses.push(dom.insert(parent_pose, new_pose(&name, cframe_of(&pose))));
+        }
+    }
+    Ok(dom)
+}
@@ -0,0 +1,104 @@
+// offline asset id -> creation date estimate, used to flag sounds/animations that an old
+// client's CDN snapshot won't have. asset ids are handed out sequentially across every asset
+// type, so interpolating between known (id, date) points is accurate to a few weeks, which is
+// plenty for deciding whether an asset predates a client.
+use crate::content_uri::ContentUri;
+use crate::pipeline::PassContext;
+use chrono::NaiveDate;
+use rbx_dom_weak::WeakDom;
+
+// approximate first asset id seen at the start of each year
+const ID_DATE_ANCHORS: [(u64, i32); 18] = [
+    (1, 2006),
+    (1_800_000, 2008),
+    (6_000_000, 2009),
+    (20_000_000, 2010),
+    (40_000_000, 2011),
+    (70_000_000, 2012),
+    (100_000_000, 2013),
+    (140_000_000, 2014),
+    (200_000_000, 2015),
+    (330_000_000, 2016),
+    (580_000_000, 2017),
+    (1_300_000_000, 2018),
+    (2_700_000_000, 2019),
+    (4_500_000_000, 2020),
+    (6_200_000_000, 2021),
+    (8_400_000_000, 2022),
+    (12_000_000_000, 2023),
+    (15_600_000_000, 2024),
+];
+
+const CHECKED_PROPERTIES: [&str; 2] = ["SoundId", "AnimationId"];
+
+pub struct LateAsset {
+    pub instance_name: String,
+    pub class: String,
+    pub property: String,
+    pub asset_id: u64,
+    pub estimated_date: NaiveDate,
+}
+
+fn year_start(year: i32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, 1, 1).expect("anchor years are valid dates")
+}
+
+pub fn estimate_asset_date(asset_id: u64) -> NaiveDate {
+    let upper = ID_DATE_ANCHORS.iter().position(|&(id, _)| id > asset_id);
+    let (lower, upper) = match upper {
+        Some(0) => return year_start(ID_DATE_ANCHORS[0].1),
+        Some(i) => (ID_DATE_ANCHORS[i - 1], ID_DATE_ANCHORS[i]),
+        // past the last anchor, extrapolate with the last year's rate
+        None => (
+            ID_DATE_ANCHORS[ID_DATE_ANCHORS.len() - 2],
+            ID_DATE_ANCHORS[ID_DATE_ANCHORS.len() - 1],
+        ),
+    };
+
+    let (lower_id, lower_year) = lower;
+    let (upper_id, upper_year) = upper;
+    let span_days = (year_start(upper_year) - year_start(lower_year)).num_days() as f64;
+    let fraction = (asset_id - lower_id) as f64 / (upper_id - lower_id) as f64;
+    // far past the last anchor the offset runs off the calendar, those ids are just "newest"
+    chrono::Duration::try_days((span_days * fraction) as i64)
+        .and_then(|offset| year_start(lower_year).checked_add_signed(offset))
+        .unwrap_or(NaiveDate::MAX)
+}
+
+pub fn find_late_assets(dom: &WeakDom, cutoff: NaiveDate) -> Vec<LateAsset> {
+    let mut late = Vec::new();
+    for instance in dom.descendants() {
+        for prop_name in CHECKED_PROPERTIES {
+            let uri = instance.properties.get(&prop_name.into()).and_then(ContentUri::from_variant);
+            let Some(asset_id) = uri.and_then(|uri| uri.asset_id()) else { continue };
+            let estimated_date = estimate_asset_date(asset_id);
+            if estimated_date > cutoff {
+                late.push(LateAsset {
+                    instance_name: instance.name.clone(),
+                    class: instance.class.to_string(),
+                    property: prop_name.to_string(),
+                    asset_id,
+                    estimated_date,
+                });
+            }
+        }
+    }
+    late
+}
+
+pub fn report_late_assets(dom: &WeakDom, cutoff: NaiveDate, ctx: &mut PassContext) {
+    let late = find_late_assets(dom, cutoff);
+    for asset in &late {
+        ctx.warn(format!(
+            "{} '{}' {} uses asset {} (estimated {}), newer than {}",
+            asset.class, asset.instance_name, asset.property, asset.asset_id, asset.estimated_date, cutoff
+        ));
+    }
+    if !late.is_empty() {
+        ctx.info(format!(
+            "{} sound/animation reference(s) are likely missing from a {} CDN snapshot",
+            late.len(),
+            cutoff
+        ));
+    }
+}
@@ -0,0 +1,276 @@
+// where assets referenced by id come from, everything that fetches one goes through here
+//
+// a ContentProvider hands over an asset's bytes by id. the ones here, by what's given for them:
+//
+//   roblox                 roblox's asset delivery, with the ROBLOSECURITY env var as the cookie
+//   <dir>                  files named after the id
+//   http(s)://...          a mirror, the url a prefix for the id or a template with {id}
+//   s3://<bucket>/<prefix> objects named like the files in a dir, see s3.rs
+//
+// a dir or bucket is checked for <id>.<ext> for each extension the caller expects, an empty
+// extension is the bare id. old versions are <id>_v<version>.<ext>, the way fetch names them, and
+// go in a url's {version} or a version parameter added to it. AssetSource is the provider a
+// command was given, anything else implementing the trait can be wrapped in one.
+use crate::s3::S3Bucket;
+use std::env;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+// anything bigger than this is more likely an error page than the asset
+const MAX_DOWNLOAD_BYTES: u64 = 64 * 1024 * 1024;
+const DOWNLOAD_TIMEOUT: Duration = Duration::from_secs(30);
+
+pub struct Download {
+    pub data: Vec<u8>,
+    // empty for files
+    pub content_type: String,
+    // the url, path or object it came from
+    pub location: String,
+}
+
+// a GET with the timeout and size limit every asset download has. non-2xx answers are errors
+pub(crate) fn download(url: &str, headers: &[(&str, &str)]) -> Result<Download, Box<dyn Error>> {
+    download_if_found(url, headers)?.ok_or_else(|| format!("{}: not found", url).into())
+}
+
+// download, with None for a 404
+pub(crate) fn download_if_found(url: &str, headers: &[(&str, &str)]) -> Result<Option<Download>, Box<dyn Error>> {
+    download_unless_missing(url, headers, |status, _| status == 404)
+}
+
+// download, with None for an error status and body is_missing says means there's nothing there
+pub(crate) fn download_unless_missing(
+    url: &str,
+    headers: &[(&str, &str)],
+    is_missing: impl Fn(u16, &str) -> bool,
+) -> Result<Option<Download>, Box<dyn Error>> {
+    let mut request = ureq::AgentBuilder::new().timeout(DOWNLOAD_TIMEOUT).build().get(url);
+    for (name, value) in headers {
+        request = request.set(name, value);
+    }
+    let response = match request.call() {
+        Err(ureq::Error::Status(status, response)) => {
+            let body = response.into_string().unwrap_or_default();
+            if is_missing(status, &body) {
+                return Ok(None);
+            }
+            return Err(format!("{}: status code {}", url, status).into());
+        }
+        response => response.map_err(|e| e.to_string())?,
+    };
+    let content_type = response.content_type().to_owned();
+    let mut data = Vec::new();
+    response.into_reader().take(MAX_DOWNLOAD_BYTES + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_DOWNLOAD_BYTES {
+        return Err(format!("{} is over {} MB", url, MAX_DOWNLOAD_BYTES / 1024 / 1024).into());
+    }
+    Ok(Some(Download { data, content_type, location: url.to_owned() }))
+}
+
+// <id>, or <id>_v<version> for an old version
+pub(crate) fn file_stem(id: u64, version: Option<u64>) -> String {
+    match version {
+        Some(version) => format!("{}_v{}", id, version),
+        None => id.to_string(),
+    }
+}
+
+fn file_name(stem: &str, extension: &str) -> String {
+    if extension.is_empty() {
+        stem.to_owned()
+    } else {
+        format!("{}.{}", stem, extension)
+    }
+}
+
+// fills in {version}, or adds a version parameter when the url has no place for it
+pub(crate) fn versioned_url(url: &str, version: Option<u64>) -> String {
+    match version {
+        Some(version) if url.contains("{version}") => url.replace("{version}", &version.to_string()),
+        Some(version) => {
+            let separator = if url.contains('?') { '&' } else { '?' };
+            format!("{}{}version={}", url, separator, version)
+        }
+        // a template written for versions still has to make a url without one
+        None => url.replace("&version={version}", "").replace("{version}", ""),
+    }
+}
+
+pub trait ContentProvider: Send + Sync {
+    // where assets come from, for messages
+    fn describe(&self) -> String;
+
+    // a specific version of the asset, the current one for None. extensions are what a file
+    // named after the id can end in, providers that aren't files ignore them
+    fn fetch_version(&self, id: u64, version: Option<u64>, extensions: &[&str]) -> Result<Download, Box<dyn Error>>;
+}
+
+pub struct LocalDir {
+    pub dir: PathBuf,
+}
+
+impl ContentProvider for LocalDir {
+    fn describe(&self) -> String {
+        self.dir.display().to_string()
+    }
+
+    fn fetch_version(&self, id: u64, version: Option<u64>, extensions: &[&str]) -> Result<Download, Box<dyn Error>> {
+        let stem = file_stem(id, version);
+        let path = extensions
+            .iter()
+            .map(|extension| self.dir.join(file_name(&stem, extension)))
+            .find(|path| path.is_file())
+            .ok_or_else(|| {
+                let names: Vec<String> = extensions.iter().map(|extension| file_name(&stem, extension)).collect();
+                format!("no {} in {}", names.join(" or "), self.dir.display())
+            })?;
+        Ok(Download {
+            data: fs::read(&path)?,
+            content_type: String::new(),
+            location: path.display().to_string(),
+        })
+    }
+}
+
+pub struct Mirror {
+    // a prefix for the id, or a template with {id} (and {version})
+    pub url: String,
+    // sent with every request, a cookie or a token
+    pub headers: Vec<(String, String)>,
+}
+
+impl ContentProvider for Mirror {
+    fn describe(&self) -> String {
+        self.url.clone()
+    }
+
+    fn fetch_version(&self, id: u64, version: Option<u64>, _extensions: &[&str]) -> Result<Download, Box<dyn Error>> {
+        let url = if self.url.contains("{id}") {
+            self.url.replace("{id}", &id.to_string())
+        } else {
+            format!("{}{}", self.url, id)
+        };
+        let headers: Vec<(&str, &str)> = self.headers.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect();
+        download(&versioned_url(&url, version), &headers)
+    }
+}
+
+// roblox's own asset delivery, some assets need the account cookie
+pub struct RobloxCdn {
+    mirror: Mirror,
+}
+
+impl RobloxCdn {
+    pub fn new(cookie: Option<&str>) -> Self {
+        let headers = cookie
+            .map(|cookie| vec![("Cookie".to_owned(), format!(".ROBLOSECURITY={}", cookie))])
+            .unwrap_or_default();
+        Self {
+            mirror: Mirror {
+                url: crate::fetch::DEFAULT_URL.to_owned(),
+                headers,
+            },
+        }
+    }
+}
+
+impl ContentProvider for RobloxCdn {
+    fn describe(&self) -> String {
+        "roblox".to_owned()
+    }
+
+    fn fetch_version(&self, id: u64, version: Option<u64>, extensions: &[&str]) -> Result<Download, Box<dyn Error>> {
+        self.mirror.fetch_version(id, version, extensions)
+    }
+}
+
+pub struct S3Mirror {
+    pub bucket: S3Bucket,
+    // "" or ending in /
+    pub prefix: String,
+}
+
+impl ContentProvider for S3Mirror {
+    fn describe(&self) -> String {
+        format!("s3://{}/{}", self.bucket.bucket, self.prefix)
+    }
+
+    fn fetch_version(&self, id: u64, version: Option<u64>, extensions: &[&str]) -> Result<Download, Box<dyn Error>> {
+        let stem = file_stem(id, version);
+        for extension in extensions {
+            if let Some(download) = self.bucket.get(&format!("{}{}", self.prefix, file_name(&stem, extension)))? {
+                return Ok(download);
+            }
+        }
+        let names: Vec<String> = extensions.iter().map(|extension| file_name(&stem, extension)).collect();
+        Err(format!("no {} in {} (or none readable with these credentials)", names.join(" or "), self.describe()).into())
+    }
+}
+
+// the provider a command was given, cheap to clone
+#[derive(Clone)]
+pub struct AssetSource(Arc<dyn ContentProvider>);
+
+impl fmt::Debug for AssetSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "AssetSource({})", self.0.describe())
+    }
+}
+
+impl FromStr for AssetSource {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.is_empty() {
+            return Err("expected roblox, a directory, a url or s3://bucket/prefix".to_owned());
+        }
+        if s == "roblox" {
+            return Ok(Self::new(RobloxCdn::new(env::var("ROBLOSECURITY").ok().as_deref())));
+        }
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return Ok(Self::new(Mirror { url: s.to_owned(), headers: Vec::new() }));
+        }
+        if let Some(rest) = s.strip_prefix("s3://") {
+            let (bucket, prefix) = rest.split_once('/').unwrap_or((rest, ""));
+            if bucket.is_empty() {
+                return Err(format!("no bucket in {}", s));
+            }
+            let prefix = match prefix.trim_matches('/') {
+                "" => String::new(),
+                prefix => format!("{}/", prefix),
+            };
+            return Ok(Self::new(S3Mirror { bucket: S3Bucket::from_env(bucket), prefix }));
+        }
+        Ok(Self::new(LocalDir { dir: PathBuf::from(s) }))
+    }
+}
+
+impl AssetSource {
+    pub fn new(provider: impl ContentProvider + 'static) -> Self {
+        Self(Arc::new(provider))
+    }
+
+    pub fn describe(&self) -> String {
+        self.0.describe()
+    }
+
+    pub fn fetch(&self, id: u64, extensions: &[&str]) -> Result<Vec<u8>, Box<dyn Error>> {
+        self.fetch_version(id, None, extensions)
+    }
+
+    // a specific version of the asset, the current one for None
+    pub fn fetch_version(&self, id: u64, version: Option<u64>, extensions: &[&str]) -> Result<Vec<u8>, Box<dyn Error>> {
+        Ok(self.0.fetch_version(id, version, extensions)?.data)
+    }
+
+    // with where it came from and its content type
+    pub fn fetch_download(&self, id: u64, version: Option<u64>, extensions: &[&str]) -> Result<Download, Box<dyn Error>> {
+        self.0.fetch_version(id, version, extensions)
+    }
+}
@@ -0,0 +1,79 @@
+// where rbxassetid:// references point once they're turned into urls
+//
+// a format is either a plain prefix the id gets appended to, or a template with placeholders:
+//   {id}    the asset id
+//   {type}  mesh, texture, sound, animation, video or asset, going by the property it's in
+//   {hash}  sha256 of the id as hex, for mirrors that shard their storage by it
+// a config file can give each type its own format, types it leaves out use the default:
+//   { "default": "http://mirror/asset/?id={id}", "mesh": "http://meshes.mirror/{hash}" }
+use crate::content::sha256_hex;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::error::Error;
+
+pub const ASSET_TYPES: [&str; 6] = ["mesh", "texture", "sound", "animation", "video", "asset"];
+
+#[derive(Deserialize, Default, Clone)]
+pub struct AssetUrlConfig {
+    #[serde(default)]
+    pub default: Option<String>,
+    #[serde(flatten)]
+    pub types: HashMap<String, String>,
+}
+
+impl AssetUrlConfig {
+    pub fn from_json(data: &str) -> Result<Self, Box<dyn Error>> {
+        let config: AssetUrlConfig = serde_json::from_str(data)?;
+        if let Some(unknown) = config.types.keys().find(|key| !ASSET_TYPES.contains(&key.as_str())) {
+            return Err(format!("unknown asset type '{}', expected one of {}", unknown, ASSET_TYPES.join(", ")).into());
+        }
+        Ok(config)
+    }
+}
+
+pub struct AssetUrlFormats {
+    default: String,
+    types: HashMap<String, String>,
+}
+
+impl AssetUrlFormats {
+    // the config's default wins over the one given here
+    pub fn new(default: String, config: AssetUrlConfig) -> Self {
+        Self {
+            default: config.default.unwrap_or(default),
+            types: config.types,
+        }
+    }
+
+    pub fn url(&self, id: &str, asset_type: &str) -> String {
+        let format = self.types.get(asset_type).unwrap_or(&self.default);
+        if !format.contains('{') {
+            return format!("{}{}", format, id);
+        }
+        let mut url = format.replace("{id}", id).replace("{type}", asset_type);
+        if url.contains("{hash}") {
+            url = url.replace("{hash}", &sha256_hex(id.as_bytes()));
+        }
+        url
+    }
+}
+
+// what kind of asset a content property holds, from its name
+pub fn asset_type(property: &str) -> &'static str {
+    if property.contains("Mesh") {
+        "mesh"
+    } else if property.contains("Sound") {
+        "sound"
+    } else if property.contains("Animation") {
+        "animation"
+    } else if property.contains("Video") {
+        "video"
+    } else if ["Texture", "Image", "Template", "Graphic", "Skybox", "Map", "Face", "Decal"]
+        .iter()
+        .any(|part| property.contains(part))
+    {
+        "texture"
+    } else {
+        "asset"
+    }
+}
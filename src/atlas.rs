@@ -0,0 +1,250 @@
+// textures of exported meshes packed into atlases, with the meshes' uvs moved to match
+//
+// old clients keep every texture in memory at its own size, and places built out of hundreds of
+// textured SpecialMeshes run out long before they run out of anything else. this reads the
+// manifest.json export-meshes writes, packs the textures into as few atlases as fit in
+// max_size, and writes every mesh again with its uvs squeezed into its texture's spot. each
+// texture gets `padding` pixels of its own edge around it so mipmaps don't bleed neighbours in.
+//
+// meshes used with more than one texture, and meshes whose uvs go outside 0..1 (tiling, which an
+// atlas can't do) keep their own texture. atlas.json says which atlas each mesh now goes with.
+use crate::mesh_export::MeshExport;
+use crate::mesh_types::IntermediateMesh;
+use crate::{filemesh, serialize_mesh, RobloxMeshVersion};
+use image::RgbaImage;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::Path;
+
+const ATLAS_MANIFEST_NAME: &str = "atlas.json";
+// uvs this far outside 0..1 are rounding, not tiling
+const UV_SLACK: f32 = 1e-3;
+
+pub struct AtlasOptions {
+    pub max_size: u32,
+    pub padding: u32,
+    pub version: RobloxMeshVersion,
+}
+
+#[derive(Serialize)]
+pub struct AtlasedMesh {
+    pub source: String,
+    pub file: String,
+    // the texture the mesh had, and the atlas it's in now
+    pub texture: String,
+    pub atlas: String,
+    // x, y, width, height in the atlas, padding not included
+    pub rect: [u32; 4],
+}
+
+#[derive(Serialize, Default)]
+pub struct AtlasPack {
+    pub atlases: Vec<String>,
+    pub meshes: Vec<AtlasedMesh>,
+    // mesh file -> why it kept its own texture
+    pub skipped: BTreeMap<String, String>,
+}
+
+impl AtlasPack {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (file, reason) in &self.skipped {
+            let _ = writeln!(out, "skipped: {} ({})", file, reason);
+        }
+        let textures: BTreeSet<&str> =
+            self.meshes.iter().map(|mesh| mesh.texture.as_str()).collect();
+        let _ = writeln!(
+            out,
+            "{} mesh(es) with {} texture(s) packed into {} atlas(es), {} skipped",
+            self.meshes.len(),
+            textures.len(),
+            self.atlases.len(),
+            self.skipped.len()
+        );
+        out
+    }
+}
+
+// one texture and everything using it
+struct Packed {
+    texture: String,
+    image: RgbaImage,
+    meshes: Vec<(String, String, IntermediateMesh)>,
+    atlas: usize,
+    x: u32,
+    y: u32,
+}
+
+// shelves filled left to right, top to bottom. textures come tallest first so each shelf is as
+// tall as its first one
+#[derive(Default)]
+struct Sheet {
+    shelf_y: u32,
+    shelf_height: u32,
+    cursor_x: u32,
+    width: u32,
+    height: u32,
+}
+
+impl Sheet {
+    fn place(&mut self, width: u32, height: u32, max_size: u32) -> Option<(u32, u32)> {
+        if self.cursor_x + width > max_size || self.shelf_height == 0 {
+            let next_y = self.shelf_y + self.shelf_height;
+            if next_y + height > max_size {
+                return None;
+            }
+            self.shelf_y = next_y;
+            self.shelf_height = height;
+            self.cursor_x = 0;
+        }
+        if height > self.shelf_height {
+            return None;
+        }
+        let spot = (self.cursor_x, self.shelf_y);
+        self.cursor_x += width;
+        self.width = self.width.max(self.cursor_x);
+        self.height = self.height.max(self.shelf_y + height);
+        Some(spot)
+    }
+}
+
+// the texture with its edge pixels repeated `padding` times around it
+fn copy_padded(atlas: &mut RgbaImage, image: &RgbaImage, x: u32, y: u32, padding: u32) {
+    let (width, height) = image.dimensions();
+    for py in 0..height + padding * 2 {
+        for px in 0..width + padding * 2 {
+            let source_x = px.saturating_sub(padding).min(width - 1);
+            let source_y = py.saturating_sub(padding).min(height - 1);
+            atlas.put_pixel(x + px, y + py, *image.get_pixel(source_x, source_y));
+        }
+    }
+}
+
+pub fn atlas_pack(manifest_path: &Path, out_dir: &Path, options: &AtlasOptions) -> Result<AtlasPack, Box<dyn Error>> {
+    let export: MeshExport = serde_json::from_str(&fs::read_to_string(manifest_path)?)
+        .map_err(|e| format!("{} isn't an export-meshes manifest: {}", manifest_path.display(), e))?;
+    let base = manifest_path.parent().unwrap_or(Path::new(""));
+    fs::create_dir_all(out_dir)?;
+    let mut pack = AtlasPack::default();
+
+    // texture file -> the texture and its meshes
+    let mut by_texture: BTreeMap<String, Packed> = BTreeMap::new();
+    for exported in &export.meshes {
+        let [texture] = exported.textures.as_slice() else {
+            let reason = format!("used with {} textures", exported.textures.len());
+            pack.skipped.insert(exported.file.clone(), reason);
+            continue;
+        };
+        let mesh = filemesh::parse_filemesh(&fs::read(base.join(&exported.file))?)?;
+        let tiles = mesh
+            .vertices
+            .iter()
+            .flat_map(|vertex| vertex.uv)
+            .any(|c| !(-UV_SLACK..=1.0 + UV_SLACK).contains(&c));
+        if tiles {
+            pack.skipped.insert(exported.file.clone(), "uvs go outside 0..1".to_owned());
+            continue;
+        }
+        if !by_texture.contains_key(&texture.file) {
+            let image = match image::open(base.join(&texture.file)) {
+                Ok(image) => image.to_rgba8(),
+                Err(e) => {
+                    pack.skipped.insert(exported.file.clone(), format!("couldn't read {}: {}", texture.file, e));
+                    continue;
+                }
+            };
+            let (width, height) = image.dimensions();
+            if width + options.padding * 2 > options.max_size || height + options.padding * 2 > options.max_size {
+                let reason = format!("{} is {}x{}, bigger than the atlas", texture.file, width, height);
+                pack.skipped.insert(exported.file.clone(), reason);
+                continue;
+            }
+            by_texture.insert(
+                texture.file.clone(),
+                Packed {
+                    texture: texture.source.clone(),
+                    image,
+                    meshes: Vec::new(),
+                    atlas: 0,
+                    x: 0,
+                    y: 0,
+                },
+            );
+        }
+        if let Some(packed) = by_texture.get_mut(&texture.file) {
+            packed.meshes.push((exported.source.clone(), exported.file.clone(), mesh));
+        }
+    }
+
+    let mut order: Vec<&mut Packed> = by_texture.values_mut().collect();
+    order.sort_by_key(|packed| std::cmp::Reverse(packed.image.height()));
+    let mut sheets: Vec<Sheet> = Vec::new();
+    for packed in &mut order {
+        let width = packed.image.width() + options.padding * 2;
+        let height = packed.image.height() + options.padding * 2;
+        let spot = sheets
+            .iter_mut()
+            .enumerate()
+            .find_map(|(index, sheet)| sheet.place(width, height, options.max_size).map(|(x, y)| (index, x, y)));
+        let (atlas, x, y) = match spot {
+            Some(spot) => spot,
+            None => {
+                let mut sheet = Sheet::default();
+                let (x, y) = sheet
+                    .place(width, height, options.max_size)
+                    .ok_or("a texture doesn't fit in an empty atlas")?;
+                sheets.push(sheet);
+                (sheets.len() - 1, x, y)
+            }
+        };
+        (packed.atlas, packed.x, packed.y) = (atlas, x, y);
+    }
+
+    // power of two sides, the oldest clients can't use anything else
+    let mut images: Vec<RgbaImage> = sheets
+        .iter()
+        .map(|sheet| RgbaImage::new(sheet.width.next_power_of_two(), sheet.height.next_power_of_two()))
+        .collect();
+    pack.atlases = (0..sheets.len()).map(|index| format!("atlas_{}.png", index)).collect();
+    for packed in by_texture.values_mut() {
+        let atlas = &mut images[packed.atlas];
+        copy_padded(atlas, &packed.image, packed.x, packed.y, options.padding);
+        let (atlas_width, atlas_height) = (atlas.width() as f32, atlas.height() as f32);
+        let (x, y) = (packed.x + options.padding, packed.y + options.padding);
+        let (width, height) = packed.image.dimensions();
+        for (source, file, mesh) in &mut packed.meshes {
+            for vertex in &mut mesh.vertices {
+                let [u, v] = vertex.uv.map(|c| c.clamp(0.0, 1.0));
+                vertex.uv = [
+                    (x as f32 + u * width as f32) / atlas_width,
+                    (y as f32 + v * height as f32) / atlas_height,
+                ];
+            }
+            fs::write(out_dir.join(&*file), serialize_mesh(mesh, options.version)?)?;
+            pack.meshes.push(AtlasedMesh {
+                source: source.clone(),
+                file: file.clone(),
+                texture: packed.texture.clone(),
+                atlas: pack.atlases[packed.atlas].clone(),
+                rect: [x, y, width, height],
+            });
+        }
+    }
+    for (image, name) in images.iter().zip(&pack.atlases) {
+        image.save(out_dir.join(name))?;
+    }
+    // unchanged for the ones left out, so the output dir is complete
+    for file in pack.skipped.keys() {
+        let Some(exported) = export.meshes.iter().find(|mesh| &mesh.file == file) else { continue };
+        fs::copy(base.join(&exported.file), out_dir.join(&exported.file))?;
+        for texture in &exported.textures {
+            fs::copy(base.join(&texture.file), out_dir.join(&texture.file))?;
+        }
+    }
+    pack.meshes.sort_by(|a, b| a.file.cmp(&b.file));
+    fs::write(out_dir.join(ATLAS_MANIFEST_NAME), serde_json::to_string_pretty(&pack)?)?;
+    Ok(pack)
+}
@@ -0,0 +1,77 @@
+// orientation and units for meshes going to or coming from DCC tools
+//
+// roblox is y up. blender is z up, so an obj written as is lies on its back there until it's
+// rotated by hand. the conversion is a rotation about x, which keeps the winding, plus a uniform
+// scale (studs to whatever the tool works in). normals and tangents are rotated but not scaled.
+use crate::mesh_types::IntermediateMesh;
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum UpAxis {
+    #[default]
+    Y,
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisConversion {
+    // the up axis of the other side, roblox's is always y
+    pub up: UpAxis,
+    // other side units per stud
+    pub scale: f32,
+}
+
+impl Default for AxisConversion {
+    fn default() -> Self {
+        Self { up: UpAxis::Y, scale: 1.0 }
+    }
+}
+
+// y up -> z up, -z forward becomes y forward. the + 0.0 keeps negated zeros from being
+// written as -0.000000
+fn y_to_z([x, y, z]: [f32; 3]) -> [f32; 3] {
+    [x, -z + 0.0, y]
+}
+
+fn z_to_y([x, y, z]: [f32; 3]) -> [f32; 3] {
+    [x, z, -y + 0.0]
+}
+
+impl AxisConversion {
+    pub fn new(up: UpAxis, scale: f32) -> Result<Self, String> {
+        if !(scale.is_finite() && scale > 0.0) {
+            return Err(format!("scale has to be a positive number, got {}", scale));
+        }
+        Ok(Self { up, scale })
+    }
+
+    // roblox space -> the other side's, for exporting
+    pub fn to_target(&self, mesh: &mut IntermediateMesh) {
+        for vertex in &mut mesh.vertices {
+            let pos = vertex.pos.map(|c| c * self.scale);
+            if self.up == UpAxis::Z {
+                vertex.pos = y_to_z(pos);
+                vertex.normal = y_to_z(vertex.normal);
+                let [x, y, z] = y_to_z([vertex.tangent[0], vertex.tangent[1], vertex.tangent[2]]);
+                vertex.tangent = [x, y, z, vertex.tangent[3]];
+            } else {
+                vertex.pos = pos;
+            }
+        }
+    }
+
+    // the other side's space -> roblox's, for importing
+    pub fn from_target(&self, mesh: &mut IntermediateMesh) {
+        for vertex in &mut mesh.vertices {
+            let pos = vertex.pos.map(|c| c / self.scale);
+            if self.up == UpAxis::Z {
+                vertex.pos = z_to_y(pos);
+                vertex.normal = z_to_y(vertex.normal);
+                let [x, y, z] = z_to_y([vertex.tangent[0], vertex.tangent[1], vertex.tangent[2]]);
+                vertex.tangent = [x, y, z, vertex.tangent[3]];
+            } else {
+                vertex.pos = pos;
+            }
+        }
+    }
+}
@@ -0,0 +1,174 @@
+// a floor under the spawns when there's nothing else to stand on
+//
+// places that were built on terrain lose their floor when the terrain is stripped for a client
+// that can't show it, and players spawn onto a pad over the void. a spawn has a floor when some
+// collidable part other than itself is under it, going by boxes with rotation ignored like the
+// thumbnail bounds. when no spawn has one a baseplate goes in, under all of them with its top
+// where the lowest one stands. places without spawns drop players at the origin, so that's
+// checked instead. terrain that's still in the place is taken to be the floor.
+use crate::pipeline::PassContext;
+use crate::world_space::{has_terrain, is_a};
+use rbx_dom_weak::types::{CFrame, Color3uint8, Matrix3, Ref, Vector3};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_types::Variant;
+use std::str::FromStr;
+
+const BASEPLATE_NAME: &str = "Baseplate";
+// past the spawns on each side, when they're spread wider than the baseplate
+const MARGIN: f32 = 64.0;
+// a pad sunk this far into the floor still stands on it
+const TOLERANCE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Baseplate {
+    pub size: Vector3,
+    pub color: Color3uint8,
+}
+
+impl Default for Baseplate {
+    fn default() -> Self {
+        Self {
+            size: Vector3::new(512.0, 20.0, 512.0),
+            // Dark green, what the classic baseplates were
+            color: Color3uint8::new(40, 127, 71),
+        }
+    }
+}
+
+impl FromStr for Baseplate {
+    type Err = String;
+
+    // "x,y,z" studs, or "x,y,z,r,g,b" with a 0-255 color
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let numbers: Vec<f32> = s
+            .split(',')
+            .map(|part| part.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("expected x,y,z or x,y,z,r,g,b, got '{}'", s))?;
+        let (size, color) = match numbers[..] {
+            [x, y, z] => (Vector3::new(x, y, z), None),
+            [x, y, z, r, g, b] => (Vector3::new(x, y, z), Some([r, g, b])),
+            _ => return Err(format!("expected 3 or 6 numbers for x,y,z or x,y,z,r,g,b, got {}", numbers.len())),
+        };
+        if size.x <= 0.0 || size.y <= 0.0 || size.z <= 0.0 {
+            return Err(format!("a baseplate can't be {},{},{} big", size.x, size.y, size.z));
+        }
+        let color = match color {
+            Some(channels) if channels.iter().any(|c| !(0.0..=255.0).contains(c)) => {
+                return Err(format!("color channels go from 0 to 255, got '{}'", s));
+            }
+            Some([r, g, b]) => Color3uint8::new(r as u8, g as u8, b as u8),
+            None => Self::default().color,
+        };
+        Ok(Self { size, color })
+    }
+}
+
+// a part's box, (min, max)
+fn part_box(cframe: &CFrame, size: &Vector3) -> (Vector3, Vector3) {
+    let p = cframe.position;
+    (
+        Vector3::new(p.x - size.x / 2.0, p.y - size.y / 2.0, p.z - size.z / 2.0),
+        Vector3::new(p.x + size.x / 2.0, p.y + size.y / 2.0, p.z + size.z / 2.0),
+    )
+}
+
+fn collidable_boxes(dom: &WeakDom) -> Vec<(Ref, (Vector3, Vector3))> {
+    dom.descendants()
+        .filter(|instance| is_a(&instance.class, "BasePart"))
+        .filter(|instance| !matches!(instance.properties.get(&"CanCollide".into()), Some(Variant::Bool(false))))
+        .filter_map(|instance| match (
+            instance.properties.get(&"CFrame".into()),
+            instance.properties.get(&"Size".into()),
+        ) {
+            (Some(Variant::CFrame(cframe)), Some(Variant::Vector3(size))) => {
+                Some((instance.referent(), part_box(cframe, size)))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+// where players stand: the spawns' (x, feet, z), or the origin without any
+fn spawn_points(dom: &WeakDom) -> Vec<(Option<Ref>, Vector3)> {
+    let spawns: Vec<(Option<Ref>, Vector3)> = dom
+        .descendants()
+        .filter(|instance| instance.class == "SpawnLocation")
+        .filter_map(|instance| match (
+            instance.properties.get(&"CFrame".into()),
+            instance.properties.get(&"Size".into()),
+        ) {
+            (Some(Variant::CFrame(cframe)), Some(Variant::Vector3(size))) => {
+                let (_, max) = part_box(cframe, size);
+                Some((Some(instance.referent()), Vector3::new(cframe.position.x, max.y, cframe.position.z)))
+            }
+            _ => None,
+        })
+        .collect();
+    if spawns.is_empty() {
+        return vec![(None, Vector3::new(0.0, 0.0, 0.0))];
+    }
+    spawns
+}
+
+fn has_floor(point: Vector3, spawn: Option<Ref>, boxes: &[(Ref, (Vector3, Vector3))]) -> bool {
+    boxes.iter().any(|&(referent, (min, max))| {
+        Some(referent) != spawn
+            && (min.x..=max.x).contains(&point.x)
+            && (min.z..=max.z).contains(&point.z)
+            // the origin without spawns has players dropped on whatever is there at any height
+            && (spawn.is_none() || max.y <= point.y + TOLERANCE)
+    })
+}
+
+pub fn ensure_baseplate(dom: &mut WeakDom, baseplate: &Baseplate, ctx: &mut PassContext) {
+    let workspace = dom
+        .root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == "Workspace"));
+    let Some(workspace) = workspace else {
+        ctx.warn("no workspace, can't add a baseplate");
+        return;
+    };
+    if has_terrain(dom) {
+        ctx.info("the place has terrain, taking it to be the floor");
+        return;
+    }
+    let points = spawn_points(dom);
+    let boxes = collidable_boxes(dom);
+    if points.iter().any(|&(spawn, point)| has_floor(point, spawn, &boxes)) {
+        return;
+    }
+
+    let (mut min, mut max) = (points[0].1, points[0].1);
+    for &(_, point) in &points {
+        min = Vector3::new(min.x.min(point.x), min.y.min(point.y), min.z.min(point.z));
+        max = Vector3::new(max.x.max(point.x), max.y.max(point.y), max.z.max(point.z));
+    }
+    // bigger than asked for only when the spawns are spread out past it
+    let cover = |size: f32, spread: f32| if spread > 0.0 { size.max(spread + MARGIN * 2.0) } else { size };
+    let size = Vector3::new(
+        cover(baseplate.size.x, max.x - min.x),
+        baseplate.size.y,
+        cover(baseplate.size.z, max.z - min.z),
+    );
+    let position = Vector3::new((min.x + max.x) / 2.0, min.y - size.y / 2.0, (min.z + max.z) / 2.0);
+    let part = InstanceBuilder::new("Part")
+        .with_name(BASEPLATE_NAME)
+        .with_property("Size", Variant::Vector3(size))
+        .with_property("CFrame", Variant::CFrame(CFrame::new(position, Matrix3::identity())))
+        .with_property("Anchored", Variant::Bool(true))
+        .with_property("Locked", Variant::Bool(true))
+        .with_property("Color", Variant::Color3uint8(baseplate.color));
+    let referent = dom.insert(workspace, part);
+    let under = if points[0].0.is_some() { "the spawns" } else { "the origin, there are no spawns" };
+    ctx.converted(
+        referent,
+        format!(
+            "added a {}x{}x{} baseplate at y {}, there was nothing under {}",
+            size.x, size.y, size.z, min.y, under
+        ),
+    );
+}
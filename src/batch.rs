@@ -0,0 +1,159 @@
+// --batch, one conversion run over every file a glob matches
+//
+// the pattern is split at its first component with a * or ? in it: what's before is the base
+// dir that gets walked, what's after is matched against each file's path under it. * and ? stay
+// within a component, ** matches any number of them. a pattern without either is a dir and
+// matches everything under it. outputs go in the out dir at the same path as under the base, so
+// assets/a/b.obj from 'assets/**/*.obj' becomes out/a/b.mesh. quote the pattern so the shell
+// doesn't expand it first.
+//
+// files are converted on every core, and one failing doesn't stop the rest. the errors come
+// back with the file they're from, for the summary at the end.
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// failures listed in the summary, the rest are only counted
+const FAILURES_SHOWN: usize = 50;
+
+fn has_wildcard(component: &str) -> bool {
+    component.contains(['*', '?'])
+}
+
+// one path component against one pattern component
+fn matches_component(pattern: &[char], name: &[char]) -> bool {
+    match (pattern.first(), name.first()) {
+        (None, None) => true,
+        (Some('*'), _) => {
+            matches_component(&pattern[1..], name) || (!name.is_empty() && matches_component(pattern, &name[1..]))
+        }
+        (Some('?'), Some(_)) => matches_component(&pattern[1..], &name[1..]),
+        (Some(p), Some(n)) if p == n => matches_component(&pattern[1..], &name[1..]),
+        _ => false,
+    }
+}
+
+fn matches_path(pattern: &[Vec<char>], path: &[Vec<char>]) -> bool {
+    match (pattern.first(), path.first()) {
+        (None, None) => true,
+        (Some(p), _) if p.iter().collect::<String>() == "**" => {
+            matches_path(&pattern[1..], path) || (!path.is_empty() && matches_path(pattern, &path[1..]))
+        }
+        (Some(p), Some(name)) => matches_component(p, name) && matches_path(&pattern[1..], &path[1..]),
+        _ => false,
+    }
+}
+
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        // symlinked dirs aren't followed, they can loop
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            walk(&entry.path(), files)?;
+        } else if file_type.is_file() {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+// the base dir and every matching file's path under it, sorted
+pub fn expand(pattern: &str) -> io::Result<(PathBuf, Vec<PathBuf>)> {
+    let components: Vec<Component> = Path::new(pattern).components().collect();
+    let split = components
+        .iter()
+        .position(|component| has_wildcard(&component.as_os_str().to_string_lossy()))
+        .unwrap_or(components.len());
+    let base: PathBuf = components[..split].iter().collect();
+    let base = if base.as_os_str().is_empty() { PathBuf::from(".") } else { base };
+    let rest: Vec<Vec<char>> = if split == components.len() {
+        vec!["**".chars().collect(), "*".chars().collect()]
+    } else {
+        components[split..].iter().map(|component| component.as_os_str().to_string_lossy().chars().collect()).collect()
+    };
+
+    let mut files = Vec::new();
+    walk(&base, &mut files)?;
+    let mut matched: Vec<PathBuf> = files
+        .into_iter()
+        .filter_map(|file| file.strip_prefix(&base).ok().map(Path::to_path_buf))
+        .filter(|relative| {
+            let path: Vec<Vec<char>> =
+                relative.components().map(|component| component.as_os_str().to_string_lossy().chars().collect()).collect();
+            matches_path(&rest, &path)
+        })
+        .collect();
+    matched.sort();
+    Ok((base, matched))
+}
+
+pub struct BatchReport {
+    pub converted: usize,
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl BatchReport {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let total = self.converted + self.failed.len();
+        let _ = writeln!(out, "converted {} of {} file(s), {} failed", self.converted, total, self.failed.len());
+        for (path, error) in self.failed.iter().take(FAILURES_SHOWN) {
+            let _ = writeln!(out, "  {}: {}", path.display(), error);
+        }
+        if self.failed.len() > FAILURES_SHOWN {
+            let _ = writeln!(out, "  and {} more", self.failed.len() - FAILURES_SHOWN);
+        }
+        out
+    }
+}
+
+// convert(input, output) for every file, its output being the same path under out_dir with the
+// given extension (or its own when None)
+pub fn run_batch(
+    base: &Path,
+    files: &[PathBuf],
+    out_dir: &Path,
+    extension: Option<&str>,
+    convert: impl Fn(&Path, &Path) -> Result<(), String> + Sync,
+) -> BatchReport {
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get()).clamp(1, files.len().max(1));
+    let next = AtomicUsize::new(0);
+    let results: Vec<Mutex<Option<Result<(), String>>>> = files.iter().map(|_| Mutex::new(None)).collect();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(relative) = files.get(index) else { break };
+                let input = base.join(relative);
+                let output = match extension {
+                    Some(extension) => out_dir.join(relative).with_extension(extension),
+                    None => out_dir.join(relative),
+                };
+                let result = output
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .map_err(|e| e.to_string())
+                    .and_then(|_| convert(&input, &output));
+                match &result {
+                    Ok(()) => println!("[{}/{}] {} -> {}", index + 1, files.len(), input.display(), output.display()),
+                    Err(e) => println!("[{}/{}] {} failed: {}", index + 1, files.len(), input.display(), e),
+                }
+                *results[index].lock().unwrap_or_else(|e| e.into_inner()) = Some(result);
+            });
+        }
+    });
+    let mut report = BatchReport { converted: 0, failed: Vec::new() };
+    for (relative, result) in files.iter().zip(results) {
+        match result.into_inner().unwrap_or_else(|e| e.into_inner()) {
+            Some(Ok(())) => report.converted += 1,
+            Some(Err(e)) => report.failed.push((base.join(relative), e)),
+            None => report.failed.push((base.join(relative), "never ran".to_owned())),
+        }
+    }
+    report
+}
@@ -0,0 +1,6 @@
+// no console window on windows, this is meant to be double clicked
+#![windows_subsystem = "windows"]
+
+fn main() -> eframe::Result {
+    roblox_utils::gui::run()
+}
@@ -0,0 +1,112 @@
+// binary output for clients that predate parts of the format
+//
+// a client refuses the whole file when a PROP chunk uses a type id it doesn't know, even for a
+// property it would have ignored anyway. the writer is told to avoid what has an older encoding:
+//   - shared strings are written inline and there's no SSTR chunk (before 2019)
+//   - Content properties are written as plain strings (before 2024)
+// anything else newer than the era fails the conversion, listing the properties that need it.
+use crate::rbxl_chunks;
+use clap::ValueEnum;
+use std::collections::{BTreeSet, HashMap};
+use std::error::Error;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BinaryCompat {
+    #[value(name = "2014")]
+    Era2014,
+    #[value(name = "2016")]
+    Era2016,
+    #[value(name = "2019")]
+    Era2019,
+    #[value(name = "2022")]
+    Era2022,
+}
+
+pub const SHARED_STRING_YEAR: u32 = 2019;
+pub const CONTENT_YEAR: u32 = 2024;
+
+// property type ids after the original set and roughly when clients started reading them
+const TYPE_INTRODUCED: [(u8, u32); 14] = [
+    (0x15, 2014), // NumberSequence
+    (0x16, 2014), // ColorSequence
+    (0x17, 2014), // NumberRange
+    (0x18, 2014), // Rect
+    (0x19, 2015), // PhysicalProperties
+    (0x1A, 2016), // Color3uint8
+    (0x1B, 2015), // Int64
+    (0x1C, SHARED_STRING_YEAR),
+    (0x1D, 2019), // Bytecode
+    (0x1E, 2020), // OptionalCFrame
+    (0x1F, 2021), // UniqueId
+    (0x20, 2022), // Font
+    (0x21, 2023), // SecurityCapabilities
+    (0x22, CONTENT_YEAR),
+];
+
+// properties listed in the error before the rest are only counted
+const PROPERTIES_SHOWN: usize = 10;
+
+impl BinaryCompat {
+    pub fn year(self) -> u32 {
+        match self {
+            Self::Era2014 => 2014,
+            Self::Era2016 => 2016,
+            Self::Era2019 => 2019,
+            Self::Era2022 => 2022,
+        }
+    }
+}
+
+pub(crate) fn type_year(type_id: u8) -> u32 {
+    TYPE_INTRODUCED
+        .iter()
+        .find(|(id, _)| *id == type_id)
+        .map_or(2013, |(_, year)| *year)
+}
+
+// looks through written binary output for chunks and property types the era can't read
+pub fn check_output(bytes: &[u8], era: BinaryCompat) -> Result<(), Box<dyn Error>> {
+    let mut class_names = HashMap::new();
+    let mut too_new = BTreeSet::new();
+    for chunk in rbxl_chunks::chunks(bytes) {
+        let chunk = chunk?;
+        match &chunk.name {
+            b"INST" => {
+                let inst = rbxl_chunks::parse_inst(&chunk.data)?;
+                class_names.insert(inst.class_id, inst.class_name);
+            }
+            b"PROP" => {
+                let prop = rbxl_chunks::parse_prop_header(&chunk.data)?;
+                let year = type_year(prop.type_id);
+                if year > era.year() {
+                    let class = class_names.get(&prop.class_id).map_or("?", String::as_str);
+                    too_new.insert(format!(
+                        "{}.{} ({}, {})",
+                        class,
+                        prop.prop_name,
+                        rbxl_chunks::type_name(prop.type_id),
+                        year
+                    ));
+                }
+            }
+            b"SSTR" if era.year() < SHARED_STRING_YEAR => {
+                too_new.insert(format!("SSTR chunk ({})", SHARED_STRING_YEAR));
+            }
+            _ => {}
+        }
+    }
+    if too_new.is_empty() {
+        return Ok(());
+    }
+    let mut listed: Vec<String> = too_new.iter().take(PROPERTIES_SHOWN).cloned().collect();
+    if too_new.len() > PROPERTIES_SHOWN {
+        listed.push(format!("and {} more", too_new.len() - PROPERTIES_SHOWN));
+    }
+    Err(format!(
+        "--binary-compat {}: the place needs binary features newer than {}: {}",
+        era.year(),
+        era.year(),
+        listed.join(", ")
+    )
+    .into())
+}
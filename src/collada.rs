@@ -0,0 +1,501 @@
+// .dae in, the collada files a lot of 2010s community models were passed around as
+//
+// every geometry the visual scene instances is merged into one mesh, with the transforms of the
+// nodes it's under applied, so a model comes in laid out like it was in the tool that exported it.
+// a file without a scene gets each of its geometries once, as they are. triangles, polylist,
+// polygons, trifans and tristrips are read, lines aren't. a controller (a skinned mesh) comes in as
+// its geometry at the bind pose, without bones. positions, normals, the first uv set and vertex
+// colors are kept; materials aren't, the same as obj import. v is flipped like obj's is.
+//
+// the file's own up axis and unit are reported rather than applied, the caller picks the axis
+// conversion with them (or without, see main.rs).
+use crate::axes::UpAxis;
+use crate::error::{ConversionError, Result};
+use crate::importer::{ImportCleanup, clean_faces};
+use crate::mesh_normals::{DEFAULT_SMOOTH_ANGLE, recompute_normals};
+use crate::mesh_tangents::generate_tangents;
+use crate::mesh_types::{DEFAULT_TANGENT, IntermediateMesh, IntermediateVertex, WHITE};
+use std::collections::HashMap;
+use std::io::Cursor;
+use xml::reader::{EventReader, XmlEvent};
+
+// deeper than any real scene, stops instance_node loops
+const MAX_NODE_DEPTH: usize = 64;
+const NONE: u32 = u32::MAX;
+
+type Matrix = [[f32; 4]; 4];
+const IDENTITY: Matrix = [[1.0, 0.0, 0.0, 0.0], [0.0, 1.0, 0.0, 0.0], [0.0, 0.0, 1.0, 0.0], [0.0, 0.0, 0.0, 1.0]];
+
+fn dae_err(message: impl Into<String>) -> ConversionError {
+    ConversionError::Unsupported(format!("dae: {}", message.into()))
+}
+
+pub struct DaeImport {
+    pub mesh: IntermediateMesh,
+    pub cleanup: ImportCleanup,
+    // from <asset>, y when the file doesn't say. x up files are read as y up
+    pub up_axis: UpAxis,
+    pub meters_per_unit: f32,
+    // geometry instances merged
+    pub instances: usize,
+}
+
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    children: Vec<Element>,
+    text: String,
+}
+
+impl Element {
+    fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes.iter().find(|(name, _)| name == key).map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> + 'a {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    fn numbers<T: std::str::FromStr>(&self) -> Result<Vec<T>> {
+        self.text
+            .split_ascii_whitespace()
+            .map(|word| word.parse().map_err(|_| dae_err(format!("'{}' in <{}> isn't a number", word, self.name))))
+            .collect()
+    }
+
+    // every element under this one (itself included) with an id, for resolving urls
+    fn index_ids<'a>(&'a self, ids: &mut HashMap<&'a str, &'a Element>) {
+        if let Some(id) = self.attribute("id") {
+            ids.insert(id, self);
+        }
+        for child in &self.children {
+            child.index_ids(ids);
+        }
+    }
+}
+
+fn parse_tree(data: &[u8]) -> Result<Element> {
+    let mut stack: Vec<Element> = Vec::new();
+    for event in EventReader::new(Cursor::new(data)) {
+        match event.map_err(|e| dae_err(e.to_string()))? {
+            XmlEvent::StartElement { name, attributes, .. } => stack.push(Element {
+                name: name.local_name,
+                attributes: attributes.into_iter().map(|attribute| (attribute.name.local_name, attribute.value)).collect(),
+                children: Vec::new(),
+                text: String::new(),
+            }),
+            XmlEvent::Characters(text) => {
+                if let Some(element) = stack.last_mut() {
+                    element.text.push_str(&text);
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                let element = stack.pop().ok_or_else(|| dae_err("unbalanced elements"))?;
+                match stack.last_mut() {
+                    Some(parent) => parent.children.push(element),
+                    None => return Ok(element),
+                }
+            }
+            _ => {}
+        }
+    }
+    Err(dae_err("the file ends early"))
+}
+
+fn multiply(a: &Matrix, b: &Matrix) -> Matrix {
+    let mut out = [[0.0; 4]; 4];
+    for (row, out_row) in out.iter_mut().enumerate() {
+        for (column, value) in out_row.iter_mut().enumerate() {
+            *value = (0..4).map(|k| a[row][k] * b[k][column]).sum();
+        }
+    }
+    out
+}
+
+fn node_transform(node: &Element) -> Result<Matrix> {
+    let mut transform = IDENTITY;
+    for child in &node.children {
+        let values: Vec<f32> = match child.name.as_str() {
+            "matrix" | "translate" | "rotate" | "scale" => child.numbers()?,
+            _ => continue,
+        };
+        let step = match (child.name.as_str(), values.as_slice()) {
+            ("matrix", m) if m.len() == 16 => {
+                [[m[0], m[1], m[2], m[3]], [m[4], m[5], m[6], m[7]], [m[8], m[9], m[10], m[11]], [m[12], m[13], m[14], m[15]]]
+            }
+            ("translate", &[x, y, z]) => [[1.0, 0.0, 0.0, x], [0.0, 1.0, 0.0, y], [0.0, 0.0, 1.0, z], [0.0, 0.0, 0.0, 1.0]],
+            ("scale", &[x, y, z]) => [[x, 0.0, 0.0, 0.0], [0.0, y, 0.0, 0.0], [0.0, 0.0, z, 0.0], [0.0, 0.0, 0.0, 1.0]],
+            ("rotate", &[x, y, z, degrees]) => {
+                let length = (x * x + y * y + z * z).sqrt();
+                if length == 0.0 {
+                    continue;
+                }
+                let [x, y, z] = [x / length, y / length, z / length];
+                let (sin, cos) = degrees.to_radians().sin_cos();
+                let t = 1.0 - cos;
+                [
+                    [t * x * x + cos, t * x * y - sin * z, t * x * z + sin * y, 0.0],
+                    [t * x * y + sin * z, t * y * y + cos, t * y * z - sin * x, 0.0],
+                    [t * x * z - sin * y, t * y * z + sin * x, t * z * z + cos, 0.0],
+                    [0.0, 0.0, 0.0, 1.0],
+                ]
+            }
+            _ => return Err(dae_err(format!("<{}> with {} value(s)", child.name, values.len()))),
+        };
+        transform = multiply(&transform, &step);
+    }
+    Ok(transform)
+}
+
+// a float source's values and how many make one item
+struct Source {
+    values: Vec<f32>,
+    stride: usize,
+}
+
+impl Source {
+    fn get(&self, index: u32) -> Option<&[f32]> {
+        let start = index as usize * self.stride;
+        self.values.get(start..start + self.stride)
+    }
+}
+
+fn read_source(element: &Element) -> Result<Source> {
+    let values = element
+        .child("float_array")
+        .ok_or_else(|| dae_err(format!("source {} has no float_array", element.attribute("id").unwrap_or("?"))))?
+        .numbers()?;
+    let stride = element
+        .child("technique_common")
+        .and_then(|technique| technique.child("accessor"))
+        .and_then(|accessor| accessor.attribute("stride"))
+        .map_or(Ok(1), |stride| stride.parse().map_err(|_| dae_err("bad accessor stride")))?;
+    Ok(Source { values, stride: stride.max(1) })
+}
+
+fn strip_hash(url: &str) -> &str {
+    url.strip_prefix('#').unwrap_or(url)
+}
+
+// where each attribute of a corner comes from: the source, and the index's place in a corner
+#[derive(Default)]
+struct Inputs<'a> {
+    position: Option<(&'a str, usize)>,
+    normal: Option<(&'a str, usize)>,
+    // lowest set wins
+    uv: Option<(&'a str, usize, u32)>,
+    color: Option<(&'a str, usize)>,
+    // indices per corner
+    stride: usize,
+}
+
+impl<'a> Inputs<'a> {
+    fn add(&mut self, semantic: &str, source: &'a str, offset: usize, set: u32) {
+        match semantic {
+            "POSITION" => self.position = Some((source, offset)),
+            "NORMAL" => self.normal = self.normal.or(Some((source, offset))),
+            "TEXCOORD" if self.uv.is_none_or(|(_, _, lowest)| set < lowest) => self.uv = Some((source, offset, set)),
+            "COLOR" => self.color = self.color.or(Some((source, offset))),
+            _ => {}
+        }
+    }
+
+    fn read(primitive: &'a Element, ids: &HashMap<&str, &'a Element>) -> Result<Self> {
+        let mut inputs = Self::default();
+        for input in primitive.children_named("input") {
+            let semantic = input.attribute("semantic").unwrap_or_default();
+            let source = strip_hash(input.attribute("source").unwrap_or_default());
+            let offset: usize = input.attribute("offset").and_then(|offset| offset.parse().ok()).unwrap_or(0);
+            let set = input.attribute("set").and_then(|set| set.parse().ok()).unwrap_or(0);
+            inputs.stride = inputs.stride.max(offset + 1);
+            if semantic != "VERTEX" {
+                inputs.add(semantic, source, offset, set);
+                continue;
+            }
+            // the <vertices> inputs all share the VERTEX index
+            let vertices = ids.get(source).ok_or_else(|| dae_err(format!("no vertices '{}'", source)))?;
+            for shared in vertices.children_named("input") {
+                let shared_source = strip_hash(shared.attribute("source").unwrap_or_default());
+                inputs.add(shared.attribute("semantic").unwrap_or_default(), shared_source, offset, 0);
+            }
+        }
+        Ok(inputs)
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Shape {
+    Triangles,
+    Fan,
+    Strip,
+}
+
+// a primitive's index lists with how their corners make triangles. a polylist's one <p> is split
+// into a fan per polygon
+fn polygons(primitive: &Element, stride: usize) -> Result<Vec<(Vec<u32>, Shape)>> {
+    let lists: Vec<Vec<u32>> = primitive.children_named("p").map(Element::numbers).collect::<Result<_>>()?;
+    let shape = match primitive.name.as_str() {
+        "triangles" => Shape::Triangles,
+        "tristrips" => Shape::Strip,
+        "polylist" => {
+            let counts: Vec<usize> = primitive.child("vcount").map_or(Ok(Vec::new()), Element::numbers)?;
+            let indices = lists.concat();
+            let mut start = 0;
+            let mut polygons = Vec::with_capacity(counts.len());
+            for count in counts {
+                let end = start + count * stride;
+                let polygon = indices.get(start..end).ok_or_else(|| dae_err("vcount runs past the indices"))?;
+                polygons.push((polygon.to_vec(), Shape::Fan));
+                start = end;
+            }
+            return Ok(polygons);
+        }
+        _ => Shape::Fan,
+    };
+    Ok(lists.into_iter().map(|list| (list, shape)).collect())
+}
+
+struct Builder {
+    mesh: IntermediateMesh,
+    missing_normals: usize,
+    sources: HashMap<String, Source>,
+}
+
+impl Builder {
+    fn source(&mut self, ids: &HashMap<&str, &Element>, id: &str) -> Result<()> {
+        if !self.sources.contains_key(id) {
+            let element = ids.get(id).ok_or_else(|| dae_err(format!("no source '{}'", id)))?;
+            self.sources.insert(id.to_owned(), read_source(element)?);
+        }
+        Ok(())
+    }
+
+    fn add_geometry(&mut self, geometry: &Element, transform: &Matrix, ids: &HashMap<&str, &Element>) -> Result<()> {
+        let Some(mesh) = geometry.child("mesh") else {
+            return Ok(());
+        };
+        // normals go through the inverse transpose, which is the cofactor matrix over the determinant
+        let m = transform;
+        let cofactor = [
+            [m[1][1] * m[2][2] - m[1][2] * m[2][1], m[1][2] * m[2][0] - m[1][0] * m[2][2], m[1][0] * m[2][1] - m[1][1] * m[2][0]],
+            [m[0][2] * m[2][1] - m[0][1] * m[2][2], m[0][0] * m[2][2] - m[0][2] * m[2][0], m[0][1] * m[2][0] - m[0][0] * m[2][1]],
+            [m[0][1] * m[1][2] - m[0][2] * m[1][1], m[0][2] * m[1][0] - m[0][0] * m[1][2], m[0][0] * m[1][1] - m[0][1] * m[1][0]],
+        ];
+        let determinant = m[0][0] * cofactor[0][0] + m[0][1] * cofactor[0][1] + m[0][2] * cofactor[0][2];
+        let mirrored = determinant < 0.0;
+
+        for primitive in &mesh.children {
+            if !matches!(primitive.name.as_str(), "triangles" | "polylist" | "polygons" | "trifans" | "tristrips") {
+                continue;
+            }
+            let inputs = Inputs::read(primitive, ids)?;
+            let Some((position_source, position_offset)) = inputs.position else {
+                return Err(dae_err(format!("a <{}> without positions", primitive.name)));
+            };
+            for id in [Some(position_source), inputs.normal.map(|n| n.0), inputs.uv.map(|uv| uv.0), inputs.color.map(|c| c.0)]
+                .into_iter()
+                .flatten()
+            {
+                self.source(ids, id)?;
+            }
+
+            // corner index tuples -> vertex, so corners sharing every index share a vertex
+            let mut corners: HashMap<[u32; 4], u32> = HashMap::new();
+            let mut corner = |builder: &mut Self, indices: &[u32]| -> Result<u32> {
+                let index_at = |offset: usize| indices.get(offset).copied().unwrap_or(NONE);
+                let key = [
+                    index_at(position_offset),
+                    inputs.normal.map_or(NONE, |(_, offset)| index_at(offset)),
+                    inputs.uv.map_or(NONE, |(_, offset, _)| index_at(offset)),
+                    inputs.color.map_or(NONE, |(_, offset)| index_at(offset)),
+                ];
+                if let Some(&vertex) = corners.get(&key) {
+                    return Ok(vertex);
+                }
+                let sources = &builder.sources;
+                let pos = sources[position_source]
+                    .get(key[0])
+                    .filter(|pos| pos.len() >= 3)
+                    .ok_or_else(|| dae_err(format!("position {} isn't there", key[0])))?;
+                let pos = [pos[0], pos[1], pos[2]];
+                let normal = inputs
+                    .normal
+                    .and_then(|(id, _)| sources[id].get(key[1]))
+                    .filter(|normal| normal.len() >= 3)
+                    .map(|normal| [normal[0], normal[1], normal[2]]);
+                let uv = inputs.uv.and_then(|(id, _, _)| sources[id].get(key[2])).filter(|uv| uv.len() >= 2);
+                let color = inputs.color.and_then(|(id, _)| sources[id].get(key[3])).filter(|color| color.len() >= 3);
+                let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+                let transformed = [0, 1, 2].map(|row| (0..3).map(|k| m[row][k] * pos[k]).sum::<f32>() + m[row][3]);
+                let normal = normal.map(|normal| {
+                    let n = [0, 1, 2].map(|row| (0..3).map(|k| cofactor[row][k] * normal[k]).sum::<f32>());
+                    let length = (n[0] * n[0] + n[1] * n[1] + n[2] * n[2]).sqrt();
+                    let sign = if mirrored { -1.0 } else { 1.0 };
+                    if length > 0.0 { n.map(|c| c * sign / length) } else { [0.0, 1.0, 0.0] }
+                });
+                if normal.is_none() {
+                    builder.missing_normals += 1;
+                }
+                builder.mesh.vertices.push(IntermediateVertex {
+                    pos: transformed,
+                    normal: normal.unwrap_or([0.0, 1.0, 0.0]),
+                    uv: uv.map_or([0.0, 0.0], |uv| [uv[0], 1.0 - uv[1]]),
+                    color: color.map_or(WHITE, |c| [byte(c[0]), byte(c[1]), byte(c[2]), c.get(3).map_or(255, |&a| byte(a))]),
+                    tangent: DEFAULT_TANGENT,
+                });
+                let vertex = (builder.mesh.vertices.len() - 1) as u32;
+                corners.insert(key, vertex);
+                Ok(vertex)
+            };
+
+            let stride = inputs.stride.max(1);
+            let triangle = |builder: &mut Self, [a, b, c]: [u32; 3]| {
+                builder.mesh.faces.push(if mirrored { [a, c, b] } else { [a, b, c] });
+            };
+            for (list, shape) in polygons(primitive, stride)? {
+                let vertices: Vec<u32> = list.chunks_exact(stride).map(|indices| corner(self, indices)).collect::<Result<_>>()?;
+                match shape {
+                    Shape::Triangles => {
+                        for face in vertices.chunks_exact(3) {
+                            triangle(self, [face[0], face[1], face[2]]);
+                        }
+                    }
+                    Shape::Fan => {
+                        for i in 1..vertices.len().saturating_sub(1) {
+                            triangle(self, [vertices[0], vertices[i], vertices[i + 1]]);
+                        }
+                    }
+                    Shape::Strip => {
+                        for i in 0..vertices.len().saturating_sub(2) {
+                            let [a, b, c] = [vertices[i], vertices[i + 1], vertices[i + 2]];
+                            // every other triangle of a strip goes the other way round
+                            triangle(self, if i % 2 == 0 { [a, b, c] } else { [b, a, c] });
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// the geometry a geometry or controller instance ends up at
+fn instanced_geometry<'a>(instance: &Element, ids: &HashMap<&str, &'a Element>) -> Option<&'a Element> {
+    let target = ids.get(strip_hash(instance.attribute("url")?))?;
+    match target.name.as_str() {
+        "geometry" => Some(target),
+        "controller" => {
+            let source = target.child("skin").or_else(|| target.child("morph"))?.attribute("source")?;
+            ids.get(strip_hash(source)).copied().filter(|geometry| geometry.name == "geometry")
+        }
+        _ => None,
+    }
+}
+
+fn walk_node(
+    node: &Element,
+    parent: &Matrix,
+    depth: usize,
+    ids: &HashMap<&str, &Element>,
+    builder: &mut Builder,
+    instances: &mut usize,
+) -> Result<()> {
+    if depth > MAX_NODE_DEPTH {
+        return Err(dae_err("nodes nest too deep, or instance each other"));
+    }
+    let transform = multiply(parent, &node_transform(node)?);
+    for child in &node.children {
+        match child.name.as_str() {
+            "instance_geometry" | "instance_controller" => {
+                if let Some(geometry) = instanced_geometry(child, ids) {
+                    builder.add_geometry(geometry, &transform, ids)?;
+                    *instances += 1;
+                }
+            }
+            "instance_node" => {
+                if let Some(target) = child.attribute("url").and_then(|url| ids.get(strip_hash(url))) {
+                    walk_node(target, &transform, depth + 1, ids, builder, instances)?;
+                }
+            }
+            "node" => walk_node(child, &transform, depth + 1, ids, builder, instances)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+pub fn dae_to_intermediate(data: &[u8]) -> Result<DaeImport> {
+    let root = parse_tree(data)?;
+    if root.name != "COLLADA" {
+        return Err(dae_err(format!("the root is <{}>, not <COLLADA>", root.name)));
+    }
+    let mut ids = HashMap::new();
+    root.index_ids(&mut ids);
+    let asset = root.child("asset");
+    let up_axis = match asset.and_then(|asset| asset.child("up_axis")).map(|up| up.text.trim()) {
+        Some("Z_UP") => UpAxis::Z,
+        _ => UpAxis::Y,
+    };
+    let meters_per_unit = asset
+        .and_then(|asset| asset.child("unit"))
+        .and_then(|unit| unit.attribute("meter"))
+        .and_then(|meter| meter.parse().ok())
+        .unwrap_or(1.0);
+
+    let mut builder = Builder {
+        mesh: IntermediateMesh {
+            vertices: Vec::new(),
+            faces: Vec::new(),
+            skin: None,
+            facs: None,
+        },
+        missing_normals: 0,
+        sources: HashMap::new(),
+    };
+    let mut instances = 0;
+    let scene = root
+        .child("scene")
+        .and_then(|scene| scene.child("instance_visual_scene"))
+        .and_then(|instance| instance.attribute("url"))
+        .and_then(|url| ids.get(strip_hash(url)).copied())
+        .or_else(|| root.child("library_visual_scenes").and_then(|library| library.child("visual_scene")));
+    match scene {
+        Some(scene) => {
+            for node in scene.children_named("node") {
+                walk_node(node, &IDENTITY, 0, &ids, &mut builder, &mut instances)?;
+            }
+        }
+        None => {
+            for library in root.children_named("library_geometries") {
+                for geometry in library.children_named("geometry") {
+                    builder.add_geometry(geometry, &IDENTITY, &ids)?;
+                    instances += 1;
+                }
+            }
+        }
+    }
+
+    let mut mesh = builder.mesh;
+    let mut cleanup = clean_faces(&mut mesh);
+    if mesh.faces.is_empty() {
+        return Err(ConversionError::NoMeshData);
+    }
+    if builder.missing_normals > 0 {
+        cleanup.missing_normals = builder.missing_normals;
+        recompute_normals(&mut mesh, DEFAULT_SMOOTH_ANGLE);
+    } else {
+        generate_tangents(&mut mesh);
+    }
+    Ok(DaeImport {
+        mesh,
+        cleanup,
+        up_axis,
+        meters_per_unit,
+        instances,
+    })
+}
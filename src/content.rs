@@ -0,0 +1,176 @@
+// checks a legacy client's content folder against what a place references
+//
+// every rbxasset:// reference in the place has to exist under the content dir. a missing texture
+// doesn't error in old clients, the part just renders blank, so this is the only way to notice.
+// files are also checked for being empty or not matching their extension, and against a sha256
+// manifest (json of relative path -> hash) if one is given. a manifest can be written from a
+// known good install with write_manifest.
+use crate::content_uri::ContentUri;
+use crate::path::path_of;
+use rbx_dom_weak::WeakDom;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+pub type Manifest = BTreeMap<String, String>;
+
+#[derive(Default)]
+pub struct ContentReport {
+    pub checked: usize,
+    // relative path -> instances referencing it
+    pub missing: BTreeMap<String, Vec<String>>,
+    // relative path -> why it's considered broken
+    pub corrupt: BTreeMap<String, String>,
+}
+
+impl ContentReport {
+    pub fn is_ok(&self) -> bool {
+        self.missing.is_empty() && self.corrupt.is_empty()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (path, users) in &self.missing {
+            let _ = writeln!(out, "missing: {} (used by {})", path, users.join(", "));
+        }
+        for (path, reason) in &self.corrupt {
+            let _ = writeln!(out, "corrupt: {} ({})", path, reason);
+        }
+        let _ = writeln!(
+            out,
+            "{} file(s) checked, {} missing, {} corrupt",
+            self.checked,
+            self.missing.len(),
+            self.corrupt.len()
+        );
+        out
+    }
+}
+
+pub(crate) fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+// rbxasset://textures\face.png?x=1 -> textures/face.png, None for other schemes or paths that
+// would leave the content dir
+pub fn asset_path_from_uri(uri: &ContentUri) -> Option<String> {
+    let path = uri.local_path()?;
+    let escapes = Path::new(&path)
+        .components()
+        .any(|component| !matches!(component, Component::Normal(_) | Component::CurDir));
+    if path.is_empty() || escapes {
+        return None;
+    }
+    Some(path)
+}
+
+// relative path -> paths of the instances referencing it
+pub fn referenced_assets(dom: &WeakDom) -> BTreeMap<String, Vec<String>> {
+    let mut assets: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for instance in dom.descendants() {
+        for (prop_name, value) in &instance.properties {
+            let Some(path) = ContentUri::from_variant(value).and_then(|uri| asset_path_from_uri(&uri)) else { continue };
+            let user = format!("{}.{}", path_of(dom, instance.referent()), prop_name);
+            let users = assets.entry(path).or_default();
+            if !users.contains(&user) {
+                users.push(user);
+            }
+        }
+    }
+    assets
+}
+
+// cheap sanity checks that catch truncated downloads and files saved with the wrong extension
+fn format_problem(path: &str, data: &[u8]) -> Option<&'static str> {
+    if data.is_empty() {
+        return Some("empty file");
+    }
+    let extension = path.rsplit_once('.').map(|(_, e)| e.to_ascii_lowercase()).unwrap_or_default();
+    let signature_ok = match extension.as_str() {
+        "png" => data.starts_with(b"\x89PNG\r\n\x1a\n"),
+        "jpg" | "jpeg" => data.starts_with(&[0xFF, 0xD8, 0xFF]),
+        "ogg" => data.starts_with(b"OggS"),
+        "wav" => data.starts_with(b"RIFF"),
+        "mesh" => data.starts_with(b"version "),
+        _ => true,
+    };
+    (!signature_ok).then_some("contents don't match the file extension")
+}
+
+// clients run on windows so references don't have to match the case on disk
+pub(crate) fn resolve(content_dir: &Path, relative: &str) -> Option<PathBuf> {
+    let exact = content_dir.join(relative);
+    if exact.exists() {
+        return Some(exact);
+    }
+    let mut current = content_dir.to_path_buf();
+    for part in relative.split('/').filter(|part| !part.is_empty() && *part != ".") {
+        let found = fs::read_dir(&current)
+            .ok()?
+            .filter_map(Result::ok)
+            .find(|entry| entry.file_name().to_string_lossy().eq_ignore_ascii_case(part))?;
+        current = found.path();
+    }
+    Some(current)
+}
+
+pub fn verify_content(content_dir: &Path, dom: &WeakDom, manifest: Option<&Manifest>) -> ContentReport {
+    let mut report = ContentReport::default();
+    for (path, users) in referenced_assets(dom) {
+        report.checked += 1;
+        let Some((resolved, Ok(data))) = resolve(content_dir, &path).map(|file| (file.clone(), fs::read(file)))
+        else {
+            report.missing.insert(path, users);
+            continue;
+        };
+        if let Some(problem) = format_problem(&path, &data) {
+            report.corrupt.insert(path, problem.to_owned());
+            continue;
+        }
+        // the manifest is keyed by the name on disk
+        let key = resolved.strip_prefix(content_dir).unwrap_or(&resolved).to_string_lossy().replace('\\', "/");
+        if let Some(expected) = manifest.and_then(|manifest| manifest.get(&key)) {
+            let actual = sha256_hex(&data);
+            if !actual.eq_ignore_ascii_case(expected) {
+                report.corrupt.insert(path, format!("sha256 {} doesn't match manifest {}", actual, expected));
+            }
+        }
+    }
+    report
+}
+
+pub fn load_manifest(path: &Path) -> Result<Manifest, Box<dyn Error>> {
+    Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> std::io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            collect_files(&entry.path(), files)?;
+        } else {
+            files.push(entry.path());
+        }
+    }
+    Ok(())
+}
+
+// hashes everything under the content dir, keyed by forward slash relative path
+pub fn write_manifest(content_dir: &Path, output: &Path) -> Result<usize, Box<dyn Error>> {
+    let mut files = Vec::new();
+    collect_files(content_dir, &mut files)?;
+    let mut manifest = Manifest::new();
+    for file in &files {
+        let relative = file.strip_prefix(content_dir)?.to_string_lossy().replace('\\', "/");
+        manifest.insert(relative, sha256_hex(&fs::read(file)?));
+    }
+    let count = manifest.len();
+    fs::write(output, serde_json::to_string_pretty(&manifest)?)?;
+    Ok(count)
+}
@@ -0,0 +1,172 @@
+// the forms an asset reference takes in a place, parsed once so passes don't each pick at strings
+//
+//   rbxassetid://1818                      AssetId(1818)
+//   1818                                   AssetId(1818), studio accepts a bare id too
+//   http://www.roblox.com/asset/?id=1818   Http, asset_id() is 1818
+//   rbxasset://textures/face.png           Local("textures/face.png"), relative to the content dir
+//   rbxhttp://Asset/?id=1818               RbxHttp, relative to the base url, asset_id() is 1818
+//
+// schemes are matched case-insensitively, anything else (rbxthumb://, empty strings) is Other
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{Ustr, WeakDom};
+use rbx_types::{Content, ContentId, Variant};
+use std::fmt;
+
+const ASSET_ID_SCHEME: &str = "rbxassetid://";
+const LOCAL_SCHEME: &str = "rbxasset://";
+const RBXHTTP_SCHEME: &str = "rbxhttp://";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ContentUri {
+    AssetId(u64),
+    Http(String),
+    Local(String),
+    RbxHttp(String),
+    Other(String),
+}
+
+fn strip_scheme<'a>(uri: &'a str, scheme: &str) -> Option<&'a str> {
+    let prefix = uri.get(..scheme.len())?;
+    prefix.eq_ignore_ascii_case(scheme).then(|| &uri[scheme.len()..])
+}
+
+// the id= query parameter, e.g. asset/?id=1818&version=2
+fn query_id(url: &str) -> Option<u64> {
+    let (_, query) = url.split_once('?')?;
+    query
+        .split('#')
+        .next()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| key.eq_ignore_ascii_case("id"))
+        .and_then(|(_, value)| value.trim().parse().ok())
+}
+
+fn replace_query_id(url: &str, id: u64) -> String {
+    let Some((base, rest)) = url.split_once('?') else { return url.to_owned() };
+    let (query, fragment) = rest.split_once('#').map_or((rest, None), |(query, fragment)| (query, Some(fragment)));
+    let query: Vec<String> = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if key.eq_ignore_ascii_case("id") => format!("{}={}", key, id),
+            _ => pair.to_owned(),
+        })
+        .collect();
+    let mut url = format!("{}?{}", base, query.join("&"));
+    if let Some(fragment) = fragment {
+        url.push('#');
+        url.push_str(fragment);
+    }
+    url
+}
+
+impl ContentUri {
+    pub fn parse(uri: &str) -> Self {
+        let uri = uri.trim();
+        if let Some(id) = strip_scheme(uri, ASSET_ID_SCHEME).and_then(|id| id.trim_end_matches('/').parse().ok()) {
+            return Self::AssetId(id);
+        }
+        if !uri.is_empty() && uri.bytes().all(|b| b.is_ascii_digit())
+            && let Ok(id) = uri.parse()
+        {
+            return Self::AssetId(id);
+        }
+        if let Some(path) = strip_scheme(uri, LOCAL_SCHEME) {
+            return Self::Local(path.to_owned());
+        }
+        if let Some(path) = strip_scheme(uri, RBXHTTP_SCHEME) {
+            return Self::RbxHttp(path.to_owned());
+        }
+        if strip_scheme(uri, "http://").is_some() || strip_scheme(uri, "https://").is_some() {
+            return Self::Http(uri.to_owned());
+        }
+        Self::Other(uri.to_owned())
+    }
+
+    // the uri of a Content, ContentId or string property
+    pub fn from_variant(value: &Variant) -> Option<Self> {
+        let uri = match value {
+            Variant::Content(content) => content.as_uri()?,
+            Variant::ContentId(content_id) => content_id.as_str(),
+            Variant::String(s) => s.as_str(),
+            _ => return None,
+        };
+        Some(Self::parse(uri))
+    }
+
+    // the same kind of value as `original` holding this uri
+    pub fn to_variant(&self, original: &Variant) -> Variant {
+        match original {
+            Variant::ContentId(_) => Variant::ContentId(ContentId::from(self.to_string())),
+            Variant::String(_) => Variant::String(self.to_string()),
+            _ => Variant::Content(Content::from_uri(self.to_string())),
+        }
+    }
+
+    pub fn asset_id(&self) -> Option<u64> {
+        match self {
+            Self::AssetId(id) => Some(*id),
+            Self::Http(url) | Self::RbxHttp(url) => query_id(url),
+            Self::Local(_) | Self::Other(_) => None,
+        }
+    }
+
+    // the same reference pointing at another asset, in the same form
+    pub fn with_asset_id(&self, id: u64) -> Self {
+        match self {
+            Self::AssetId(_) => Self::AssetId(id),
+            Self::Http(url) => Self::Http(replace_query_id(url, id)),
+            Self::RbxHttp(url) => Self::RbxHttp(replace_query_id(url, id)),
+            Self::Local(_) | Self::Other(_) => self.clone(),
+        }
+    }
+
+    // rbxasset://textures\face.png?x=1 -> textures/face.png
+    pub fn local_path(&self) -> Option<String> {
+        let Self::Local(path) = self else { return None };
+        let path = path.split(['?', '#']).next()?.replace('\\', "/");
+        Some(path.trim_start_matches('/').to_owned())
+    }
+}
+
+impl fmt::Display for ContentUri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AssetId(id) => write!(f, "{}{}", ASSET_ID_SCHEME, id),
+            Self::Local(path) => write!(f, "{}{}", LOCAL_SCHEME, path),
+            Self::RbxHttp(path) => write!(f, "{}{}", RBXHTTP_SCHEME, path),
+            Self::Http(url) | Self::Other(url) => f.write_str(url),
+        }
+    }
+}
+
+// runs `rewrite` over every content reference in the place, it gets the property name and the
+// parsed uri and returns the replacement, or None to leave it. returns what changed per instance
+pub fn rewrite_content<F>(dom: &mut WeakDom, mut rewrite: F) -> Vec<(Ref, Vec<(Ustr, ContentUri)>)>
+where
+    F: FnMut(&str, &ContentUri) -> Option<ContentUri>,
+{
+    let referents: Vec<Ref> = dom.descendants().map(|instance| instance.referent()).collect();
+    let mut changes = Vec::new();
+    for referent in referents {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        let mut changed = Vec::new();
+        for (prop_name, value) in instance.properties.iter_mut() {
+            // plain strings could be anything, only typed content is rewritten
+            if !matches!(value, Variant::Content(_) | Variant::ContentId(_)) {
+                continue;
+            }
+            let Some(uri) = ContentUri::from_variant(value) else { continue };
+            if let Some(new_uri) = rewrite(prop_name, &uri)
+                && new_uri != uri
+            {
+                *value = new_uri.to_variant(value);
+                changed.push((*prop_name, new_uri));
+            }
+        }
+        if !changed.is_empty() {
+            changes.push((referent, changed));
+        }
+    }
+    changes
+}
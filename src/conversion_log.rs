@@ -0,0 +1,81 @@
+// _ConversionLog, how a converted place was made written into the place itself
+//
+//   Workspace._ConversionLog   Folder, a Model when folders are turned into models
+//     Tool       StringValue   roblox_utils_cli 0.1.0
+//     Date       StringValue   2026-10-16 20:29:30 UTC
+//     Preset     StringValue   the preset's name, "none" without one
+//     Warnings   IntValue
+//     Passes     same class as the log, an IntValue per pass that ran with how much it changed
+//     Previous   the log of an earlier conversion, when the input was already converted
+//
+// plain values rather than attributes, which old clients drop, so anyone opening an archived
+// place in whatever client it was made for can still tell how it was produced. the passes keep
+// the order they ran in, so logs diff cleanly between runs.
+use crate::pipeline::RunReport;
+use crate::presets::Preset;
+use chrono::Utc;
+use clap::ValueEnum;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_types::Variant;
+
+pub const LOG_NAME: &str = "_ConversionLog";
+
+fn string_value(name: &str, value: impl Into<String>) -> InstanceBuilder {
+    InstanceBuilder::new("StringValue")
+        .with_name(name)
+        .with_property("Value", Variant::String(value.into()))
+}
+
+fn int_value(name: &str, value: usize) -> InstanceBuilder {
+    InstanceBuilder::new("IntValue")
+        .with_name(name)
+        .with_property("Value", Variant::Int64(value as i64))
+}
+
+// adds the log to the workspace, after the passes so their counts are known. an earlier log
+// already in the place goes inside the new one
+pub fn write_conversion_log(dom: &mut WeakDom, report: &RunReport, preset: Option<Preset>, folders_to_models: bool) {
+    let workspace = dom
+        .root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == "Workspace"));
+    let Some(workspace) = workspace else {
+        println!("[legacy_place::conversion_log] no workspace, the conversion log isn't written");
+        return;
+    };
+    let previous: Option<Ref> = dom.get_by_ref(workspace).and_then(|instance| {
+        instance
+            .children()
+            .iter()
+            .copied()
+            .find(|&child| dom.get_by_ref(child).is_some_and(|child| child.name == LOG_NAME))
+    });
+
+    let class = if folders_to_models { "Model" } else { "Folder" };
+    let preset = preset
+        .and_then(|preset| preset.to_possible_value())
+        .map_or_else(|| "none".to_owned(), |value| value.get_name().to_owned());
+    let passes = report
+        .changes
+        .iter()
+        .fold(InstanceBuilder::new(class).with_name("Passes"), |passes, (pass, changed)| {
+            passes.with_child(int_value(pass, *changed))
+        });
+    let log = InstanceBuilder::new(class)
+        .with_name(LOG_NAME)
+        .with_child(string_value("Tool", format!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION"))))
+        .with_child(string_value("Date", Utc::now().format("%Y-%m-%d %H:%M:%S UTC").to_string()))
+        .with_child(string_value("Preset", preset))
+        .with_child(int_value("Warnings", report.warnings.len()))
+        .with_child(passes);
+    let log = dom.insert(workspace, log);
+    if let Some(previous) = previous {
+        dom.transfer_within(previous, log);
+        if let Some(instance) = dom.get_by_ref_mut(previous) {
+            instance.name = "Previous".into();
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// long running inbox/outbox conversion service
+//
+// files dropped into the inbox are picked up by a fixed pool of workers and the result lands in
+// the outbox next to a <name>.report.json. the action comes from the extension (.obj, .glb and
+// .gltf -> filemesh, .mesh -> obj, places -> fix-place) and can be changed with a <file>.json sidecar
+// holding the same options as the http api, e.g. place.rbxl.json: { "preset": "2013" }.
+//
+// a job is claimed by moving it into inbox/.processing, so anything found there on startup
+// was interrupted and gets queued again. inputs that fail are moved to outbox/failed. files
+// being written into the inbox should end in .part until they're complete.
+use crate::serve::{self, Options, Reply, Upload};
+use serde_json::{Value, json};
+use std::error::Error;
+use std::fs;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PROCESSING_DIR: &str = ".processing";
+const FAILED_DIR: &str = "failed";
+const REPORT_SUFFIX: &str = ".report.json";
+
+static NEXT_JOB: AtomicU64 = AtomicU64::new(0);
+
+pub struct JobDirs {
+    pub inbox: PathBuf,
+    pub outbox: PathBuf,
+}
+
+impl JobDirs {
+    fn processing(&self) -> PathBuf {
+        self.inbox.join(PROCESSING_DIR)
+    }
+
+    fn failed(&self) -> PathBuf {
+        self.outbox.join(FAILED_DIR)
+    }
+}
+
+fn sidecar_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".json");
+    path.with_file_name(name)
+}
+
+fn action_for_extension(extension: &str) -> Option<&'static str> {
+    match extension {
+        "obj" => Some("obj-to-filemesh"),
+        "mesh" => Some("filemesh-to-obj"),
+        "glb" | "gltf" => Some("gltf-to-filemesh"),
+        "rbxl" | "rbxlx" | "rbxm" | "rbxmx" => Some("fix-place"),
+        _ => None,
+    }
+}
+
+fn default_action(name: &str) -> Option<&'static str> {
+    action_for_extension(&name.rsplit_once('.')?.1.to_ascii_lowercase())
+}
+
+fn is_job_file(name: &str) -> bool {
+    !name.starts_with('.') && !name.ends_with(".json") && !name.ends_with(".part")
+}
+
+fn read_options(path: &Path) -> Result<Options, Box<dyn Error>> {
+    let sidecar = sidecar_path(path);
+    if !sidecar.exists() {
+        return Ok(Options::new());
+    }
+    let value: Value = serde_json::from_str(&fs::read_to_string(sidecar)?)?;
+    let Value::Object(map) = value else {
+        return Err("sidecar options must be a json object".into());
+    };
+    // options are strings like in a query string, so true and 5 become "true" and "5"
+    Ok(map
+        .into_iter()
+        .map(|(key, value)| match value {
+            Value::String(s) => (key, s),
+            other => (key, other.to_string()),
+        })
+        .collect())
+}
+
+fn move_with_sidecar(from: &Path, to_dir: &Path) -> io::Result<PathBuf> {
+    let to = to_dir.join(from.file_name().unwrap_or_default());
+    fs::rename(from, &to)?;
+    let sidecar = sidecar_path(from);
+    if sidecar.exists() {
+        fs::rename(sidecar, sidecar_path(&to))?;
+    }
+    Ok(to)
+}
+
+fn process(dirs: &JobDirs, path: &Path) -> Value {
+    let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+    let start = Instant::now();
+    let result: Result<(String, Reply), String> = (|| {
+        let options = read_options(path).map_err(|e| format!("bad sidecar: {}", e))?;
+        let action = options
+            .get("action")
+            .map(String::as_str)
+            .or_else(|| default_action(&name))
+            .ok_or_else(|| format!("no action for '{}', set one in {}.json", name, name))?
+            .to_owned();
+        let upload = Upload {
+            file: fs::read(path).map_err(|e| e.to_string())?,
+            filename: Some(name.clone()),
+            options,
+        };
+        let reply = panic::catch_unwind(AssertUnwindSafe(|| serve::run_action(&action, &upload)))
+            .map_err(|_| "conversion panicked".to_owned())?
+            .map_err(|serve::HttpError(_, message)| message)?;
+        Ok((action, reply))
+    })();
+
+    let mut report = json!({
+        "input": name,
+        "duration_ms": start.elapsed().as_millis() as u64,
+    });
+    let written = result.and_then(|(action, reply)| {
+        report["action"] = json!(action);
+        let (filename, bytes) = match reply {
+            Reply::File { bytes, filename, report: run } => {
+                // fix-place's warnings and verify summary
+                if let Some(run) = run {
+                    report["run"] = run;
+                }
+                (filename, bytes)
+            }
+            Reply::Json(value) => (format!("{}.info.json", name), value.to_string().into_bytes()),
+        };
+        fs::write(dirs.outbox.join(&filename), bytes).map_err(|e| e.to_string())?;
+        Ok(filename)
+    });
+    match written {
+        Ok(output) => {
+            report["status"] = json!("ok");
+            report["output"] = json!(output);
+            let _ = fs::remove_file(sidecar_path(path));
+            let _ = fs::remove_file(path);
+        }
+        Err(message) => {
+            report["status"] = json!("failed");
+            report["error"] = json!(message);
+            if let Err(e) = move_with_sidecar(path, &dirs.failed()) {
+                println!("[legacy_place::daemon] couldn't move '{}' to failed: {}", name, e);
+            }
+        }
+    }
+    let report_path = dirs.outbox.join(format!("{}{}", name, REPORT_SUFFIX));
+    if let Err(e) = fs::write(&report_path, serde_json::to_string_pretty(&report).unwrap_or_default()) {
+        println!("[legacy_place::daemon] couldn't write report for '{}': {}", name, e);
+    }
+    report
+}
+
+fn worker(dirs: Arc<JobDirs>, queue: Arc<Mutex<Receiver<PathBuf>>>) {
+    loop {
+        // the lock is only held while waiting, never while converting
+        let job = match queue.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(_) => return,
+        };
+        let Ok(path) = job else { return };
+        let report = process(&dirs, &path);
+        println!(
+            "[legacy_place::daemon] {} {} in {} ms{}",
+            report["input"].as_str().unwrap_or_default(),
+            report["status"].as_str().unwrap_or_default(),
+            report["duration_ms"],
+            report["error"].as_str().map(|e| format!(": {}", e)).unwrap_or_default()
+        );
+    }
+}
+
+fn pending_jobs(dirs: &JobDirs) -> io::Result<Vec<PathBuf>> {
+    let mut jobs: Vec<_> = fs::read_dir(&dirs.inbox)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|ty| ty.is_file()))
+        .filter(|entry| is_job_file(&entry.file_name().to_string_lossy()))
+        .map(|entry| entry.path())
+        .collect();
+    jobs.sort();
+    Ok(jobs)
+}
+
+// queues an upload from the http api, returns the job id
+pub fn submit(dirs: &JobDirs, upload: &Upload) -> io::Result<String> {
+    // the extension ends up in a path in the inbox, so only ones a job can have are taken from
+    // the client's filename, anything else (x./../../etc) goes by the action instead
+    let extension = upload
+        .filename
+        .as_deref()
+        .and_then(|name| name.rsplit_once('.'))
+        .map(|(_, extension)| extension.to_ascii_lowercase())
+        .filter(|extension| action_for_extension(extension).is_some())
+        .unwrap_or_else(|| {
+            match upload.options.get("action").map(String::as_str) {
+                Some("obj-to-filemesh") => "obj",
+                Some("gltf-to-filemesh") => "glb",
+                Some("filemesh-to-obj" | "filemesh-to-gltf" | "filemesh-to-filemesh") => "mesh",
+                _ => "rbxl",
+            }
+            .to_owned()
+        });
+    let millis = chrono::Utc::now().timestamp_millis();
+    let id = format!("job-{}-{}", millis, NEXT_JOB.fetch_add(1, Ordering::Relaxed));
+    let path = dirs.inbox.join(format!("{}.{}", id, extension));
+
+    // the sidecar goes first so the job is never picked up without its options
+    let mut options = upload.options.clone();
+    if let Some(filename) = &upload.filename {
+        options.insert("filename".to_owned(), filename.clone());
+    }
+    fs::write(sidecar_path(&path), serde_json::to_string(&options)?)?;
+    let partial = path.with_extension(format!("{}.part", extension));
+    fs::write(&partial, &upload.file)?;
+    fs::rename(partial, &path)?;
+    Ok(id)
+}
+
+fn find_job_file(dir: &Path, id: &str) -> Option<PathBuf> {
+    let prefix = format!("{}.", id);
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|name| name.starts_with(&prefix) && is_job_file(name))
+        .map(|name| dir.join(name))
+}
+
+fn job_report(dirs: &JobDirs, id: &str) -> Option<Value> {
+    let prefix = format!("{}.", id);
+    let report = fs::read_dir(&dirs.outbox)
+        .ok()?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .find(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            name.starts_with(&prefix) && name.ends_with(REPORT_SUFFIX)
+        })?;
+    serde_json::from_str(&fs::read_to_string(report).ok()?).ok()
+}
+
+pub fn job_status(dirs: &JobDirs, id: &str) -> Option<Value> {
+    if let Some(report) = job_report(dirs, id) {
+        return Some(report);
+    }
+    if find_job_file(&dirs.processing(), id).is_some() {
+        return Some(json!({ "status": "running" }));
+    }
+    find_job_file(&dirs.inbox, id).map(|_| json!({ "status": "queued" }))
+}
+
+pub fn job_output(dirs: &JobDirs, id: &str) -> Option<Reply> {
+    let report = job_report(dirs, id)?;
+    let filename = report["output"].as_str()?.to_owned();
+    let bytes = fs::read(dirs.outbox.join(&filename)).ok()?;
+    Some(Reply::File { bytes, filename, report: None })
+}
+
+pub fn run(dirs: JobDirs, workers: usize, poll: Duration, bind: Option<String>) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dirs.processing())?;
+    fs::create_dir_all(dirs.failed())?;
+
+    // jobs left over from a previous run that didn't finish
+    for entry in fs::read_dir(dirs.processing())?.filter_map(Result::ok) {
+        let path = entry.path();
+        if is_job_file(&entry.file_name().to_string_lossy()) {
+            println!("[legacy_place::daemon] requeueing interrupted job {}", path.display());
+            move_with_sidecar(&path, &dirs.inbox)?;
+        }
+    }
+
+    let dirs = Arc::new(dirs);
+    // a rendezvous channel, so a job is only claimed once a worker is free to take it
+    let (sender, receiver) = mpsc::sync_channel::<PathBuf>(0);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..workers.max(1) {
+        let dirs = Arc::clone(&dirs);
+        let receiver = Arc::clone(&receiver);
+        thread::spawn(move || worker(dirs, receiver));
+    }
+
+    if let Some(bind) = bind {
+        let dirs = Arc::clone(&dirs);
+        thread::spawn(move || {
+            if let Err(e) = serve::run(&bind, 2, 256 * 1024 * 1024, Some(dirs)) {
+                println!("[legacy_place::daemon] http api stopped: {}", e);
+            }
+        });
+    }
+
+    println!(
+        "[legacy_place::daemon] watching {} with {} worker(s), results go to {}",
+        dirs.inbox.display(),
+        workers.max(1),
+        dirs.outbox.display()
+    );
+    loop {
+        for path in pending_jobs(&dirs)? {
+            let claimed = match move_with_sidecar(&path, &dirs.processing()) {
+                Ok(claimed) => claimed,
+                // someone else took it or it's still being written
+                Err(_) => continue,
+            };
+            sender.send(claimed)?;
+        }
+        thread::sleep(poll);
+    }
+}
@@ -0,0 +1,110 @@
+// parsed places kept on disk, so running a few commands on the same huge place parses it once
+//
+// entries are keyed by the sha256 of the input file (and the tool version, since the reflection
+// database can change what a parse produces). an entry is the parsed dom written back out as a
+// binary place with zstd chunks, which reads much faster than the xml it came from and a bit
+// faster than lz4. a bad or unreadable entry is ignored and the input is parsed again.
+//
+// going through the binary format isn't lossless: every instance of a class gets the properties
+// any of them has (with the default value), and a property binary can't hold is dropped. so the
+// run that writes an entry hands back what it wrote, read back in, the same dom a later run gets
+// from the entry. with --cache-dir an xml place comes out like it had been saved as binary first,
+// which can differ from a run without one.
+use crate::content::sha256_hex;
+use crate::dom_limits::{self, DomLimits};
+use rbx_binary::CompressionType;
+use rbx_dom_weak::WeakDom;
+use std::error::Error;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+const ENTRY_EXTENSION: &str = "rbxl";
+// oldest entries past this are removed, each one is about the size of the place
+const MAX_ENTRIES: usize = 8;
+
+fn entry_path(cache_dir: &Path, input_bytes: &[u8]) -> PathBuf {
+    let key = sha256_hex(input_bytes);
+    cache_dir.join(format!("{}-{}.{}", &key[..32], env!("CARGO_PKG_VERSION"), ENTRY_EXTENSION))
+}
+
+fn prune(cache_dir: &Path) -> std::io::Result<()> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION) {
+            entries.push((fs::metadata(&path)?.modified()?, path));
+        }
+    }
+    if entries.len() <= MAX_ENTRIES {
+        return Ok(());
+    }
+    entries.sort();
+    for (_, path) in &entries[..entries.len() - MAX_ENTRIES] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn read_entry(entry: &[u8], parse_threads: usize) -> Result<WeakDom, Box<dyn Error>> {
+    Ok(rbx_binary::Deserializer::new().decompression_threads(parse_threads).deserialize(Cursor::new(entry))?)
+}
+
+// the entry, and the dom read back from it
+fn write_entry(dom: &WeakDom, path: &Path, parse_threads: usize) -> Result<WeakDom, Box<dyn Error>> {
+    let root_refs: Vec<_> = dom.root().children().to_vec();
+    let mut output = Vec::new();
+    rbx_binary::Serializer::new()
+        .compression_type(CompressionType::Zstd)
+        .serialize(&mut output, dom, &root_refs)
+        .map_err(|e| e.to_string())?;
+    let cached = read_entry(&output, parse_threads)?;
+    // written under another name first so a crash never leaves half an entry behind
+    let partial = path.with_extension("partial");
+    fs::write(&partial, output)?;
+    fs::rename(&partial, path)?;
+    Ok(cached)
+}
+
+// load_place, going through the cache when there is one
+pub fn load_place_cached(
+    input_bytes: &[u8],
+    cache_dir: Option<&Path>,
+    parse_threads: usize,
+    limits: &DomLimits,
+) -> Result<WeakDom, Box<dyn Error>> {
+    let Some(cache_dir) = cache_dir else {
+        return crate::load_place_with_limits(input_bytes, parse_threads, limits);
+    };
+    let path = entry_path(cache_dir, input_bytes);
+    if let Ok(cached) = fs::read(&path) {
+        match read_entry(&cached, parse_threads) {
+            Ok(dom) => {
+                println!("[legacy_place::cache] using cached parse {}", path.display());
+                // the entry passed whatever limits it was cached under
+                dom_limits::check_dom(&dom, limits)?;
+                return Ok(dom);
+            }
+            Err(e) => println!("[legacy_place::cache] ignoring unreadable cache entry {}: {}", path.display(), e),
+        }
+    }
+
+    let dom = crate::load_place_with_limits(input_bytes, parse_threads, limits)?;
+    let stored = fs::create_dir_all(cache_dir)
+        .map_err(Box::<dyn Error>::from)
+        .and_then(|_| write_entry(&dom, &path, parse_threads));
+    match stored {
+        Ok(cached) => {
+            println!("[legacy_place::cache] cached parse as {}", path.display());
+            if let Err(e) = prune(cache_dir) {
+                println!("[legacy_place::cache] couldn't prune {}: {}", cache_dir.display(), e);
+            }
+            Ok(cached)
+        }
+        // nothing was cached, so later runs parse the input again and get this same dom
+        Err(e) => {
+            println!("[legacy_place::cache] couldn't cache the parse: {}", e);
+            Ok(dom)
+        }
+    }
+}
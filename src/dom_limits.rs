@@ -0,0 +1,120 @@
+// how deep and how big a place can be before anything walks it
+//
+// rbx_xml reads and writes a place one nested call per level, and python gets the tree as nested
+// dicts the same way, so a chain of instances thousands deep runs out of stack instead of
+// failing. studio never saves one, but salvaged and recovered files can stitch instances into
+// long chains, and a tree that reaches an instance twice would be walked forever. places are
+// checked before and after they're parsed, and one past a limit is an error saying which.
+use rbx_dom_weak::WeakDom;
+use rbx_dom_weak::types::Ref;
+use std::collections::HashSet;
+
+// far past anything real, the deepest studio places are a few dozen levels
+pub const DEFAULT_MAX_DEPTH: usize = 1000;
+pub const DEFAULT_MAX_INSTANCES: usize = 5_000_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DomLimits {
+    // levels below the root, the services are 1
+    pub max_depth: usize,
+    pub max_instances: usize,
+}
+
+impl Default for DomLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: DEFAULT_MAX_DEPTH,
+            max_instances: DEFAULT_MAX_INSTANCES,
+        }
+    }
+}
+
+pub struct DomShape {
+    // the root not included
+    pub instances: usize,
+    pub depth: usize,
+}
+
+// "Workspace.Map.Model", cut short, for saying where a chain went too deep
+fn short_path(dom: &WeakDom, referent: Ref, limits: &DomLimits) -> String {
+    let mut names = Vec::new();
+    let mut current = referent;
+    while current != dom.root_ref() && names.len() <= limits.max_depth {
+        let Some(instance) = dom.get_by_ref(current) else { break };
+        names.push(instance.name.as_str());
+        current = instance.parent();
+    }
+    names.reverse();
+    if names.len() > 6 {
+        format!("{}...{}", names[..3].join("."), names[names.len() - 3..].join("."))
+    } else {
+        names.join(".")
+    }
+}
+
+// walks the tree without recursing, so it's safe on the trees it's there to catch
+pub fn check_dom(dom: &WeakDom, limits: &DomLimits) -> Result<DomShape, String> {
+    let mut seen = HashSet::new();
+    let mut stack = vec![(dom.root_ref(), 0)];
+    let mut depth = 0;
+    while let Some((referent, level)) = stack.pop() {
+        let Some(instance) = dom.get_by_ref(referent) else {
+            return Err(format!("{} has a child that isn't in the place", short_path(dom, referent, limits)));
+        };
+        if !seen.insert(referent) {
+            return Err(format!(
+                "{} is reached twice walking the place, its parents loop back on themselves",
+                instance.name
+            ));
+        }
+        if seen.len() - 1 > limits.max_instances {
+            return Err(format!("the place has more than {} instances (--max-instances)", limits.max_instances));
+        }
+        if level > limits.max_depth {
+            return Err(format!(
+                "instances are nested more than {} deep at {} (--max-depth)",
+                limits.max_depth,
+                short_path(dom, referent, limits)
+            ));
+        }
+        depth = depth.max(level);
+        for &child in instance.children() {
+            if dom.get_by_ref(child).is_some_and(|child| child.parent() != referent) {
+                return Err(format!(
+                    "{} is a child of {} but has another parent",
+                    dom.get_by_ref(child).map_or("?", |child| child.name.as_str()),
+                    instance.name
+                ));
+            }
+            stack.push((child, level + 1));
+        }
+    }
+    Ok(DomShape {
+        instances: seen.len() - 1,
+        depth,
+    })
+}
+
+// how deep the <Item>s of an xml place go, read off the text before rbx_xml recurses into them
+pub fn check_xml_nesting(xml: &[u8], limits: &DomLimits) -> Result<(), String> {
+    let mut depth: usize = 0;
+    let mut rest = xml;
+    while let Some(at) = rest.iter().position(|&byte| byte == b'<') {
+        rest = &rest[at + 1..];
+        if rest.starts_with(b"Item ") || rest.starts_with(b"Item>") {
+            depth += 1;
+            if depth > limits.max_depth {
+                return Err(format!("items are nested more than {} deep (--max-depth)", limits.max_depth));
+            }
+        } else if rest.starts_with(b"/Item>") {
+            depth = depth.saturating_sub(1);
+        } else if rest.starts_with(b"![CDATA[") {
+            // script sources can hold anything
+            match rest.windows(3).position(|window| window == b"]]>") {
+                Some(end) => rest = &rest[end + 3..],
+                None => break,
+            }
+        }
+    }
+    Ok(())
+}
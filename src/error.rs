@@ -17,6 +17,9 @@ pub enum ConversionError { // mesh conversion errors, should probably make this
 
     #[error("failed to parse roblox mesh: {0}")]
     RobloxMeshParse(String),
+
+    #[error("failed to parse binary place: {0}")]
+    BinaryPlaceParse(String),
 }
 
 pub type Result<T> = std::result::Result<T, ConversionError>;
\ No newline at end of file
@@ -0,0 +1,179 @@
+// assets downloaded by id, old versions included, for archiving
+//
+// the list has one asset per line, `<id>` for the current version or `<id>@<version>` for an
+// old one, `#` starts a comment. assets come from a content provider, roblox's asset delivery
+// unless another is given (see asset_source.rs), which puts the version next to the id. files
+// are named <id>.<ext> or <id>_v<version>.<ext>, the
+// extension going by what sniff says the bytes are, and each gets a <file>.json next to it with
+// where and when it came from and its hash.
+//
+// files that are already there are left alone, so a run that died halfway can be started again.
+// a failed download is reported and the rest go on.
+use crate::asset_source::{self, AssetSource, RobloxCdn};
+use crate::content::sha256_hex;
+use crate::sniff;
+use serde::Serialize;
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub const DEFAULT_URL: &str = "https://assetdelivery.roblox.com/v1/asset/?id={id}";
+// what a dir or bucket of assets has them as, another fetch's output or the files exported from a place
+const ARCHIVED_EXTENSIONS: [&str; 11] = ["", "rbxm", "rbxmx", "mesh", "png", "jpg", "ogg", "mp3", "wav", "rbxl", "rbxlx"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FetchEntry {
+    pub id: u64,
+    pub version: Option<u64>,
+}
+
+impl FetchEntry {
+    fn stem(&self) -> String {
+        asset_source::file_stem(self.id, self.version)
+    }
+}
+
+pub fn parse_list(text: &str) -> Result<Vec<FetchEntry>, Box<dyn Error>> {
+    let mut entries = Vec::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let bad = || format!("line {}: expected <id> or <id>@<version>, got {:?}", number + 1, line);
+        let (id, version) = match line.split_once('@') {
+            Some((id, version)) => (id, Some(version.trim().parse().map_err(|_| bad())?)),
+            None => (line, None),
+        };
+        let id = id.trim().parse().map_err(|_| bad())?;
+        entries.push(FetchEntry { id, version });
+    }
+    Ok(entries)
+}
+
+pub struct FetchOptions {
+    pub source: AssetSource,
+    pub delay: Duration,
+    // download again even when the file is there
+    pub force: bool,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        Self {
+            source: AssetSource::new(RobloxCdn::new(None)),
+            delay: Duration::from_millis(250),
+            force: false,
+        }
+    }
+}
+
+// what goes in the sidecar
+#[derive(Serialize)]
+pub struct FetchedAsset {
+    pub id: u64,
+    pub version: Option<u64>,
+    pub file: String,
+    pub url: String,
+    pub fetched_at: String,
+    pub bytes: usize,
+    pub sha256: String,
+    pub kind: String,
+    pub content_type: String,
+}
+
+#[derive(Default)]
+pub struct FetchResult {
+    pub fetched: Vec<FetchedAsset>,
+    // already in the output dir
+    pub skipped: Vec<String>,
+    // entry -> error
+    pub failed: Vec<(String, String)>,
+}
+
+impl FetchResult {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (entry, error) in &self.failed {
+            let _ = writeln!(out, "failed: {} ({})", entry, error);
+        }
+        let _ = writeln!(
+            out,
+            "{} fetched, {} already there, {} failed",
+            self.fetched.len(),
+            self.skipped.len(),
+            self.failed.len()
+        );
+        out
+    }
+}
+
+fn sidecar_path(file: &Path) -> PathBuf {
+    let mut name = file.as_os_str().to_owned();
+    name.push(".json");
+    PathBuf::from(name)
+}
+
+// a file from an earlier run, found by its sidecar since the extension isn't known before
+fn existing(out_dir: &Path, stem: &str) -> Option<String> {
+    fs::read_dir(out_dir)
+        .ok()?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .find(|name| {
+            name.strip_suffix(".json")
+                .and_then(|file| file.rsplit_once('.'))
+                .is_some_and(|(file_stem, _)| file_stem == stem)
+        })
+}
+
+fn fetch_one(entry: &FetchEntry, out_dir: &Path, options: &FetchOptions) -> Result<FetchedAsset, Box<dyn Error>> {
+    let download = options.source.fetch_download(entry.id, entry.version, &ARCHIVED_EXTENSIONS)?;
+    let sniffed = sniff::sniff(&download.data);
+    let file = format!("{}.{}", entry.stem(), sniffed.extension);
+    let path = out_dir.join(&file);
+    fs::write(&path, &download.data)?;
+    let fetched = FetchedAsset {
+        id: entry.id,
+        version: entry.version,
+        file,
+        url: download.location,
+        fetched_at: chrono::Utc::now().to_rfc3339(),
+        bytes: download.data.len(),
+        sha256: sha256_hex(&download.data),
+        kind: sniffed.kind,
+        content_type: download.content_type,
+    };
+    // the sidecar last, a file without one gets downloaded again
+    fs::write(sidecar_path(&path), serde_json::to_string_pretty(&fetched)?)?;
+    Ok(fetched)
+}
+
+pub fn fetch_all(entries: &[FetchEntry], out_dir: &Path, options: &FetchOptions) -> Result<FetchResult, Box<dyn Error>> {
+    fs::create_dir_all(out_dir)?;
+    let mut result = FetchResult::default();
+    let mut first = true;
+    for entry in entries {
+        let stem = entry.stem();
+        if !options.force
+            && let Some(sidecar) = existing(out_dir, &stem)
+        {
+            result.skipped.push(sidecar.trim_end_matches(".json").to_owned());
+            continue;
+        }
+        if !first {
+            std::thread::sleep(options.delay);
+        }
+        first = false;
+        match fetch_one(entry, out_dir, options) {
+            Ok(fetched) => {
+                println!("[legacy_place::fetch] {} ({}, {} bytes)", fetched.file, fetched.kind, fetched.bytes);
+                result.fetched.push(fetched);
+            }
+            Err(e) => result.failed.push((stem, e.to_string())),
+        }
+    }
+    Ok(result)
+}
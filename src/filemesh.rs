@@ -1,41 +1,155 @@
 // https://devforum.roblox.com/t/roblox-filemesh-format-specification/326114/ 
 use crate::error::{ConversionError, Result};
-use crate::mesh_types::{FileMeshFace, FileMeshHeaderV2, FileMeshHeaderV3, FileMeshHeaderV4, FileMeshHeaderV5, FileMeshVertex, IntermediateMesh, IntermediateVertex};
+use crate::mesh_types::{
+    DEFAULT_TANGENT, FileMeshBone, FileMeshEnvelope, FileMeshFace, FileMeshHeaderV2, FileMeshHeaderV3,
+    FileMeshHeaderV4, FileMeshHeaderV5, FileMeshSubset, FileMeshVertex, IntermediateMesh, IntermediateVertex,
+    MAX_SUBSET_BONES, MeshBone, MeshFacs, MeshSkin, WHITE,
+};
 use byteorder::{LittleEndian, ReadBytesExt};
 use std::cmp::min;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::{self, Write as FmtWrite};
 use std::io::{Cursor, Read};
 
 const FILEMESH_VERTEX_SIZE_WITH_RGBA: usize = std::mem::size_of::<FileMeshVertex>();
 
-pub fn parse_filemesh(data: &[u8]) -> Result<IntermediateMesh> {
+// the "version x.xx" line and the rest of the file
+pub(crate) fn read_header(data: &[u8]) -> Result<(&str, &[u8])> {
     let newline = data
         .iter()
         .position(|&b| b == b'\n')
         .ok_or_else(|| parse_err("missing version header"))?;
 
     let mut header_bytes = &data[..newline];
-    if header_bytes.ends_with(&[b'\r']) {
+    if header_bytes.ends_with(b"\r") {
         header_bytes = &header_bytes[..header_bytes.len() - 1];
     }
 
     let version_str = std::str::from_utf8(header_bytes)
         .map_err(|_| parse_err("header is not valid UTF-8"))?
         .trim();
+    Ok((version_str, &data[newline + 1..]))
+}
+
+// the mesh with the faces of every lod level, and where each level starts
+pub(crate) fn parse_filemesh_all_lods(data: &[u8]) -> Result<(IntermediateMesh, Vec<u32>)> {
+    let (version_str, body) = read_header(data)?;
+
+    let (mesh, lod_offsets) = match version_str {
+        "version 1.00" => (parse_v1(body, true)?, Vec::new()),
+        "version 1.01" => (parse_v1(body, false)?, Vec::new()),
+        "version 2.00" => (parse_v2(body)?, Vec::new()),
+        "version 3.00" | "version 3.01" => parse_v3(body)?,
+        "version 4.00" | "version 4.01" => parse_v4(body)?,
+        "version 5.00" => parse_v5(body)?,
+        _ => {
+            return Err(ConversionError::Unsupported(format!(
+                "unsupported filemesh version: {}",
+                version_str
+            )));
+        }
+    };
+    check_face_indices(&mesh)?;
+    Ok((mesh, lod_offsets))
+}
 
-    let body = &data[newline + 1..];
-
-    match version_str {
-        "version 1.00" => parse_v1(body, true),
-        "version 1.01" => parse_v1(body, false),
-        "version 2.00" => parse_v2(body),
-        "version 3.00" | "version 3.01" => parse_v3(body),
-        "version 4.00" | "version 4.01" => parse_v4(body),
-        "version 5.00" => parse_v5(body),
-        _ => Err(ConversionError::Unsupported(format!(
-            "unsupported filemesh version: {}",
-            version_str
+fn check_face_indices(mesh: &IntermediateMesh) -> Result<()> {
+    let num_verts = mesh.vertices.len();
+    match mesh.faces.iter().position(|face| face.iter().any(|&index| index as usize >= num_verts)) {
+        Some(face) => Err(parse_err(format!(
+            "face {} uses vertices {:?}, the mesh only has {}",
+            face, mesh.faces[face], num_verts
         ))),
+        None => Ok(()),
+    }
+}
+
+// faces of each lod level, offsets are [0, end of lod 0, end of lod 1, ..]. no offsets (v1/v2)
+// or just one means the whole mesh is lod 0, and so do offsets that go backwards, which only a
+// damaged file has
+fn lod_ranges(offsets: &[u32], num_faces: usize) -> Vec<std::ops::Range<usize>> {
+    if offsets.len() < 2 || offsets.windows(2).any(|pair| pair[0] > pair[1]) {
+        return std::iter::once(0..num_faces).collect();
+    }
+    offsets
+        .windows(2)
+        .map(|pair| min(pair[0] as usize, num_faces)..min(pair[1] as usize, num_faces))
+        .collect()
+}
+
+// the highest detail level, which is what every client draws when it doesn't pick a lod
+pub fn parse_filemesh(data: &[u8]) -> Result<IntermediateMesh> {
+    let (mut mesh, lod_offsets) = parse_filemesh_all_lods(data)?;
+    let base = lod_ranges(&lod_offsets, mesh.faces.len())[0].clone();
+    mesh.faces.truncate(base.end);
+    mesh.faces.drain(..base.start);
+    Ok(mesh)
+}
+
+// every lod level as its own mesh, highest detail first. the levels share the file's vertices,
+// each one keeps only the vertices its faces use
+pub fn parse_filemesh_lods(data: &[u8]) -> Result<Vec<IntermediateMesh>> {
+    let (mesh, lod_offsets) = parse_filemesh_all_lods(data)?;
+    Ok(lod_ranges(&lod_offsets, mesh.faces.len())
+        .into_iter()
+        .filter(|range| !range.is_empty())
+        .map(|range| compact_vertices(&mesh, &mesh.faces[range]))
+        .collect())
+}
+
+// the highest detail level with all of the file's vertices, and the faces of every lower level in
+// those same vertices, the way serialize_mesh_with_lods takes them back
+pub fn parse_filemesh_with_lods(data: &[u8]) -> Result<(IntermediateMesh, Vec<Vec<[u32; 3]>>)> {
+    let (mut mesh, lod_offsets) = parse_filemesh_all_lods(data)?;
+    let ranges = lod_ranges(&lod_offsets, mesh.faces.len());
+    let lods = ranges[1..]
+        .iter()
+        .filter(|range| !range.is_empty())
+        .map(|range| mesh.faces[range.clone()].to_vec())
+        .collect();
+    mesh.faces.truncate(ranges[0].end);
+    mesh.faces.drain(..ranges[0].start);
+    Ok((mesh, lods))
+}
+
+// one lod level, 0 is the highest detail
+pub fn parse_filemesh_lod(data: &[u8], level: usize) -> Result<IntermediateMesh> {
+    let (mesh, lod_offsets) = parse_filemesh_all_lods(data)?;
+    let ranges = lod_ranges(&lod_offsets, mesh.faces.len());
+    let range = ranges.get(level).cloned().ok_or_else(|| {
+        ConversionError::Unsupported(format!("there's no lod {}, the mesh has {} level(s)", level, ranges.len()))
+    })?;
+    if range.is_empty() {
+        return Err(ConversionError::NoMeshData);
+    }
+    Ok(compact_vertices(&mesh, &mesh.faces[range]))
+}
+
+// a mesh of just these faces and the vertices they use
+pub(crate) fn compact_vertices(mesh: &IntermediateMesh, faces: &[[u32; 3]]) -> IntermediateMesh {
+    let mut remap: HashMap<u32, u32> = HashMap::new();
+    let mut used = Vec::new();
+    let faces = faces
+        .iter()
+        .map(|face| {
+            face.map(|index| {
+                *remap.entry(index).or_insert_with(|| {
+                    used.push(index as usize);
+                    used.len() as u32 - 1
+                })
+            })
+        })
+        .collect();
+    let skin = mesh.skin.as_ref().map(|skin| MeshSkin {
+        bones: skin.bones.clone(),
+        joints: used.iter().map(|&index| skin.joints[index]).collect(),
+        weights: used.iter().map(|&index| skin.weights[index]).collect(),
+    });
+    IntermediateMesh {
+        vertices: used.iter().map(|&index| mesh.vertices[index]).collect(),
+        faces,
+        skin,
+        facs: mesh.facs.clone(),
     }
 }
 
@@ -46,20 +160,218 @@ pub fn filemesh_to_obj_bytes(data: &[u8]) -> Result<Vec<u8>> {
 
 pub fn mesh_to_obj_bytes(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
     let mut output = String::new();
+    write_obj_vertices(&mut output, mesh)?;
+    for face in &mesh.faces {
+        write_obj_face(&mut output, face)?;
+    }
+    Ok(output.into_bytes())
+}
+
+// every lod level in one obj, each its own object named lod0, lod1, .. highest detail first. the
+// levels are written with their own vertices, so each one can be hidden or exported on its own
+pub fn lods_to_obj(lods: &[IntermediateMesh]) -> Result<Vec<u8>> {
+    let mut output = String::new();
+    let mut first_vertex = 0;
+    for (level, lod) in lods.iter().enumerate() {
+        fmt_ok(writeln!(&mut output, "o lod{}", level))?;
+        write_obj_vertices(&mut output, lod)?;
+        for face in &lod.faces {
+            write_obj_face(&mut output, &face.map(|index| index + first_vertex))?;
+        }
+        first_vertex += lod.vertices.len() as u32;
+    }
+    Ok(output.into_bytes())
+}
+
+// an obj and the mtl it points at, when the mesh had colors to put in one
+pub struct ObjWithMaterials {
+    pub obj: Vec<u8>,
+    pub mtl: Option<Vec<u8>>,
+    pub materials: usize,
+    // faces in regions that aren't one color, left without a material
+    pub mixed_faces: usize,
+}
+
+// like mesh_to_obj_bytes, but every connected region of the mesh that's a single vertex color
+// gets a material with that diffuse color, which dcc tools show without any setup. mtl_name is
+// the file name the obj refers to. a mesh that's all white doesn't get an mtl at all
+pub fn mesh_to_obj_with_materials(mesh: &IntermediateMesh, mtl_name: &str) -> Result<ObjWithMaterials> {
+    let colors = region_colors(mesh);
+    if colors.iter().all(|color| color.is_none_or(|color| color == WHITE)) {
+        return Ok(ObjWithMaterials {
+            obj: mesh_to_obj_bytes(mesh)?,
+            mtl: None,
+            materials: 0,
+            mixed_faces: 0,
+        });
+    }
+
+    let mut mixed = Vec::new();
+    let mut by_color: BTreeMap<[u8; 4], Vec<&[u32; 3]>> = BTreeMap::new();
+    for (face, color) in mesh.faces.iter().zip(&colors) {
+        match color {
+            Some(color) => by_color.entry(*color).or_default().push(face),
+            None => mixed.push(face),
+        }
+    }
+
+    let mut output = String::new();
+    fmt_ok(writeln!(&mut output, "mtllib {}", mtl_name))?;
+    write_obj_vertices(&mut output, mesh)?;
+    // before any usemtl so they keep the default material
+    for face in &mixed {
+        write_obj_face(&mut output, face)?;
+    }
+    let mut mtl = String::new();
+    for (color, faces) in &by_color {
+        let name = material_name(*color);
+        fmt_ok(writeln!(&mut output, "usemtl {}", name))?;
+        for face in faces {
+            write_obj_face(&mut output, face)?;
+        }
+        fmt_ok(writeln!(
+            &mut mtl,
+            "newmtl {}\nKd {:.6} {:.6} {:.6}\nd {:.6}\n",
+            name,
+            color[0] as f32 / 255.0,
+            color[1] as f32 / 255.0,
+            color[2] as f32 / 255.0,
+            color[3] as f32 / 255.0
+        ))?;
+    }
+
+    Ok(ObjWithMaterials {
+        obj: output.into_bytes(),
+        mtl: Some(mtl.into_bytes()),
+        materials: by_color.len(),
+        mixed_faces: mixed.len(),
+    })
+}
+
+// an obj of line segments, one per vertex from its position out along its normal, for seeing
+// where shading goes wrong without loading the mesh into a client
+pub struct NormalLines {
+    pub obj: Vec<u8>,
+    // zero, nan or far from unit length normals, which shade black or speckled
+    pub bad_normals: usize,
+}
 
+// segments are 5% of the bounding box diagonal, long enough to see and short enough to not cover
+// the mesh
+const NORMAL_LINE_FRACTION: f32 = 0.05;
+
+pub fn normals_to_obj(mesh: &IntermediateMesh) -> Result<NormalLines> {
+    let mut low = [f32::INFINITY; 3];
+    let mut high = [f32::NEG_INFINITY; 3];
     for vertex in &mesh.vertices {
+        for axis in 0..3 {
+            low[axis] = low[axis].min(vertex.pos[axis]);
+            high[axis] = high[axis].max(vertex.pos[axis]);
+        }
+    }
+    let diagonal = (0..3).map(|axis| (high[axis] - low[axis]).powi(2)).sum::<f32>().sqrt();
+    let length = if diagonal.is_finite() && diagonal > 0.0 { diagonal * NORMAL_LINE_FRACTION } else { 0.1 };
+
+    let mut output = String::new();
+    let mut bad_normals = 0;
+    for vertex in &mesh.vertices {
+        let [x, y, z] = vertex.pos;
+        let normal = vertex.normal;
+        let magnitude = normal.iter().map(|c| c * c).sum::<f32>().sqrt();
+        if !(0.9..=1.1).contains(&magnitude) {
+            bad_normals += 1;
+        }
+        let normal = if magnitude.is_finite() { normal } else { [0.0; 3] };
+        fmt_ok(writeln!(&mut output, "v {:.6} {:.6} {:.6}", x, y, z))?;
         fmt_ok(writeln!(
             &mut output,
             "v {:.6} {:.6} {:.6}",
+            x + normal[0] * length,
+            y + normal[1] * length,
+            z + normal[2] * length
+        ))?;
+    }
+    for index in 0..mesh.vertices.len() {
+        fmt_ok(writeln!(&mut output, "l {} {}", index * 2 + 1, index * 2 + 2))?;
+    }
+    Ok(NormalLines {
+        obj: output.into_bytes(),
+        bad_normals,
+    })
+}
+
+fn material_name(color: [u8; 4]) -> String {
+    if color[3] == 255 {
+        format!("color_{:02x}{:02x}{:02x}", color[0], color[1], color[2])
+    } else {
+        format!("color_{:02x}{:02x}{:02x}{:02x}", color[0], color[1], color[2], color[3])
+    }
+}
+
+fn find_root(parent: &mut [usize], mut index: usize) -> usize {
+    while parent[index] != index {
+        parent[index] = parent[parent[index]];
+        index = parent[index];
+    }
+    index
+}
+
+// the color of the connected region each face is in, None when the region has more than one.
+// regions are connected through shared vertices only, a color change always splits vertices so
+// parts touching at a seam stay separate
+fn region_colors(mesh: &IntermediateMesh) -> Vec<Option<[u8; 4]>> {
+    let vertex_count = mesh.vertices.len();
+    let mut parent: Vec<usize> = (0..vertex_count).collect();
+    let valid = |face: &[u32; 3]| face.iter().all(|&index| (index as usize) < vertex_count);
+    for face in mesh.faces.iter().filter(|face| valid(face)) {
+        for &other in &face[1..] {
+            let (a, b) = (find_root(&mut parent, face[0] as usize), find_root(&mut parent, other as usize));
+            parent[a] = b;
+        }
+    }
+
+    let mut colors: HashMap<usize, Option<[u8; 4]>> = HashMap::new();
+    for face in mesh.faces.iter().filter(|face| valid(face)) {
+        for &index in face {
+            let color = mesh.vertices[index as usize].color;
+            colors
+                .entry(find_root(&mut parent, index as usize))
+                .and_modify(|region| {
+                    if *region != Some(color) {
+                        *region = None;
+                    }
+                })
+                .or_insert(Some(color));
+        }
+    }
+    mesh.faces
+        .iter()
+        .map(|face| if valid(face) { colors[&find_root(&mut parent, face[0] as usize)] } else { None })
+        .collect()
+}
+
+// colored meshes get the `v x y z r g b` extension most tools read, alpha only makes it into the
+// mtl (see mesh_to_obj_with_materials)
+fn write_obj_vertices(output: &mut String, mesh: &IntermediateMesh) -> Result<()> {
+    let colored = mesh.has_vertex_colors();
+    for vertex in &mesh.vertices {
+        fmt_ok(write!(
+            output,
+            "v {:.6} {:.6} {:.6}",
             vertex.pos[0],
             vertex.pos[1],
             vertex.pos[2]
         ))?;
+        if colored {
+            let [r, g, b, _] = vertex.color.map(|channel| channel as f32 / 255.0);
+            fmt_ok(write!(output, " {:.6} {:.6} {:.6}", r, g, b))?;
+        }
+        fmt_ok(writeln!(output))?;
     }
 
     for vertex in &mesh.vertices {
         fmt_ok(writeln!(
-            &mut output,
+            output,
             "vt {:.6} {:.6}",
             vertex.uv[0],
             1.0 - vertex.uv[1]
@@ -68,7 +380,7 @@ pub fn mesh_to_obj_bytes(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
 
     for vertex in &mesh.vertices {
         fmt_ok(writeln!(
-            &mut output,
+            output,
             "vn {:.6} {:.6} {:.6}",
             vertex.normal[0],
             vertex.normal[1],
@@ -76,53 +388,122 @@ pub fn mesh_to_obj_bytes(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
         ))?;
     }
 
-    for face in &mesh.faces {
-        fmt_ok(writeln!(
-            &mut output,
-            "f {}/{}/{} {}/{}/{} {}/{}/{}",
-            face[0] + 1,
-            face[0] + 1,
-            face[0] + 1,
-            face[1] + 1,
-            face[1] + 1,
-            face[1] + 1,
-            face[2] + 1,
-            face[2] + 1,
-            face[2] + 1
-        ))?;
-    }
+    Ok(())
+}
 
-    Ok(output.into_bytes())
+fn write_obj_face(output: &mut String, face: &[u32; 3]) -> Result<()> {
+    fmt_ok(writeln!(
+        output,
+        "f {}/{}/{} {}/{}/{} {}/{}/{}",
+        face[0] + 1,
+        face[0] + 1,
+        face[0] + 1,
+        face[1] + 1,
+        face[1] + 1,
+        face[1] + 1,
+        face[2] + 1,
+        face[2] + 1,
+        face[2] + 1
+    ))
 }
 
+// 2007 era meshes come with blank lines, data split over lines, junk after it and face counts that
+// don't match, so the face count is only checked against and the mesh is every complete face of
+// vectors up to the first one that doesn't parse. what's off is printed
 fn parse_v1(body: &[u8], scale_half: bool) -> Result<IntermediateMesh> {
-    let body_str = std::str::from_utf8(body).map_err(|_| parse_err("ascii mesh is not UTF-8"))?;
-    let mut lines = body_str.lines();
+    let body = read_v1_body(body)?;
+    for problem in &body.problems {
+        println!("[legacy_place::filemesh] {}", problem);
+    }
+    let vectors: Vec<[f32; 3]> = body.vectors.iter().map(|&(vector, _, _)| vector).collect();
+    if vectors.len() < 9 {
+        return Err(parse_err("no complete face in ascii mesh"));
+    }
+    Ok(v1_mesh(&vectors, vectors.len() / 9, scale_half))
+}
 
-    let faces_line = lines
-        .next()
-        .ok_or_else(|| parse_err("missing face count"))?
-        .trim();
-    let num_faces: usize = faces_line
-        .parse()
-        .map_err(|_| parse_err("invalid face count"))?;
+// an ascii mesh after its version line
+struct V1Body {
+    // [x, y, z] with the offset and length of its brackets in the body
+    vectors: Vec<([f32; 3], usize, usize)>,
+    // where each line starts in the body
+    line_starts: Vec<usize>,
+    // anything a well formed file wouldn't have
+    problems: Vec<String>,
+}
 
-    let data_line = lines
-        .next()
-        .ok_or_else(|| parse_err("missing vertex data line"))?
-        .trim();
+impl V1Body {
+    // 1 based line and column in the file of an offset in the body, the version is line 1
+    fn locate(&self, offset: usize) -> (usize, usize) {
+        let line = self.line_starts.partition_point(|&start| start <= offset);
+        (line + 1, offset - self.line_starts[line - 1] + 1)
+    }
+}
+
+const V1_VECTOR_KINDS: [&str; 3] = ["position", "normal", "uv"];
 
-    let vectors = parse_bracket_vectors(data_line)?;
-    if vectors.len() != num_faces * 9 {
-        return Err(parse_err("unexpected vertex vector count"));
+fn describe_v1_vector(index: usize) -> String {
+    format!("vector {} (face {}, corner {}, {})", index + 1, index / 9 + 1, index % 9 / 3 + 1, V1_VECTOR_KINDS[index % 3])
+}
+
+fn read_v1_body(body: &[u8]) -> Result<V1Body> {
+    let text = std::str::from_utf8(body).map_err(|_| parse_err("ascii mesh is not UTF-8"))?;
+    let line_starts = std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(index, _)| index + 1))
+        .collect();
+    let count_start = text.len() - text.trim_start().len();
+    let count_line = text[count_start..].lines().next().unwrap_or_default();
+    // no count, the vectors start right away
+    let (face_count, data_start) = match count_line.trim().parse::<usize>() {
+        Ok(count) => (Some(count), count_start + count_line.len()),
+        Err(_) => (None, count_start),
+    };
+    let (mut vectors, failure) = bracket_vectors(&text[data_start..]);
+    for vector in &mut vectors {
+        vector.1 += data_start;
+    }
+    let mut body = V1Body { vectors, line_starts, problems: Vec::new() };
+
+    if face_count.is_none() {
+        let (line, _) = body.locate(count_start);
+        body.problems.push(format!("line {} isn't a face count, the faces are counted from the vectors", line));
+    }
+    if let Some((offset, message)) = failure {
+        let (line, column) = body.locate(data_start + offset);
+        body.problems.push(format!(
+            "{} at line {}, column {}: {}, it and everything after it are skipped",
+            describe_v1_vector(body.vectors.len()),
+            line,
+            column,
+            message
+        ));
     }
+    let (complete, left_over) = (body.vectors.len() / 9, body.vectors.len() % 9);
+    if left_over > 0 {
+        body.problems.push(format!("the last {} vector(s) don't make a whole face and are skipped", left_over));
+    }
+    if let Some(count) = face_count
+        && count != complete
+    {
+        body.problems.push(format!("the face count says {} faces, there are {}", count, complete));
+    }
+    if let (Some(first), Some(last)) = (body.vectors.first(), body.vectors.last()) {
+        let (first_line, last_line) = (body.locate(first.1).0, body.locate(last.1).0);
+        if first_line != last_line {
+            body.problems.push(format!("the vectors go over lines {}-{} instead of one", first_line, last_line));
+        }
+    }
+    Ok(body)
+}
 
+// position, normal and uv of each corner of each face
+fn v1_mesh(vectors: &[[f32; 3]], num_faces: usize, scale_half: bool) -> IntermediateMesh {
     let mut vertices = Vec::with_capacity(num_faces * 3);
     let mut faces = Vec::with_capacity(num_faces);
 
     for face_index in 0..num_faces {
         let mut face = [0u32; 3];
-        for corner in 0..3 {
+        for (corner, face_index_slot) in face.iter_mut().enumerate() {
             let base = face_index * 9 + corner * 3;
             let pos_vec = vectors[base];
             let norm_vec = vectors[base + 1];
@@ -137,16 +518,72 @@ fn parse_v1(body: &[u8], scale_half: bool) -> Result<IntermediateMesh> {
                 pos,
                 normal: norm_vec,
                 uv: [uv_vec[0], 1.0 - uv_vec[1]],
+                color: WHITE,
+                tangent: DEFAULT_TANGENT,
             };
 
             let stored_index = vertices.len() as u32;
             vertices.push(vertex);
-            face[corner] = stored_index;
+            *face_index_slot = stored_index;
         }
         faces.push(face);
     }
 
-    Ok(IntermediateMesh { vertices, faces })
+    IntermediateMesh { vertices, faces, skin: None, facs: None }
+}
+
+// where in the file each face of an ascii mesh came from, for finding what breaks a hand edited
+// or mangled one
+pub struct V1Trace {
+    // "line 3, columns 1-180 (bytes 17-196)" for each face
+    pub face_sources: Vec<String>,
+    // what parse_v1 warns about, empty for a well formed file
+    pub problems: Vec<String>,
+}
+
+// the same mesh parse_v1 reads
+pub fn trace_v1(data: &[u8]) -> Result<(IntermediateMesh, V1Trace)> {
+    let (version_str, body) = read_header(data)?;
+    let scale_half = match version_str {
+        "version 1.00" => true,
+        "version 1.01" => false,
+        _ => return Err(ConversionError::Unsupported(format!("{} isn't an ascii (version 1) mesh", version_str))),
+    };
+    let body_start = data.len() - body.len();
+    let body = read_v1_body(body)?;
+    let face_sources = body
+        .vectors
+        .chunks_exact(9)
+        .map(|face| {
+            let (start, end) = (face[0].1, face[8].1 + face[8].2 - 1);
+            let ((line, first), (end_line, last)) = (body.locate(start), body.locate(end));
+            let bytes = (body_start + start, body_start + end);
+            if line == end_line {
+                format!("line {}, columns {}-{} (bytes {}-{})", line, first, last, bytes.0, bytes.1)
+            } else {
+                format!("lines {}-{} (bytes {}-{})", line, end_line, bytes.0, bytes.1)
+            }
+        })
+        .collect();
+    let vectors: Vec<[f32; 3]> = body.vectors.iter().map(|&(vector, _, _)| vector).collect();
+    let mesh = v1_mesh(&vectors, vectors.len() / 9, scale_half);
+    Ok((mesh, V1Trace { face_sources, problems: body.problems }))
+}
+
+impl V1Trace {
+    // the mesh as an obj with a comment above each face saying where it came from
+    pub fn to_obj(&self, mesh: &IntermediateMesh) -> Result<Vec<u8>> {
+        let mut output = String::new();
+        for problem in &self.problems {
+            fmt_ok(writeln!(&mut output, "# {}", problem))?;
+        }
+        write_obj_vertices(&mut output, mesh)?;
+        for (index, (face, source)) in mesh.faces.iter().zip(&self.face_sources).enumerate() {
+            fmt_ok(writeln!(&mut output, "# face {}: {}", index + 1, source))?;
+            write_obj_face(&mut output, face)?;
+        }
+        Ok(output.into_bytes())
+    }
 }
 
 fn parse_v2(body: &[u8]) -> Result<IntermediateMesh> {
@@ -170,10 +607,10 @@ fn parse_v2(body: &[u8]) -> Result<IntermediateMesh> {
     let vertices = read_vertices(&mut cursor, num_verts as usize, has_rgba)?;
     let faces = read_faces(&mut cursor, num_faces as usize)?;
 
-    Ok(IntermediateMesh { vertices, faces })
+    Ok(IntermediateMesh { vertices, faces, skin: None, facs: None })
 }
 
-fn parse_v3(body: &[u8]) -> Result<IntermediateMesh> {
+fn parse_v3(body: &[u8]) -> Result<(IntermediateMesh, Vec<u32>)> {
     let mut cursor = Cursor::new(body);
 
     let header_size = cursor.read_u16::<LittleEndian>()?;
@@ -197,21 +634,17 @@ fn parse_v3(body: &[u8]) -> Result<IntermediateMesh> {
         _ => return Err(parse_err("unsupported v3 vertex stride")),
     };
     let vertices = read_vertices(&mut cursor, num_verts as usize, has_rgba)?;
-    let mut faces = read_faces(&mut cursor, num_faces as usize)?;
+    let faces = read_faces(&mut cursor, num_faces as usize)?;
 
     let mut lod_offsets = Vec::with_capacity(num_lod_offsets);
     for _ in 0..num_lod_offsets {
         lod_offsets.push(cursor.read_u32::<LittleEndian>()?);
     }
 
-    let base_face_count = lod_offsets.get(1).copied().unwrap_or(num_faces);
-    let base_face_count = min(base_face_count, num_faces);
-    faces.truncate(min(base_face_count as usize, faces.len()));
-
-    Ok(IntermediateMesh { vertices, faces })
+    Ok((IntermediateMesh { vertices, faces, skin: None, facs: None }, lod_offsets))
 }
 
-fn parse_v4(body: &[u8]) -> Result<IntermediateMesh> {
+fn parse_v4(body: &[u8]) -> Result<(IntermediateMesh, Vec<u32>)> {
     let mut cursor = Cursor::new(body);
 
     let header_size = cursor.read_u16::<LittleEndian>()?;
@@ -223,53 +656,48 @@ fn parse_v4(body: &[u8]) -> Result<IntermediateMesh> {
     let num_verts = cursor.read_u32::<LittleEndian>()?;
     let num_faces = cursor.read_u32::<LittleEndian>()?;
     let num_lod_offsets = cursor.read_u16::<LittleEndian>()? as usize;
-    let num_bones = cursor.read_u16::<LittleEndian>()?;
-    let sizeof_bone_names = cursor.read_u32::<LittleEndian>()?;
-    let num_subsets = cursor.read_u16::<LittleEndian>()?;
+    let num_bones = cursor.read_u16::<LittleEndian>()? as usize;
+    let sizeof_bone_names = cursor.read_u32::<LittleEndian>()? as usize;
+    let num_subsets = cursor.read_u16::<LittleEndian>()? as usize;
     let _num_high_quality_lods = cursor.read_u8()?;
     let _unused = cursor.read_u8()?;
 
-    if num_bones != 0 || sizeof_bone_names != 0 || num_subsets != 0 {
-        return Err(ConversionError::Unsupported(
-            "v4 meshes with skinning/subsets are not supported".to_string(),
-        ));
-    }
-
-    let vertex_block_bytes = {
+    // skinned meshes always use the full vertex, unskinned ones have been seen without rgba
+    let has_rgba = if num_bones > 0 {
+        true
+    } else {
         let total_len = cursor.get_ref().len();
         let current_pos = cursor.position() as usize;
         let faces_bytes = num_faces as usize * std::mem::size_of::<FileMeshFace>();
         let lod_bytes = num_lod_offsets * 4;
-        total_len
+        let trailing_bytes = sizeof_bone_names + num_subsets * std::mem::size_of::<FileMeshSubset>();
+        let vertex_block_bytes = total_len
             .checked_sub(current_pos)
-            .and_then(|remaining| remaining.checked_sub(faces_bytes + lod_bytes))
-            .ok_or_else(|| parse_err("invalid v4 vertex block size"))?
-    };
-    let sizeof_vertex = vertex_block_bytes / num_verts as usize;
-    let has_rgba = match sizeof_vertex {
-        s if s == FILEMESH_VERTEX_SIZE_WITH_RGBA => true,
-        s if s == FILEMESH_VERTEX_SIZE_WITH_RGBA - 4 => false,
-        _ => {
-            return Err(parse_err("unsupported v4 vertex stride"));
+            .and_then(|remaining| remaining.checked_sub(faces_bytes + lod_bytes + trailing_bytes))
+            .ok_or_else(|| parse_err("invalid v4 vertex block size"))?;
+        match vertex_block_bytes / (num_verts as usize).max(1) {
+            s if s == FILEMESH_VERTEX_SIZE_WITH_RGBA => true,
+            s if s == FILEMESH_VERTEX_SIZE_WITH_RGBA - 4 => false,
+            _ => {
+                return Err(parse_err("unsupported v4 vertex stride"));
+            }
         }
     };
 
-    let mut vertices = read_vertices(&mut cursor, num_verts as usize, has_rgba)?;
-    let mut faces = read_faces(&mut cursor, num_faces as usize)?;
-
-    let mut lod_offsets = Vec::with_capacity(num_lod_offsets);
-    for _ in 0..num_lod_offsets {
-        lod_offsets.push(cursor.read_u32::<LittleEndian>()?);
-    }
-
-    let base_face_count = lod_offsets.get(1).copied().unwrap_or(num_faces);
-    let base_face_count = min(base_face_count, num_faces);
-    faces.truncate(min(base_face_count as usize, faces.len()));
-
-    Ok(IntermediateMesh { vertices: vertices.drain(..).collect(), faces })
+    let (vertices, faces, lod_offsets, skin) = read_skinned_body(
+        &mut cursor,
+        num_verts as usize,
+        num_faces as usize,
+        num_lod_offsets,
+        num_bones,
+        sizeof_bone_names,
+        num_subsets,
+        has_rgba,
+    )?;
+    Ok((IntermediateMesh { vertices, faces, skin, facs: None }, lod_offsets))
 }
 
-fn parse_v5(body: &[u8]) -> Result<IntermediateMesh> {
+fn parse_v5(body: &[u8]) -> Result<(IntermediateMesh, Vec<u32>)> {
     let mut cursor = Cursor::new(body);
 
     let header_size = cursor.read_u16::<LittleEndian>()?;
@@ -281,39 +709,251 @@ fn parse_v5(body: &[u8]) -> Result<IntermediateMesh> {
     let num_verts = cursor.read_u32::<LittleEndian>()?;
     let num_faces = cursor.read_u32::<LittleEndian>()?;
     let num_lod_offsets = cursor.read_u16::<LittleEndian>()? as usize;
-    let num_bones = cursor.read_u16::<LittleEndian>()?;
-    let sizeof_bone_names = cursor.read_u32::<LittleEndian>()?;
-    let num_subsets = cursor.read_u16::<LittleEndian>()?;
+    let num_bones = cursor.read_u16::<LittleEndian>()? as usize;
+    let sizeof_bone_names = cursor.read_u32::<LittleEndian>()? as usize;
+    let num_subsets = cursor.read_u16::<LittleEndian>()? as usize;
     let _num_high_quality_lods = cursor.read_u8()?;
     let _unused = cursor.read_u8()?;
+    // the facs block comes last, after everything read_skinned_body handles
     let facs_format = cursor.read_u32::<LittleEndian>()?;
-    let facs_size = cursor.read_u32::<LittleEndian>()?;
+    let facs_size = cursor.read_u32::<LittleEndian>()? as usize;
+
+    let (vertices, faces, lod_offsets, skin) = read_skinned_body(
+        &mut cursor,
+        num_verts as usize,
+        num_faces as usize,
+        num_lod_offsets,
+        num_bones,
+        sizeof_bone_names,
+        num_subsets,
+        true,
+    )?;
+    let facs = if facs_size > 0 {
+        if facs_format != 1 {
+            return Err(parse_err(format!("unknown facs data format {}", facs_format)));
+        }
+        let facs_data = read_block(&mut cursor, facs_size)?;
+        Some(read_facs(&facs_data)?)
+    } else {
+        None
+    };
+    Ok((IntermediateMesh { vertices, faces, skin, facs }, lod_offsets))
+}
 
-    if num_bones != 0 || sizeof_bone_names != 0 || num_subsets != 0 {
-        return Err(ConversionError::Unsupported(
-            "v5 meshes with skinning/subsets are not supported".to_string(),
-        ));
+fn read_names(cursor: &mut Cursor<&[u8]>, size: usize) -> Result<Vec<String>> {
+    let buffer = read_block(cursor, size)?;
+    Ok(buffer
+        .split(|&b| b == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+fn read_facs(data: &[u8]) -> Result<MeshFacs> {
+    let mut cursor = Cursor::new(data);
+    let sizeof_bone_names = cursor.read_u32::<LittleEndian>()? as usize;
+    let sizeof_control_names = cursor.read_u32::<LittleEndian>()? as usize;
+    let _sizeof_transforms = cursor.read_u64::<LittleEndian>()?;
+    let sizeof_two_pose = cursor.read_u32::<LittleEndian>()? as usize;
+    let sizeof_three_pose = cursor.read_u32::<LittleEndian>()? as usize;
+
+    let bone_names = read_names(&mut cursor, sizeof_bone_names)?;
+    let control_names = read_names(&mut cursor, sizeof_control_names)?;
+
+    let mut transforms: [Vec<f32>; 6] = Default::default();
+    for channel in &mut transforms {
+        let version = cursor.read_u16::<LittleEndian>()?;
+        let rows = cursor.read_u32::<LittleEndian>()? as usize;
+        let cols = cursor.read_u32::<LittleEndian>()? as usize;
+        if rows != bone_names.len() || cols != control_names.len() {
+            return Err(parse_err("facs matrix size doesn't match the bone and control names"));
+        }
+        let count = rows * cols;
+        *channel = match version {
+            1 => (0..count).map(|_| cursor.read_f32::<LittleEndian>()).collect::<std::io::Result<_>>()?,
+            2 => {
+                let low = cursor.read_f32::<LittleEndian>()?;
+                let high = cursor.read_f32::<LittleEndian>()?;
+                (0..count)
+                    .map(|_| {
+                        let q = cursor.read_u16::<LittleEndian>()?;
+                        Ok(low + (high - low) * q as f32 / u16::MAX as f32)
+                    })
+                    .collect::<std::io::Result<_>>()?
+            }
+            _ => return Err(parse_err(format!("unknown facs matrix version {}", version))),
+        };
     }
 
-    if facs_format != 0 || facs_size != 0 {
-        return Err(ConversionError::Unsupported(
-            "v5 meshes with FACS data are not supported".to_string(),
-        ));
+    let correctives = read_block(&mut cursor, sizeof_two_pose.saturating_add(sizeof_three_pose))?;
+    let mut cursor = Cursor::new(correctives.as_slice());
+    let mut two_pose_correctives = Vec::with_capacity(sizeof_two_pose / 4);
+    for _ in 0..sizeof_two_pose / 4 {
+        two_pose_correctives.push([cursor.read_u16::<LittleEndian>()?, cursor.read_u16::<LittleEndian>()?]);
+    }
+    let mut three_pose_correctives = Vec::with_capacity(sizeof_three_pose / 6);
+    for _ in 0..sizeof_three_pose / 6 {
+        three_pose_correctives.push([
+            cursor.read_u16::<LittleEndian>()?,
+            cursor.read_u16::<LittleEndian>()?,
+            cursor.read_u16::<LittleEndian>()?,
+        ]);
     }
 
-    let mut vertices = read_vertices(&mut cursor, num_verts as usize, true)?;
-    let mut faces = read_faces(&mut cursor, num_faces as usize)?;
+    Ok(MeshFacs {
+        bone_names,
+        control_names,
+        two_pose_correctives,
+        three_pose_correctives,
+        transforms,
+    })
+}
+
+// everything after the v4/v5 header: vertices, envelopes, faces, lods, bones, names, subsets
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn read_skinned_body(
+    cursor: &mut Cursor<&[u8]>,
+    num_verts: usize,
+    num_faces: usize,
+    num_lod_offsets: usize,
+    num_bones: usize,
+    sizeof_bone_names: usize,
+    num_subsets: usize,
+    has_rgba: bool,
+) -> Result<(Vec<IntermediateVertex>, Vec<[u32; 3]>, Vec<u32>, Option<MeshSkin>)> {
+    let vertices = read_vertices(cursor, num_verts, has_rgba)?;
+
+    let mut envelopes = Vec::new();
+    if num_bones > 0 {
+        envelopes.reserve(num_verts);
+        for _ in 0..num_verts {
+            let mut bones = [0u8; 4];
+            let mut weights = [0u8; 4];
+            cursor.read_exact(&mut bones)?;
+            cursor.read_exact(&mut weights)?;
+            envelopes.push(FileMeshEnvelope { bones, weights });
+        }
+    }
+
+    let faces = read_faces(cursor, num_faces)?;
 
     let mut lod_offsets = Vec::with_capacity(num_lod_offsets);
     for _ in 0..num_lod_offsets {
         lod_offsets.push(cursor.read_u32::<LittleEndian>()?);
     }
 
-    let base_face_count = lod_offsets.get(1).copied().unwrap_or(num_faces);
-    let base_face_count = min(base_face_count, num_faces);
-    faces.truncate(min(base_face_count as usize, faces.len()));
+    if num_bones == 0 {
+        return Ok((vertices, faces, lod_offsets, None));
+    }
+
+    let mut file_bones = Vec::with_capacity(num_bones);
+    for _ in 0..num_bones {
+        let bone_name_index = cursor.read_u32::<LittleEndian>()?;
+        let parent_index = cursor.read_u16::<LittleEndian>()?;
+        let lod_parent_index = cursor.read_u16::<LittleEndian>()?;
+        let culling = cursor.read_f32::<LittleEndian>()?;
+        let mut rotation = [0f32; 9];
+        for value in &mut rotation {
+            *value = cursor.read_f32::<LittleEndian>()?;
+        }
+        let mut position = [0f32; 3];
+        for value in &mut position {
+            *value = cursor.read_f32::<LittleEndian>()?;
+        }
+        file_bones.push(FileMeshBone {
+            boneNameIndex: bone_name_index,
+            parentIndex: parent_index,
+            lodParentIndex: lod_parent_index,
+            culling,
+            rotation,
+            position,
+        });
+    }
+
+    let name_buffer = read_block(cursor, sizeof_bone_names)?;
+
+    let mut subsets = Vec::with_capacity(num_subsets);
+    for _ in 0..num_subsets {
+        let faces_begin = cursor.read_u32::<LittleEndian>()?;
+        let faces_length = cursor.read_u32::<LittleEndian>()?;
+        let verts_begin = cursor.read_u32::<LittleEndian>()?;
+        let verts_length = cursor.read_u32::<LittleEndian>()?;
+        let num_bone_indices = cursor.read_u32::<LittleEndian>()?;
+        let mut bone_indices = [0u16; MAX_SUBSET_BONES];
+        for index in &mut bone_indices {
+            *index = cursor.read_u16::<LittleEndian>()?;
+        }
+        subsets.push(FileMeshSubset {
+            facesBegin: faces_begin,
+            facesLength: faces_length,
+            vertsBegin: verts_begin,
+            vertsLength: verts_length,
+            numBoneIndices: num_bone_indices,
+            boneIndices: bone_indices,
+        });
+    }
+
+    let bones = file_bones
+        .iter()
+        .map(|bone| {
+            let start = bone.boneNameIndex as usize;
+            let name = name_buffer
+                .get(start..)
+                .and_then(|rest| rest.split(|&b| b == 0).next())
+                .map(|name| String::from_utf8_lossy(name).into_owned())
+                .ok_or_else(|| parse_err("bone name index out of range"))?;
+            let parent_index = bone.parentIndex;
+            let parent = match parent_index {
+                0xFFFF => None,
+                index if (index as usize) < num_bones => Some(index as usize),
+                _ => return Err(parse_err("bone parent index out of range")),
+            };
+            Ok(MeshBone {
+                name,
+                parent,
+                lod_parent: bone.lodParentIndex,
+                culling: bone.culling,
+                rotation: bone.rotation,
+                position: bone.position,
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // envelope bone indices point into the bone list of the subset the vertex belongs to
+    let mut joints = vec![[0u16; 4]; num_verts];
+    let mut weights = vec![[0f32; 4]; num_verts];
+    for subset in &subsets {
+        let begin = subset.vertsBegin as usize;
+        let end = min(begin + subset.vertsLength as usize, num_verts);
+        let subset_bones = subset.boneIndices;
+        for vertex in begin..end {
+            let envelope = envelopes[vertex];
+            let total: f32 = envelope.weights.iter().map(|&w| w as f32).sum();
+            for slot in 0..4 {
+                let local = envelope.bones[slot] as usize;
+                let global = subset_bones.get(local).copied().unwrap_or(0);
+                if global as usize >= num_bones {
+                    return Err(parse_err("envelope bone index out of range"));
+                }
+                joints[vertex][slot] = global;
+                weights[vertex][slot] = if total > 0.0 { envelope.weights[slot] as f32 / total } else { 0.0 };
+            }
+        }
+    }
 
-    Ok(IntermediateMesh { vertices: vertices.drain(..).collect(), faces })
+    Ok((vertices, faces, lod_offsets, Some(MeshSkin { bones, joints, weights })))
+}
+
+// `size` bytes from the cursor. sizes come from the header, so they're checked against what's
+// left before anything is allocated, a few bytes of header shouldn't ask for 4 GB
+fn read_block(cursor: &mut Cursor<&[u8]>, size: usize) -> Result<Vec<u8>> {
+    let left = cursor.get_ref().len().saturating_sub(cursor.position() as usize);
+    if size > left {
+        return Err(parse_err(format!("a {} byte block doesn't fit in the {} bytes left", size, left)));
+    }
+    let mut buffer = vec![0u8; size];
+    cursor.read_exact(&mut buffer)?;
+    Ok(buffer)
 }
 
 fn read_vertices(cursor: &mut Cursor<&[u8]>, count: usize, has_rgba: bool) -> Result<Vec<IntermediateVertex>> {
@@ -328,20 +968,23 @@ fn read_vertices(cursor: &mut Cursor<&[u8]>, count: usize, has_rgba: bool) -> Re
         let nz = cursor.read_f32::<LittleEndian>()?;
         let tu = cursor.read_f32::<LittleEndian>()?;
         let tv = cursor.read_f32::<LittleEndian>()?;
-        let _tx = cursor.read_i8()?;
-        let _ty = cursor.read_i8()?;
-        let _tz = cursor.read_i8()?;
-        let _ts = cursor.read_i8()?;
+        // tx ty tz ts, signed bytes
+        let mut tangent = [0u8; 4];
+        cursor.read_exact(&mut tangent)?;
+        let tangent = tangent.map(|value| value as i8 as f32 / 127.0);
 
+        let mut color = WHITE;
         if has_rgba {
-            let mut rgba = [0u8; 4];
-            cursor.read_exact(&mut rgba)?;
+            cursor.read_exact(&mut color)?;
         }
 
         vertices.push(IntermediateVertex {
             pos: [px, py, pz],
             normal: [nx, ny, nz],
-            uv: [tu, 1.0 - tv],
+            // kept top left like the writers and importers, only obj flips it
+            uv: [tu, tv],
+            color,
+            tangent,
         });
     }
 
@@ -359,37 +1002,29 @@ fn read_faces(cursor: &mut Cursor<&[u8]>, count: usize) -> Result<Vec<[u32; 3]>>
     Ok(faces)
 }
 
-fn parse_bracket_vectors(input: &str) -> Result<Vec<[f32; 3]>> {
-    let mut vectors = Vec::new();
-    let mut rest = input;
-
-    while let Some(start) = rest.find('[') {
-        let after_start = &rest[start + 1..];
-        let end_rel = after_start
-            .find(']')
-            .ok_or_else(|| parse_err("missing closing bracket in ASCII mesh"))?;
-        let end = start + 1 + end_rel;
-        let inside = &rest[start + 1..end];
-
-        let mut components = Vec::new();
-        for comp in inside.split(',') {
-            components.push(
-                comp.trim()
-                    .parse::<f32>()
-                    .map_err(|_| parse_err("invalid float in ASCII mesh"))?,
-            );
-        }
+// each [x, y, z] with the offset and length of its brackets in `input`, up to the first one that
+// doesn't parse, and where that one starts and what's wrong with it
+type BracketVectors = (Vec<([f32; 3], usize, usize)>, Option<(usize, &'static str)>);
 
-        if components.len() != 3 {
-            return Err(parse_err("expected three components per vector"));
+fn bracket_vectors(input: &str) -> BracketVectors {
+    let mut vectors = Vec::new();
+    let mut offset = 0;
+
+    while let Some(start) = input[offset..].find('[').map(|start| offset + start) {
+        let Some(end) = input[start + 1..].find(']').map(|end| start + 1 + end) else {
+            return (vectors, Some((start, "missing closing bracket in ASCII mesh")));
+        };
+        let components: std::result::Result<Vec<f32>, _> =
+            input[start + 1..end].split(',').map(|comp| comp.trim().parse::<f32>()).collect();
+        match components.as_deref() {
+            Ok(&[x, y, z]) => vectors.push(([x, y, z], start, end + 1 - start)),
+            Ok(_) => return (vectors, Some((start, "expected three components per vector"))),
+            Err(_) => return (vectors, Some((start, "invalid float in ASCII mesh"))),
         }
-
-        vectors.push([components[0], components[1], components[2]]);
-
-        rest = &rest[end + 1..];
+        offset = end + 1;
     }
 
-    Ok(vectors)
+    (vectors, None)
 }
 
 fn parse_err(message: impl Into<String>) -> ConversionError {
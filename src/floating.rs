@@ -0,0 +1,254 @@
+// unanchored parts with nothing under them, which fall as soon as the place starts
+//
+// stripping terrain or unions takes away what furniture and props were resting on, and on join
+// it all comes down at once. this is geometry only, no physics: unanchored parts in the workspace
+// are grouped into assemblies by their joints, welds and constraints, an assembly with an
+// anchored part holds itself up, and otherwise it's held up when one of its parts has some
+// collidable part under it, at most `within` studs down. boxes are the parts' world space bounds.
+// terrain isn't looked at, parts resting on it are listed too.
+use crate::path::path_of;
+use crate::pipeline::PassContext;
+use crate::world_space::{has_terrain, is_a, part_bounds};
+use rbx_dom_weak::types::{Ref, Vector3};
+use rbx_dom_weak::{Instance, WeakDom};
+use rbx_types::Variant;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as FmtWrite;
+
+// studs, parts are bucketed by the grid cells their footprint covers
+const CELL_SIZE: f32 = 32.0;
+// parts covering more cells than this are checked against everything instead
+const MAX_CELLS: i64 = 256;
+// joined in one rigid piece, or hanging off each other
+const JOINT_PROPERTIES: [(&str, [&str; 2]); 3] = [
+    ("JointInstance", ["Part0", "Part1"]),
+    ("WeldConstraint", ["Part0", "Part1"]),
+    ("Constraint", ["Attachment0", "Attachment1"]),
+];
+
+struct PartBox {
+    referent: Ref,
+    min: Vector3,
+    max: Vector3,
+    anchored: bool,
+    collides: bool,
+}
+
+fn part_box(instance: &Instance) -> Option<PartBox> {
+    let (min, max) = part_bounds(instance)?;
+    let flag = |name: &str| match instance.properties.get(&name.into()) {
+        Some(Variant::Bool(value)) => Some(*value),
+        _ => None,
+    };
+    Some(PartBox {
+        referent: instance.referent(),
+        min,
+        max,
+        anchored: flag("Anchored") == Some(true),
+        collides: flag("CanCollide") != Some(false),
+    })
+}
+
+fn find(parents: &mut [usize], mut index: usize) -> usize {
+    while parents[index] != index {
+        parents[index] = parents[parents[index]];
+        index = parents[index];
+    }
+    index
+}
+
+// the part a joint end is on, an attachment's parent for constraints
+fn joined_part(dom: &WeakDom, value: Option<&Variant>) -> Option<Ref> {
+    let Some(Variant::Ref(referent)) = value else { return None };
+    let instance = dom.get_by_ref(*referent)?;
+    if instance.class == "Attachment" {
+        Some(instance.parent())
+    } else {
+        Some(*referent)
+    }
+}
+
+// assembly index of every part, by index into `parts`
+fn assemblies(dom: &WeakDom, parts: &[PartBox]) -> Vec<usize> {
+    let index_of: HashMap<Ref, usize> = parts.iter().enumerate().map(|(index, part)| (part.referent, index)).collect();
+    let mut parents: Vec<usize> = (0..parts.len()).collect();
+    for instance in dom.descendants() {
+        let Some(ends) = JOINT_PROPERTIES
+            .iter()
+            .find(|(class, _)| is_a(&instance.class, class))
+            .map(|(_, ends)| ends)
+        else {
+            continue;
+        };
+        // keeps parts apart rather than together
+        if instance.class == "NoCollisionConstraint" {
+            continue;
+        }
+        let [a, b] = ends.map(|end| joined_part(dom, instance.properties.get(&end.into())).and_then(|part| index_of.get(&part)));
+        if let (Some(&a), Some(&b)) = (a, b) {
+            let (a, b) = (find(&mut parents, a), find(&mut parents, b));
+            parents[a] = b;
+        }
+    }
+    (0..parts.len()).map(|index| find(&mut parents, index)).collect()
+}
+
+fn cells(part: &PartBox) -> Option<Vec<(i64, i64)>> {
+    let cell = |value: f32| (value / CELL_SIZE).floor() as i64;
+    let (x0, x1, z0, z1) = (cell(part.min.x), cell(part.max.x), cell(part.min.z), cell(part.max.z));
+    if (x1 - x0 + 1) * (z1 - z0 + 1) > MAX_CELLS {
+        return None;
+    }
+    Some((x0..=x1).flat_map(|x| (z0..=z1).map(move |z| (x, z))).collect())
+}
+
+pub struct FloatingAssembly {
+    pub parts: Vec<Ref>,
+    // where the assembly is, the path of its first part
+    pub path: String,
+    // how far down the nearest thing under it is, none over the void
+    pub drop: Option<f32>,
+}
+
+pub struct FloatingReport {
+    pub within: f32,
+    pub unanchored: usize,
+    pub assemblies: Vec<FloatingAssembly>,
+    pub terrain: bool,
+}
+
+pub fn find_floating(dom: &WeakDom, within: f32) -> FloatingReport {
+    let workspace = dom
+        .root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == "Workspace"));
+    let parts: Vec<PartBox> = match workspace {
+        Some(workspace) => dom
+            .descendants_of(workspace)
+            .filter(|instance| instance.class != "Terrain" && is_a(&instance.class, "BasePart"))
+            .filter_map(part_box)
+            .collect(),
+        None => Vec::new(),
+    };
+    let assembly_of = assemblies(dom, &parts);
+
+    let mut grid: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+    let mut big = Vec::new();
+    for (index, part) in parts.iter().enumerate().filter(|(_, part)| part.collides) {
+        match cells(part) {
+            Some(cells) => cells.into_iter().for_each(|cell| grid.entry(cell).or_default().push(index)),
+            None => big.push(index),
+        }
+    }
+
+    // assembly -> (held up, its parts, nearest drop)
+    let mut by_assembly: BTreeMap<usize, (bool, Vec<usize>, Option<f32>)> = BTreeMap::new();
+    for (index, part) in parts.iter().enumerate() {
+        let entry = by_assembly.entry(assembly_of[index]).or_default();
+        entry.0 |= part.anchored;
+        entry.1.push(index);
+    }
+    let mut unanchored = 0;
+    for (_, (held, members, drop)) in by_assembly.iter_mut() {
+        if *held {
+            continue;
+        }
+        unanchored += members.len();
+        'parts: for &index in members.iter() {
+            let part = &parts[index];
+            let nearby: Vec<usize> = match cells(part) {
+                Some(cells) => cells.iter().filter_map(|cell| grid.get(cell)).flatten().copied().collect(),
+                None => grid.values().flatten().copied().collect(),
+            };
+            for other in nearby.into_iter().chain(big.iter().copied()) {
+                let below = &parts[other];
+                if assembly_of[other] == assembly_of[index]
+                    || below.max.x < part.min.x
+                    || below.min.x > part.max.x
+                    || below.max.z < part.min.z
+                    || below.min.z > part.max.z
+                    // under it means starting lower, it can reach up around the part
+                    || below.min.y > part.min.y
+                {
+                    continue;
+                }
+                let gap = (part.min.y - below.max.y).max(0.0);
+                if gap <= within {
+                    *held = true;
+                    break 'parts;
+                }
+                *drop = Some(drop.map_or(gap, |drop: f32| drop.min(gap)));
+            }
+        }
+    }
+
+    let mut floating: Vec<FloatingAssembly> = by_assembly
+        .into_values()
+        .filter(|(held, _, _)| !held)
+        .map(|(_, members, drop)| {
+            let parts: Vec<Ref> = members.iter().map(|&index| parts[index].referent).collect();
+            FloatingAssembly {
+                path: path_of(dom, parts[0]),
+                parts,
+                drop,
+            }
+        })
+        .collect();
+    floating.sort_by(|a, b| a.path.cmp(&b.path));
+    FloatingReport {
+        within,
+        unanchored,
+        assemblies: floating,
+        terrain: has_terrain(dom),
+    }
+}
+
+impl FloatingReport {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let parts: usize = self.assemblies.iter().map(|assembly| assembly.parts.len()).sum();
+        let _ = writeln!(
+            out,
+            "{} of {} unanchored parts, in {} assemblies, have nothing within {} studs under them",
+            parts,
+            self.unanchored,
+            self.assemblies.len(),
+            self.within
+        );
+        for assembly in &self.assemblies {
+            let fall = match assembly.drop {
+                Some(drop) => format!("falls {:.1} studs", drop),
+                None => "falls into the void".to_owned(),
+            };
+            let _ = writeln!(out, "  {} ({} parts) {}", assembly.path, assembly.parts.len(), fall);
+        }
+        if self.terrain {
+            let _ = writeln!(out, "the place has terrain, which wasn't looked at, some of these might be resting on it");
+        }
+        out
+    }
+}
+
+// studs, what --anchor-floating uses without a value
+pub const DEFAULT_WITHIN: f32 = 1.0;
+
+// anchors every part of the floating assemblies that are in the pass's scope
+pub fn anchor_floating(dom: &mut WeakDom, within: f32, ctx: &mut PassContext) {
+    let report = find_floating(dom, within);
+    for assembly in &report.assemblies {
+        if !ctx.in_scope(dom, assembly.parts[0]) {
+            continue;
+        }
+        for &part in &assembly.parts {
+            if let Some(instance) = dom.get_by_ref_mut(part) {
+                instance.properties.insert("Anchored".into(), Variant::Bool(true));
+            }
+        }
+        ctx.converted(assembly.parts[0], format!("anchored {} ({} parts), nothing was under it", assembly.path, assembly.parts.len()));
+    }
+    if report.terrain && !report.assemblies.is_empty() {
+        ctx.warn("the place has terrain, parts resting on it were anchored too");
+    }
+}
@@ -0,0 +1,351 @@
+// binary gltf (.glb) export, mainly so skinned v4/v5 meshes can be opened in blender with their
+// bones and weights intact. unskinned meshes come out as plain geometry.
+//
+// bones become nodes with their transform relative to the parent bone, inverse bind matrices
+// come from the mesh space bone cframes stored in the file.
+use crate::error::{ConversionError, Result};
+use crate::mesh_types::{IntermediateMesh, MeshBone};
+use serde_json::{Value, json};
+
+pub(crate) const GLB_MAGIC: &[u8; 4] = b"glTF";
+pub(crate) const CHUNK_JSON: u32 = 0x4E4F534A;
+pub(crate) const CHUNK_BIN: u32 = 0x004E4942;
+
+pub(crate) const FLOAT: u32 = 5126;
+pub(crate) const UNSIGNED_INT: u32 = 5125;
+pub(crate) const UNSIGNED_SHORT: u32 = 5123;
+pub(crate) const UNSIGNED_BYTE: u32 = 5121;
+const ARRAY_BUFFER: u32 = 34962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34963;
+
+// rigid transform, rotation is row major
+#[derive(Clone, Copy)]
+pub(crate) struct Transform {
+    pub rotation: [f32; 9],
+    pub position: [f32; 3],
+}
+
+impl Transform {
+    pub const IDENTITY: Self = Self {
+        rotation: [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0],
+        position: [0.0; 3],
+    };
+
+    pub fn of_bone(bone: &MeshBone) -> Self {
+        Self {
+            rotation: bone.rotation,
+            position: bone.position,
+        }
+    }
+
+    pub fn inverse(&self) -> Self {
+        let r = self.rotation;
+        let transposed = [r[0], r[3], r[6], r[1], r[4], r[7], r[2], r[5], r[8]];
+        let p = self.position;
+        let position = [
+            -(transposed[0] * p[0] + transposed[1] * p[1] + transposed[2] * p[2]),
+            -(transposed[3] * p[0] + transposed[4] * p[1] + transposed[5] * p[2]),
+            -(transposed[6] * p[0] + transposed[7] * p[1] + transposed[8] * p[2]),
+        ];
+        Self {
+            rotation: transposed,
+            position,
+        }
+    }
+
+    // self * other
+    pub fn then(&self, other: &Self) -> Self {
+        let (a, b) = (self.rotation, other.rotation);
+        let mut rotation = [0f32; 9];
+        for row in 0..3 {
+            for col in 0..3 {
+                rotation[row * 3 + col] = (0..3).map(|k| a[row * 3 + k] * b[k * 3 + col]).sum();
+            }
+        }
+        let p = other.position;
+        let position = [
+            a[0] * p[0] + a[1] * p[1] + a[2] * p[2] + self.position[0],
+            a[3] * p[0] + a[4] * p[1] + a[5] * p[2] + self.position[1],
+            a[6] * p[0] + a[7] * p[1] + a[8] * p[2] + self.position[2],
+        ];
+        Self { rotation, position }
+    }
+
+    // drops any scale, bones and inverse bind matrices are expected to be rigid
+    pub fn from_column_major(m: &[f32]) -> Self {
+        Self {
+            rotation: [m[0], m[4], m[8], m[1], m[5], m[9], m[2], m[6], m[10]],
+            position: [m[12], m[13], m[14]],
+        }
+    }
+
+    pub fn from_quaternion(q: [f32; 4], position: [f32; 3]) -> Self {
+        let [x, y, z, w] = q;
+        Self {
+            rotation: [
+                1.0 - 2.0 * (y * y + z * z), 2.0 * (x * y - z * w), 2.0 * (x * z + y * w),
+                2.0 * (x * y + z * w), 1.0 - 2.0 * (x * x + z * z), 2.0 * (y * z - x * w),
+                2.0 * (x * z - y * w), 2.0 * (y * z + x * w), 1.0 - 2.0 * (x * x + y * y),
+            ],
+            position,
+        }
+    }
+
+    // a node's local transform, either a matrix or translation/rotation
+    pub fn of_node(node: &Value) -> Self {
+        let floats = |key: &str| -> Option<Vec<f32>> {
+            node.get(key)?.as_array()?.iter().map(|v| v.as_f64().map(|v| v as f32)).collect()
+        };
+        if let Some(matrix) = floats("matrix").filter(|m| m.len() == 16) {
+            return Self::from_column_major(&matrix);
+        }
+        let position = match floats("translation").as_deref() {
+            Some(&[x, y, z]) => [x, y, z],
+            _ => [0.0; 3],
+        };
+        let rotation = match floats("rotation").as_deref() {
+            Some(&[x, y, z, w]) => [x, y, z, w],
+            _ => [0.0, 0.0, 0.0, 1.0],
+        };
+        Self::from_quaternion(rotation, position)
+    }
+
+    // gltf wants column major 4x4
+    pub fn to_column_major(self) -> [f32; 16] {
+        let r = self.rotation;
+        let p = self.position;
+        [
+            r[0], r[3], r[6], 0.0,
+            r[1], r[4], r[7], 0.0,
+            r[2], r[5], r[8], 0.0,
+            p[0], p[1], p[2], 1.0,
+        ]
+    }
+
+    // (x, y, z, w)
+    pub fn quaternion(&self) -> [f32; 4] {
+        let m = self.rotation;
+        let trace = m[0] + m[4] + m[8];
+        let q = if trace > 0.0 {
+            let s = (trace + 1.0).sqrt() * 2.0;
+            [(m[7] - m[5]) / s, (m[2] - m[6]) / s, (m[3] - m[1]) / s, 0.25 * s]
+        } else if m[0] > m[4] && m[0] > m[8] {
+            let s = (1.0 + m[0] - m[4] - m[8]).sqrt() * 2.0;
+            [0.25 * s, (m[1] + m[3]) / s, (m[2] + m[6]) / s, (m[7] - m[5]) / s]
+        } else if m[4] > m[8] {
+            let s = (1.0 + m[4] - m[0] - m[8]).sqrt() * 2.0;
+            [(m[1] + m[3]) / s, 0.25 * s, (m[5] + m[7]) / s, (m[2] - m[6]) / s]
+        } else {
+            let s = (1.0 + m[8] - m[0] - m[4]).sqrt() * 2.0;
+            [(m[2] + m[6]) / s, (m[5] + m[7]) / s, 0.25 * s, (m[3] - m[1]) / s]
+        };
+        let length = q.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if length == 0.0 {
+            return [0.0, 0.0, 0.0, 1.0];
+        }
+        q.map(|v| v / length)
+    }
+}
+
+// glb chunks and buffer views have to start on 4 byte boundaries
+fn pad_to_4(bytes: &mut Vec<u8>, fill: u8) {
+    bytes.resize(bytes.len().next_multiple_of(4), fill);
+}
+
+// collects the binary chunk and the views/accessors pointing into it
+#[derive(Default)]
+pub(crate) struct GlbBuilder {
+    pub buffer: Vec<u8>,
+    pub views: Vec<Value>,
+    pub accessors: Vec<Value>,
+}
+
+impl GlbBuilder {
+    fn push_view(&mut self, bytes: &[u8], target: Option<u32>) -> usize {
+        pad_to_4(&mut self.buffer, 0);
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.buffer.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some(target) = target {
+            view["target"] = json!(target);
+        }
+        self.buffer.extend_from_slice(bytes);
+        self.views.push(view);
+        self.views.len() - 1
+    }
+
+    // returns the accessor index
+    pub fn push_floats(&mut self, values: &[f32], kind: &str, components: usize, with_bounds: bool, target: Option<u32>) -> usize {
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, target);
+        let mut accessor = json!({
+            "bufferView": view,
+            "componentType": FLOAT,
+            "count": values.len() / components,
+            "type": kind,
+        });
+        if with_bounds && !values.is_empty() {
+            let mut min = vec![f32::MAX; components];
+            let mut max = vec![f32::MIN; components];
+            for item in values.chunks_exact(components) {
+                for (i, &v) in item.iter().enumerate() {
+                    min[i] = min[i].min(v);
+                    max[i] = max[i].max(v);
+                }
+            }
+            accessor["min"] = json!(min);
+            accessor["max"] = json!(max);
+        }
+        self.accessors.push(accessor);
+        self.accessors.len() - 1
+    }
+
+    pub fn push_indices(&mut self, values: &[u32]) -> usize {
+        let bytes: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(ELEMENT_ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_INT,
+            "count": values.len(),
+            "type": "SCALAR",
+        }));
+        self.accessors.len() - 1
+    }
+
+    pub fn push_joints(&mut self, values: &[[u16; 4]]) -> usize {
+        let bytes: Vec<u8> = values.iter().flatten().flat_map(|v| v.to_le_bytes()).collect();
+        let view = self.push_view(&bytes, Some(ARRAY_BUFFER));
+        self.accessors.push(json!({
+            "bufferView": view,
+            "componentType": UNSIGNED_SHORT,
+            "count": values.len(),
+            "type": "VEC4",
+        }));
+        self.accessors.len() - 1
+    }
+
+    pub fn finish(mut self, mut document: Value) -> Result<Vec<u8>> {
+        pad_to_4(&mut self.buffer, 0);
+        document["asset"] = json!({ "version": "2.0", "generator": "roblox_utils" });
+        document["buffers"] = json!([{ "byteLength": self.buffer.len() }]);
+        document["bufferViews"] = Value::Array(self.views);
+        document["accessors"] = Value::Array(self.accessors);
+        let mut json_bytes = serde_json::to_vec(&document)
+            .map_err(|e| ConversionError::Unsupported(format!("failed to write gltf json: {}", e)))?;
+        pad_to_4(&mut json_bytes, b' ');
+
+        let total = 12 + 8 + json_bytes.len() + 8 + self.buffer.len();
+        let mut out = Vec::with_capacity(total);
+        out.extend_from_slice(GLB_MAGIC);
+        out.extend_from_slice(&2u32.to_le_bytes());
+        out.extend_from_slice(&(total as u32).to_le_bytes());
+        out.extend_from_slice(&(json_bytes.len() as u32).to_le_bytes());
+        out.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+        out.extend_from_slice(&json_bytes);
+        out.extend_from_slice(&(self.buffer.len() as u32).to_le_bytes());
+        out.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        out.extend_from_slice(&self.buffer);
+        Ok(out)
+    }
+}
+
+// filemesh colors are srgb bytes, gltf ones are linear (see importer::vertex_color)
+fn linear_color(color: [u8; 4]) -> [f32; 4] {
+    let linear = |byte: u8| {
+        let value = byte as f32 / 255.0;
+        if value <= 0.04045 { value / 12.92 } else { ((value + 0.055) / 1.055).powf(2.4) }
+    };
+    [linear(color[0]), linear(color[1]), linear(color[2]), color[3] as f32 / 255.0]
+}
+
+pub fn mesh_to_glb(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
+    let mut glb = GlbBuilder::default();
+
+    let positions: Vec<f32> = mesh.vertices.iter().flat_map(|v| v.pos).collect();
+    let normals: Vec<f32> = mesh.vertices.iter().flat_map(|v| v.normal).collect();
+    // gltf and roblox both put the uv origin top left
+    let uvs: Vec<f32> = mesh.vertices.iter().flat_map(|v| v.uv).collect();
+    let indices: Vec<u32> = mesh.faces.iter().flatten().copied().collect();
+
+    let position_accessor = glb.push_floats(&positions, "VEC3", 3, true, Some(ARRAY_BUFFER));
+    let normal_accessor = glb.push_floats(&normals, "VEC3", 3, false, Some(ARRAY_BUFFER));
+    let uv_accessor = glb.push_floats(&uvs, "VEC2", 2, false, Some(ARRAY_BUFFER));
+    let index_accessor = glb.push_indices(&indices);
+
+    let mut attributes = json!({
+        "POSITION": position_accessor,
+        "NORMAL": normal_accessor,
+        "TEXCOORD_0": uv_accessor,
+    });
+    if mesh.has_vertex_colors() {
+        let colors: Vec<f32> = mesh.vertices.iter().flat_map(|v| linear_color(v.color)).collect();
+        attributes["COLOR_0"] = json!(glb.push_floats(&colors, "VEC4", 4, false, Some(ARRAY_BUFFER)));
+    }
+    let mut mesh_node = json!({ "name": "Mesh", "mesh": 0 });
+    let mut nodes = Vec::new();
+    let mut scene_nodes = vec![0];
+    let mut document = json!({});
+
+    if let Some(skin) = &mesh.skin {
+        attributes["JOINTS_0"] = json!(glb.push_joints(&skin.joints));
+        let weights: Vec<f32> = skin.weights.iter().flatten().copied().collect();
+        attributes["WEIGHTS_0"] = json!(glb.push_floats(&weights, "VEC4", 4, false, Some(ARRAY_BUFFER)));
+
+        let inverse_binds: Vec<f32> = skin
+            .bones
+            .iter()
+            .flat_map(|bone| Transform::of_bone(bone).inverse().to_column_major())
+            .collect();
+        let inverse_bind_accessor = glb.push_floats(&inverse_binds, "MAT4", 16, false, None);
+
+        // node 0 is the mesh, bone i is node i + 1
+        let mut bone_nodes: Vec<Value> = skin
+            .bones
+            .iter()
+            .map(|bone| {
+                let world = Transform::of_bone(bone);
+                let local = match bone.parent {
+                    Some(parent) => Transform::of_bone(&skin.bones[parent]).inverse().then(&world),
+                    None => world,
+                };
+                json!({
+                    "name": bone.name,
+                    "translation": local.position,
+                    "rotation": local.quaternion(),
+                })
+            })
+            .collect();
+        for (index, bone) in skin.bones.iter().enumerate() {
+            match bone.parent {
+                Some(parent) => {
+                    let children = bone_nodes[parent]
+                        .as_object_mut()
+                        .map(|node| node.entry("children").or_insert_with(|| json!([])));
+                    if let Some(Value::Array(children)) = children {
+                        children.push(json!(index + 1));
+                    }
+                }
+                None => scene_nodes.push(index + 1),
+            }
+        }
+        nodes.append(&mut bone_nodes);
+
+        mesh_node["skin"] = json!(0);
+        document["skins"] = json!([{
+            "joints": (1..=skin.bones.len()).collect::<Vec<_>>(),
+            "inverseBindMatrices": inverse_bind_accessor,
+        }]);
+    }
+
+    nodes.insert(0, mesh_node);
+    document["meshes"] = json!([{
+        "name": "Mesh",
+        "primitives": [{ "attributes": attributes, "indices": index_accessor, "mode": 4 }],
+    }]);
+    document["nodes"] = Value::Array(nodes);
+    document["scenes"] = json!([{ "nodes": scene_nodes }]);
+    document["scene"] = json!(0);
+    glb.finish(document)
+}
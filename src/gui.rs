@@ -0,0 +1,397 @@
+// minimal desktop frontend, built with `cargo build --features gui --bin roblox_utils_gui`
+//
+// drop a mesh or place on the window (or type a path), pick options, hit convert. the result is
+// written next to the input and everything the conversion reported shows up underneath.
+use crate::binary_compat::BinaryCompat;
+use crate::pipeline::ConversionObserver;
+use crate::presets::Preset;
+use crate::tags::TagConversion;
+use crate::xml_compat::XmlCompat;
+use crate::{FixPlaceOptions, OutputFormat, RobloxMeshVersion};
+use clap::ValueEnum;
+use eframe::egui;
+use rbx_dom_weak::types::Ref;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::thread;
+use std::time::Instant;
+
+enum Event {
+    Converted(String),
+    Warning(String),
+    Info(String),
+    PassComplete(String, usize),
+    Finished(Result<PathBuf, String>),
+}
+
+// forwards observer hooks to the ui thread
+struct ChannelObserver(Sender<Event>);
+
+impl ConversionObserver for ChannelObserver {
+    fn on_instance_converted(&mut self, _pass: &str, _referent: Ref, message: &str) {
+        let _ = self.0.send(Event::Converted(message.to_owned()));
+    }
+
+    fn on_warning(&mut self, _pass: &str, message: &str) {
+        let _ = self.0.send(Event::Warning(message.to_owned()));
+    }
+
+    fn on_info(&mut self, _pass: &str, message: &str) {
+        let _ = self.0.send(Event::Info(message.to_owned()));
+    }
+
+    fn on_pass_complete(&mut self, pass: &str, changed: usize) {
+        let _ = self.0.send(Event::PassComplete(pass.to_owned(), changed));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum InputKind {
+    Obj,
+    Gltf,
+    Filemesh,
+    Place,
+}
+
+impl InputKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension()?.to_string_lossy().to_ascii_lowercase();
+        match extension.as_str() {
+            "obj" => Some(Self::Obj),
+            "glb" => Some(Self::Gltf),
+            "mesh" => Some(Self::Filemesh),
+            "rbxl" | "rbxlx" | "rbxm" | "rbxmx" => Some(Self::Place),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone)]
+struct PlaceSettings {
+    preset: Option<Preset>,
+    output_format: OutputFormat,
+    folders_to_models: bool,
+    convert_meshparts: bool,
+    legacy_size_grid: bool,
+    inject_leaderstats: bool,
+    convert_assetid_to_url: bool,
+    tag_conversion: Option<TagConversion>,
+    xml_compat: Option<XmlCompat>,
+    binary_compat: Option<BinaryCompat>,
+    expand_shared_strings: bool,
+    verify: bool,
+    stable_output: bool,
+}
+
+impl Default for PlaceSettings {
+    fn default() -> Self {
+        Self {
+            preset: None,
+            output_format: OutputFormat::SameAsInput,
+            folders_to_models: false,
+            convert_meshparts: false,
+            legacy_size_grid: false,
+            inject_leaderstats: false,
+            convert_assetid_to_url: false,
+            tag_conversion: None,
+            xml_compat: None,
+            binary_compat: None,
+            expand_shared_strings: false,
+            verify: false,
+            stable_output: false,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Report {
+    lines: Vec<(bool, String)>,
+    warnings: usize,
+    passes: Vec<(String, usize)>,
+    result: Option<Result<PathBuf, String>>,
+    started: Option<Instant>,
+    elapsed_ms: u128,
+}
+
+struct App {
+    input: String,
+    mesh_version: RobloxMeshVersion,
+    // filemesh input only: re-save as another version instead of exporting an obj
+    filemesh_to_filemesh: bool,
+    place: PlaceSettings,
+    report: Report,
+    events: Option<Receiver<Event>>,
+}
+
+impl Default for App {
+    fn default() -> Self {
+        Self {
+            input: String::new(),
+            mesh_version: RobloxMeshVersion::V2_00,
+            filemesh_to_filemesh: false,
+            place: PlaceSettings::default(),
+            report: Report::default(),
+            events: None,
+        }
+    }
+}
+
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|v| v.get_name().to_owned())
+        .unwrap_or_default()
+}
+
+// a combo box over a clap value enum with a "none" entry
+fn optional_enum<T: ValueEnum + Clone + PartialEq>(ui: &mut egui::Ui, label: &str, value: &mut Option<T>) {
+    let selected = value.as_ref().map(value_name).unwrap_or_else(|| "none".to_owned());
+    egui::ComboBox::from_label(label).selected_text(selected).show_ui(ui, |ui| {
+        ui.selectable_value(value, None, "none");
+        for variant in T::value_variants() {
+            ui.selectable_value(value, Some(variant.clone()), value_name(variant));
+        }
+    });
+}
+
+fn output_path(input: &Path, kind: InputKind, settings: &PlaceSettings, to_filemesh: bool) -> PathBuf {
+    let stem = input.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = match kind {
+        InputKind::Obj | InputKind::Gltf => "mesh".to_owned(),
+        InputKind::Filemesh if to_filemesh => "mesh".to_owned(),
+        InputKind::Filemesh => "obj".to_owned(),
+        InputKind::Place => {
+            let input_extension = input.extension().unwrap_or_default().to_string_lossy().to_ascii_lowercase();
+            let is_model = input_extension.starts_with("rbxm");
+            let xml = match settings.output_format {
+                OutputFormat::SameAsInput => input_extension.ends_with('x'),
+                OutputFormat::Xml => true,
+                OutputFormat::Binary => false,
+            };
+            format!("{}{}", if is_model { "rbxm" } else { "rbxl" }, if xml { "x" } else { "" })
+        }
+    };
+    input.with_file_name(format!("{}_converted.{}", stem, extension))
+}
+
+fn convert(
+    input: &Path,
+    output: &Path,
+    kind: InputKind,
+    version: RobloxMeshVersion,
+    to_filemesh: bool,
+    settings: PlaceSettings,
+    events: Sender<Event>,
+) -> Result<(), Box<dyn Error>> {
+    let data = fs::read(input)?;
+    let bytes = match kind {
+        InputKind::Obj => crate::convert_obj_to_filemesh(&data, version)?,
+        InputKind::Gltf => crate::convert_gltf_to_filemesh(&data, version)?,
+        InputKind::Filemesh if to_filemesh => crate::convert_filemesh_to_filemesh(&data, version)?,
+        InputKind::Filemesh => crate::convert_filemesh_to_obj(&data)?,
+        InputKind::Place => {
+            let mut options = FixPlaceOptions::new()
+                .output_format(settings.output_format)
+                .folders_to_models(settings.folders_to_models)
+                .convert_meshparts(settings.convert_meshparts)
+                .legacy_size_grid(settings.legacy_size_grid)
+                .inject_leaderstats(settings.inject_leaderstats)
+                .convert_assetid_to_url(settings.convert_assetid_to_url)
+                .tag_conversion(settings.tag_conversion)
+                .xml_compat(settings.xml_compat)
+                .binary_compat(settings.binary_compat)
+                .expand_shared_strings(settings.expand_shared_strings)
+                .verify(settings.verify)
+                .stable_output(settings.stable_output)
+                .observer(ChannelObserver(events));
+            if let Some(preset) = settings.preset {
+                options = options.preset(preset);
+            }
+            crate::fix_place(&data, options)?
+        }
+    };
+    fs::write(output, bytes)?;
+    Ok(())
+}
+
+impl App {
+    fn start(&mut self, ctx: &egui::Context) {
+        let input = PathBuf::from(self.input.trim());
+        let Some(kind) = InputKind::from_path(&input) else { return };
+        let output = output_path(&input, kind, &self.place, self.filemesh_to_filemesh);
+        let (sender, receiver) = mpsc::channel();
+        let (version, to_filemesh, settings) = (self.mesh_version, self.filemesh_to_filemesh, self.place.clone());
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let observer = sender.clone();
+            let result = convert(&input, &output, kind, version, to_filemesh, settings, observer)
+                .map(|_| output)
+                .map_err(|e| e.to_string());
+            let _ = sender.send(Event::Finished(result));
+            ctx.request_repaint();
+        });
+        self.report = Report {
+            started: Some(Instant::now()),
+            ..Report::default()
+        };
+        self.events = Some(receiver);
+    }
+
+    fn poll_events(&mut self) {
+        let Some(events) = &self.events else { return };
+        let mut finished = false;
+        for event in events.try_iter() {
+            match event {
+                Event::Converted(line) | Event::Info(line) => self.report.lines.push((false, line)),
+                Event::Warning(line) => {
+                    self.report.warnings += 1;
+                    self.report.lines.push((true, line));
+                }
+                Event::PassComplete(pass, changed) => self.report.passes.push((pass, changed)),
+                Event::Finished(result) => {
+                    self.report.elapsed_ms = self.report.started.map_or(0, |start| start.elapsed().as_millis());
+                    self.report.result = Some(result);
+                    finished = true;
+                }
+            }
+        }
+        if finished {
+            self.events = None;
+        }
+    }
+
+    fn options_ui(&mut self, ui: &mut egui::Ui, kind: InputKind) {
+        match kind {
+            InputKind::Obj | InputKind::Gltf => {
+                mesh_version_ui(ui, &mut self.mesh_version);
+            }
+            InputKind::Filemesh => {
+                ui.checkbox(&mut self.filemesh_to_filemesh, "convert to another mesh version instead of obj");
+                if self.filemesh_to_filemesh {
+                    mesh_version_ui(ui, &mut self.mesh_version);
+                }
+            }
+            InputKind::Place => {
+                let place = &mut self.place;
+                optional_enum(ui, "preset", &mut place.preset);
+                egui::ComboBox::from_label("output format")
+                    .selected_text(format!("{:?}", place.output_format))
+                    .show_ui(ui, |ui| {
+                        for format in [OutputFormat::SameAsInput, OutputFormat::Xml, OutputFormat::Binary] {
+                            ui.selectable_value(&mut place.output_format, format, format!("{:?}", format));
+                        }
+                    });
+                ui.checkbox(&mut place.folders_to_models, "folders to models");
+                ui.checkbox(&mut place.convert_meshparts, "meshparts to specialmeshes");
+                ui.checkbox(&mut place.legacy_size_grid, "snap to legacy size grid");
+                ui.checkbox(&mut place.inject_leaderstats, "add classic leaderboard");
+                ui.checkbox(&mut place.convert_assetid_to_url, "asset ids to urls");
+                optional_enum(ui, "tags", &mut place.tag_conversion);
+                optional_enum(ui, "xml compat", &mut place.xml_compat);
+                optional_enum(ui, "binary compat", &mut place.binary_compat);
+                ui.checkbox(&mut place.expand_shared_strings, "expand shared strings");
+                ui.checkbox(&mut place.verify, "verify output");
+                ui.checkbox(&mut place.stable_output, "stable output");
+            }
+        }
+    }
+
+    fn report_ui(&self, ui: &mut egui::Ui) {
+        let report = &self.report;
+        match &report.result {
+            None if self.events.is_some() => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label("converting...");
+                });
+            }
+            None => return,
+            Some(Ok(output)) => {
+                ui.label(format!("done in {} ms, saved to {}", report.elapsed_ms, output.display()));
+            }
+            Some(Err(error)) => {
+                ui.colored_label(ui.visuals().error_fg_color, format!("failed: {}", error));
+            }
+        }
+        if !report.passes.is_empty() {
+            let changed: Vec<_> = report
+                .passes
+                .iter()
+                .filter(|(_, changed)| *changed > 0)
+                .map(|(pass, changed)| format!("{} {}", pass, changed))
+                .collect();
+            ui.label(format!("{} warning(s), changed: {}", report.warnings, changed.join(", ")));
+        }
+        egui::ScrollArea::vertical().stick_to_bottom(true).show(ui, |ui| {
+            for (is_warning, line) in &report.lines {
+                if *is_warning {
+                    ui.colored_label(ui.visuals().warn_fg_color, line);
+                } else {
+                    ui.label(line);
+                }
+            }
+        });
+    }
+}
+
+fn mesh_version_ui(ui: &mut egui::Ui, version: &mut RobloxMeshVersion) {
+    egui::ComboBox::from_label("mesh version")
+        .selected_text(value_name(version))
+        .show_ui(ui, |ui| {
+            for variant in RobloxMeshVersion::value_variants() {
+                ui.selectable_value(version, *variant, value_name(variant));
+            }
+        });
+}
+
+impl eframe::App for App {
+    fn ui(&mut self, ui: &mut egui::Ui, _frame: &mut eframe::Frame) {
+        self.poll_events();
+        let ctx = ui.ctx().clone();
+        let dropped = ctx.input(|i| i.raw.dropped_files.first().map(|file| file.path().to_owned()));
+        if let Some(path) = dropped {
+            self.input = path.display().to_string();
+        }
+
+        egui::CentralPanel::default().show(ui, |ui| {
+            ui.heading("roblox utils");
+            ui.horizontal(|ui| {
+                ui.label("input");
+                ui.text_edit_singleline(&mut self.input);
+            });
+            let kind = InputKind::from_path(Path::new(self.input.trim()));
+            match kind {
+                None if self.input.trim().is_empty() => {
+                    ui.label("drop an .obj, .glb, .mesh, .rbxl(x) or .rbxm(x) file here");
+                }
+                None => {
+                    ui.label("unsupported file type");
+                }
+                Some(kind) => {
+                    self.options_ui(ui, kind);
+                    let running = self.events.is_some();
+                    if ui.add_enabled(!running, egui::Button::new("convert")).clicked() {
+                        self.start(&ctx);
+                    }
+                }
+            }
+            ui.separator();
+            self.report_ui(ui);
+        });
+        if self.events.is_some() {
+            ctx.request_repaint();
+        }
+    }
+}
+
+pub fn run() -> eframe::Result {
+    let options = eframe::NativeOptions {
+        viewport: egui::ViewportBuilder::default()
+            .with_inner_size([520.0, 640.0])
+            .with_drag_and_drop(true),
+        ..Default::default()
+    };
+    eframe::run_native("roblox utils", options, Box::new(|_cc| Ok(Box::new(App::default()))))
+}
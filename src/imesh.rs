@@ -0,0 +1,309 @@
+// .imesh, an IntermediateMesh exactly as it is in memory, for parsing a source mesh once
+//
+// filemeshes round tangents and bone weights to bytes and obj drops skins, so neither can stand in
+// for a parsed mesh that's about to be written several ways. an imesh is "IMESH" and a format
+// version, then zstd over every field little endian: vertices, faces, the skin if there is one
+// and the facs data if there is one. counts are u32, strings a u32 length and utf-8.
+//
+// load_obj_cached keeps imported objs in a dir keyed by the file's sha256 and the tool version
+// like dom_cache does for places, so scripts writing v2, v4 and gltf from one obj only import it
+// the first time. a bad or unreadable entry is ignored and the obj is imported again.
+use crate::content::sha256_hex;
+use crate::error::{ConversionError, Result};
+use crate::importer::{self, ImportCleanup};
+use crate::mesh_types::{IntermediateMesh, IntermediateVertex, MeshBone, MeshFacs, MeshSkin};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fs;
+use std::io::{Cursor, Read, Write};
+use std::path::{Path, PathBuf};
+
+pub const IMESH_MAGIC: &[u8; 5] = b"IMESH";
+const FORMAT_VERSION: u32 = 1;
+const ZSTD_LEVEL: i32 = 3;
+const ENTRY_EXTENSION: &str = "imesh";
+// oldest entries past this are removed
+const MAX_ENTRIES: usize = 64;
+
+fn parse_err(message: impl Into<String>) -> ConversionError {
+    ConversionError::RobloxMeshParse(format!("imesh: {}", message.into()))
+}
+
+fn write_f32s(out: &mut Vec<u8>, values: &[f32]) -> Result<()> {
+    for &value in values {
+        out.write_f32::<LittleEndian>(value)?;
+    }
+    Ok(())
+}
+
+fn write_count(out: &mut Vec<u8>, count: usize) -> Result<()> {
+    let count = u32::try_from(count).map_err(|_| ConversionError::Unsupported("imesh: too many items".to_owned()))?;
+    out.write_u32::<LittleEndian>(count)?;
+    Ok(())
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) -> Result<()> {
+    write_count(out, value.len())?;
+    out.write_all(value.as_bytes())?;
+    Ok(())
+}
+
+fn write_strings(out: &mut Vec<u8>, values: &[String]) -> Result<()> {
+    write_count(out, values.len())?;
+    values.iter().try_for_each(|value| write_string(out, value))
+}
+
+fn write_skin(out: &mut Vec<u8>, skin: &MeshSkin) -> Result<()> {
+    write_count(out, skin.bones.len())?;
+    for bone in &skin.bones {
+        write_string(out, &bone.name)?;
+        out.write_i32::<LittleEndian>(bone.parent.map_or(-1, |parent| parent as i32))?;
+        out.write_u16::<LittleEndian>(bone.lod_parent)?;
+        out.write_f32::<LittleEndian>(bone.culling)?;
+        write_f32s(out, &bone.rotation)?;
+        write_f32s(out, &bone.position)?;
+    }
+    write_count(out, skin.joints.len())?;
+    for joints in &skin.joints {
+        for &joint in joints {
+            out.write_u16::<LittleEndian>(joint)?;
+        }
+    }
+    write_count(out, skin.weights.len())?;
+    for weights in &skin.weights {
+        write_f32s(out, weights)?;
+    }
+    Ok(())
+}
+
+fn write_facs(out: &mut Vec<u8>, facs: &MeshFacs) -> Result<()> {
+    write_strings(out, &facs.bone_names)?;
+    write_strings(out, &facs.control_names)?;
+    write_count(out, facs.two_pose_correctives.len())?;
+    for pair in &facs.two_pose_correctives {
+        pair.iter().try_for_each(|&control| out.write_u16::<LittleEndian>(control))?;
+    }
+    write_count(out, facs.three_pose_correctives.len())?;
+    for triple in &facs.three_pose_correctives {
+        triple.iter().try_for_each(|&control| out.write_u16::<LittleEndian>(control))?;
+    }
+    for channel in &facs.transforms {
+        write_count(out, channel.len())?;
+        write_f32s(out, channel)?;
+    }
+    Ok(())
+}
+
+pub fn write_imesh(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
+    let mut body = Vec::new();
+    write_count(&mut body, mesh.vertices.len())?;
+    for vertex in &mesh.vertices {
+        write_f32s(&mut body, &vertex.pos)?;
+        write_f32s(&mut body, &vertex.normal)?;
+        write_f32s(&mut body, &vertex.uv)?;
+        body.write_all(&vertex.color)?;
+        write_f32s(&mut body, &vertex.tangent)?;
+    }
+    write_count(&mut body, mesh.faces.len())?;
+    for face in &mesh.faces {
+        face.iter().try_for_each(|&index| body.write_u32::<LittleEndian>(index))?;
+    }
+    body.write_u8(mesh.skin.is_some() as u8)?;
+    if let Some(skin) = &mesh.skin {
+        write_skin(&mut body, skin)?;
+    }
+    body.write_u8(mesh.facs.is_some() as u8)?;
+    if let Some(facs) = &mesh.facs {
+        write_facs(&mut body, facs)?;
+    }
+
+    let mut out = IMESH_MAGIC.to_vec();
+    out.write_u32::<LittleEndian>(FORMAT_VERSION)?;
+    out.extend(zstd::stream::encode_all(body.as_slice(), ZSTD_LEVEL)?);
+    Ok(out)
+}
+
+// a count of records `size` bytes each, refused when there aren't that many bytes left
+fn read_count(cursor: &mut Cursor<&[u8]>, size: usize) -> Result<usize> {
+    let count = cursor.read_u32::<LittleEndian>()? as usize;
+    let left = cursor.get_ref().len() - (cursor.position() as usize).min(cursor.get_ref().len());
+    if count.saturating_mul(size) > left {
+        return Err(parse_err(format!("{} items of {} bytes don't fit in the {} bytes left", count, size, left)));
+    }
+    Ok(count)
+}
+
+fn read_f32s<const N: usize>(cursor: &mut Cursor<&[u8]>) -> Result<[f32; N]> {
+    let mut values = [0.0; N];
+    for value in &mut values {
+        *value = cursor.read_f32::<LittleEndian>()?;
+    }
+    Ok(values)
+}
+
+fn read_u16s<const N: usize>(cursor: &mut Cursor<&[u8]>) -> Result<[u16; N]> {
+    let mut values = [0; N];
+    for value in &mut values {
+        *value = cursor.read_u16::<LittleEndian>()?;
+    }
+    Ok(values)
+}
+
+fn read_string(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+    let length = read_count(cursor, 1)?;
+    let mut bytes = vec![0; length];
+    cursor.read_exact(&mut bytes)?;
+    String::from_utf8(bytes).map_err(|_| parse_err("a name isn't utf-8"))
+}
+
+fn read_strings(cursor: &mut Cursor<&[u8]>) -> Result<Vec<String>> {
+    let count = read_count(cursor, 4)?;
+    (0..count).map(|_| read_string(cursor)).collect()
+}
+
+fn read_skin(cursor: &mut Cursor<&[u8]>) -> Result<MeshSkin> {
+    let count = read_count(cursor, 4 + 4 + 2 + 4 + 12 * 4)?;
+    let mut bones = Vec::with_capacity(count);
+    for _ in 0..count {
+        let name = read_string(cursor)?;
+        let parent = cursor.read_i32::<LittleEndian>()?;
+        bones.push(MeshBone {
+            name,
+            parent: usize::try_from(parent).ok(),
+            lod_parent: cursor.read_u16::<LittleEndian>()?,
+            culling: cursor.read_f32::<LittleEndian>()?,
+            rotation: read_f32s(cursor)?,
+            position: read_f32s(cursor)?,
+        });
+    }
+    let count = read_count(cursor, 8)?;
+    let joints = (0..count).map(|_| read_u16s(cursor)).collect::<Result<_>>()?;
+    let count = read_count(cursor, 16)?;
+    let weights = (0..count).map(|_| read_f32s(cursor)).collect::<Result<_>>()?;
+    Ok(MeshSkin { bones, joints, weights })
+}
+
+fn read_facs(cursor: &mut Cursor<&[u8]>) -> Result<MeshFacs> {
+    let bone_names = read_strings(cursor)?;
+    let control_names = read_strings(cursor)?;
+    let count = read_count(cursor, 4)?;
+    let two_pose_correctives = (0..count).map(|_| read_u16s(cursor)).collect::<Result<_>>()?;
+    let count = read_count(cursor, 6)?;
+    let three_pose_correctives = (0..count).map(|_| read_u16s(cursor)).collect::<Result<_>>()?;
+    let mut transforms: [Vec<f32>; 6] = Default::default();
+    for channel in &mut transforms {
+        let count = read_count(cursor, 4)?;
+        *channel = (0..count).map(|_| cursor.read_f32::<LittleEndian>()).collect::<std::io::Result<_>>()?;
+    }
+    Ok(MeshFacs {
+        bone_names,
+        control_names,
+        two_pose_correctives,
+        three_pose_correctives,
+        transforms,
+    })
+}
+
+pub fn read_imesh(data: &[u8]) -> Result<IntermediateMesh> {
+    let rest = data.strip_prefix(IMESH_MAGIC).ok_or_else(|| parse_err("not an imesh file"))?;
+    let version = rest
+        .get(..4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| parse_err("no format version"))?;
+    if version != FORMAT_VERSION {
+        return Err(parse_err(format!("format version {} isn't one this build reads", version)));
+    }
+    let body = zstd::stream::decode_all(&rest[4..])?;
+    let mut cursor = Cursor::new(body.as_slice());
+
+    let count = read_count(&mut cursor, 14 * 4)?;
+    let mut vertices = Vec::with_capacity(count);
+    for _ in 0..count {
+        let pos = read_f32s(&mut cursor)?;
+        let normal = read_f32s(&mut cursor)?;
+        let uv = read_f32s(&mut cursor)?;
+        let mut color = [0; 4];
+        cursor.read_exact(&mut color)?;
+        let tangent = read_f32s(&mut cursor)?;
+        vertices.push(IntermediateVertex { pos, normal, uv, color, tangent });
+    }
+    let count = read_count(&mut cursor, 12)?;
+    let mut faces = Vec::with_capacity(count);
+    for _ in 0..count {
+        let face = [
+            cursor.read_u32::<LittleEndian>()?,
+            cursor.read_u32::<LittleEndian>()?,
+            cursor.read_u32::<LittleEndian>()?,
+        ];
+        if face.iter().any(|&index| index as usize >= vertices.len()) {
+            return Err(parse_err("a face uses a vertex that isn't there"));
+        }
+        faces.push(face);
+    }
+    let skin = if cursor.read_u8()? != 0 { Some(read_skin(&mut cursor)?) } else { None };
+    let facs = if cursor.read_u8()? != 0 { Some(read_facs(&mut cursor)?) } else { None };
+    Ok(IntermediateMesh { vertices, faces, skin, facs })
+}
+
+fn entry_path(cache_dir: &Path, obj_data: &[u8]) -> PathBuf {
+    let key = sha256_hex(obj_data);
+    cache_dir.join(format!("{}-{}.{}", &key[..32], env!("CARGO_PKG_VERSION"), ENTRY_EXTENSION))
+}
+
+fn prune(cache_dir: &Path) -> std::io::Result<()> {
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(cache_dir)? {
+        let path = entry?.path();
+        if path.extension().is_some_and(|ext| ext == ENTRY_EXTENSION) {
+            entries.push((fs::metadata(&path)?.modified()?, path));
+        }
+    }
+    if entries.len() <= MAX_ENTRIES {
+        return Ok(());
+    }
+    entries.sort();
+    for (_, path) in &entries[..entries.len() - MAX_ENTRIES] {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+fn write_entry(mesh: &IntermediateMesh, path: &Path) -> Result<()> {
+    // written under another name first so a crash never leaves half an entry behind
+    let partial = path.with_extension("partial");
+    fs::write(&partial, write_imesh(mesh)?)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+// importer::obj_to_intermediate_cleaned, going through the cache when there is one. what the
+// import cleaned up was said when it was cached, a cached mesh comes back with nothing to report
+pub fn load_obj_cached(obj_data: &[u8], cache_dir: Option<&Path>) -> Result<(IntermediateMesh, ImportCleanup)> {
+    let Some(cache_dir) = cache_dir else {
+        return importer::obj_to_intermediate_cleaned(obj_data);
+    };
+    let path = entry_path(cache_dir, obj_data);
+    if let Ok(cached) = fs::read(&path) {
+        match read_imesh(&cached) {
+            Ok(mesh) => {
+                println!("[mesh::cache] using cached import {}", path.display());
+                return Ok((mesh, ImportCleanup::default()));
+            }
+            Err(e) => println!("[mesh::cache] ignoring unreadable cache entry {}: {}", path.display(), e),
+        }
+    }
+
+    let (mesh, cleanup) = importer::obj_to_intermediate_cleaned(obj_data)?;
+    let stored = fs::create_dir_all(cache_dir)
+        .map_err(ConversionError::from)
+        .and_then(|_| write_entry(&mesh, &path));
+    match stored {
+        Ok(()) => {
+            println!("[mesh::cache] cached import as {}", path.display());
+            if let Err(e) = prune(cache_dir) {
+                println!("[mesh::cache] couldn't prune {}: {}", cache_dir.display(), e);
+            }
+        }
+        Err(e) => println!("[mesh::cache] couldn't cache the import: {}", e),
+    }
+    Ok((mesh, cleanup))
+}
@@ -1,10 +1,176 @@
 use crate::error::{ConversionError, Result};
-use crate::mesh_types::{IntermediateMesh, IntermediateVertex};
-use std::collections::HashMap;
+use crate::gltf::{CHUNK_BIN, CHUNK_JSON, FLOAT, GLB_MAGIC, Transform, UNSIGNED_BYTE, UNSIGNED_INT, UNSIGNED_SHORT};
+use crate::mesh_normals::{DEFAULT_SMOOTH_ANGLE, recompute_normals};
+use crate::mesh_tangents::generate_tangents;
+use crate::mesh_types::{DEFAULT_TANGENT, IntermediateMesh, IntermediateVertex, MeshBone, MeshFacs, MeshSkin, WHITE};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+// faces obj import dropped, and vertices it had to work normals out for
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ImportCleanup {
+    pub degenerate: usize,
+    pub duplicate: usize,
+    pub missing_normals: usize,
+}
+
+// a triangle whose height is under this much of its longest edge is treated as having no area.
+// relative so a scan in metres with millimetre edges keeps its faces, f32 positions aren't
+// precise past about 1e-7 of an edge anyway
+const MIN_RELATIVE_HEIGHT: f32 = 1e-6;
+
+// most values (elements times components) a gltf accessor may have, 64 MB of floats. the count
+// is only a number in the json, so it's checked before anything is allocated from it
+const MAX_ACCESSOR_VALUES: usize = 1 << 24;
+
+fn is_degenerate(vertices: &[IntermediateVertex], face: &[u32; 3]) -> bool {
+    let [a, b, c] = face.map(|index| vertices[index as usize].pos);
+    let ab = [b[0] - a[0], b[1] - a[1], b[2] - a[2]];
+    let ac = [c[0] - a[0], c[1] - a[1], c[2] - a[2]];
+    let cross = [
+        ab[1] * ac[2] - ab[2] * ac[1],
+        ab[2] * ac[0] - ab[0] * ac[2],
+        ab[0] * ac[1] - ab[1] * ac[0],
+    ];
+    let bc = [c[0] - b[0], c[1] - b[1], c[2] - b[2]];
+    let longest_squared = [ab, ac, bc]
+        .iter()
+        .map(|edge| edge.iter().map(|e| e * e).sum::<f32>())
+        .fold(0.0, f32::max);
+    // |ab x ac| is the longest edge times the height over it
+    cross.iter().map(|c| c * c).sum::<f32>() <= (MIN_RELATIVE_HEIGHT * longest_squared).powi(2)
+}
+
+// the face's corner positions starting from the smallest, so the same triangle with the same
+// winding gives the same key whichever corner it starts at. flipped copies are kept, those are
+// intentional double sided geometry
+fn face_key(vertices: &[IntermediateVertex], face: &[u32; 3]) -> [[u32; 3]; 3] {
+    let corners = face.map(|index| vertices[index as usize].pos.map(f32::to_bits));
+    let first = (0..3).min_by_key(|&i| corners[i]).unwrap_or(0);
+    [corners[first], corners[(first + 1) % 3], corners[(first + 2) % 3]]
+}
+
+// drops zero area triangles and exact duplicates, roblox's mesh validator rejects uploads with either
+pub(crate) fn clean_faces(mesh: &mut IntermediateMesh) -> ImportCleanup {
+    let mut cleanup = ImportCleanup::default();
+    let mut seen = HashSet::new();
+    let vertices = &mesh.vertices;
+    mesh.faces.retain(|face| {
+        if is_degenerate(vertices, face) {
+            cleanup.degenerate += 1;
+            return false;
+        }
+        if !seen.insert(face_key(vertices, face)) {
+            cleanup.duplicate += 1;
+            return false;
+        }
+        true
+    });
+    cleanup
+}
+
+// vertices converted per job, small enough that one huge model still spreads over every thread
+const OBJ_VERTICES_PER_JOB: usize = 1 << 16;
+
+// the vertex at `idx` in a tobj mesh, and whether the obj gave it a normal
+fn obj_vertex(mesh: &tobj::Mesh, idx: usize) -> (IntermediateVertex, bool) {
+    let pos = [
+        mesh.positions[idx * 3],
+        mesh.positions[idx * 3 + 1],
+        mesh.positions[idx * 3 + 2],
+    ];
+    let has_normal = idx * 3 + 2 < mesh.normals.len();
+    let normal = if has_normal {
+        [
+            mesh.normals[idx * 3],
+            mesh.normals[idx * 3 + 1],
+            mesh.normals[idx * 3 + 2],
+        ]
+    } else {
+        [0.0, 1.0, 0.0]
+    };
+    let uv = if idx * 2 + 1 < mesh.texcoords.len() {
+        [
+            mesh.texcoords[idx * 2],
+            mesh.texcoords[idx * 2 + 1],
+        ]
+    } else {
+        [0.0, 0.0]
+    };
+    // the `v x y z r g b` extension, 0-1 srgb like blender and meshlab write it
+    let color = if idx * 3 + 2 < mesh.vertex_color.len() {
+        let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+        [
+            byte(mesh.vertex_color[idx * 3]),
+            byte(mesh.vertex_color[idx * 3 + 1]),
+            byte(mesh.vertex_color[idx * 3 + 2]),
+            255,
+        ]
+    } else {
+        WHITE
+    };
+    let vertex = IntermediateVertex {
+        pos,
+        normal,
+        uv: [uv[0], 1.0 - uv[1]],
+        color,
+        tangent: DEFAULT_TANGENT,
+    };
+    (vertex, has_normal)
+}
+
+// every part's used vertices converted on all the cores there are, in order, and how many had
+// no normal
+fn obj_vertices(parts: &[(&tobj::Mesh, Vec<u32>)]) -> (Vec<IntermediateVertex>, usize) {
+    let jobs: Vec<(&tobj::Mesh, &[u32])> = parts
+        .iter()
+        .flat_map(|(mesh, used)| used.chunks(OBJ_VERTICES_PER_JOB).map(move |chunk| (*mesh, chunk)))
+        .collect();
+    let threads = thread::available_parallelism().map_or(1, |threads| threads.get()).clamp(1, jobs.len().max(1));
+    let next = AtomicUsize::new(0);
+    let missing = AtomicUsize::new(0);
+    let converted: Vec<Mutex<Vec<IntermediateVertex>>> = jobs.iter().map(|_| Mutex::new(Vec::new())).collect();
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(&(mesh, used)) = jobs.get(index) else { break };
+                let mut vertices = Vec::with_capacity(used.len());
+                let mut job_missing = 0;
+                for &original_index in used {
+                    let (vertex, has_normal) = obj_vertex(mesh, original_index as usize);
+                    if !has_normal {
+                        job_missing += 1;
+                    }
+                    vertices.push(vertex);
+                }
+                missing.fetch_add(job_missing, Ordering::Relaxed);
+                *converted[index].lock().unwrap_or_else(|e| e.into_inner()) = vertices;
+            });
+        }
+    });
+    let mut vertices = Vec::with_capacity(parts.iter().map(|(_, used)| used.len()).sum());
+    for job in converted {
+        vertices.extend(job.into_inner().unwrap_or_else(|e| e.into_inner()));
+    }
+    (vertices, missing.into_inner())
+}
 
 pub fn obj_to_intermediate(obj_data: &[u8]) -> Result<IntermediateMesh> {
+    obj_to_intermediate_cleaned(obj_data).map(|(mesh, _)| mesh)
+}
+
+// obj_to_intermediate, plus how many faces were dropped
+pub fn obj_to_intermediate_cleaned(obj_data: &[u8]) -> Result<(IntermediateMesh, ImportCleanup)> {
     let (models, _) = tobj::load_obj_buf(
-        &mut obj_data.as_ref(),
+        &mut &obj_data[..],
         &tobj::LoadOptions {
             triangulate: true,
             single_index: true,
@@ -19,71 +185,601 @@ pub fn obj_to_intermediate(obj_data: &[u8]) -> Result<IntermediateMesh> {
         return Err(ConversionError::NoMeshData);
     }
     
-    let mut combined_vertices: Vec<IntermediateVertex> = Vec::new();
+    // each model's vertices in the order its faces first use them, and the faces on the combined
+    // buffer. the map is a vector over the model's indices, a hash lookup per corner is what made
+    // million face scans slow
+    let mut parts: Vec<(&tobj::Mesh, Vec<u32>)> = Vec::with_capacity(models.len());
     let mut combined_faces: Vec<[u32; 3]> = Vec::new();
-
-    for model in models {
+    let mut offset = 0u32;
+    for model in &models {
         let mesh = &model.mesh;
-        let mut vertex_map: HashMap<u32, u32> = HashMap::new();
-
-        let has_normals = !mesh.normals.is_empty();
-        let has_uvs = !mesh.texcoords.is_empty();
-        
-        let new_faces = mesh.indices
-            .chunks_exact(3)
-            .map(|face_indices| {
-                let mut new_face = [0u32; 3];
-                for i in 0..3 {
-                    let original_index = face_indices[i];
-                    let new_index = if let Some(&existing) = vertex_map.get(&original_index) {
-                        existing
-                    } else {
-                        let idx = original_index as usize;
-                        let pos = [
-                            mesh.positions[idx * 3],
-                            mesh.positions[idx * 3 + 1],
-                            mesh.positions[idx * 3 + 2],
-                        ];
-                        let normal = if has_normals && idx * 3 + 2 < mesh.normals.len() {
-                            [
-                                mesh.normals[idx * 3],
-                                mesh.normals[idx * 3 + 1],
-                                mesh.normals[idx * 3 + 2],
-                            ]
-                        } else {
-                            [0.0, 1.0, 0.0]
-                        };
-                        let uv = if has_uvs && idx * 2 + 1 < mesh.texcoords.len() {
-                            [
-                                mesh.texcoords[idx * 2],
-                                mesh.texcoords[idx * 2 + 1],
-                            ]
-                        } else {
-                            [0.0, 0.0]
-                        };
-
-                        let vertex = IntermediateVertex {
-                            pos,
-                            normal,
-                            uv: [uv[0], 1.0 - uv[1]],
-                        };
-                        
-                        combined_vertices.push(vertex);
-                        let stored = (combined_vertices.len() - 1) as u32;
-                        vertex_map.insert(original_index, stored);
-                        stored
-                    };
-                    new_face[i] = new_index;
+        let mut vertex_map = vec![u32::MAX; mesh.positions.len() / 3];
+        let mut used: Vec<u32> = Vec::with_capacity(vertex_map.len());
+        combined_faces.reserve(mesh.indices.len() / 3);
+        for face_indices in mesh.indices.chunks_exact(3) {
+            let mut new_face = [0u32; 3];
+            for (corner, &original_index) in new_face.iter_mut().zip(face_indices) {
+                let slot = vertex_map.get_mut(original_index as usize).ok_or_else(|| {
+                    ConversionError::Unsupported(format!("obj: face uses vertex {} which isn't there", original_index + 1))
+                })?;
+                if *slot == u32::MAX {
+                    used.push(original_index);
+                    *slot = offset + used.len() as u32 - 1;
                 }
-                new_face
-            })
-            .collect::<Vec<_>>();
-        
-        combined_faces.extend(new_faces);
+                *corner = *slot;
+            }
+            combined_faces.push(new_face);
+        }
+        offset += used.len() as u32;
+        parts.push((mesh, used));
     }
+    let (combined_vertices, missing_normals) = obj_vertices(&parts);
 
-    Ok(IntermediateMesh {
+    let mut mesh = IntermediateMesh {
         vertices: combined_vertices,
         faces: combined_faces,
-    })
+        skin: None,
+        facs: None,
+    };
+    let mut cleanup = clean_faces(&mut mesh);
+    if mesh.faces.is_empty() {
+        return Err(ConversionError::NoMeshData);
+    }
+    // a placeholder normal lights every face like it's facing up, worked out ones are better
+    // even where the obj did have some
+    if missing_normals > 0 {
+        cleanup.missing_normals = missing_normals;
+        recompute_normals(&mut mesh, DEFAULT_SMOOTH_ANGLE);
+    } else {
+        // obj has no tangents, they come from the uvs of the faces that are left
+        generate_tangents(&mut mesh);
+    }
+    Ok((mesh, cleanup))
+}
+
+// splits a .glb into its json document and binary chunk
+fn read_glb(data: &[u8]) -> Result<(Value, &[u8])> {
+    let gltf_err = |msg: &str| ConversionError::Unsupported(format!("gltf: {}", msg));
+    if !data.starts_with(GLB_MAGIC) {
+        return Err(gltf_err("not a binary .glb, export with the glTF Binary option"));
+    }
+    let mut document = None;
+    let mut bin: &[u8] = &[];
+    let mut offset = 12;
+    while offset + 8 <= data.len() {
+        let length = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+        let kind = u32::from_le_bytes(data[offset + 4..offset + 8].try_into().unwrap());
+        let chunk = data
+            .get(offset + 8..offset + 8 + length)
+            .ok_or_else(|| gltf_err("chunk runs past the end of the file"))?;
+        match kind {
+            CHUNK_JSON => {
+                document = Some(serde_json::from_slice(chunk).map_err(|e| gltf_err(&e.to_string()))?)
+            }
+            CHUNK_BIN => bin = chunk,
+            _ => {}
+        }
+        offset += 8 + length;
+    }
+    Ok((document.ok_or_else(|| gltf_err("missing json chunk"))?, bin))
+}
+
+// uris in a .gltf are percent encoded, a space in a file name comes out as %20
+fn uri_decode(uri: &str) -> String {
+    let bytes = uri.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match uri.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+            Some(byte) if bytes[i] == b'%' => {
+                out.push(byte);
+                i += 3;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+// the json document and every buffer, from a .glb or a .gltf. a .gltf's buffers are base64 data
+// uris or files next to it, found from `dir`. without a dir only embedded buffers can be read
+pub(crate) fn read_gltf(data: &[u8], dir: Option<&Path>) -> Result<(Value, Vec<Vec<u8>>)> {
+    let gltf_err = |msg: String| ConversionError::Unsupported(format!("gltf: {}", msg));
+    if data.starts_with(GLB_MAGIC) {
+        let (document, bin) = read_glb(data)?;
+        return Ok((document, vec![bin.to_vec()]));
+    }
+    let document: Value = serde_json::from_slice(data)
+        .map_err(|e| gltf_err(format!("neither a .glb nor a .gltf json document ({})", e)))?;
+    let mut buffers = Vec::new();
+    for (index, buffer) in document["buffers"].as_array().into_iter().flatten().enumerate() {
+        let Some(uri) = buffer["uri"].as_str() else {
+            return Err(gltf_err(format!("buffer {} has no uri", index)));
+        };
+        let bytes = if let Some(data_uri) = uri.strip_prefix("data:") {
+            let (_, encoded) = data_uri
+                .split_once(";base64,")
+                .ok_or_else(|| gltf_err(format!("buffer {} isn't a base64 data uri", index)))?;
+            BASE64.decode(encoded).map_err(|e| gltf_err(format!("buffer {}: {}", index, e)))?
+        } else {
+            let Some(dir) = dir else {
+                return Err(gltf_err(format!("buffer {} is a separate file ({}), embed it or send a .glb", index, uri)));
+            };
+            let path = dir.join(uri_decode(uri));
+            fs::read(&path).map_err(|e| gltf_err(format!("buffer {} ({}): {}", index, path.display(), e)))?
+        };
+        buffers.push(bytes);
+    }
+    Ok((document, buffers))
+}
+
+// every element of an accessor as floats, integer types are scaled to 0..1 if normalized
+pub(crate) fn read_accessor(document: &Value, buffers: &[Vec<u8>], index: usize) -> Result<(Vec<f32>, usize)> {
+    let gltf_err = |msg: String| ConversionError::Unsupported(format!("gltf: {}", msg));
+    let accessor = &document["accessors"][index];
+    if accessor.get("sparse").is_some() {
+        return Err(gltf_err(format!("sparse accessor {} isn't supported", index)));
+    }
+    let components = match accessor["type"].as_str() {
+        Some("SCALAR") => 1,
+        Some("VEC2") => 2,
+        Some("VEC3") => 3,
+        Some("VEC4") => 4,
+        Some("MAT4") => 16,
+        other => return Err(gltf_err(format!("unsupported accessor type {:?}", other))),
+    };
+    let component_type = accessor["componentType"].as_u64().unwrap_or(0) as u32;
+    let component_size = match component_type {
+        FLOAT | UNSIGNED_INT => 4,
+        UNSIGNED_SHORT => 2,
+        UNSIGNED_BYTE => 1,
+        other => return Err(gltf_err(format!("unsupported component type {}", other))),
+    };
+    let normalized = accessor["normalized"].as_bool().unwrap_or(false);
+    let count = accessor["count"].as_u64().unwrap_or(0);
+    let count = usize::try_from(count)
+        .ok()
+        .filter(|&count| count.checked_mul(components).is_some_and(|values| values <= MAX_ACCESSOR_VALUES))
+        .ok_or_else(|| gltf_err(format!("accessor {} has {} elements, too many to read", index, count)))?;
+    let Some(view_index) = accessor["bufferView"].as_u64() else {
+        // no view means all zeroes
+        return Ok((vec![0.0; count * components], components));
+    };
+    let view = &document["bufferViews"][view_index as usize];
+    let buffer = view["buffer"].as_u64().unwrap_or(0) as usize;
+    let bin = buffers
+        .get(buffer)
+        .ok_or_else(|| gltf_err(format!("accessor {} points at missing buffer {}", index, buffer)))?;
+    let view_offset = view["byteOffset"].as_u64().unwrap_or(0);
+    let view_length = view["byteLength"].as_u64().unwrap_or(0);
+    let accessor_offset = accessor["byteOffset"].as_u64().unwrap_or(0);
+    let stride = view["byteStride"].as_u64().map_or(component_size * components, |s| s as usize);
+    // the view has to be in the buffer and every element in the view, before sizing anything from count
+    let element_size = (component_size * components) as u64;
+    let used = match count {
+        0 => Some(0),
+        _ => (count as u64 - 1).checked_mul(stride as u64).and_then(|last| last.checked_add(element_size)),
+    };
+    let fits = view_offset.checked_add(view_length).is_some_and(|end| end <= bin.len() as u64)
+        && used.and_then(|used| used.checked_add(accessor_offset)).is_some_and(|end| end <= view_length);
+    if !fits {
+        return Err(gltf_err(format!("accessor {} runs past the end of its buffer view", index)));
+    }
+    let start = (view_offset + accessor_offset) as usize;
+
+    let mut values = Vec::with_capacity(count * components);
+    for element in 0..count {
+        for component in 0..components {
+            let at = start + element * stride + component * component_size;
+            let bytes = bin
+                .get(at..at + component_size)
+                .ok_or_else(|| gltf_err(format!("accessor {} runs past the end of the buffer", index)))?;
+            let value = match component_type {
+                FLOAT => f32::from_le_bytes(bytes.try_into().unwrap()),
+                UNSIGNED_INT => u32::from_le_bytes(bytes.try_into().unwrap()) as f32,
+                UNSIGNED_SHORT => {
+                    let v = u16::from_le_bytes(bytes.try_into().unwrap()) as f32;
+                    if normalized { v / u16::MAX as f32 } else { v }
+                }
+                _ => {
+                    let v = bytes[0] as f32;
+                    if normalized { v / u8::MAX as f32 } else { v }
+                }
+            };
+            values.push(value);
+        }
+    }
+    Ok((values, components))
+}
+
+fn node_parents(nodes: &[Value]) -> Vec<Option<usize>> {
+    let mut parents = vec![None; nodes.len()];
+    for (index, node) in nodes.iter().enumerate() {
+        for child in node["children"].as_array().into_iter().flatten() {
+            if let Some(child) = child.as_u64().filter(|&c| (c as usize) < nodes.len()) {
+                parents[child as usize] = Some(index);
+            }
+        }
+    }
+    parents
+}
+
+// transform of every node in scene space
+fn world_transforms(nodes: &[Value]) -> Vec<Option<Transform>> {
+    let parents = node_parents(nodes);
+    let mut worlds: Vec<Option<Transform>> = vec![None; nodes.len()];
+    for start in 0..nodes.len() {
+        // walk up to the first node with a known transform, then back down
+        let mut chain = vec![start];
+        let mut current = start;
+        while worlds[current].is_none() && let Some(parent) = parents[current] {
+            if chain.contains(&parent) {
+                break;
+            }
+            chain.push(parent);
+            current = parent;
+        }
+        for &node in chain.iter().rev() {
+            if worlds[node].is_some() {
+                continue;
+            }
+            let local = Transform::of_node(&nodes[node]);
+            worlds[node] = Some(match parents[node].and_then(|parent| worlds[parent]) {
+                Some(parent) => parent.then(&local),
+                None => local,
+            });
+        }
+    }
+    worlds
+}
+
+fn read_skin(document: &Value, buffers: &[Vec<u8>], skin: &Value, joints: Vec<[u16; 4]>, weights: Vec<[f32; 4]>) -> Result<MeshSkin> {
+    let nodes = document["nodes"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let joint_nodes: Vec<usize> = skin["joints"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|joint| joint.as_u64().map(|j| j as usize))
+        .filter(|&joint| joint < nodes.len())
+        .collect();
+    if joint_nodes.len() > u16::MAX as usize - 1 {
+        return Err(ConversionError::Unsupported(format!("gltf: too many joints ({})", joint_nodes.len())));
+    }
+
+    // mesh space bone transforms, straight from the inverse bind matrices when there are some
+    let worlds = world_transforms(nodes);
+    let binds: Vec<Transform> = match skin["inverseBindMatrices"].as_u64() {
+        Some(accessor) => {
+            let (matrices, _) = read_accessor(document, buffers, accessor as usize)?;
+            matrices.chunks_exact(16).map(|m| Transform::from_column_major(m).inverse()).collect()
+        }
+        None => joint_nodes.iter().map(|&node| worlds[node].unwrap_or(Transform::of_node(&nodes[node]))).collect(),
+    };
+
+    let node_parents = node_parents(nodes);
+    let bones = joint_nodes
+        .iter()
+        .enumerate()
+        .map(|(index, &node)| {
+            // nearest ancestor that is also a joint, non-joint nodes in between are skipped
+            let mut parent = None;
+            let mut current = node_parents[node];
+            let mut steps = 0;
+            while let Some(ancestor) = current && steps < nodes.len() {
+                if let Some(position) = joint_nodes.iter().position(|&j| j == ancestor) {
+                    parent = Some(position);
+                    break;
+                }
+                current = node_parents[ancestor];
+                steps += 1;
+            }
+            let bind = binds.get(index).copied().unwrap_or(Transform::of_node(&nodes[node]));
+            MeshBone {
+                name: nodes[node]["name"].as_str().map_or_else(|| format!("Bone{}", index), str::to_owned),
+                parent,
+                lod_parent: 0,
+                culling: 0.0,
+                rotation: bind.rotation,
+                position: bind.position,
+            }
+        })
+        .collect();
+
+    Ok(MeshSkin { bones, joints, weights })
+}
+
+// gltf vertex colors are linear, filemesh ones are srgb bytes like everything else roblox colors
+fn vertex_color(linear: &[f32]) -> [u8; 4] {
+    let byte = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let srgb = |value: f32| {
+        let value = value.clamp(0.0, 1.0);
+        byte(if value <= 0.0031308 { value * 12.92 } else { 1.055 * value.powf(1.0 / 2.4) - 0.055 })
+    };
+    let alpha = linear.get(3).copied().unwrap_or(1.0);
+    [srgb(linear[0]), srgb(linear[1]), srgb(linear[2]), byte(alpha)]
+}
+
+// merges every triangle primitive of the first mesh in the scene (preferring a skinned one) into
+// one mesh. the node's own transform isn't applied, same as how the mesh would be imported into
+// studio. `dir` is where a .gltf's external buffers are, see read_gltf
+pub fn gltf_to_intermediate(data: &[u8], dir: Option<&Path>) -> Result<IntermediateMesh> {
+    let (document, buffers) = read_gltf(data, dir)?;
+    let nodes = document["nodes"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let mesh_node = nodes
+        .iter()
+        .find(|node| node.get("mesh").is_some() && node.get("skin").is_some())
+        .or_else(|| nodes.iter().find(|node| node.get("mesh").is_some()));
+    let mesh_index = mesh_node.and_then(|node| node["mesh"].as_u64()).unwrap_or(0) as usize;
+    let skin = mesh_node
+        .and_then(|node| node["skin"].as_u64())
+        .map(|skin| &document["skins"][skin as usize]);
+    let Some(primitives) = document["meshes"][mesh_index]["primitives"].as_array() else {
+        return Err(ConversionError::NoMeshData);
+    };
+
+    let mut vertices = Vec::new();
+    let mut faces = Vec::new();
+    let mut joints = Vec::new();
+    let mut weights = Vec::new();
+    // per vertex, whether its primitive had a TANGENT attribute
+    let mut given_tangents = Vec::new();
+    // per morph target, one offset per vertex
+    let mut targets: Vec<Vec<[f32; 3]>> = Vec::new();
+    for primitive in primitives {
+        if primitive["mode"].as_u64().unwrap_or(4) != 4 {
+            continue;
+        }
+        let attributes = &primitive["attributes"];
+        let attribute = |name: &str| -> Result<Option<Vec<f32>>> {
+            match attributes[name].as_u64() {
+                Some(accessor) => Ok(Some(read_accessor(&document, &buffers, accessor as usize)?.0)),
+                None => Ok(None),
+            }
+        };
+        let Some(positions) = attribute("POSITION")? else { continue };
+        let normals = attribute("NORMAL")?;
+        let uvs = attribute("TEXCOORD_0")?;
+        let tangents = attribute("TANGENT")?;
+        // rgb or rgba
+        let colors = match attributes["COLOR_0"].as_u64() {
+            Some(accessor) => Some(read_accessor(&document, &buffers, accessor as usize)?),
+            None => None,
+        };
+        let base = vertices.len() as u32;
+        let count = positions.len() / 3;
+        for i in 0..count {
+            let normal = match &normals {
+                Some(n) if n.len() >= i * 3 + 3 => [n[i * 3], n[i * 3 + 1], n[i * 3 + 2]],
+                _ => [0.0, 1.0, 0.0],
+            };
+            // already top left like the writers expect, unlike obj
+            let uv = match &uvs {
+                Some(t) if t.len() >= i * 2 + 2 => [t[i * 2], t[i * 2 + 1]],
+                _ => [0.0, 0.0],
+            };
+            let color = match &colors {
+                Some((c, components)) if *components >= 3 => {
+                    c.get(i * components..i * components + components).map_or(WHITE, vertex_color)
+                }
+                _ => WHITE,
+            };
+            let tangent = tangents.as_ref().and_then(|t| t.get(i * 4..i * 4 + 4));
+            given_tangents.push(tangent.is_some());
+            vertices.push(IntermediateVertex {
+                pos: [positions[i * 3], positions[i * 3 + 1], positions[i * 3 + 2]],
+                normal,
+                uv,
+                color,
+                tangent: tangent.map_or(DEFAULT_TANGENT, |t| [t[0], t[1], t[2], t[3]]),
+            });
+        }
+
+        let indices: Vec<u32> = match primitive["indices"].as_u64() {
+            Some(accessor) => read_accessor(&document, &buffers, accessor as usize)?.0.iter().map(|&i| i as u32).collect(),
+            None => (0..count as u32).collect(),
+        };
+        for face in indices.chunks_exact(3) {
+            if face.iter().any(|&i| i as usize >= count) {
+                return Err(ConversionError::Unsupported("gltf: face index out of range".to_owned()));
+            }
+            faces.push([base + face[0], base + face[1], base + face[2]]);
+        }
+
+        let primitive_targets = primitive["targets"].as_array().map(Vec::as_slice).unwrap_or_default();
+        if targets.len() < primitive_targets.len() {
+            targets.resize(primitive_targets.len(), vec![[0.0; 3]; base as usize]);
+        }
+        for (index, target) in targets.iter_mut().enumerate() {
+            let offsets = match primitive_targets.get(index).and_then(|t| t["POSITION"].as_u64()) {
+                Some(accessor) => read_accessor(&document, &buffers, accessor as usize)?.0,
+                None => Vec::new(),
+            };
+            target.extend((0..count).map(|i| match offsets.get(i * 3..i * 3 + 3) {
+                Some(&[x, y, z]) => [x, y, z],
+                _ => [0.0; 3],
+            }));
+        }
+
+        if skin.is_some() {
+            let primitive_joints = attribute("JOINTS_0")?.unwrap_or_default();
+            let primitive_weights = attribute("WEIGHTS_0")?.unwrap_or_default();
+            for i in 0..count {
+                let mut vertex_joints = [0u16; 4];
+                let mut vertex_weights = [0f32; 4];
+                for slot in 0..4 {
+                    vertex_joints[slot] = primitive_joints.get(i * 4 + slot).copied().unwrap_or(0.0) as u16;
+                    vertex_weights[slot] = primitive_weights.get(i * 4 + slot).copied().unwrap_or(0.0).max(0.0);
+                }
+                let total: f32 = vertex_weights.iter().sum();
+                if total > 0.0 {
+                    vertex_weights = vertex_weights.map(|w| w / total);
+                } else {
+                    vertex_weights = [1.0, 0.0, 0.0, 0.0];
+                }
+                joints.push(vertex_joints);
+                weights.push(vertex_weights);
+            }
+        }
+    }
+
+    if faces.is_empty() {
+        return Err(ConversionError::NoMeshData);
+    }
+    let skin = match skin {
+        Some(skin) => {
+            let skin = read_skin(&document, &buffers, skin, joints, weights)?;
+            if skin.joints.iter().flatten().any(|&joint| joint as usize >= skin.bones.len()) {
+                return Err(ConversionError::Unsupported("gltf: vertex joint index out of range".to_owned()));
+            }
+            Some(skin)
+        }
+        None => None,
+    };
+    // facs poses move bones, so morph targets on an unskinned mesh have nowhere to go
+    let facs = match &skin {
+        Some(skin) if !targets.is_empty() => {
+            let names = &document["meshes"][mesh_index]["extras"]["targetNames"];
+            let names = (0..targets.len())
+                .map(|i| names[i].as_str().map_or_else(|| format!("Target{}", i), str::to_owned))
+                .collect();
+            Some(facs_from_morph_targets(skin, names, &targets))
+        }
+        _ => None,
+    };
+    let mut mesh = IntermediateMesh { vertices, faces, skin, facs };
+    // primitives exported without tangents get them generated, ones that have them keep theirs
+    if given_tangents.contains(&false) {
+        let given: Vec<[f32; 4]> = mesh.vertices.iter().map(|vertex| vertex.tangent).collect();
+        generate_tangents(&mut mesh);
+        for ((vertex, given), &kept) in mesh.vertices.iter_mut().zip(given).zip(&given_tangents) {
+            if kept {
+                vertex.tangent = given;
+            }
+        }
+    }
+    Ok(mesh)
+}
+
+// turns morph targets into facs controls. each pose is approximated by translating the bones:
+// a bone moves by the average offset of the vertices it drives, weighted by skinning weight.
+// rotations are left at zero, so poses that rely on twisting bones come out flatter than in
+// studio. targets named like A_B or A_B_C, where the parts are other targets, become two and
+// three pose correctives
+fn facs_from_morph_targets(skin: &MeshSkin, names: Vec<String>, targets: &[Vec<[f32; 3]>]) -> MeshFacs {
+    let parts_of = |name: &str| -> Option<Vec<usize>> {
+        let parts: Vec<&str> = name.split('_').collect();
+        if !(2..=3).contains(&parts.len()) {
+            return None;
+        }
+        parts.iter().map(|part| names.iter().position(|other| other == part)).collect()
+    };
+    // base controls first, then two pose, then three pose correctives
+    let mut order: Vec<usize> = (0..names.len()).collect();
+    order.sort_by_key(|&i| parts_of(&names[i]).map_or(0, |parts| parts.len()));
+    let control_index = |target: usize| order.iter().position(|&i| i == target).unwrap_or(0) as u16;
+
+    let mut facs = MeshFacs::default();
+    for &target in &order {
+        match parts_of(&names[target]).as_deref() {
+            Some(&[a, b]) => facs.two_pose_correctives.push([control_index(a), control_index(b)]),
+            Some(&[a, b, c]) => facs.three_pose_correctives.push([control_index(a), control_index(b), control_index(c)]),
+            _ => {}
+        }
+        facs.control_names.push(names[target].clone());
+    }
+
+    // [bone][control] offset in mesh space
+    let bone_count = skin.bones.len();
+    let mut offsets = vec![vec![[0f32; 3]; order.len()]; bone_count];
+    for (column, &target) in order.iter().enumerate() {
+        let mut sums = vec![([0f32; 3], 0f32); bone_count];
+        for (vertex, offset) in targets[target].iter().enumerate() {
+            let (Some(joints), Some(weights)) = (skin.joints.get(vertex), skin.weights.get(vertex)) else { continue };
+            for slot in 0..4 {
+                let (sum, total) = &mut sums[joints[slot] as usize];
+                for axis in 0..3 {
+                    sum[axis] += offset[axis] * weights[slot];
+                }
+                *total += weights[slot];
+            }
+        }
+        for (bone, (sum, total)) in sums.into_iter().enumerate() {
+            if total > 0.0 {
+                offsets[bone][column] = sum.map(|v| v / total);
+            }
+        }
+    }
+
+    // only bones that some pose actually moves are face bones
+    for (bone, bone_offsets) in offsets.iter().enumerate() {
+        if !bone_offsets.iter().flatten().any(|v| v.abs() > 1e-4) {
+            continue;
+        }
+        facs.bone_names.push(skin.bones[bone].name.clone());
+        let r = skin.bones[bone].rotation;
+        for offset in bone_offsets {
+            // into the bone's space, the rotation is orthonormal so transposing inverts it
+            for axis in 0..3 {
+                let local = r[axis] * offset[0] + r[3 + axis] * offset[1] + r[6 + axis] * offset[2];
+                facs.transforms[axis].push(local);
+            }
+            for channel in &mut facs.transforms[3..] {
+                channel.push(0.0);
+            }
+        }
+    }
+    facs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // a .glb with one triangle primitive whose POSITION is the given accessor, and a 12 byte bin
+    fn glb_with_accessor(accessor: &str) -> Vec<u8> {
+        let json = format!(
+            r#"{{"meshes":[{{"primitives":[{{"attributes":{{"POSITION":0}}}}]}}],"accessors":[{}],"bufferViews":[{{"buffer":0,"byteLength":12}}],"buffers":[{{"byteLength":12}}]}}"#,
+            accessor
+        );
+        let mut json = json.into_bytes();
+        json.resize(json.len().next_multiple_of(4), b' ');
+        let bin = [0u8; 12];
+        let mut out = Vec::new();
+        out.extend_from_slice(GLB_MAGIC);
+        out.extend_from_slice(&2u32.to_le_bytes());
+        out.extend_from_slice(&((12 + 8 + json.len() + 8 + bin.len()) as u32).to_le_bytes());
+        out.extend_from_slice(&(json.len() as u32).to_le_bytes());
+        out.extend_from_slice(&CHUNK_JSON.to_le_bytes());
+        out.extend_from_slice(&json);
+        out.extend_from_slice(&(bin.len() as u32).to_le_bytes());
+        out.extend_from_slice(&CHUNK_BIN.to_le_bytes());
+        out.extend_from_slice(&bin);
+        out
+    }
+
+    #[test]
+    fn huge_accessor_without_a_view_is_an_error() {
+        for count in ["4000000000", "18446744073709551615"] {
+            let glb = glb_with_accessor(&format!(r#"{{"type":"VEC3","componentType":5126,"count":{}}}"#, count));
+            assert!(gltf_to_intermediate(&glb, None).is_err());
+        }
+    }
+
+    #[test]
+    fn accessor_past_its_view_is_an_error() {
+        let glb = glb_with_accessor(r#"{"bufferView":0,"type":"VEC3","componentType":5126,"count":4000000000}"#);
+        assert!(gltf_to_intermediate(&glb, None).is_err());
+        let glb = glb_with_accessor(r#"{"bufferView":0,"byteOffset":4,"type":"VEC3","componentType":5126,"count":1}"#);
+        assert!(gltf_to_intermediate(&glb, None).is_err());
+    }
+
+    #[test]
+    fn accessor_filling_its_view_is_read() {
+        let glb = glb_with_accessor(r#"{"bufferView":0,"type":"VEC3","componentType":5126,"count":1}"#);
+        let (document, buffers) = read_gltf(&glb, None).unwrap();
+        assert_eq!(read_accessor(&document, &buffers, 0).unwrap(), (vec![0.0; 3], 3));
+    }
 }
@@ -0,0 +1,150 @@
+// models scripts load with InsertService, copied into the place so it runs without the network
+//
+// every `<receiver>:LoadAsset(<id>)` with a literal id is replaced by a clone of a copy kept in
+// ServerStorage.InsertedAssets, a Model named after the id holding what the asset contains, the
+// same shape LoadAsset returns. assets come from an AssetSource, as <id>.rbxm/.rbxmx files when
+// it's a directory. calls for assets that can't be had are left alone and warned about.
+use crate::asset_source::AssetSource;
+use crate::pipeline::PassContext;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_types::Variant;
+use std::collections::BTreeMap;
+
+const STORAGE_FOLDER: &str = "InsertedAssets";
+const CALL: &str = ":LoadAsset(";
+const MODEL_EXTENSIONS: [&str; 2] = ["rbxm", "rbxmx"];
+
+// one `<receiver>:LoadAsset(<id>)` in a script, start..end covers the whole call
+struct LoadAssetCall {
+    start: usize,
+    end: usize,
+    id: u64,
+}
+
+// walks back from the : over the expression the method is called on, e.g.
+// game:GetService("InsertService") or script.Parent.Insert, brackets and parens included
+fn receiver_start(source: &str, colon: usize) -> usize {
+    let bytes = source.as_bytes();
+    let mut start = colon;
+    let mut depth = 0;
+    while start > 0 {
+        match bytes[start - 1] {
+            b')' | b']' => depth += 1,
+            b'(' | b'[' if depth > 0 => depth -= 1,
+            _ if depth > 0 => {}
+            c if c.is_ascii_alphanumeric() || matches!(c, b'_' | b'.' | b':') => {}
+            _ => break,
+        }
+        start -= 1;
+    }
+    start
+}
+
+fn load_asset_calls(source: &str) -> Vec<LoadAssetCall> {
+    let mut calls = Vec::new();
+    for (colon, _) in source.match_indices(CALL) {
+        let args_start = colon + CALL.len();
+        let Some(args_len) = source[args_start..].find(')') else { continue };
+        // only literal ids, LoadAsset(id) with a variable can't be known ahead of time
+        let Ok(id) = source[args_start..args_start + args_len].trim().parse() else { continue };
+        let start = receiver_start(source, colon);
+        if start == colon {
+            continue;
+        }
+        calls.push(LoadAssetCall {
+            start,
+            end: args_start + args_len + 1,
+            id,
+        });
+    }
+    calls
+}
+
+fn find_or_insert_child(dom: &mut WeakDom, parent: Ref, class: &str, name: &str) -> Ref {
+    let existing = dom.get_by_ref(parent).and_then(|instance| {
+        instance.children().iter().copied().find(|&child| {
+            dom.get_by_ref(child)
+                .is_some_and(|child| child.class == class && child.name == name)
+        })
+    });
+    existing.unwrap_or_else(|| dom.insert(parent, InstanceBuilder::new(class).with_name(name)))
+}
+
+// copies the asset's top level instances into a model named after the id, like LoadAsset does
+fn store_asset(dom: &mut WeakDom, storage: Ref, id: u64, asset: &WeakDom) -> usize {
+    let model = dom.insert(storage, InstanceBuilder::new("Model").with_name(id.to_string()));
+    let roots = asset.root().children().to_vec();
+    for &root in &roots {
+        let copy = asset.clone_into_external(root, dom);
+        dom.transfer_within(copy, model);
+    }
+    roots.len()
+}
+
+pub fn inline_inserted_assets(dom: &mut WeakDom, source: &AssetSource, ctx: &mut PassContext) {
+    let scripts: Vec<(Ref, Vec<LoadAssetCall>)> = dom
+        .descendants()
+        .filter_map(|instance| match instance.properties.get(&"Source".into()) {
+            Some(Variant::String(source)) => Some((instance.referent(), load_asset_calls(source))),
+            _ => None,
+        })
+        .filter(|(_, calls)| !calls.is_empty())
+        .collect();
+    if scripts.is_empty() {
+        return;
+    }
+
+    // id -> whether it made it into ServerStorage, calls for the ones that didn't stay as they are
+    let mut stored: BTreeMap<u64, bool> = BTreeMap::new();
+    let mut storage = None;
+    for (_, calls) in &scripts {
+        for call in calls {
+            if stored.contains_key(&call.id) {
+                continue;
+            }
+            let asset = source
+                .fetch(call.id, &MODEL_EXTENSIONS)
+                .and_then(|data| crate::load_place(&data).map_err(|e| format!("couldn't read it: {}", e).into()));
+            let asset = match asset {
+                Ok(asset) => asset,
+                Err(e) => {
+                    ctx.warn(format!("asset {} is loaded by a script but couldn't be inlined: {}", call.id, e));
+                    stored.insert(call.id, false);
+                    continue;
+                }
+            };
+            let storage = *storage.get_or_insert_with(|| {
+                let server_storage = find_or_insert_child(dom, dom.root_ref(), "ServerStorage", "ServerStorage");
+                find_or_insert_child(dom, server_storage, "Folder", STORAGE_FOLDER)
+            });
+            let count = store_asset(dom, storage, call.id, &asset);
+            ctx.info(format!("inlined asset {} ({} instances at the top)", call.id, count));
+            stored.insert(call.id, true);
+        }
+    }
+
+    for (script, calls) in scripts {
+        let Some(instance) = dom.get_by_ref_mut(script) else { continue };
+        let Some(Variant::String(source)) = instance.properties.get_mut(&"Source".into()) else { continue };
+        let mut rewritten = 0;
+        // from the back so the earlier offsets stay valid
+        for call in calls.iter().rev() {
+            if stored.get(&call.id) != Some(&true) {
+                continue;
+            }
+            source.replace_range(
+                call.start..call.end,
+                &format!(
+                    "game:GetService(\"ServerStorage\").{}[\"{}\"]:Clone()",
+                    STORAGE_FOLDER, call.id
+                ),
+            );
+            rewritten += 1;
+        }
+        if rewritten > 0 {
+            let name = instance.name.to_string();
+            ctx.converted(script, format!("pointed {} LoadAsset calls in '{}' at ServerStorage", rewritten, name));
+        }
+    }
+}
@@ -0,0 +1,78 @@
+// where prepare-legacy puts a place and its localized assets for a revival launcher
+//
+// novetus keeps downloaded assets in shareddata/assetcache, a folder per kind (meshes go in
+// fonts, what SpecialMesh files were called when the folder was named), and lists places from
+// maps/. its clients run from clients/<client>/ so rbxasset:// reaches the cache three folders
+// up:
+//
+//   out_dir/maps/Custom/<stem>.rbxl(x)
+//   out_dir/shareddata/assetcache/fonts/<id>.mesh      rbxasset://../../../shareddata/assetcache/fonts/<id>.mesh
+//   out_dir/shareddata/assetcache/textures/<id>.png
+//   out_dir/shareddata/assetcache/sounds/<id>.ogg
+//
+// finobe's clients read rbxasset:// from their own content folder like roblox's did, so it takes
+// prepare-legacy's usual layout, see prepare_legacy.rs. either way out_dir is copied over the
+// launcher's install.
+use clap::ValueEnum;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Launcher {
+    Novetus,
+    Finobe,
+}
+
+// paths are relative to prepare-legacy's out dir
+pub struct AssetLayout {
+    pub place_dir: &'static str,
+    // hashed into the content manifest
+    pub content_dir: &'static str,
+    // assets go in <asset_dir>/<folder>/<id>.<ext> ...
+    pub asset_dir: &'static str,
+    // ... and are referenced as rbxasset://<url_base>/<folder>/<id>.<ext>
+    pub url_base: &'static str,
+    pub meshes: &'static str,
+    pub textures: &'static str,
+    pub sounds: &'static str,
+}
+
+// content/ goes over the client's own, assets kept apart from its textures and sounds
+pub const CONTENT_LAYOUT: AssetLayout = AssetLayout {
+    place_dir: "",
+    content_dir: "content",
+    asset_dir: "content/localized",
+    url_base: "localized",
+    meshes: "meshes",
+    textures: "textures",
+    sounds: "sounds",
+};
+
+const NOVETUS_LAYOUT: AssetLayout = AssetLayout {
+    place_dir: "maps/Custom",
+    content_dir: "shareddata",
+    asset_dir: "shareddata/assetcache",
+    // from clients/<client>/content back to the install
+    url_base: "../../../shareddata/assetcache",
+    meshes: "fonts",
+    textures: "textures",
+    sounds: "sounds",
+};
+
+impl Launcher {
+    pub fn layout(self) -> &'static AssetLayout {
+        match self {
+            Self::Novetus => &NOVETUS_LAYOUT,
+            Self::Finobe => &CONTENT_LAYOUT,
+        }
+    }
+}
+
+impl AssetLayout {
+    // the folder for localize_assets' meshes, textures or sounds
+    pub fn folder(&self, kind: &str) -> &'static str {
+        match kind {
+            "meshes" => self.meshes,
+            "textures" => self.textures,
+            _ => self.sounds,
+        }
+    }
+}
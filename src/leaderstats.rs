@@ -0,0 +1,90 @@
+// classic leaderboard for games whose leaderstats come from scripts old clients can't run
+//
+// the player list shows whatever is in a player's "leaderstats". newer games build that from
+// scripts using APIs (DataStores, task.*, :Connect on newer events) an old client doesn't have,
+// so the scoreboard just never appears. this finds the stat names those scripts use and adds a
+// plain Workspace script that makes the same stats as IntValues the old way.
+use crate::pipeline::PassContext;
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_types::Variant;
+
+const SCRIPT_NAME: &str = "LegacyLeaderboard";
+
+// stat names assigned in scripts that mention leaderstats, e.g. cash.Name = "Cash"
+fn stat_names(dom: &WeakDom) -> Vec<String> {
+    let mut names = Vec::new();
+    for instance in dom.descendants() {
+        let Some(Variant::String(source)) = instance.properties.get(&"Source".into()) else { continue };
+        if !source.contains("leaderstats") {
+            continue;
+        }
+        for (index, _) in source.match_indices(".Name") {
+            let rest = source[index + ".Name".len()..].trim_start();
+            let Some(rest) = rest.strip_prefix('=') else { continue };
+            let rest = rest.trim_start();
+            let Some(quote) = rest.chars().next().filter(|c| *c == '"' || *c == '\'') else { continue };
+            let Some(end) = rest[1..].find(quote) else { continue };
+            let name = &rest[1..end + 1];
+            if !name.is_empty() && name != "leaderstats" && !names.iter().any(|n| n == name) {
+                names.push(name.to_owned());
+            }
+        }
+    }
+    names
+}
+
+fn leaderboard_source(stats: &[String]) -> String {
+    let list: Vec<String> = stats
+        .iter()
+        .map(|stat| format!("\"{}\"", stat.replace('\\', "\\\\").replace('"', "\\\"")))
+        .collect();
+    format!(
+        "-- classic leaderboard, the game's own leaderstats came from scripts this client can't run\n\
+         local stats = {{{}}}\n\
+         \n\
+         function onPlayerEntered(player)\n\
+         \tlocal leaderstats = Instance.new(\"IntValue\")\n\
+         \tleaderstats.Name = \"leaderstats\"\n\
+         \tfor i = 1, #stats do\n\
+         \t\tlocal stat = Instance.new(\"IntValue\")\n\
+         \t\tstat.Name = stats[i]\n\
+         \t\tstat.Value = 0\n\
+         \t\tstat.Parent = leaderstats\n\
+         \tend\n\
+         \tleaderstats.Parent = player\n\
+         end\n\
+         \n\
+         game.Players.ChildAdded:connect(onPlayerEntered)\n",
+        list.join(", ")
+    )
+}
+
+pub fn inject_leaderstats(dom: &mut WeakDom, ctx: &mut PassContext) {
+    let workspace = dom
+        .root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == "Workspace"));
+    let Some(workspace) = workspace else {
+        ctx.warn("no workspace, can't add a leaderboard script");
+        return;
+    };
+    let already = dom
+        .descendants_of(workspace)
+        .any(|instance| instance.class == "Script" && instance.name == SCRIPT_NAME);
+    if already {
+        ctx.info("place already has a legacy leaderboard script");
+        return;
+    }
+    let stats = stat_names(dom);
+    if stats.is_empty() {
+        ctx.info("no scripts set up leaderstats, not adding a leaderboard");
+        return;
+    }
+    let script = InstanceBuilder::new("Script")
+        .with_name(SCRIPT_NAME)
+        .with_property("Source", Variant::String(leaderboard_source(&stats)));
+    let referent = dom.insert(workspace, script);
+    ctx.converted(referent, format!("added leaderboard script with stats {}", stats.join(", ")));
+}
@@ -0,0 +1,153 @@
+// how old clients size and weigh parts, for parts made out of modern ones
+//
+// anything that isn't FormFactor Custom gets snapped to the brick grid when an old client loads
+// it, and even Custom parts can't go below 0.2 studs. a MeshPart's mass also comes from its
+// collision mesh, while a Part + SpecialMesh weighs as much as its whole box.
+use rbx_dom_weak::Instance;
+use rbx_dom_weak::types::{CFrame, Enum, Vector3};
+use rbx_types::{CustomPhysicalProperties, PhysicalProperties, Variant};
+
+pub const FORM_FACTOR_BRICK: u32 = 1;
+pub const FORM_FACTOR_PLATE: u32 = 2;
+pub const FORM_FACTOR_CUSTOM: u32 = 3;
+
+// studs, width and depth always snap to whole ones
+pub const BRICK_HEIGHT: f32 = 1.2;
+pub const PLATE_HEIGHT: f32 = 0.4;
+
+pub const MIN_PART_SIZE: f32 = 0.2;
+pub const MAX_PART_SIZE: f32 = 2048.0;
+
+// roughly when each shape showed up, balls, blocks and cylinders have been around from the start
+pub const CORNER_WEDGE_PART_YEAR: u32 = 2009;
+pub const TRUSS_STYLE_YEAR: u32 = 2013;
+// Part.Shape Wedge and CornerWedge, before that they only existed as their own classes
+pub const PART_SHAPE_WEDGE_YEAR: u32 = 2022;
+// first clients that read CustomPhysicalProperties
+pub const CUSTOM_PHYSICAL_PROPERTIES_YEAR: u32 = 2015;
+
+// PartType values
+pub const PART_TYPE_WEDGE: u32 = 3;
+pub const PART_TYPE_CORNER_WEDGE: u32 = 4;
+
+// SpecialMesh MeshType.Wedge
+pub const MESH_TYPE_WEDGE: u32 = 2;
+
+// CollisionFidelity.Box, the only fidelity where the mesh fills its box
+const COLLISION_FIDELITY_BOX: u32 = 2;
+// rough share of the box a hull or decomposition takes up, good enough that a chair doesn't
+// weigh as much as a crate of the same size
+const COLLISION_FILL: f32 = 0.6;
+
+pub fn clamp_size(size: Vector3) -> Vector3 {
+    Vector3::new(
+        size.x.clamp(MIN_PART_SIZE, MAX_PART_SIZE),
+        size.y.clamp(MIN_PART_SIZE, MAX_PART_SIZE),
+        size.z.clamp(MIN_PART_SIZE, MAX_PART_SIZE),
+    )
+}
+
+// density, friction and elasticity the engine uses for a material
+pub fn material_physics(material: u32) -> (f32, f32, f32) {
+    match material {
+        // Wood, WoodPlanks
+        512 | 528 => (0.35, 0.48, 0.2),
+        // Marble
+        784 => (2.56, 0.2, 0.17),
+        // Slate, Granite, Cobblestone
+        800 | 832 | 880 => (2.69, 0.4, 0.2),
+        // Concrete
+        816 => (2.4, 0.7, 0.2),
+        // Brick
+        848 => (1.92, 0.8, 0.15),
+        // Pebble
+        864 => (2.21, 0.4, 0.17),
+        // CorrodedMetal, DiamondPlate, Metal
+        1040 | 1056 | 1088 => (7.85, 0.4, 0.25),
+        // Foil
+        1072 => (2.7, 0.4, 0.25),
+        // Grass
+        1280 => (0.9, 0.4, 0.1),
+        // Sand
+        1296 => (1.6, 0.5, 0.05),
+        // Fabric
+        1312 => (0.7, 0.35, 0.05),
+        // Ice
+        1536 => (0.919, 0.02, 0.15),
+        // Glass
+        1568 => (2.4, 0.25, 0.2),
+        // Plastic and everything else
+        _ => (0.7, 0.3, 0.5),
+    }
+}
+
+// what the part needs to weigh about the same once it's a plain box, None if nothing changes
+pub fn box_physical_properties(instance: &Instance) -> Option<PhysicalProperties> {
+    let fidelity = match instance.properties.get(&"CollisionFidelity".into()) {
+        Some(Variant::Enum(fidelity)) => fidelity.to_u32(),
+        _ => 0,
+    };
+    if fidelity == COLLISION_FIDELITY_BOX {
+        return None;
+    }
+    let mut custom = match instance.properties.get(&"CustomPhysicalProperties".into()) {
+        Some(Variant::PhysicalProperties(PhysicalProperties::Custom(custom))) => *custom,
+        _ => {
+            let material = match instance.properties.get(&"Material".into()) {
+                Some(Variant::Enum(material)) => material.to_u32(),
+                _ => 256,
+            };
+            let (density, friction, elasticity) = material_physics(material);
+            CustomPhysicalProperties::new(density, friction, elasticity, 1.0, 1.0, 1.0)
+        }
+    };
+    custom.set_density(custom.density() * COLLISION_FILL);
+    Some(PhysicalProperties::Custom(custom))
+}
+
+pub fn set_form_factor(instance: &mut Instance, form_factor: u32) {
+    instance
+        .properties
+        .insert("FormFactor".into(), Variant::Enum(Enum::from_u32(form_factor)));
+}
+
+fn snap(value: f32, step: f32) -> f32 {
+    (value / step).round() * step
+}
+
+// the size an old client would give the part and the FormFactor that gets it there, Brick unless
+// the height only fits the plate grid
+pub fn snap_size(size: Vector3) -> (Vector3, u32) {
+    let brick = snap(size.y, BRICK_HEIGHT).max(BRICK_HEIGHT);
+    let plate = snap(size.y, PLATE_HEIGHT).max(PLATE_HEIGHT);
+    let (height, form_factor) = if (brick - size.y).abs() <= (plate - size.y).abs() + 1e-4 {
+        (brick, FORM_FACTOR_BRICK)
+    } else {
+        (plate, FORM_FACTOR_PLATE)
+    };
+    let snapped = Vector3::new(size.x.round().max(1.0), height, size.z.round().max(1.0));
+    (snapped, form_factor)
+}
+
+// moves the part so its faces sit on the grid, only for parts turned in 90 degree steps since
+// anything else can't line up with it anyway
+pub fn snap_position(cframe: CFrame, size: Vector3) -> CFrame {
+    let rows = [cframe.orientation.x, cframe.orientation.y, cframe.orientation.z];
+    let aligned = rows.iter().all(|row| {
+        [row.x, row.y, row.z]
+            .iter()
+            .all(|v| v.abs() < 1e-3 || (v.abs() - 1.0).abs() < 1e-3)
+    });
+    if !aligned {
+        return cframe;
+    }
+    // how far the part reaches along each world axis
+    let extent = rows.map(|row| row.x.abs() * size.x + row.y.abs() * size.y + row.z.abs() * size.z);
+    let corner = |position: f32, extent: f32, step: f32| snap(position - extent / 2.0, step) + extent / 2.0;
+    let position = Vector3::new(
+        corner(cframe.position.x, extent[0], 1.0),
+        corner(cframe.position.y, extent[1], PLATE_HEIGHT),
+        corner(cframe.position.z, extent[2], 1.0),
+    );
+    CFrame::new(position, cframe.orientation)
+}
@@ -0,0 +1,388 @@
+use clap::ValueEnum;
+use std::{fs, path::{Path, PathBuf}};
+use rbx_dom_weak::WeakDom;
+use rbx_xml::{from_reader_default, to_writer_default};
+use std::io::Cursor;
+use std::collections::BTreeMap;
+use std::error::Error;
+use encoding_rs::WINDOWS_1252;
+use mappings::InstanceMappings;
+use asset_urls::AssetUrlConfig;
+use pipeline::RunReport;
+use dom_limits::DomLimits;
+pub mod anim;
+pub mod asset_era;
+pub mod asset_source;
+pub mod asset_urls;
+pub mod atlas;
+pub mod axes;
+pub mod baseplate;
+pub mod batch;
+pub mod binary_compat;
+pub mod collada;
+pub mod content;
+pub mod conversion_log;
+pub mod content_uri;
+pub mod daemon;
+pub mod dom_cache;
+pub mod dom_limits;
+pub mod error;
+pub mod fetch;
+pub mod filemesh;
+pub mod floating;
+pub mod gltf;
+#[cfg(feature = "gui")]
+pub mod gui;
+pub mod imesh;
+pub mod importer;
+pub mod inserted_assets;
+pub mod launcher;
+pub mod leaderstats;
+pub mod legacy_parts;
+pub mod mappings;
+pub mod mem_stats;
+pub mod mesh_dedup;
+pub mod mesh_export;
+pub mod mesh_info;
+pub mod mesh_lods;
+pub mod mesh_normals;
+pub mod mesh_tangents;
+pub mod mesh_topology;
+pub mod mesh_types;
+pub mod mesh_validate;
+pub mod mesh_weld;
+pub mod options;
+pub mod package_links;
+pub mod passes;
+pub mod pass_scope;
+pub mod path;
+pub mod pipeline;
+pub mod place_info;
+pub mod place_transform;
+pub mod placeholder;
+pub mod ply;
+pub mod policy;
+pub mod prepare_legacy;
+pub mod presets;
+pub mod profile;
+#[cfg(feature = "python")]
+mod python;
+pub mod rbxl_chunks;
+pub mod recover;
+pub mod region;
+pub mod repl;
+pub mod replication;
+pub mod s3;
+pub mod ser;
+pub mod serve;
+pub mod shard;
+pub mod shared_strings;
+pub mod sniff;
+pub mod suggest;
+pub mod tags;
+pub mod thumbnail;
+pub mod tools;
+pub mod unique_ids;
+pub mod universe;
+pub mod verify;
+pub mod world_space;
+pub mod xml_compat;
+
+pub use options::{FixPlaceOptions, OutputFormat};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RobloxMeshVersion {
+    V1_00,
+    V1_01,
+    V2_00,
+    V3_00,
+    V4_00,
+    V5_00,
+}
+
+// clients by year, for picking a mesh version without knowing which years read which
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TargetClient {
+    #[value(name = "2010")]
+    Client2010,
+    #[value(name = "2014")]
+    Client2014,
+    #[value(name = "2018")]
+    Client2018,
+}
+
+// roughly when clients started reading each mesh version, oldest first
+const MESH_VERSION_YEARS: [(RobloxMeshVersion, u32); 6] = [
+    (RobloxMeshVersion::V1_00, 2006),
+    (RobloxMeshVersion::V1_01, 2009),
+    (RobloxMeshVersion::V2_00, 2013),
+    (RobloxMeshVersion::V3_00, 2019),
+    (RobloxMeshVersion::V4_00, 2021),
+    (RobloxMeshVersion::V5_00, 2022),
+];
+
+impl RobloxMeshVersion {
+    // v3 and up carry lower detail levels after the mesh's own faces
+    pub fn has_lods(self) -> bool {
+        !matches!(self, Self::V1_00 | Self::V1_01 | Self::V2_00)
+    }
+}
+
+impl TargetClient {
+    pub fn year(self) -> u32 {
+        match self {
+            Self::Client2010 => 2010,
+            Self::Client2014 => 2014,
+            Self::Client2018 => 2018,
+        }
+    }
+
+    // the newest version the client reads
+    pub fn mesh_version(self) -> RobloxMeshVersion {
+        MESH_VERSION_YEARS
+            .iter()
+            .rev()
+            .find(|(_, year)| *year <= self.year())
+            .map_or(RobloxMeshVersion::V1_00, |(version, _)| *version)
+    }
+}
+
+pub fn is_binary_rbxl(bytes: &[u8]) -> bool {
+    const MAGIC: [u8; 16] = [
+        0x3C, 0x72, 0x6F, 0x62, 0x6C, 0x6F, 0x78, 0x21,
+        0x89, 0xFF, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00,
+    ];
+    bytes.starts_with(&MAGIC)
+}
+
+// todo: merge with serialize_mesh and make an arg determine which to do
+pub fn convert_obj_to_filemesh(obj_data: &[u8], version: RobloxMeshVersion) -> error::Result<Vec<u8>> {
+    let mesh = importer::obj_to_intermediate(obj_data)?;
+    let bytes = match version {
+        RobloxMeshVersion::V1_00 => ser::write_v1(&mesh, ser::V1Version::V1_00)?,
+        RobloxMeshVersion::V1_01 => ser::write_v1(&mesh, ser::V1Version::V1_01)?,
+        RobloxMeshVersion::V2_00 => ser::write_v2(&mesh)?,
+        RobloxMeshVersion::V3_00 => ser::write_v3(&mesh)?,
+        RobloxMeshVersion::V4_00 => ser::write_v4(&mesh)?,
+        RobloxMeshVersion::V5_00 => ser::write_v5(&mesh)?,
+    };
+    Ok(bytes)
+}
+pub fn serialize_mesh(mesh: &mesh_types::IntermediateMesh, version: RobloxMeshVersion) -> error::Result<Vec<u8>> {
+    serialize_mesh_with_lods(mesh, &[], version)
+}
+
+// lower detail levels after the mesh's own faces, see mesh_lods::generate_lods. v1 and v2 have
+// nowhere to put them
+pub fn serialize_mesh_with_lods(
+    mesh: &mesh_types::IntermediateMesh,
+    lods: &[Vec<[u32; 3]>],
+    version: RobloxMeshVersion,
+) -> error::Result<Vec<u8>> {
+    if !lods.is_empty() && !version.has_lods() {
+        return Err(error::ConversionError::Unsupported("lods need a v3 or newer mesh".to_owned()));
+    }
+    let bytes = match version {
+        RobloxMeshVersion::V1_00 => ser::write_v1(mesh, ser::V1Version::V1_00)?,
+        RobloxMeshVersion::V1_01 => ser::write_v1(mesh, ser::V1Version::V1_01)?,
+        RobloxMeshVersion::V2_00 => ser::write_v2(mesh)?,
+        RobloxMeshVersion::V3_00 => ser::write_v3_with_lods(mesh, lods)?,
+        RobloxMeshVersion::V4_00 => ser::write_v4_with_lods(mesh, lods)?,
+        RobloxMeshVersion::V5_00 => ser::write_v5_with_lods(mesh, lods)?,
+    };
+    Ok(bytes)
+}
+
+
+// an ascii mesh laid out some other way than serialize_mesh does, see ser::V1Format
+pub fn serialize_mesh_v1(
+    mesh: &mesh_types::IntermediateMesh,
+    version: RobloxMeshVersion,
+    format: ser::V1Format,
+) -> error::Result<Vec<u8>> {
+    let version = match version {
+        RobloxMeshVersion::V1_00 => ser::V1Version::V1_00,
+        RobloxMeshVersion::V1_01 => ser::V1Version::V1_01,
+        _ => {
+            return Err(error::ConversionError::Unsupported(
+                "legacy line endings and formatting are only for ascii (v1) meshes".to_owned(),
+            ));
+        }
+    };
+    ser::write_v1_formatted(mesh, version, format)
+}
+
+// any filemesh version to another, lods kept when the target version has them
+pub fn convert_filemesh_to_filemesh(filemesh_data: &[u8], version: RobloxMeshVersion) -> error::Result<Vec<u8>> {
+    let (mesh, lods) = filemesh::parse_filemesh_with_lods(filemesh_data)?;
+    let lods = if version.has_lods() { lods.as_slice() } else { &[] };
+    serialize_mesh_with_lods(&mesh, lods, version)
+}
+
+pub fn convert_filemesh_to_obj(filemesh_data: &[u8]) -> error::Result<Vec<u8>> {
+    filemesh::filemesh_to_obj_bytes(filemesh_data)
+}
+
+// glb with the skeleton and weights when the mesh has them
+pub fn convert_filemesh_to_gltf(filemesh_data: &[u8]) -> error::Result<Vec<u8>> {
+    let mesh = filemesh::parse_filemesh(filemesh_data)?;
+    gltf::mesh_to_glb(&mesh)
+}
+
+// v1-v3 have nowhere to put bones, a skinned gltf written as one of those loses its skin. a .gltf
+// has to have its buffers embedded, there's no file to find them next to
+pub fn convert_gltf_to_filemesh(gltf_data: &[u8], version: RobloxMeshVersion) -> error::Result<Vec<u8>> {
+    let mesh = importer::gltf_to_intermediate(gltf_data, None)?;
+    serialize_mesh(&mesh, version)
+}
+
+pub fn load_instance_mappings(path: &PathBuf) -> Result<InstanceMappings, Box<dyn Error>> {
+    let data = fs::read_to_string(path)?;
+    Ok(InstanceMappings::from_json(&data)?)
+}
+
+pub fn load_asset_url_config(path: &PathBuf) -> Result<AssetUrlConfig, Box<dyn Error>> {
+    let data = fs::read_to_string(path)?;
+    AssetUrlConfig::from_json(&data)
+}
+
+pub fn load_asset_aliases(path: &PathBuf) -> Result<BTreeMap<u64, u64>, Box<dyn Error>> {
+    let data = fs::read_to_string(path)?;
+    mesh_dedup::aliases_from_json(&data)
+}
+
+pub fn load_pass_scopes(path: &PathBuf) -> Result<pass_scope::PassScopes, Box<dyn Error>> {
+    let data = fs::read_to_string(path)?;
+    pass_scope::scopes_from_json(&data)
+}
+
+pub fn load_place(input_bytes: &[u8]) -> Result<WeakDom, Box<dyn Error>> {
+    load_place_with_threads(input_bytes, 1)
+}
+
+// binary chunks are decompressed on parse_threads threads, xml is always parsed on one
+pub fn load_place_with_threads(input_bytes: &[u8], parse_threads: usize) -> Result<WeakDom, Box<dyn Error>> {
+    load_place_with_limits(input_bytes, parse_threads, &DomLimits::default())
+}
+
+// a place past the limits is an error instead of a stack overflow, see dom_limits
+pub fn load_place_with_limits(
+    input_bytes: &[u8],
+    parse_threads: usize,
+    limits: &DomLimits,
+) -> Result<WeakDom, Box<dyn Error>> {
+    let dom = if is_binary_rbxl(input_bytes) {
+        let reader = Cursor::new(input_bytes);
+        rbx_binary::Deserializer::new()
+            .decompression_threads(parse_threads)
+            .deserialize(reader)
+            .map_err(|e| Box::<dyn Error>::from(e.to_string()))?
+    } else {
+        // fix for some saveinstances
+        let xml_str = match String::from_utf8(input_bytes.to_vec()) {
+            Ok(s) => s,
+            Err(_) => {
+                let (cow, _, _) = WINDOWS_1252.decode(input_bytes);
+                cow.into_owned()
+            }
+        };
+        dom_limits::check_xml_nesting(xml_str.as_bytes(), limits)?;
+        let mut reader = Cursor::new(xml_str.as_bytes());
+        from_reader_default(&mut reader).map_err(|e| Box::<dyn Error>::from(e.to_string()))?
+    };
+    dom_limits::check_dom(&dom, limits)?;
+    Ok(dom)
+}
+
+// xml for .rbxlx/.rbxmx, binary for anything else
+pub fn save_dom(dom: &WeakDom, path: &Path) -> Result<(), Box<dyn Error>> {
+    let root_refs: Vec<_> = dom.root().children().to_vec();
+    let mut output = Vec::new();
+    let is_xml = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("rbxlx") || ext.eq_ignore_ascii_case("rbxmx"));
+    if is_xml {
+        to_writer_default(&mut output, dom, &root_refs).map_err(|e| e.to_string())?;
+    } else {
+        rbx_binary::to_writer(&mut output, dom, &root_refs).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, output)?;
+    Ok(())
+}
+
+pub fn fix_place(input_bytes: &[u8], options: FixPlaceOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    fix_place_with_report(input_bytes, options).map(|(output, _)| output)
+}
+
+// fix_place, plus the warnings and per pass/class counts of the run
+pub fn fix_place_with_report(
+    input_bytes: &[u8],
+    mut options: FixPlaceOptions,
+) -> Result<(Vec<u8>, RunReport), Box<dyn Error>> {
+    let is_binary_input = is_binary_rbxl(input_bytes);
+    let pipeline = options.take_pipeline();
+    if options.mem_stats {
+        mem_stats::enable();
+    }
+    let allocs_before_parse = mem_stats::snapshot();
+    let mut dom =
+        dom_cache::load_place_cached(input_bytes, options.cache_dir.as_deref(), options.parse_threads, &options.dom_limits)?;
+    let parse_allocs = mem_stats::snapshot().since(&allocs_before_parse);
+    let mut report = pipeline.run_observed(&mut dom, options.observer.as_mut())?;
+    if options.conversion_log {
+        conversion_log::write_conversion_log(&mut dom, &report, options.preset, options.folders_to_models);
+    }
+    let root_refs: Vec<_> = dom.root().children().to_vec();
+    let mut output = Vec::new();
+    let should_output_xml = match options.output_format {
+        OutputFormat::SameAsInput => !is_binary_input,
+        OutputFormat::Xml => true,
+        OutputFormat::Binary => false,
+    };
+    if should_output_xml {
+        to_writer_default(&mut output, &dom, &root_refs).map_err(|e| Box::<dyn Error>::from(e.to_string()))?;
+        if let Some(filtering) = replication::filtering_enabled(&dom) {
+            output = replication::write_filtering_enabled(&output, filtering)?;
+        }
+        if let Some(era) = options.xml_compat {
+            output = xml_compat::apply_xml_compat(&output, era)?;
+        }
+        if options.binary_compat.is_some() {
+            report.warn(
+                options.observer.as_mut(),
+                "binary-compat",
+                "output is xml, --binary-compat only applies to binary output",
+            );
+        }
+    } else {
+        if replication::filtering_enabled(&dom) == Some(true) {
+            report.warn(
+                options.observer.as_mut(),
+                "replication",
+                "binary output can't carry FilteringEnabled, save as xml to keep it",
+            );
+        }
+        let era_year = options.binary_compat.map(|era| era.year());
+        rbx_binary::Serializer::new()
+            .inline_shared_strings(
+                options.expand_shared_strings || era_year.is_some_and(|year| year < binary_compat::SHARED_STRING_YEAR),
+            )
+            .legacy_content(era_year.is_some_and(|year| year < binary_compat::CONTENT_YEAR))
+            .serialize(&mut output, &dom, &root_refs)
+            .map_err(|e| Box::<dyn Error>::from(e.to_string()))?;
+        if let Some(era) = options.binary_compat {
+            binary_compat::check_output(&output, era)?;
+        }
+    }
+    if options.verify {
+        let summary = verify::verify_output(&dom, &output)?;
+        options.observer.on_info("verify", &summary.to_text());
+        report.verify = Some(summary);
+    }
+    // after writing, so the peaks include serializing
+    if let Some(stats) = report.mem_stats.as_mut() {
+        stats.collect(&dom, parse_allocs);
+    }
+    Ok((output, report))
+}
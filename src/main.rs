@@ -1,400 +1,1493 @@
-use clap::{Parser, Subcommand, ValueEnum};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 use std::{fs, path::PathBuf};
-use std::collections::HashMap;
-use rbx_dom_weak::{WeakDom, Ustr, InstanceBuilder};
-use rbx_types::Variant;
-use rbx_binary::{from_reader, to_writer};
-use rbx_xml::{from_reader_default, to_writer_default};
-use chrono::Utc;
-use std::io::Cursor;
+use rbx_xml::to_writer_default;
+use chrono::{NaiveDate, Utc};
 use std::error::Error;
-use rbx_types::Content;
-use encoding_rs::WINDOWS_1252;
-mod error;
-mod filemesh;
-mod importer;
-mod mesh_types;
-mod ser;
+use roblox_utils::baseplate::Baseplate;
+use roblox_utils::binary_compat::BinaryCompat;
+use roblox_utils::dom_limits::{self as limits, DomLimits};
+use roblox_utils::importer::ImportCleanup;
+use roblox_utils::asset_source::{self, AssetSource};
+use roblox_utils::axes::{AxisConversion, UpAxis};
+use roblox_utils::package_links::PackageVersion;
+use roblox_utils::policy::FailRule;
+use roblox_utils::place_transform::{MirrorAxis, PlaceTransform};
+use roblox_utils::presets::Preset;
+use roblox_utils::shard::Grid;
+use roblox_utils::tags::TagConversion;
+use roblox_utils::thumbnail::ThumbnailCamera;
+use roblox_utils::unique_ids::UniqueIdHandling;
+use roblox_utils::world_space::RebaseOrigin;
+use roblox_utils::xml_compat::XmlCompat;
+use rbx_types::Vector3;
+use roblox_utils::*;
 
-#[derive(ValueEnum, Clone, Copy, Debug)]
-enum RobloxMeshVersion {
-    V1_00,
-    V1_01,
-    V2_00,
-    V3_00,
-    V4_00,
-    V5_00,
-}
+// counts nothing until --mem-stats turns it on
+#[global_allocator]
+static ALLOCATOR: mem_stats::CountingAllocator = mem_stats::CountingAllocator;
 
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+    // the input is a glob and the output a dir, every match converted into it, see batch.rs
+    #[arg(long, global = true)]
+    batch: bool,
 }
 
-#[derive(Subcommand)]
+// parsed once at startup, the size of the fix-place variant doesn't matter
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Subcommand)]
 enum Commands {
     ObjToFilemesh {
+        input: PathBuf,
+        #[arg(required_unless_present = "out")]
+        output: Option<PathBuf>,
+        // wins over --target-client when both are given
+        #[arg(required_unless_present_any = ["target_client", "out"])]
+        version: Option<RobloxMeshVersion>,
+        // write the newest mesh version this client reads
+        #[arg(long, value_enum)]
+        target_client: Option<TargetClient>,
+        // up axis of the tool the obj came from, z for blender
+        #[arg(long, value_enum, default_value_t = UpAxis::Y)]
+        up_axis: UpAxis,
+        // obj units per stud
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        // cap holes in the mesh, up to --max-hole-perimeter around
+        #[arg(long)]
+        fill_holes: bool,
+        // studs, after --scale
+        #[arg(long, default_value_t = 4.0)]
+        max_hole_perimeter: f32,
+        // merge vertices this many studs apart (after --scale) that look the same, see mesh_weld.rs
+        #[arg(long, value_name = "EPSILON")]
+        weld: Option<f32>,
+        // simplified lod levels to add after the full mesh, each about half the faces of the last
+        #[arg(long, value_name = "N", default_value_t = 0)]
+        lods: usize,
+        // work the normals out from the faces instead of using the obj's, see mesh_normals.rs
+        #[arg(long)]
+        recompute_normals: bool,
+        // degrees between faces past which an edge stays hard
+        #[arg(long, value_name = "DEG", default_value_t = mesh_normals::DEFAULT_SMOOTH_ANGLE, requires = "recompute_normals")]
+        smooth_angle: f32,
+        // keep imported objs here as .imesh so later runs on the same file skip the import
+        #[arg(long, value_name = "DIR")]
+        cache_intermediate: Option<PathBuf>,
+        // another file written from the same import, as FORMAT:PATH with FORMAT a mesh version
+        // (v2 for v2-00), obj or ply. can be given any number of times
+        #[arg(long, value_name = "FORMAT:PATH", value_parser = parse_mesh_output)]
+        out: Vec<MeshOutput>,
+        #[command(flatten)]
+        upload: UploadCheckArgs,
+        #[command(flatten)]
+        v1: V1FormatArgs,
+    },
+    FilemeshToObj {
+        input: PathBuf,
+        output: PathBuf,
+        // which lod level to convert, the highest detail (0) when not given
+        #[arg(long)]
+        lod: Option<usize>,
+        // up axis of the tool the obj is for, z for blender
+        #[arg(long, value_enum, default_value_t = UpAxis::Y)]
+        up_axis: UpAxis,
+        // obj units per stud
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        // also write <output>.normals.obj with a line along every vertex normal
+        #[arg(long)]
+        debug_normals: bool,
+        // read an ascii (v1) mesh as far as it goes and comment every face with where it is in the
+        // file, for finding the vector that breaks a mesh
+        #[arg(long, conflicts_with = "lod")]
+        debug_v1: bool,
+        // every lod level, each as its own object in the obj. vertex colors aren't written
+        #[arg(long, conflicts_with_all = ["lod", "debug_v1"])]
+        all_lods: bool,
+    },
+    FilemeshToGltf {
+        input: PathBuf,
+        output: PathBuf,
+    },
+    PlyToFilemesh {
+        // ascii or binary little endian, vertex colors are kept
         input: PathBuf,
         output: PathBuf,
         version: RobloxMeshVersion,
+        // up axis of the tool the ply came from, z for blender
+        #[arg(long, value_enum, default_value_t = UpAxis::Y)]
+        up_axis: UpAxis,
+        // ply units per stud
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        // merge vertices this many studs apart (after --scale) that look the same, see mesh_weld.rs
+        #[arg(long, value_name = "EPSILON")]
+        weld: Option<f32>,
+        // cap holes in the mesh, up to --max-hole-perimeter around
+        #[arg(long)]
+        fill_holes: bool,
+        // studs, after --scale
+        #[arg(long, default_value_t = 4.0)]
+        max_hole_perimeter: f32,
+        #[command(flatten)]
+        upload: UploadCheckArgs,
+        #[command(flatten)]
+        v1: V1FormatArgs,
     },
-    FilemeshToObj {
+    DaeToFilemesh {
+        input: PathBuf,
+        output: PathBuf,
+        version: RobloxMeshVersion,
+        // up axis the dae was made with, what the file says when not given
+        #[arg(long, value_enum)]
+        up_axis: Option<UpAxis>,
+        // dae units per stud
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        // merge vertices this many studs apart (after --scale) that look the same, see mesh_weld.rs
+        #[arg(long, value_name = "EPSILON")]
+        weld: Option<f32>,
+        // cap holes in the mesh, up to --max-hole-perimeter around
+        #[arg(long)]
+        fill_holes: bool,
+        // studs, after --scale
+        #[arg(long, default_value_t = 4.0)]
+        max_hole_perimeter: f32,
+        #[command(flatten)]
+        upload: UploadCheckArgs,
+        #[command(flatten)]
+        v1: V1FormatArgs,
+    },
+    FilemeshToPly {
         input: PathBuf,
         output: PathBuf,
+        // which lod level to convert, the highest detail (0) when not given
+        #[arg(long)]
+        lod: Option<usize>,
+        // up axis of the tool the ply is for, z for blender
+        #[arg(long, value_enum, default_value_t = UpAxis::Y)]
+        up_axis: UpAxis,
+        // ply units per stud
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        // binary little endian instead of ascii
+        #[arg(long)]
+        binary: bool,
     },
-    FilemeshToFilemesh {
+    GltfToFilemesh {
+        // .glb, or .gltf with its buffers embedded or next to it
         input: PathBuf,
         output: PathBuf,
         version: RobloxMeshVersion,
+        // cap holes in the mesh, up to --max-hole-perimeter around
+        #[arg(long)]
+        fill_holes: bool,
+        // studs
+        #[arg(long, default_value_t = 4.0)]
+        max_hole_perimeter: f32,
+        // merge vertices this many studs apart that look the same, see mesh_weld.rs
+        #[arg(long, value_name = "EPSILON")]
+        weld: Option<f32>,
+        #[command(flatten)]
+        upload: UploadCheckArgs,
+        #[command(flatten)]
+        v1: V1FormatArgs,
+    },
+    AnimToGltf {
+        // rbxm(x) with a KeyframeSequence in it
+        input: PathBuf,
+        // model with the Motor6D rig the animation was made for
+        rig: PathBuf,
+        output: PathBuf,
+    },
+    GltfToAnim {
+        // glb with an animation on nodes named after the rig's parts
+        input: PathBuf,
+        rig: PathBuf,
+        // .rbxm or .rbxmx
+        output: PathBuf,
+        // keyframes per second
+        #[arg(long, default_value_t = 30.0)]
+        fps: f32,
+    },
+    // any filemesh version to another without going through obj. the lods come along when the
+    // target version has room for them (v3 and up)
+    FilemeshToFilemesh {
+        input: PathBuf,
+        output: PathBuf,
+        // the version to write, --version or a third positional
+        #[arg(required_unless_present = "version_flag", value_name = "VERSION")]
+        version: Option<RobloxMeshVersion>,
+        #[arg(long = "version", id = "version_flag", value_name = "VERSION", conflicts_with = "version")]
+        version_flag: Option<RobloxMeshVersion>,
+        // which lod level to convert on its own, every level when not given
+        #[arg(long)]
+        lod: Option<usize>,
+        // write the mesh's oriented bounding box (8 vertices, 12 triangles) instead of the mesh
+        #[arg(long)]
+        placeholder: bool,
+        // also write every lod level as <name>_lod<n>.mesh (v2) here, for clients without lods
+        #[arg(long, value_name = "DIR")]
+        export_lods: Option<PathBuf>,
+        #[command(flatten)]
+        upload: UploadCheckArgs,
+        #[command(flatten)]
+        v1: V1FormatArgs,
+    },
+    // meshes in a dir (named by asset id) with the same geometry, see mesh_dedup.rs
+    MeshDedup {
+        dir: PathBuf,
+        // write duplicate id -> kept id for fix-place --asset-aliases
+        #[arg(long)]
+        aliases: Option<PathBuf>,
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    // version, counts, lods, bones, facs, bounds and vertex colors of a filemesh
+    MeshInfo {
+        input: PathBuf,
+        #[arg(long)]
+        json: bool,
+    },
+    // fewer faces by the same edge collapses --lods uses, see mesh_lods.rs
+    MeshSimplify {
+        // filemesh, or obj by extension
+        input: PathBuf,
+        // obj by extension, a filemesh of --version otherwise
+        output: PathBuf,
+        #[arg(long, value_name = "N", required_unless_present = "target_ratio", conflicts_with = "target_ratio")]
+        target_faces: Option<usize>,
+        // fraction of the faces to keep, 0.5 for half
+        #[arg(long, value_name = "RATIO")]
+        target_ratio: Option<f32>,
+        #[arg(long, value_enum)]
+        version: Option<RobloxMeshVersion>,
+    },
+    MeshCheck {
+        // filemesh, or obj by extension
+        input: PathBuf,
+        // non-manifold edges, holes and disconnected pieces
+        #[arg(long)]
+        topology: bool,
+        // a dynamic head's facs poses, written out as json
+        #[arg(long, value_name = "FILE")]
+        facs_json: Option<PathBuf>,
+        #[command(flatten)]
+        upload: UploadCheckArgs,
     },
     FixPlace {
         input: PathBuf,
         output: PathBuf,
+        #[command(flatten)]
+        fix: FixPlaceArgs,
+        // warnings and per pass/class counts, also printed at the end
+        #[arg(long)]
+        report: Option<PathBuf>,
+        // print peak rss, dom size, interned strings and allocations per pass
+        #[arg(long)]
+        mem_stats: bool,
+    },
+    ListPresets,
+    // fix-place with a preset, then every mesh, texture and sound the place loads by id converted
+    // into a content folder for the client, see prepare_legacy.rs
+    PrepareLegacy {
+        input: PathBuf,
+        out_dir: PathBuf,
+        // the mesh version the client reads
+        version: RobloxMeshVersion,
+        // where assets referenced by id come from: roblox, a dir of <id> files, a mirror url or
+        // s3://bucket/prefix, see asset_source.rs
+        #[arg(long, visible_alias = "content-source", value_name = "SOURCE")]
+        asset_source: AssetSource,
+        // pixels, bigger textures are scaled down to fit
+        #[arg(long, default_value_t = prepare_legacy::DEFAULT_MAX_TEXTURE_SIZE)]
+        max_texture_size: u32,
+        // the client's own content dir, rbxasset:// references have to be in it or the package
+        #[arg(long, value_name = "DIR")]
+        client_content: Option<PathBuf>,
+        // lay the package out the way this launcher loads it, see launcher.rs
+        #[arg(long, value_enum)]
+        launcher: Option<launcher::Launcher>,
+        #[command(flatten)]
+        fix: FixPlaceArgs,
+        // the whole run's report, also printed at the end
+        #[arg(long)]
+        report: Option<PathBuf>,
+    },
+    PlaceRecover {
+        input: PathBuf,
+        output: PathBuf,
+        #[arg(long)]
+        report: Option<PathBuf>,
+        // deepest nesting the recovered place can have, see dom_limits.rs
+        #[arg(long, value_name = "LEVELS", default_value_t = limits::DEFAULT_MAX_DEPTH)]
+        max_depth: usize,
+    },
+    RbxlInspect {
+        input: PathBuf,
+    },
+    Repl {
+        input: PathBuf,
+        // keep parsed places here so later runs on the same file skip the parse
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    Daemon {
+        inbox: PathBuf,
+        outbox: PathBuf,
+        #[arg(long, default_value_t = 2)]
+        workers: usize,
+        #[arg(long, default_value_t = 1000)]
+        poll_ms: u64,
+        // also accept jobs over http on this address
+        #[arg(long)]
+        bind: Option<String>,
+    },
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        bind: String,
+        #[arg(long, default_value_t = 4)]
+        workers: usize,
+        #[arg(long, default_value_t = 256)]
+        max_upload_mb: usize,
+    },
+    VerifyContent {
+        content_dir: PathBuf,
+        place: PathBuf,
+        // json of relative path -> sha256 to check files against
+        #[arg(long)]
+        manifest: Option<PathBuf>,
+        // hash the content dir into a manifest first, for use with other installs
+        #[arg(long)]
+        write_manifest: Option<PathBuf>,
+        // keep parsed places here so later runs on the same file skip the parse
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    // format, instance and class counts read straight from the file
+    PlaceInfo {
+        input: PathBuf,
+        // guess which studio saved it from what it uses, and the preset that goes with that
+        #[arg(long)]
+        detect_era: bool,
+    },
+    // fix-place options for taking the place to an older client, and what they'll lose
+    Suggest {
+        input: PathBuf,
+        // year of the target client
+        #[arg(long, value_name = "YEAR")]
+        target: u32,
+        // the options as json, what the http api takes and a daemon sidecar holds
+        #[arg(long)]
+        sidecar: Option<PathBuf>,
+    },
+    // unanchored parts that fall when the place starts, with nothing under them
+    FloatingParts {
+        input: PathBuf,
+        // studs between a part and what's under it that still count as resting on it
+        #[arg(long, default_value_t = floating::DEFAULT_WITHIN)]
+        within: f32,
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    // the parts touching a box and the models they're in, saved as a model. see region.rs
+    ExtractRegion {
+        input: PathBuf,
+        // corners of the box, "x,y,z"
+        #[arg(value_parser = region::parse_point, allow_hyphen_values = true)]
+        min: Vector3,
+        #[arg(value_parser = region::parse_point, allow_hyphen_values = true)]
+        max: Vector3,
+        // .rbxm, or .rbxmx for xml
+        output: PathBuf,
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    // the place scaled, turned and mirrored, see place_transform.rs
+    PlaceTransform {
+        input: PathBuf,
+        output: PathBuf,
+        #[arg(long, default_value_t = 1.0)]
+        scale: f32,
+        // degrees around the vertical axis
+        #[arg(long, default_value_t = 0.0, allow_hyphen_values = true)]
+        rotate: f32,
+        #[arg(long, value_enum)]
+        mirror: Option<MirrorAxis>,
+        // the point that stays put, "center" for the middle of the map or x,y,z
+        #[arg(long, default_value = "0,0,0", allow_hyphen_values = true)]
+        around: RebaseOrigin,
+        // warnings and counts, also printed at the end
         #[arg(long)]
-        folders_to_models: bool,
+        report: Option<PathBuf>,
+    },
+    // the map split into a grid of places with everything else in each, see shard.rs
+    Shard {
+        input: PathBuf,
+        out_dir: PathBuf,
+        // <cols>x<rows>, columns along x and rows along z
+        grid: Grid,
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    PlaceProfile {
+        input: PathBuf,
+        #[arg(long, default_value_t = 20)]
+        top: usize,
+        // keep parsed places here so later runs on the same file skip the parse
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    // every mesh the place uses converted to one version, optionally with the textures they're paired with
+    ExportMeshes {
+        input: PathBuf,
+        out_dir: PathBuf,
+        version: RobloxMeshVersion,
+        // where meshes and textures referenced by id come from: roblox, a dir of <id> files, a
+        // mirror url or s3://bucket/prefix, see asset_source.rs
+        #[arg(long, visible_alias = "content-source", value_name = "SOURCE")]
+        asset_source: Option<AssetSource>,
+        // for rbxasset:// references
         #[arg(long)]
-        convert_meshparts: bool,
+        content_dir: Option<PathBuf>,
+        // copy each mesh's TextureId next to it and list the pairs in the manifest
         #[arg(long)]
-        force_xml: bool,
+        with_textures: bool,
+        // write each mesh's oriented bounding box instead of the mesh
         #[arg(long)]
-        force_binary: bool,
+        placeholder: bool,
+        #[arg(long, value_name = "DIR")]
+        cache_dir: Option<PathBuf>,
+    },
+    // says what each file is (mesh, model, place, image, audio, lua...), for extensionless downloads
+    Sniff {
+        inputs: Vec<PathBuf>,
+    },
+    // textures from an export-meshes dir packed into atlases, see atlas.rs
+    AtlasPack {
+        // the manifest.json export-meshes wrote
+        manifest: PathBuf,
+        out_dir: PathBuf,
+        version: RobloxMeshVersion,
+        #[arg(long, default_value_t = 1024)]
+        max_size: u32,
+        // pixels of edge around each texture
+        #[arg(long, default_value_t = 2)]
+        padding: u32,
+    },
+    // downloads the assets in a list, old versions too, with a json sidecar for each. see fetch.rs
+    Fetch {
+        // one <id> or <id>@<version> per line
+        list: PathBuf,
+        out_dir: PathBuf,
+        // {id} and {version} are filled in, version is added as a query parameter without {version}
+        #[arg(long, default_value = fetch::DEFAULT_URL, conflicts_with = "content_source")]
+        url: String,
+        // a file holding the .ROBLOSECURITY cookie
+        #[arg(long, conflicts_with = "content_source")]
+        cookie_file: Option<PathBuf>,
+        // roblox, a dir, a mirror url or s3://bucket/prefix instead of --url, see asset_source.rs
+        #[arg(long, value_name = "SOURCE")]
+        content_source: Option<AssetSource>,
+        // wait between downloads, the endpoint rate limits
+        #[arg(long, default_value_t = 250)]
+        delay_ms: u64,
+        // download again even when the file is already there
         #[arg(long)]
-        convert_assetid_to_url: bool,
-        #[arg(long, default_value = "http://www.roblox.com/asset/?id=")]
-        asset_url_format: String,
+        force: bool,
+    },
+    // a start place and its sub-places converted together, see universe.rs
+    Universe {
+        #[command(subcommand)]
+        command: UniverseCommands,
+    },
+}
+
+// parsed once too, fix carries all of fix-place's options
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Subcommand)]
+enum UniverseCommands {
+    // convert every place with the same options and rewrite the place ids between them
+    Fix {
+        // json listing the places and their ids
+        manifest: PathBuf,
+        out_dir: PathBuf,
+        #[command(flatten)]
+        fix: FixPlaceArgs,
+        // every place's warnings and counts, also printed at the end
         #[arg(long)]
-        instance_mappings_file: Option<PathBuf>,
+        report: Option<PathBuf>,
     },
+    // list the places scripts teleport to and whether they're part of the universe
+    Scan { manifest: PathBuf },
 }
 
-fn is_binary_rbxl(bytes: &[u8]) -> bool {
-    const MAGIC: [u8; 16] = [
-        0x3C, 0x72, 0x6F, 0x62, 0x6C, 0x6F, 0x78, 0x21,
-        0x89, 0xFF, 0x0D, 0x0A, 0x1A, 0x0A, 0x00, 0x00,
-    ];
-    bytes.starts_with(&MAGIC)
+// everything fix-place changes about a place, shared with universe fix
+#[derive(Clone, Args)]
+struct FixPlaceArgs {
+    #[arg(long)]
+    folders_to_models: bool,
+    #[arg(long)]
+    convert_meshparts: bool,
+    #[arg(long)]
+    legacy_size_grid: bool,
+    // year of the target client, part shapes it doesn't have get replaced
+    #[arg(long, value_name = "YEAR")]
+    shape_fallbacks: Option<u32>,
+    // year of the target client, texture offsets and tiling it doesn't have get baked out
+    #[arg(long, value_name = "YEAR")]
+    texture_tiling: Option<u32>,
+    // year of the target client, spawns and team pads get set up the way it expects
+    #[arg(long, value_name = "YEAR")]
+    spawn_fixups: Option<u32>,
+    // year of the target client, FilteringEnabled and streaming get set the way it expects
+    #[arg(long, value_name = "YEAR")]
+    replication_flags: Option<u32>,
+    // year of the target client, tools get turned into what it supports
+    #[arg(long, value_name = "YEAR")]
+    tool_fixups: Option<u32>,
+    // <id>.rbxm/.rbxmx gear that scripts load by id, put in StarterPack
+    #[arg(long, requires = "tool_fixups")]
+    gear_dir: Option<PathBuf>,
+    // remove PackageLinks, packages stay as the copy saved in the place
+    #[arg(long)]
+    resolve_packages: bool,
+    // expand packages to their published version first, from a dir of <id>.rbxm/.rbxmx files
+    // (<id>_v<version> for pinned ones, like fetch saves them), a url ({id}, {version}), roblox
+    // or s3://bucket/prefix
+    #[arg(long, value_name = "SOURCE", requires = "resolve_packages")]
+    package_source: Option<AssetSource>,
+    #[arg(long, value_enum, default_value_t = PackageVersion::Latest, requires = "package_source")]
+    package_version: PackageVersion,
+    // copy models scripts load with InsertService:LoadAsset(id) into ServerStorage, from a
+    // dir of <id>.rbxm/.rbxmx files, a url to download them from ({id} or a prefix), roblox or
+    // s3://bucket/prefix
+    #[arg(long, value_name = "SOURCE")]
+    inline_inserted_assets: Option<AssetSource>,
+    #[arg(long)]
+    inject_leaderstats: bool,
+    // strip UniqueId/HistoryId for old targets, or regenerate duplicates from merged in models
+    #[arg(long, value_enum)]
+    unique_ids: Option<UniqueIdHandling>,
+    // add a baseplate when nothing is under the spawns, x,y,z studs and optionally an r,g,b color
+    #[arg(long, value_name = "X,Y,Z[,R,G,B]", num_args = 0..=1, default_missing_value = "512,20,512")]
+    ensure_baseplate: Option<Baseplate>,
+    // anchor unanchored parts with nothing within this many studs under them, 1 without a value
+    #[arg(long, value_name = "STUDS", num_args = 0..=1, default_missing_value = "1")]
+    anchor_floating: Option<f32>,
+    // move the map so x,y,z (or the middle of it with "center") is at the origin, old clients
+    // jitter far away from it
+    #[arg(long, value_name = "X,Y,Z")]
+    rebase_origin: Option<RebaseOrigin>,
+    #[arg(long, conflicts_with = "force_binary")]
+    force_xml: bool,
+    #[arg(long)]
+    force_binary: bool,
+    #[arg(long)]
+    convert_assetid_to_url: bool,
+    // prefix for the id, or a template using {id}, {type} and {hash}
+    #[arg(long, default_value = options::DEFAULT_ASSET_URL_FORMAT)]
+    asset_url_format: String,
+    // json of per asset type url formats
+    #[arg(long)]
+    asset_url_config: Option<PathBuf>,
+    // json of duplicate asset id -> id to use, as mesh-dedup --aliases writes
+    #[arg(long)]
+    asset_aliases: Option<PathBuf>,
+    #[arg(long)]
+    instance_mappings_file: Option<PathBuf>,
+    // json of pass name -> the classes it's limited to, see pass_scope.rs
+    #[arg(long)]
+    pass_scopes: Option<PathBuf>,
+    #[arg(long, value_enum)]
+    preset: Option<Preset>,
+    #[arg(long, value_enum)]
+    convert_tags: Option<TagConversion>,
+    #[arg(long)]
+    asset_cutoff_date: Option<NaiveDate>,
+    #[arg(long, value_enum)]
+    xml_compat: Option<XmlCompat>,
+    // oldest client the binary output has to load in
+    #[arg(long, value_enum)]
+    binary_compat: Option<BinaryCompat>,
+    #[arg(long, conflicts_with = "dedup_shared_strings")]
+    expand_shared_strings: bool,
+    // read the written place back and fail if instances or key properties didn't survive
+    #[arg(long)]
+    verify: bool,
+    // minimum size in bytes for a repeated value to be worth sharing
+    #[arg(long, value_name = "MIN_BYTES")]
+    dedup_shared_strings: Option<usize>,
+    #[arg(long)]
+    stable_output: bool,
+    // write a _ConversionLog into the workspace with the version, date, preset and pass counts
+    #[arg(long)]
+    conversion_log: bool,
+    // auto, current or x,y,z,lx,ly,lz
+    #[arg(long)]
+    thumbnail_camera: Option<ThumbnailCamera>,
+    // print every warning instead of a few of each kind and a count
+    #[arg(long)]
+    show_all_warnings: bool,
+    // class=NAME, source=TEXT, source:CLASS=TEXT or path=PATH, exits non-zero if the output would match
+    #[arg(long, value_name = "RULE")]
+    fail_if: Vec<FailRule>,
+    // keep parsed places here so later runs on the same file skip the parse
+    #[arg(long, value_name = "DIR")]
+    cache_dir: Option<PathBuf>,
+    // decompress binary input chunks on this many threads
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    parse_threads: usize,
+    // deepest nesting a place can have before it's refused, see dom_limits.rs
+    #[arg(long, value_name = "LEVELS", default_value_t = limits::DEFAULT_MAX_DEPTH)]
+    max_depth: usize,
+    #[arg(long, value_name = "N", default_value_t = limits::DEFAULT_MAX_INSTANCES)]
+    max_instances: usize,
 }
 
-// todo: merge with serialize_mesh and make an arg determine which to do
-fn convert_obj_to_filemesh(obj_data: &[u8], version: RobloxMeshVersion) -> error::Result<Vec<u8>> {
-    let mesh = importer::obj_to_intermediate(obj_data)?;
-    let bytes = match version {
-        RobloxMeshVersion::V1_00 => ser::write_v1(&mesh, ser::V1Version::V1_00)?,
-        RobloxMeshVersion::V1_01 => ser::write_v1(&mesh, ser::V1Version::V1_01)?,
-        RobloxMeshVersion::V2_00 => ser::write_v2(&mesh)?,
-        RobloxMeshVersion::V3_00 => ser::write_v3(&mesh)?,
-        RobloxMeshVersion::V4_00 => ser::write_v4(&mesh)?,
-        RobloxMeshVersion::V5_00 => ser::write_v5(&mesh)?,
-    };
-    Ok(bytes)
+impl FixPlaceArgs {
+    // borrowed so universe fix can build the same options for every place
+    fn to_options(&self) -> Result<FixPlaceOptions, Box<dyn Error>> {
+        let output_format = if self.force_xml {
+            OutputFormat::Xml
+        } else if self.force_binary {
+            OutputFormat::Binary
+        } else {
+            OutputFormat::SameAsInput
+        };
+        let mut options = FixPlaceOptions::new()
+            .output_format(output_format)
+            .folders_to_models(self.folders_to_models)
+            .convert_meshparts(self.convert_meshparts)
+            .legacy_size_grid(self.legacy_size_grid)
+            .shape_fallbacks(self.shape_fallbacks)
+            .texture_tiling(self.texture_tiling)
+            .spawn_fixups(self.spawn_fixups)
+            .replication_flags(self.replication_flags)
+            .tool_fixups(self.tool_fixups)
+            .gear_dir(self.gear_dir.clone())
+            .resolve_packages(self.resolve_packages)
+            .package_source(self.package_source.clone())
+            .package_version(self.package_version)
+            .inline_inserted_assets(self.inline_inserted_assets.clone())
+            .inject_leaderstats(self.inject_leaderstats)
+            .unique_ids(self.unique_ids)
+            .ensure_baseplate(self.ensure_baseplate)
+            .anchor_floating(self.anchor_floating)
+            .rebase_origin(self.rebase_origin)
+            .convert_assetid_to_url(self.convert_assetid_to_url)
+            .asset_url_format(self.asset_url_format.as_str())
+            .tag_conversion(self.convert_tags)
+            .asset_cutoff_date(self.asset_cutoff_date)
+            .xml_compat(self.xml_compat)
+            .binary_compat(self.binary_compat)
+            .expand_shared_strings(self.expand_shared_strings)
+            .verify(self.verify)
+            .dedup_shared_strings(self.dedup_shared_strings)
+            .stable_output(self.stable_output)
+            .conversion_log(self.conversion_log)
+            .thumbnail_camera(self.thumbnail_camera)
+            .fail_if(self.fail_if.clone())
+            .cache_dir(self.cache_dir.clone())
+            .parse_threads(self.parse_threads)
+            .dom_limits(DomLimits { max_depth: self.max_depth, max_instances: self.max_instances });
+        if let Some(preset) = self.preset {
+            options = options.preset(preset);
+        }
+        if let Some(path) = &self.asset_url_config {
+            options = options.asset_url_config(load_asset_url_config(path)?);
+        }
+        if let Some(path) = &self.asset_aliases {
+            options = options.asset_aliases(load_asset_aliases(path)?);
+        }
+        // a mappings file overrides the preset's rules per class
+        if let Some(path) = &self.instance_mappings_file {
+            options = options.extend_mappings(load_instance_mappings(path)?);
+        }
+        if let Some(path) = &self.pass_scopes {
+            options = options.pass_scopes(load_pass_scopes(path)?);
+        }
+        if self.show_all_warnings {
+            options = options.observer(pipeline::LogObserver);
+        }
+        Ok(options)
+    }
+}
+
+// how v1 (ascii) meshes are written, see ser::V1Format
+#[derive(Clone, Args)]
+struct V1FormatArgs {
+    // \r\n line endings like meshes saved on windows
+    #[arg(long)]
+    legacy_line_endings: bool,
+    // v1 meshes written the way roblox's own exporter wrote them: \r\n and msvc printf %g numbers
+    #[arg(long)]
+    legacy_v1: bool,
+}
+
+impl V1FormatArgs {
+    fn serialize(&self, mesh: &mesh_types::IntermediateMesh, lods: &[Vec<[u32; 3]>], version: RobloxMeshVersion) -> Result<Vec<u8>, Box<dyn Error>> {
+        let format = ser::V1Format {
+            crlf: self.legacy_line_endings || self.legacy_v1,
+            legacy_numbers: self.legacy_v1,
+        };
+        if format == ser::V1Format::default() {
+            return Ok(serialize_mesh_with_lods(mesh, lods, version)?);
+        }
+        // only v1 takes the formatting, and v1 can't have lods
+        if !lods.is_empty() {
+            return Err("lods need a v3 or newer mesh".into());
+        }
+        Ok(serialize_mesh_v1(mesh, version, format)?)
+    }
+}
+
+#[derive(Clone, Args)]
+struct UploadCheckArgs {
+    // fail before writing anything when the mesh wouldn't upload, see mesh_validate.rs
+    #[arg(long)]
+    validate_upload: bool,
+    // also check it against what this client takes
+    #[arg(long, value_enum, requires = "validate_upload")]
+    validate_client: Option<TargetClient>,
+}
+
+impl UploadCheckArgs {
+    fn check(&self, mesh: &mesh_types::IntermediateMesh, version: Option<RobloxMeshVersion>) -> Result<(), Box<dyn Error>> {
+        if !self.validate_upload {
+            return Ok(());
+        }
+        let problems = mesh_validate::validate_upload(mesh, version, self.validate_client);
+        if problems.is_empty() {
+            println!("passes the upload checks");
+            return Ok(());
+        }
+        for problem in &problems {
+            println!("{}", problem);
+        }
+        Err(format!("the mesh wouldn't upload, {} problem(s)", problems.len()).into())
+    }
 }
-fn serialize_mesh(mesh: &mesh_types::IntermediateMesh, version: RobloxMeshVersion) -> error::Result<Vec<u8>> {
-    let bytes = match version {
-        RobloxMeshVersion::V1_00 => ser::write_v1(mesh, ser::V1Version::V1_00)?,
-        RobloxMeshVersion::V1_01 => ser::write_v1(mesh, ser::V1Version::V1_01)?,
-        RobloxMeshVersion::V2_00 => ser::write_v2(mesh)?,
-        RobloxMeshVersion::V3_00 => ser::write_v3(mesh)?,
-        RobloxMeshVersion::V4_00 => ser::write_v4(mesh)?,
-        RobloxMeshVersion::V5_00 => ser::write_v5(mesh)?,
+
+#[derive(Clone)]
+enum MeshOutputKind {
+    Mesh(RobloxMeshVersion),
+    Obj,
+    Ply,
+}
+
+#[derive(Clone)]
+struct MeshOutput {
+    kind: MeshOutputKind,
+    path: PathBuf,
+}
+
+// "v4:out.mesh", "v1-01:old.mesh", "obj:check.obj"
+fn parse_mesh_output(s: &str) -> Result<MeshOutput, String> {
+    let (format, path) = s.split_once(':').ok_or_else(|| format!("expected FORMAT:PATH, got '{}'", s))?;
+    if path.is_empty() {
+        return Err(format!("no path after '{}:'", format));
+    }
+    let kind = match format {
+        "obj" => MeshOutputKind::Obj,
+        "ply" => MeshOutputKind::Ply,
+        _ => {
+            let name = if format.contains('-') { format.to_owned() } else { format!("{}-00", format) };
+            let version = RobloxMeshVersion::from_str(&name, true)
+                .map_err(|_| format!("'{}' isn't a mesh version, obj or ply", format))?;
+            MeshOutputKind::Mesh(version)
+        }
     };
-    Ok(bytes)
+    Ok(MeshOutput { kind, path: PathBuf::from(path) })
 }
 
+fn parse_mesh_lod(data: &[u8], lod: Option<usize>) -> error::Result<mesh_types::IntermediateMesh> {
+    match lod {
+        Some(level) => filemesh::parse_filemesh_lod(data, level),
+        None => filemesh::parse_filemesh(data),
+    }
+}
 
-fn convert_filemesh_to_obj(filemesh_data: &[u8]) -> error::Result<Vec<u8>> {
-    filemesh::filemesh_to_obj_bytes(filemesh_data)
+fn fill_mesh_holes(mesh: &mut mesh_types::IntermediateMesh, max_perimeter: f32) {
+    let fill = mesh_topology::fill_holes(mesh, max_perimeter);
+    println!(
+        "filled {} hole(s) with {} face(s), left {} bigger than {} or not closed",
+        fill.filled, fill.faces_added, fill.skipped, max_perimeter
+    );
 }
 
-const LEGACY_FONT_SIZE_OPTIONS: [(i64, u32); 10] = [
-    (8, 0), (9, 1), (10, 2), (11, 3), (12, 4),
-    (14, 5), (18, 6), (24, 7), (36, 8), (48, 9),
-];
-const FONT_SIZE_COMPATIBILITY: [(u32, u32); 5] = [(10, 7), (11, 8), (12, 9), (13, 9), (14, 9)];
+// one --out. lods go in the versions that hold them, obj and ply get the full mesh turned back
+// to the axes it came in with
+fn write_mesh_output(
+    output: &MeshOutput,
+    mesh: &mesh_types::IntermediateMesh,
+    levels: &[Vec<[u32; 3]>],
+    v1: &V1FormatArgs,
+    axes: &AxisConversion,
+) -> Result<(), Box<dyn Error>> {
+    let bytes = match output.kind {
+        MeshOutputKind::Mesh(version) => {
+            let holds_lods = !matches!(version, RobloxMeshVersion::V1_00 | RobloxMeshVersion::V1_01 | RobloxMeshVersion::V2_00);
+            if !levels.is_empty() && !holds_lods {
+                println!("{} gets no lods, its version can't have them", output.path.display());
+            }
+            v1.serialize(mesh, if holds_lods { levels } else { &[] }, version)?
+        }
+        MeshOutputKind::Obj | MeshOutputKind::Ply => {
+            // neither keeps bones
+            let mut mesh = mesh_types::IntermediateMesh {
+                vertices: mesh.vertices.clone(),
+                faces: mesh.faces.clone(),
+                skin: None,
+                facs: None,
+            };
+            axes.to_target(&mut mesh);
+            if matches!(output.kind, MeshOutputKind::Ply) {
+                ply::mesh_to_ply(&mesh, false)?
+            } else {
+                filemesh::mesh_to_obj_bytes(&mesh)?
+            }
+        }
+    };
+    fs::write(&output.path, bytes)?;
+    println!("wrote {}", output.path.display());
+    Ok(())
+}
 
-fn font_size_name_from_value(value: u32) -> &'static str {
-    match value {
-        0 => "Size8", 1 => "Size9", 2 => "Size10", 3 => "Size11", 4 => "Size12",
-        5 => "Size14", 6 => "Size18", 7 => "Size24", 8 => "Size36", 9 => "Size48",
-        10 => "Size28", 11 => "Size32", 12 => "Size42", 13 => "Size60", 14 => "Size96",
-        _ => "Unknown",
+fn print_cleanup(cleanup: &ImportCleanup, recomputing: bool) {
+    if cleanup.degenerate + cleanup.duplicate > 0 {
+        println!(
+            "dropped {} zero area and {} duplicate face(s)",
+            cleanup.degenerate, cleanup.duplicate
+        );
+    }
+    if cleanup.missing_normals > 0 && !recomputing {
+        println!(
+            "{} vertices had no normal, worked them all out smoothing under {} degrees",
+            cleanup.missing_normals,
+            mesh_normals::DEFAULT_SMOOTH_ANGLE
+        );
     }
 }
 
-fn normalize_font_size_value(value: u32) -> u32 {
-    FONT_SIZE_COMPATIBILITY
-        .iter()
-        .find(|&&(modern, _)| value == modern)
-        .map_or(value, |&(_, legacy)| legacy)
+fn weld_mesh(mesh: &mut mesh_types::IntermediateMesh, epsilon: f32) -> Result<(), Box<dyn Error>> {
+    if epsilon < 0.0 || !epsilon.is_finite() {
+        return Err(format!("can't weld within {} studs", epsilon).into());
+    }
+    let stats = mesh_weld::weld(mesh, epsilon);
+    println!(
+        "welded {} vertices down to {}, dropped {} collapsed face(s)",
+        stats.vertices_before, stats.vertices_after, stats.faces_dropped
+    );
+    Ok(())
 }
 
-fn font_enum_from_text_size(text_size: i64) -> u32 {
-    LEGACY_FONT_SIZE_OPTIONS
-        .iter()
-        .min_by_key(|&&(size, _)| (text_size - size).abs())
-        .map(|&(_, enum_val)| enum_val)
-        .unwrap_or(0)
+impl Commands {
+    // input, output and the output's extension (None keeps the input's) of the commands that turn
+    // one file into another, the ones --batch can run. not with outputs besides the main one,
+    // every file would write over them
+    fn batch_paths(&mut self) -> Option<(&mut PathBuf, &mut PathBuf, Option<&'static str>)> {
+        match self {
+            Self::ObjToFilemesh { input, output: Some(output), out, .. } if out.is_empty() => Some((input, output, Some("mesh"))),
+            Self::GltfToFilemesh { input, output, .. }
+            | Self::PlyToFilemesh { input, output, .. }
+            | Self::DaeToFilemesh { input, output, .. }
+            | Self::FilemeshToFilemesh { input, output, export_lods: None, .. } => Some((input, output, Some("mesh"))),
+            Self::FilemeshToObj { input, output, debug_normals: false, .. } => Some((input, output, Some("obj"))),
+            Self::FilemeshToPly { input, output, .. } => Some((input, output, Some("ply"))),
+            Self::FilemeshToGltf { input, output } | Self::AnimToGltf { input, output, .. } => Some((input, output, Some("glb"))),
+            Self::GltfToAnim { input, output, .. } => Some((input, output, Some("rbxm"))),
+            Self::MeshSimplify { input, output, version, .. } => {
+                let extension = if version.is_some() { "mesh" } else { "obj" };
+                Some((input, output, Some(extension)))
+            }
+            Self::FixPlace { input, output, report: None, .. }
+            | Self::PlaceRecover { input, output, report: None, .. }
+            | Self::PlaceTransform { input, output, report: None, .. } => Some((input, output, None)),
+            _ => None,
+        }
+    }
 }
 
-fn apply_instance_conversions(
-    dom: &mut WeakDom,
-    folders_to_models: bool,
-    mappings: &HashMap<Ustr, Ustr>,
-    convert_assetid_to_url: bool,
-    asset_url_format: &str,
-    convert_meshpart_to_specialmesh: bool,
-) {
-    let instance_refs: Vec<_> = dom.descendants().map(|instance| instance.referent()).collect();
-    let text_size_key: Ustr = "TextSize".into();
-    let font_size_key: Ustr = "FontSize".into();
-    let folder_class: Ustr = "Folder".into();
-    let model_class: Ustr = "Model".into();
-    let meshpart_class: Ustr = "MeshPart".into();
-    let part_class: Ustr = "Part".into();
+// the command once per file the input glob matches, see batch.rs
+fn run_batch(mut command: Commands) -> Result<(), Box<dyn Error>> {
+    let Some((input, output, extension)) = command.batch_paths() else {
+        return Err("--batch works with the commands that convert one file to another, without --out or reports".into());
+    };
+    let (pattern, out_dir) = (input.to_string_lossy().into_owned(), output.clone());
+    let (base, files) = batch::expand(&pattern).map_err(|e| format!("{}: {}", pattern, e))?;
+    if files.is_empty() {
+        return Err(format!("nothing matches {}", pattern).into());
+    }
+    let report = batch::run_batch(&base, &files, &out_dir, extension, |input, output| {
+        let mut command = command.clone();
+        let (command_input, command_output, _) = command.batch_paths().ok_or("the command can't be batched")?;
+        *command_input = input.to_path_buf();
+        *command_output = output.to_path_buf();
+        run(command).map_err(|e| e.to_string())
+    });
+    print!("{}", report.to_text());
+    if !report.failed.is_empty() {
+        return Err(format!("{} file(s) failed", report.failed.len()).into());
+    }
+    Ok(())
+}
 
-    for instance_ref in instance_refs {
-        let mut pending_special_mesh: Option<(InstanceBuilder, String, rbx_dom_weak::types::Vector3)> = None;
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+    if cli.batch {
+        return run_batch(cli.command);
+    }
+    run(cli.command)
+}
 
-        if let Some(instance) = dom.get_by_ref_mut(instance_ref) {
-            if let Some(new_class) = mappings.get(&instance.class) {
-                println!(
-                    "[legacy_place::convert] mapped instance '{}' from {} to {}",
-                    instance.name, instance.class, new_class
-                );
-                instance.class = *new_class;
-            }
-            if instance.class == meshpart_class && convert_meshpart_to_specialmesh {
-                let initial_size = match instance.properties.get(&"InitialSize".into()) {
-                    Some(Variant::Vector3(v)) => *v,
-                    _ => {
-                        println!(
-                            "[legacy_place::convert] meshpart '{}' missing initialsize property, skipping conversion",
-                            instance.name
-                        );
-                        continue;
-                    },
-                };
-                let size = match instance.properties.get(&"Size".into()) {
-                    Some(Variant::Vector3(v)) => *v,
-                    _ => {
-                        println!(
-                            "[legacy_place::convert] meshpart '{}' missing size property, skipping conversion",
-                            instance.name
-                        );
-                        continue;
-                    },
-                };
-                if initial_size.x == 0.0 || initial_size.y == 0.0 || initial_size.z == 0.0 {
+fn run(command: Commands) -> Result<(), Box<dyn Error>> {
+    match command {
+        Commands::ObjToFilemesh {
+            input,
+            output,
+            version,
+            target_client,
+            up_axis,
+            scale,
+            fill_holes,
+            max_hole_perimeter,
+            weld,
+            lods,
+            recompute_normals,
+            smooth_angle,
+            cache_intermediate,
+            out,
+            upload,
+            v1,
+        } => {
+            let version = match (&output, version, target_client) {
+                (None, _, _) => None,
+                (Some(_), Some(version), _) => Some(version),
+                (Some(_), None, Some(client)) => {
+                    let version = client.mesh_version();
                     println!(
-                        "[legacy_place::convert] meshpart '{}' has zero initialsize, skipping conversion",
-                        instance.name
+                        "writing {}, the newest mesh version {} clients read",
+                        version.to_possible_value().map_or_else(String::new, |value| value.get_name().to_owned()),
+                        client.year()
                     );
-                    continue;
+                    Some(version)
                 }
-                let scale = rbx_dom_weak::types::Vector3 {
-                    x: size.x / initial_size.x,
-                    y: size.y / initial_size.y,
-                    z: size.z / initial_size.z,
-                };
-                instance.class = part_class;
-                let mesh_id = instance.properties.get(&"MeshId".into()).unwrap_or(&Variant::Content(Content::from_uri(String::new()))).clone();
-                let instance_name = instance.name.clone();
-                let special_mesh = InstanceBuilder::new("SpecialMesh")
-                    .with_name("Mesh")
-                    .with_property("Scale", Variant::Vector3(scale))
-                    .with_property("MeshType", Variant::Enum(rbx_dom_weak::types::Enum::from_u32(5)))
-                    .with_property("MeshId", mesh_id);
-                pending_special_mesh = Some((special_mesh, instance_name, scale));
+                (Some(_), None, None) => return Err("give a mesh version or --target-client".into()),
+            };
+            let obj_data = fs::read(input)?;
+            let (mut mesh, cleanup) = imesh::load_obj_cached(&obj_data, cache_intermediate.as_deref())?;
+            print_cleanup(&cleanup, recompute_normals);
+            AxisConversion::new(up_axis, scale)?.from_target(&mut mesh);
+            if let Some(epsilon) = weld {
+                weld_mesh(&mut mesh, epsilon)?;
             }
-
-            if folders_to_models && instance.class == folder_class {
+            if fill_holes {
+                fill_mesh_holes(&mut mesh, max_hole_perimeter);
+            }
+            // after filling, so the caps get normals of their own
+            if recompute_normals {
+                let stats = mesh_normals::recompute_normals(&mut mesh, smooth_angle);
                 println!(
-                    "[legacy_place::convert] converted folder '{}' to model",
-                    instance.name
+                    "recomputed normals smoothing under {} degrees, {} vertices now {}",
+                    smooth_angle, stats.vertices_before, stats.vertices_after
                 );
-                instance.class = model_class;
             }
-            if instance.class == "KeyframeSequence" {
-                instance.class = "Part".into();
-                println!("[legacy_place::convert] converted keyframesequence '{}' to part to avoid errors in old clients", instance.name);
+            let levels = if lods > 0 { mesh_lods::generate_lods(&mesh, lods) } else { Vec::new() };
+            let upload = UploadCheckArgs { validate_client: upload.validate_client.or(target_client), ..upload };
+            let out_versions = out.iter().filter_map(|extra| match extra.kind {
+                MeshOutputKind::Mesh(version) => Some(version),
+                _ => None,
+            });
+            for version in version.into_iter().chain(out_versions) {
+                upload.check(&mesh, Some(version))?;
             }
-
-            if instance.class == "UnionOperation" {
-                println!("[legacy_place::convert] reading MeshData2 for unionoperation '{}'", instance.name);
-                let mesh_data_variant = instance.properties.get(&"PhysicalConfigData".into()).cloned();
-                println!("mesh_data_variant: {:?}", mesh_data_variant);
+            if let (Some(output), Some(version)) = (output, version) {
+                fs::write(output, v1.serialize(&mesh, &levels, version)?)?;
             }
-
-            let mut font_size_to_add: Option<Variant> = None;
-            let mut props_to_update: Vec<(Ustr, Variant)> = Vec::new();
-
-            for (prop_name, prop_value) in &instance.properties {
-                if *prop_name == text_size_key {
-                    let text_size_opt = match prop_value {
-                        Variant::Int64(val) => Some(*val),
-                        Variant::Int32(val) => Some(*val as i64),
-                        Variant::Float32(val) => Some(*val as i64),
-                        Variant::Float64(val) => Some(*val as i64),
-                        _ => None,
-                    };
-                    if let Some(text_size) = text_size_opt {
-                        let enum_value = normalize_font_size_value(font_enum_from_text_size(text_size));
-                        font_size_to_add = Some(Variant::Enum(rbx_dom_weak::types::Enum::from_u32(enum_value)));
-                        println!(
-                            "[legacy_place::convert] converted TextSize {} on '{}' to FontSize {}",
-                            text_size,
-                            instance.name,
-                            font_size_name_from_value(enum_value)
-                        );
-                    } else {
-                        println!(
-                            "[legacy_place::convert] textsize on '{}' has unexpected type: {:?}",
-                            instance.name,
-                            prop_value
-                        );
-                    }
+            for extra in &out {
+                write_mesh_output(extra, &mesh, &levels, &v1, &AxisConversion::new(up_axis, scale)?)?;
+            }
+            for (level, faces) in levels.iter().enumerate() {
+                println!("lod {}: {} faces", level + 1, faces.len());
+            }
+            if levels.len() < lods {
+                println!("the mesh couldn't be simplified past lod {}", levels.len());
+            }
+        }
+        Commands::FilemeshToObj { input, output, lod, up_axis, scale, debug_normals, debug_v1, all_lods } => {
+            let data = fs::read(input)?;
+            let (mut mesh, trace) = if debug_v1 {
+                let (mesh, trace) = filemesh::trace_v1(&data)?;
+                (mesh, Some(trace))
+            } else {
+                (parse_mesh_lod(&data, lod)?, None)
+            };
+            let axes = AxisConversion::new(up_axis, scale)?;
+            axes.to_target(&mut mesh);
+            if all_lods {
+                let mut lods = filemesh::parse_filemesh_lods(&data)?;
+                for lod in &mut lods {
+                    axes.to_target(lod);
                 }
-
-                if convert_assetid_to_url {
-                    if let Variant::Content(content) = prop_value {
-                        if let Some(uri) = content.as_uri() {
-                            if let Some(id_part) = uri.strip_prefix("rbxassetid://") {
-                                if id_part.parse::<u64>().is_ok() {
-                                    let new_url = format!("{}{}", asset_url_format, id_part);
-                                    println!(
-                                        "[legacy_place::convert] converting asset ID on '{}', property '{}' changed to {}",
-                                        instance.name, prop_name, new_url
-                                    );
-                                    props_to_update.push((*prop_name, Variant::Content(Content::from_uri(new_url))));
-                                }
-                            }
-                        }
-                    }
+                fs::write(&output, filemesh::lods_to_obj(&lods)?)?;
+                for (level, lod) in lods.iter().enumerate() {
+                    println!("lod {}: {} faces, {} vertices", level, lod.faces.len(), lod.vertices.len());
+                }
+            } else if let Some(trace) = &trace {
+                // v1 meshes have no colors, so no mtl
+                fs::write(&output, trace.to_obj(&mesh)?)?;
+                for problem in &trace.problems {
+                    println!("{}", problem);
+                }
+                println!("{} face(s) read, {} problem(s)", mesh.faces.len(), trace.problems.len());
+            } else {
+                // vertex colors go in an mtl next to the obj
+                let mtl_path = output.with_extension("mtl");
+                let mtl_name = mtl_path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                let exported = filemesh::mesh_to_obj_with_materials(&mesh, &mtl_name)?;
+                fs::write(&output, exported.obj)?;
+                if let Some(mtl) = exported.mtl {
+                    fs::write(&mtl_path, mtl)?;
+                    println!(
+                        "{} color material(s) in {}, {} face(s) with mixed colors left without one",
+                        exported.materials,
+                        mtl_path.display(),
+                        exported.mixed_faces
+                    );
                 }
             }
-
-            if let Some(font_size) = font_size_to_add {
-                instance.properties.insert(font_size_key, font_size);
-                instance.properties.remove(&text_size_key);
+            if debug_normals {
+                let normals_path = output.with_extension("normals.obj");
+                let lines = filemesh::normals_to_obj(&mesh)?;
+                fs::write(&normals_path, lines.obj)?;
+                println!(
+                    "{} normal(s) drawn in {}, {} not unit length",
+                    mesh.vertices.len(),
+                    normals_path.display(),
+                    lines.bad_normals
+                );
             }
-
-            for (prop_name, new_value) in props_to_update {
-                instance.properties.insert(prop_name, new_value);
+        }
+        Commands::MeshDedup { dir, aliases, report } => {
+            let dedup = mesh_dedup::dedup_meshes(&dir)?;
+            print!("{}", dedup.to_text());
+            if let Some(path) = report {
+                fs::write(path, dedup.to_json()?)?;
+            }
+            if let Some(path) = aliases {
+                let aliases = dedup.aliases();
+                fs::write(&path, mesh_dedup::aliases_to_json(&aliases)?)?;
+                println!("{} alias(es) written to {}", aliases.len(), path.display());
             }
         }
-        if convert_meshpart_to_specialmesh {
-            if let Some((special_mesh, instance_name, scale)) = pending_special_mesh {
-                dom.insert(instance_ref, special_mesh);
-                println!("[legacy_place::convert] converted meshpart '{}' -> part + specialmesh scale=({}, {}, {})",
-                    instance_name, scale.x, scale.y, scale.z
-                );
+        Commands::MeshInfo { input, json } => {
+            let info = mesh_info::mesh_info(&fs::read(&input)?)?;
+            if json {
+                println!("{}", info.to_json()?);
+            } else {
+                print!("{}", info.to_text());
             }
         }
-    }
-}
-
-fn load_instance_mappings(path: &PathBuf) -> Result<HashMap<Ustr, Ustr>, Box<dyn Error>> {
-    let data = fs::read_to_string(path)?;
-    let raw: HashMap<String, String> = serde_json::from_str(&data)?;
-    Ok(raw.into_iter().map(|(k, v)| (k.into(), v.into())).collect())
-}
-
-fn fix_place(
-    input_bytes: &[u8],
-    force_xml_output: bool,
-    force_binary_output: bool,
-    folders_to_models: bool,
-    convert_assetid_to_url: bool,
-    asset_url_format: String,
-    convert_meshpart_to_specialmesh: bool,
-    instance_mappings: Option<HashMap<Ustr, Ustr>>,
-) -> Result<Vec<u8>, Box<dyn Error>> {
-    let start = Utc::now();
-    let is_binary_input = is_binary_rbxl(input_bytes);
-    let mut dom: WeakDom = if is_binary_input {
-        let mut reader = Cursor::new(input_bytes);
-        from_reader(&mut reader).map_err(|e| Box::<dyn Error>::from(e.to_string()))?
-    } else {
-        // fix for some saveinstances
-        let xml_str = match String::from_utf8(input_bytes.to_vec()) {
-            Ok(s) => s,
-            Err(_) => {
-                let (cow, _, _) = WINDOWS_1252.decode(input_bytes);
-                cow.into_owned()
+        Commands::MeshSimplify { input, output, target_faces, target_ratio, version } => {
+            let data = fs::read(&input)?;
+            let is_obj = |path: &PathBuf| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("obj"));
+            let version = match version {
+                Some(version) => Some(version),
+                None if is_obj(&output) => None,
+                None => return Err("give a --version for a filemesh output".into()),
+            };
+            let mesh = if is_obj(&input) { importer::obj_to_intermediate(&data)? } else { filemesh::parse_filemesh(&data)? };
+            let target = match (target_faces, target_ratio) {
+                (Some(faces), _) => faces,
+                (None, Some(ratio)) if ratio > 0.0 && ratio <= 1.0 => (mesh.faces.len() as f32 * ratio).round() as usize,
+                (None, Some(ratio)) => return Err(format!("--target-ratio has to be above 0 and at most 1, not {}", ratio).into()),
+                (None, None) => return Err("give --target-faces or --target-ratio".into()),
+            };
+            let simplified = mesh_lods::simplify(&mesh, target);
+            let bytes = match version {
+                Some(version) => serialize_mesh(&simplified, version)?,
+                None => filemesh::mesh_to_obj_bytes(&simplified)?,
+            };
+            fs::write(&output, bytes)?;
+            println!(
+                "{} faces down to {}, {} vertices down to {}",
+                mesh.faces.len(),
+                simplified.faces.len(),
+                mesh.vertices.len(),
+                simplified.vertices.len()
+            );
+            if simplified.faces.len() > target {
+                println!("the mesh couldn't be simplified down to {} faces", target);
             }
-        };
-        let mut reader = Cursor::new(xml_str.as_bytes());
-        from_reader_default(&mut reader).map_err(|e| Box::<dyn Error>::from(e.to_string()))?
-    };
-    let mappings = instance_mappings.unwrap_or_default();
-    apply_instance_conversions(
-        &mut dom,
-        folders_to_models,
-        &mappings,
-        convert_assetid_to_url,
-        &asset_url_format,
-        convert_meshpart_to_specialmesh,
-    );
-    let root_refs: Vec<_> = dom.root().children().to_vec();
-    let mut output = Vec::new();
-    let should_output_xml = (!is_binary_input && !force_binary_output) || force_xml_output;
-    if should_output_xml {
-        to_writer_default(&mut output, &dom, &root_refs).map_err(|e| Box::<dyn Error>::from(e.to_string()))?;
-    } else {
-        to_writer(&mut output, &dom, &root_refs).map_err(|e| Box::<dyn Error>::from(e.to_string()))?;
-    }
-    let end = Utc::now();
-    let elapsed = end.signed_duration_since(start);
-    println!("done in {} ms", elapsed.num_milliseconds());
-    Ok(output)
-}
-
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
-    match cli.command {
-        Commands::ObjToFilemesh { input, output, version } => {
-            let obj_data = fs::read(input)?;
-            let bytes = convert_obj_to_filemesh(&obj_data, version)?;
-            fs::write(output, bytes)?;
         }
-        Commands::FilemeshToObj { input, output } => {
+        Commands::MeshCheck { input, topology, facs_json, upload } => {
+            let data = fs::read(&input)?;
+            let is_obj = input.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("obj"));
+            let mesh = if is_obj { importer::obj_to_intermediate(&data)? } else { filemesh::parse_filemesh(&data)? };
+            println!("vertices: {}", mesh.vertices.len());
+            println!("faces: {}", mesh.faces.len());
+            if let Some(facs) = &mesh.facs {
+                println!("facs: {} controls on {} bones", facs.control_names.len(), facs.bone_names.len());
+            }
+            if topology {
+                print!("{}", mesh_topology::analyze(&mesh).to_text());
+            }
+            if let Some(path) = facs_json {
+                let Some(facs) = &mesh.facs else {
+                    return Err("the mesh has no facs data, only v5 dynamic heads do".into());
+                };
+                fs::write(&path, facs.to_json()?)?;
+                println!("facs written to {}", path.display());
+            }
+            upload.check(&mesh, None)?;
+        }
+        Commands::FilemeshToGltf { input, output } => {
             let data = fs::read(input)?;
-            let bytes = convert_filemesh_to_obj(&data)?;
+            fs::write(output, convert_filemesh_to_gltf(&data)?)?;
+        }
+        Commands::PlyToFilemesh { input, output, version, up_axis, scale, weld, fill_holes, max_hole_perimeter, upload, v1 } => {
+            let (mut mesh, cleanup) = ply::ply_to_intermediate(&fs::read(input)?)?;
+            print_cleanup(&cleanup, false);
+            AxisConversion::new(up_axis, scale)?.from_target(&mut mesh);
+            if let Some(epsilon) = weld {
+                weld_mesh(&mut mesh, epsilon)?;
+            }
+            if fill_holes {
+                fill_mesh_holes(&mut mesh, max_hole_perimeter);
+            }
+            upload.check(&mesh, Some(version))?;
+            fs::write(output, v1.serialize(&mesh, &[], version)?)?;
+        }
+        Commands::DaeToFilemesh { input, output, version, up_axis, scale, weld, fill_holes, max_hole_perimeter, upload, v1 } => {
+            let import = collada::dae_to_intermediate(&fs::read(input)?)?;
+            println!(
+                "{} geometry instance(s), {} up, {} meter(s) to the unit",
+                import.instances,
+                import.up_axis.to_possible_value().map_or_else(String::new, |value| value.get_name().to_owned()),
+                import.meters_per_unit
+            );
+            print_cleanup(&import.cleanup, false);
+            let mut mesh = import.mesh;
+            AxisConversion::new(up_axis.unwrap_or(import.up_axis), scale)?.from_target(&mut mesh);
+            if let Some(epsilon) = weld {
+                weld_mesh(&mut mesh, epsilon)?;
+            }
+            if fill_holes {
+                fill_mesh_holes(&mut mesh, max_hole_perimeter);
+            }
+            upload.check(&mesh, Some(version))?;
+            fs::write(output, v1.serialize(&mesh, &[], version)?)?;
+        }
+        Commands::FilemeshToPly { input, output, lod, up_axis, scale, binary } => {
+            let mut mesh = parse_mesh_lod(&fs::read(input)?, lod)?;
+            AxisConversion::new(up_axis, scale)?.to_target(&mut mesh);
+            fs::write(output, ply::mesh_to_ply(&mesh, binary)?)?;
+        }
+        Commands::AnimToGltf { input, rig, output } => {
+            let animation = load_place(&fs::read(input)?)?;
+            let rig = load_place(&fs::read(rig)?)?;
+            fs::write(output, anim::keyframe_sequence_to_glb(&animation, &rig)?)?;
+        }
+        Commands::GltfToAnim { input, rig, output, fps } => {
+            let rig = load_place(&fs::read(rig)?)?;
+            let dom = anim::glb_to_keyframe_sequence(&fs::read(input)?, &rig, fps)?;
+            save_dom(&dom, &output)?;
+        }
+        Commands::GltfToFilemesh { input, output, version, fill_holes, max_hole_perimeter, weld, upload, v1 } => {
+            let data = fs::read(&input)?;
+            let mut mesh = importer::gltf_to_intermediate(&data, input.parent())?;
+            if let Some(epsilon) = weld {
+                weld_mesh(&mut mesh, epsilon)?;
+            }
+            if fill_holes {
+                fill_mesh_holes(&mut mesh, max_hole_perimeter);
+            }
+            upload.check(&mesh, Some(version))?;
+            fs::write(output, v1.serialize(&mesh, &[], version)?)?;
+        }
+        Commands::FilemeshToFilemesh { input, output, version, version_flag, lod, placeholder, export_lods, upload, v1 } => {
+            let version = version.or(version_flag).ok_or("give the version to write")?;
+            let data = fs::read(&input)?;
+            // one level when it's picked or replaced by a box, otherwise the whole lod chain
+            let (mut mesh, mut lods) = match lod {
+                None if !placeholder => filemesh::parse_filemesh_with_lods(&data)?,
+                _ => (parse_mesh_lod(&data, lod)?, Vec::new()),
+            };
+            if placeholder {
+                mesh = placeholder::placeholder_mesh(&mesh)?;
+            }
+            if !lods.is_empty() && !version.has_lods() {
+                println!("v1 and v2 meshes have no lods, only the highest detail level is written ({} dropped)", lods.len());
+                lods.clear();
+            }
+            upload.check(&mesh, Some(version))?;
+            let bytes = v1.serialize(&mesh, &lods, version)?;
             fs::write(output, bytes)?;
+            if let Some(dir) = export_lods {
+                fs::create_dir_all(&dir)?;
+                let stem = input.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+                let lods = filemesh::parse_filemesh_lods(&data)?;
+                for (level, lod) in lods.iter().enumerate() {
+                    let path = dir.join(format!("{}_lod{}.mesh", stem, level));
+                    fs::write(&path, serialize_mesh(lod, RobloxMeshVersion::V2_00)?)?;
+                    println!("lod {}: {} faces -> {}", level, lod.faces.len(), path.display());
+                }
+            }
         }
-        Commands::FilemeshToFilemesh { input, output, version } => {
+        Commands::FixPlace { input, output, fix, report, mem_stats } => {
             let data = fs::read(input)?;
-            let mesh = filemesh::parse_filemesh(&data)?;
-            let bytes = serialize_mesh(&mesh, version)?;
-            fs::write(output, bytes)?;
+            let options = fix.to_options()?.mem_stats(mem_stats);
+            let start = Utc::now();
+            let (out, run) = fix_place_with_report(&data, options)?;
+            let elapsed = Utc::now().signed_duration_since(start);
+            print!("{}", run.stats_table());
+            println!("done in {} ms", elapsed.num_milliseconds());
+            if let Some(stats) = &run.mem_stats {
+                print!("{}", stats.to_text());
+            }
+            fs::write(output, out)?;
+            if let Some(report_path) = report {
+                fs::write(report_path, run.to_text())?;
+            }
         }
-        Commands::FixPlace {
+        Commands::PrepareLegacy {
             input,
-            output,
-            folders_to_models,
-            convert_meshparts,
-            force_xml,
-            force_binary,
-            convert_assetid_to_url,
-            asset_url_format,
-            instance_mappings_file,
+            out_dir,
+            version,
+            asset_source,
+            max_texture_size,
+            client_content,
+            launcher,
+            fix,
+            report,
         } => {
+            if fix.preset.is_none() {
+                return Err("prepare-legacy needs a --preset".into());
+            }
+            let start = Utc::now();
+            let prepared = prepare_legacy::prepare_legacy(
+                &input,
+                &out_dir,
+                fix.to_options()?,
+                asset_source,
+                version,
+                max_texture_size,
+                client_content.as_deref(),
+                launcher,
+            )?;
+            let elapsed = Utc::now().signed_duration_since(start);
+            let text = prepared.to_text();
+            print!("{}", text);
+            println!("done in {} ms", elapsed.num_milliseconds());
+            if let Some(report_path) = report {
+                fs::write(report_path, text)?;
+            }
+            if !prepared.is_ok() {
+                return Err("content folder is incomplete".into());
+            }
+        }
+        Commands::RbxlInspect { input } => {
             let data = fs::read(input)?;
-            let mappings = if let Some(path) = instance_mappings_file {
-                Some(load_instance_mappings(&path)?)
-            } else { None };
-            let out = fix_place(
-                &data,
-                force_xml,
-                force_binary,
-                folders_to_models,
-                convert_assetid_to_url,
-                asset_url_format,
-                convert_meshparts,
-                mappings,
+            print!("{}", rbxl_chunks::inspect(&data)?);
+        }
+        Commands::Serve { bind, workers, max_upload_mb } => {
+            serve::run(&bind, workers, max_upload_mb * 1024 * 1024, None)?;
+        }
+        Commands::Daemon { inbox, outbox, workers, poll_ms, bind } => {
+            daemon::run(
+                daemon::JobDirs { inbox, outbox },
+                workers,
+                std::time::Duration::from_millis(poll_ms),
+                bind,
             )?;
+        }
+        Commands::Repl { input, cache_dir } => {
+            let data = fs::read(input)?;
+            repl::run(dom_cache::load_place_cached(&data, cache_dir.as_deref(), 1, &DomLimits::default())?)?;
+        }
+        Commands::PlaceInfo { input, detect_era } => {
+            let info = place_info::place_info(&fs::read(input)?)?;
+            print!("{}", info.to_text());
+            if detect_era {
+                print!("{}", info.detect_era().to_text());
+            }
+        }
+        Commands::Suggest { input, target, sidecar } => {
+            let info = place_info::place_info(&fs::read(input)?)?;
+            let suggestion = suggest::suggest(&info, target);
+            print!("{}", suggestion.to_text());
+            if let Some(path) = sidecar {
+                fs::write(path, suggestion.to_json()?)?;
+            }
+        }
+        Commands::FloatingParts { input, within, cache_dir } => {
+            let data = fs::read(input)?;
+            let dom = dom_cache::load_place_cached(&data, cache_dir.as_deref(), 1, &DomLimits::default())?;
+            print!("{}", floating::find_floating(&dom, within).to_text());
+        }
+        Commands::ExtractRegion { input, min, max, output, cache_dir } => {
+            let data = fs::read(input)?;
+            let dom = dom_cache::load_place_cached(&data, cache_dir.as_deref(), 1, &DomLimits::default())?;
+            let extracted = region::extract_region(&dom, min, max)?;
+            if extracted.total_parts == 0 {
+                return Err("no parts touch the box".into());
+            }
+            save_dom(&extracted.dom, &output)?;
+            println!(
+                "{} part(s) touch the box, {} model(s) with them, {} part(s) written to {}",
+                extracted.parts,
+                extracted.models,
+                extracted.total_parts,
+                output.display()
+            );
+            if extracted.terrain {
+                println!("the place has terrain, which isn't in the model");
+            }
+        }
+        Commands::PlaceTransform { input, output, scale, rotate, mirror, around, report } => {
+            if scale <= 0.0 || !scale.is_finite() {
+                return Err(format!("can't scale a place by {}", scale).into());
+            }
+            let transform = PlaceTransform { scale, rotate, mirror, around };
+            if transform.is_identity() {
+                return Err("nothing to do, give --scale, --rotate or --mirror".into());
+            }
+            let data = fs::read(input)?;
+            let options = FixPlaceOptions::new().with_pass(passes::TransformPlace { transform });
+            let (out, run) = fix_place_with_report(&data, options)?;
+            print!("{}", run.stats_table());
             fs::write(output, out)?;
+            if let Some(report_path) = report {
+                fs::write(report_path, run.to_text())?;
+            }
+        }
+        Commands::Shard { input, out_dir, grid, cache_dir } => {
+            let data = fs::read(&input)?;
+            let mut dom = dom_cache::load_place_cached(&data, cache_dir.as_deref(), 1, &DomLimits::default())?;
+            fs::create_dir_all(&out_dir)?;
+            let stem = input.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+            let report = shard::shard_place(&data, &mut dom, grid, &stem, &out_dir)?;
+            print!("{}", report.to_text());
+            let manifest = out_dir.join(shard::MANIFEST_NAME);
+            fs::write(&manifest, report.to_json()?)?;
+            println!("manifest written to {}", manifest.display());
+        }
+        Commands::PlaceProfile { input, top, cache_dir } => {
+            let data = fs::read(input)?;
+            let dom = dom_cache::load_place_cached(&data, cache_dir.as_deref(), 1, &DomLimits::default())?;
+            print!("{}", profile::profile_place(&dom).to_text(top));
+        }
+        Commands::ExportMeshes {
+            input,
+            out_dir,
+            version,
+            asset_source,
+            content_dir,
+            with_textures,
+            placeholder,
+            cache_dir,
+        } => {
+            let data = fs::read(input)?;
+            let dom = dom_cache::load_place_cached(&data, cache_dir.as_deref(), 1, &DomLimits::default())?;
+            let sources = mesh_export::MeshSources {
+                assets: asset_source.as_ref(),
+                content_dir: content_dir.as_deref(),
+            };
+            let export = mesh_export::export_meshes(&dom, &out_dir, version, &sources, with_textures, placeholder)?;
+            print!("{}", export.to_text());
+        }
+        Commands::Sniff { inputs } => {
+            for input in inputs {
+                let sniffed = sniff::sniff(&fs::read(&input)?);
+                print!("{}: {}", input.display(), sniffed.to_text());
+            }
+        }
+        Commands::AtlasPack { manifest, out_dir, version, max_size, padding } => {
+            let options = atlas::AtlasOptions { max_size, padding, version };
+            let pack = atlas::atlas_pack(&manifest, &out_dir, &options)?;
+            print!("{}", pack.to_text());
+        }
+        Commands::Fetch { list, out_dir, url, cookie_file, content_source, delay_ms, force } => {
+            let entries = fetch::parse_list(&fs::read_to_string(&list)?)?;
+            let cookie = match cookie_file {
+                Some(path) => Some(fs::read_to_string(path)?.trim().to_owned()),
+                None => None,
+            };
+            let source = match content_source {
+                Some(source) => source,
+                None if url == fetch::DEFAULT_URL => AssetSource::new(asset_source::RobloxCdn::new(cookie.as_deref())),
+                None => {
+                    let headers = cookie.map(|cookie| ("Cookie".to_owned(), format!(".ROBLOSECURITY={}", cookie)));
+                    AssetSource::new(asset_source::Mirror { url, headers: headers.into_iter().collect() })
+                }
+            };
+            let options = fetch::FetchOptions {
+                source,
+                delay: std::time::Duration::from_millis(delay_ms),
+                force,
+            };
+            let result = fetch::fetch_all(&entries, &out_dir, &options)?;
+            print!("{}", result.to_text());
+        }
+        Commands::Universe { command } => match command {
+            UniverseCommands::Fix { manifest, out_dir, fix, report } => {
+                let places = universe::Universe::load(&manifest)?;
+                let start = Utc::now();
+                let result = universe::fix_universe(&places, &out_dir, || fix.to_options())?;
+                let elapsed = Utc::now().signed_duration_since(start);
+                let text = result.to_text();
+                print!("{}", text);
+                println!("done in {} ms", elapsed.num_milliseconds());
+                if let Some(report_path) = report {
+                    fs::write(report_path, text)?;
+                }
+            }
+            UniverseCommands::Scan { manifest } => {
+                let places = universe::Universe::load(&manifest)?;
+                for target in universe::scan_teleports(&places)? {
+                    let note = if target.in_universe { "" } else { "  (not in the universe)" };
+                    println!("{} -> {}{}", target.file.display(), target.place_id, note);
+                }
+            }
+        },
+        Commands::PlaceRecover { input, output, report, max_depth } => {
+            let data = fs::read(input)?;
+            let limits = DomLimits { max_depth, ..DomLimits::default() };
+            let (dom, damage) = recover::recover_place(&data, &limits)?;
+            let root_refs: Vec<_> = dom.root().children().to_vec();
+            let mut out = Vec::new();
+            to_writer_default(&mut out, &dom, &root_refs).map_err(|e| Box::<dyn Error>::from(e.to_string()))?;
+            fs::write(output, out)?;
+            let text = damage.to_text();
+            print!("{}", text);
+            if let Some(report_path) = report {
+                fs::write(report_path, text)?;
+            }
+        }
+        Commands::VerifyContent { content_dir, place, manifest, write_manifest, cache_dir } => {
+            if let Some(path) = write_manifest {
+                let count = content::write_manifest(&content_dir, &path)?;
+                println!("wrote {} hashes to {}", count, path.display());
+            }
+            let dom = dom_cache::load_place_cached(&fs::read(place)?, cache_dir.as_deref(), 1, &DomLimits::default())?;
+            let manifest = manifest.map(|path| content::load_manifest(&path)).transpose()?;
+            let report = content::verify_content(&content_dir, &dom, manifest.as_ref());
+            print!("{}", report.to_text());
+            if !report.is_ok() {
+                return Err("content folder is incomplete".into());
+            }
+        }
+        Commands::ListPresets => {
+            for preset in Preset::value_variants() {
+                if let Some(value) = preset.to_possible_value() {
+                    println!("{:<10} {}", value.get_name(), preset.load().description);
+                }
+            }
         }
     }
     Ok(())
@@ -0,0 +1,268 @@
+// instance mapping file support
+//
+// each key is a source class name, the value is one of:
+//   "Model"                         plain class change (the original flat format)
+//   { "class": "Part", ... }        a single rule
+//   [ { "when": {...}, ... }, ... ] several rules, first one whose conditions match wins
+//
+// a rule can contain:
+//   class     new class name, omitted keeps the class
+//   when      property conditions that all have to match (null means "property is missing")
+//   rename    carry a property over under a new name. the renames happen all at once, so
+//             { "A": "B", "B": "C" } moves A to B and B to C. when two go to the same name the
+//             one whose old name sorts last is kept
+//   set       properties to set after the class change
+//   remove    properties to drop
+//   keep      if present, every property not listed here is dropped
+//   children  instances to insert under the converted instance
+use crate::pipeline::PassContext;
+use rbx_dom_weak::{Instance, InstanceBuilder, Ustr};
+use rbx_types::{
+    BrickColor, Color3, Color3uint8, Content, ContentId, Enum, UDim, UDim2, Variant, Vector2,
+    Vector3,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawMapping {
+    Class(String),
+    Rule(Box<MappingRule>),
+    Rules(Vec<MappingRule>),
+}
+
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct MappingRule {
+    pub class: Option<String>,
+    pub when: HashMap<String, Value>,
+    pub rename: BTreeMap<String, String>,
+    pub set: HashMap<String, Value>,
+    pub remove: Vec<String>,
+    pub keep: Option<Vec<String>>,
+    pub children: Vec<ChildSpec>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ChildSpec {
+    pub class: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub properties: HashMap<String, Value>,
+}
+
+#[derive(Deserialize, Default, Debug)]
+#[serde(from = "HashMap<String, RawMapping>")]
+pub struct InstanceMappings {
+    rules: HashMap<Ustr, Vec<MappingRule>>,
+}
+
+impl From<HashMap<String, RawMapping>> for InstanceMappings {
+    fn from(raw: HashMap<String, RawMapping>) -> Self {
+        let rules = raw
+            .into_iter()
+            .map(|(class, mapping)| {
+                let rules = match mapping {
+                    RawMapping::Class(new_class) => vec![MappingRule {
+                        class: Some(new_class),
+                        ..Default::default()
+                    }],
+                    RawMapping::Rule(rule) => vec![*rule],
+                    RawMapping::Rules(rules) => rules,
+                };
+                (Ustr::from(class.as_str()), rules)
+            })
+            .collect();
+        Self { rules }
+    }
+}
+
+impl InstanceMappings {
+    pub fn from_json(data: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(data)
+    }
+
+    // rules from `other` replace the rules for the same class in `self`
+    pub fn extend(&mut self, other: InstanceMappings) {
+        self.rules.extend(other.rules);
+    }
+
+    // the rules for a class, to describe what a mapping will do to it
+    pub fn rules_for(&self, class: &str) -> Option<&[MappingRule]> {
+        self.rules.get(&Ustr::from(class)).map(Vec::as_slice)
+    }
+
+    // first rule for the instance's class whose conditions hold
+    pub fn find(&self, instance: &Instance) -> Option<&MappingRule> {
+        self.rules
+            .get(&instance.class)?
+            .iter()
+            .find(|rule| rule.matches(instance))
+    }
+}
+
+impl MappingRule {
+    pub fn matches(&self, instance: &Instance) -> bool {
+        self.when.iter().all(|(prop_name, expected)| {
+            let current = instance.properties.get(&Ustr::from(prop_name.as_str()));
+            match (current, expected) {
+                (None, Value::Null) => true,
+                (None, _) | (Some(_), Value::Null) => false,
+                (Some(current), expected) => {
+                    json_to_variant(expected, Some(current)).is_some_and(|v| &v == current)
+                }
+            }
+        })
+    }
+
+    // applies the rule and returns the children that should be inserted under the instance
+    pub fn apply(&self, instance: &mut Instance, ctx: &mut PassContext) -> Vec<InstanceBuilder> {
+        if let Some(class) = &self.class {
+            instance.class = class.as_str().into();
+        }
+
+        let renamed: Vec<_> = self
+            .rename
+            .iter()
+            .filter_map(|(from, to)| Some((to, instance.properties.remove(&Ustr::from(from.as_str()))?)))
+            .collect();
+        for (to, value) in renamed {
+            instance.properties.insert(to.as_str().into(), value);
+        }
+
+        for (prop_name, json_value) in &self.set {
+            let key = Ustr::from(prop_name.as_str());
+            match json_to_variant(json_value, instance.properties.get(&key)) {
+                Some(value) => {
+                    instance.properties.insert(key, value);
+                }
+                None => ctx.warn(format!(
+                    "mapping for '{}' has an unusable value for property '{}': {}",
+                    instance.name, prop_name, json_value
+                )),
+            }
+        }
+
+        for prop_name in &self.remove {
+            instance.properties.remove(&Ustr::from(prop_name.as_str()));
+        }
+
+        if let Some(keep) = &self.keep {
+            instance
+                .properties
+                .retain(|prop_name, _| keep.iter().any(|k| k.as_str() == prop_name.as_str()));
+        }
+
+        self.children.iter().map(|child| child.to_builder(ctx)).collect()
+    }
+}
+
+impl ChildSpec {
+    fn to_builder(&self, ctx: &mut PassContext) -> InstanceBuilder {
+        let mut builder =
+            InstanceBuilder::new(self.class.as_str()).with_name(self.name.as_deref().unwrap_or(&self.class));
+        for (prop_name, json_value) in &self.properties {
+            match json_to_variant(json_value, None) {
+                Some(value) => builder.add_property(prop_name.as_str(), value),
+                None => ctx.warn(format!(
+                    "mapping child '{}' has an unusable value for property '{}': {}",
+                    self.class, prop_name, json_value
+                )),
+            }
+        }
+        builder
+    }
+}
+
+// converts a json value to a variant. when the property already exists its type is used,
+// otherwise the type comes from an explicit { "type": "Vector3", "value": [1, 2, 3] } object
+// or is guessed from the json type
+pub fn json_to_variant(value: &Value, hint: Option<&Variant>) -> Option<Variant> {
+    if let Value::Object(map) = value {
+        let ty = map.get("type")?.as_str()?;
+        return typed_json_to_variant(ty, map.get("value")?);
+    }
+
+    match hint {
+        Some(Variant::Bool(_)) => typed_json_to_variant("Bool", value),
+        Some(Variant::Int32(_)) => typed_json_to_variant("Int32", value),
+        Some(Variant::Int64(_)) => typed_json_to_variant("Int64", value),
+        Some(Variant::Float32(_)) => typed_json_to_variant("Float32", value),
+        Some(Variant::Float64(_)) => typed_json_to_variant("Float64", value),
+        Some(Variant::String(_)) => typed_json_to_variant("String", value),
+        Some(Variant::Content(_)) => typed_json_to_variant("Content", value),
+        Some(Variant::ContentId(_)) => typed_json_to_variant("ContentId", value),
+        Some(Variant::Enum(_)) => typed_json_to_variant("Enum", value),
+        Some(Variant::BrickColor(_)) => typed_json_to_variant("BrickColor", value),
+        Some(Variant::Vector2(_)) => typed_json_to_variant("Vector2", value),
+        Some(Variant::Vector3(_)) => typed_json_to_variant("Vector3", value),
+        Some(Variant::Color3(_)) => typed_json_to_variant("Color3", value),
+        Some(Variant::Color3uint8(_)) => typed_json_to_variant("Color3uint8", value),
+        Some(Variant::UDim(_)) => typed_json_to_variant("UDim", value),
+        Some(Variant::UDim2(_)) => typed_json_to_variant("UDim2", value),
+        _ => match value {
+            Value::Bool(b) => Some(Variant::Bool(*b)),
+            Value::Number(n) if n.is_i64() => Some(Variant::Int32(n.as_i64()? as i32)),
+            Value::Number(n) => Some(Variant::Float32(n.as_f64()? as f32)),
+            Value::String(s) => Some(Variant::String(s.clone())),
+            Value::Array(_) => typed_json_to_variant("Vector3", value),
+            _ => None,
+        },
+    }
+}
+
+fn typed_json_to_variant(ty: &str, value: &Value) -> Option<Variant> {
+    let floats = |count: usize| -> Option<Vec<f32>> {
+        let items = value.as_array()?;
+        if items.len() != count {
+            return None;
+        }
+        items.iter().map(|v| v.as_f64().map(|f| f as f32)).collect()
+    };
+
+    let variant = match ty {
+        "Bool" => Variant::Bool(value.as_bool()?),
+        "Int32" => Variant::Int32(value.as_i64()? as i32),
+        "Int64" => Variant::Int64(value.as_i64()?),
+        "Float32" => Variant::Float32(value.as_f64()? as f32),
+        "Float64" => Variant::Float64(value.as_f64()?),
+        "String" => Variant::String(value.as_str()?.to_owned()),
+        "Content" => Variant::Content(Content::from_uri(value.as_str()?)),
+        "ContentId" => Variant::ContentId(ContentId::from(value.as_str()?)),
+        "Enum" => Variant::Enum(Enum::from_u32(value.as_u64()? as u32)),
+        "BrickColor" => Variant::BrickColor(match value {
+            Value::String(name) => BrickColor::from_name(name)?,
+            _ => BrickColor::from_number(value.as_u64()? as u16)?,
+        }),
+        "Vector2" => {
+            let v = floats(2)?;
+            Variant::Vector2(Vector2::new(v[0], v[1]))
+        }
+        "Vector3" => {
+            let v = floats(3)?;
+            Variant::Vector3(Vector3::new(v[0], v[1], v[2]))
+        }
+        "Color3" => {
+            let v = floats(3)?;
+            Variant::Color3(Color3::new(v[0], v[1], v[2]))
+        }
+        "Color3uint8" => {
+            let v = floats(3)?;
+            Variant::Color3uint8(Color3uint8::new(v[0] as u8, v[1] as u8, v[2] as u8))
+        }
+        "UDim" => {
+            let v = floats(2)?;
+            Variant::UDim(UDim::new(v[0], v[1] as i32))
+        }
+        "UDim2" => {
+            let v = floats(4)?;
+            Variant::UDim2(UDim2::new(UDim::new(v[0], v[1] as i32), UDim::new(v[2], v[3] as i32)))
+        }
+        _ => return None,
+    };
+    Some(variant)
+}
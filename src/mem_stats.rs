@@ -0,0 +1,185 @@
+// --mem-stats, where the memory goes when converting a big place
+//
+// allocations are counted by CountingAllocator, which only the cli installs as the global
+// allocator. it does nothing past one atomic load until enable() is called, so builds that
+// install it don't pay for it unless asked. peak rss comes from /proc and is linux only.
+use rbx_dom_weak::WeakDom;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static ALLOCATIONS: AtomicU64 = AtomicU64::new(0);
+static ALLOCATED_BYTES: AtomicU64 = AtomicU64::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+pub struct CountingAllocator;
+
+fn count_alloc(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size as u64, Ordering::Relaxed);
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_LIVE_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+fn count_dealloc(size: usize) {
+    // frees of memory allocated before counting started would go below zero
+    let _ = LIVE_BYTES.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |live| Some(live.saturating_sub(size)));
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        if ENABLED.load(Ordering::Relaxed) {
+            count_alloc(layout.size());
+        }
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        if ENABLED.load(Ordering::Relaxed) {
+            count_alloc(layout.size());
+        }
+        unsafe { System.alloc_zeroed(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        if ENABLED.load(Ordering::Relaxed) {
+            count_dealloc(layout.size());
+        }
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if ENABLED.load(Ordering::Relaxed) {
+            count_dealloc(layout.size());
+            count_alloc(new_size);
+        }
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+pub fn enable() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllocSnapshot {
+    pub allocations: u64,
+    pub bytes: u64,
+    pub live: usize,
+}
+
+pub fn snapshot() -> AllocSnapshot {
+    AllocSnapshot {
+        allocations: ALLOCATIONS.load(Ordering::Relaxed),
+        bytes: ALLOCATED_BYTES.load(Ordering::Relaxed),
+        live: LIVE_BYTES.load(Ordering::Relaxed),
+    }
+}
+
+// what happened between two snapshots
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AllocDelta {
+    pub allocations: u64,
+    pub bytes: u64,
+    // heap still held afterwards, negative when it freed more than it allocated
+    pub retained: i64,
+}
+
+impl AllocSnapshot {
+    pub fn since(&self, earlier: &AllocSnapshot) -> AllocDelta {
+        AllocDelta {
+            allocations: self.allocations - earlier.allocations,
+            bytes: self.bytes - earlier.bytes,
+            retained: self.live as i64 - earlier.live as i64,
+        }
+    }
+}
+
+// VmHWM from /proc/self/status, in bytes
+pub fn peak_rss() -> Option<u64> {
+    let status = fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmHWM:"))?;
+    let kb: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kb * 1024)
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemStats {
+    pub peak_rss: Option<u64>,
+    pub peak_heap: usize,
+    pub parse: AllocDelta,
+    pub instances: usize,
+    pub properties: usize,
+    pub interned_strings: usize,
+    pub interned_bytes: usize,
+    // (pass name, allocations during it)
+    pub passes: Vec<(String, AllocDelta)>,
+}
+
+fn mb(bytes: f64) -> f64 {
+    bytes / (1024.0 * 1024.0)
+}
+
+impl MemStats {
+    // everything but the per pass numbers, which the pipeline fills in
+    pub fn collect(&mut self, dom: &WeakDom, parse: AllocDelta) {
+        self.peak_rss = peak_rss();
+        self.peak_heap = PEAK_LIVE_BYTES.load(Ordering::Relaxed);
+        self.parse = parse;
+        self.instances = dom.descendants().count() - 1;
+        self.properties = dom.descendants().map(|instance| instance.properties.len()).sum();
+        self.interned_strings = ustr::num_entries();
+        self.interned_bytes = ustr::total_allocated();
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        match self.peak_rss {
+            Some(rss) => {
+                let _ = writeln!(out, "peak rss: {:.1} MB", mb(rss as f64));
+            }
+            None => {
+                let _ = writeln!(out, "peak rss: unavailable on this platform");
+            }
+        }
+        let _ = writeln!(
+            out,
+            "dom: {} instances, {} properties, {:.1} MB held after parsing",
+            self.instances,
+            self.properties,
+            mb(self.parse.retained as f64)
+        );
+        let _ = writeln!(
+            out,
+            "interned strings: {} ({:.1} MB)",
+            self.interned_strings,
+            mb(self.interned_bytes as f64)
+        );
+        if self.parse.allocations == 0 {
+            let _ = writeln!(out, "allocations weren't counted, the counting allocator isn't installed");
+            return out;
+        }
+        let _ = writeln!(out, "peak heap: {:.1} MB", mb(self.peak_heap as f64));
+        let _ = writeln!(out, "{:<24} {:>12} {:>12} {:>12}", "stage", "allocs", "MB", "retained MB");
+        let stages = std::iter::once(("parse", &self.parse))
+            .chain(self.passes.iter().map(|(name, delta)| (name.as_str(), delta)));
+        for (name, delta) in stages {
+            let _ = writeln!(
+                out,
+                "{:<24} {:>12} {:>12.1} {:>12.1}",
+                name,
+                delta.allocations,
+                mb(delta.bytes as f64),
+                mb(delta.retained as f64)
+            );
+        }
+        out
+    }
+}
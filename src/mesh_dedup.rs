@@ -0,0 +1,158 @@
+// the same mesh uploaded under different asset ids
+//
+// meshes get reuploaded all the time, and a mirror or a downgraded place ends up with copies of
+// one shape under several ids. the hash is over the faces, each face's corners (position and
+// uv, rounded to 1e-4) rotated to start at the smallest one so the winding stays, and the faces
+// sorted, so vertex order, face order and splitting don't change it. uvs count because an alias
+// swaps one mesh for another under the same texture. files are named by asset id, like the
+// directories export-meshes and AssetSource use.
+//
+// the alias map sends every id in a group to the smallest one, the oldest upload, and is what
+// fix-place --asset-aliases reads:
+//   { "24913208": 1818, "30011002": 1818 }
+use crate::filemesh;
+use crate::mesh_types::IntermediateMesh;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::Path;
+
+const QUANTUM: f32 = 1e-4;
+
+type Corner = [i64; 5];
+
+fn corner(mesh: &IntermediateMesh, index: u32) -> Corner {
+    let vertex = &mesh.vertices[index as usize];
+    let q = |value: f32| (value / QUANTUM).round() as i64;
+    [q(vertex.pos[0]), q(vertex.pos[1]), q(vertex.pos[2]), q(vertex.uv[0]), q(vertex.uv[1])]
+}
+
+pub fn geometry_hash(mesh: &IntermediateMesh) -> String {
+    let vertex_count = mesh.vertices.len();
+    let mut faces: Vec<[Corner; 3]> = mesh
+        .faces
+        .iter()
+        .filter(|face| face.iter().all(|&index| (index as usize) < vertex_count))
+        .map(|face| {
+            let mut corners = face.map(|index| corner(mesh, index));
+            let first = (0..3).min_by_key(|&i| corners[i]).unwrap_or(0);
+            corners.rotate_left(first);
+            corners
+        })
+        .collect();
+    faces.sort_unstable();
+
+    let mut hasher = Sha256::new();
+    for face in &faces {
+        for value in face.iter().flatten() {
+            hasher.update(value.to_le_bytes());
+        }
+    }
+    hasher.finalize().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+#[derive(Serialize)]
+pub struct DuplicateGroup {
+    pub hash: String,
+    pub files: Vec<String>,
+    // smallest asset id among the files, what the others are aliased to
+    pub canonical: Option<u64>,
+}
+
+#[derive(Serialize, Default)]
+pub struct MeshDedup {
+    pub meshes: usize,
+    pub groups: Vec<DuplicateGroup>,
+    // files that aren't meshes this can read
+    pub skipped: Vec<String>,
+}
+
+fn asset_id(file: &str) -> Option<u64> {
+    let stem = file.rsplit_once('.').map_or(file, |(stem, _)| stem);
+    stem.parse().ok()
+}
+
+pub fn dedup_meshes(dir: &Path) -> Result<MeshDedup, Box<dyn Error>> {
+    let mut files: Vec<_> = fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect();
+    files.sort();
+
+    let mut dedup = MeshDedup::default();
+    let mut by_hash: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for file in files {
+        let data = fs::read(dir.join(&file))?;
+        match filemesh::parse_filemesh(&data) {
+            Ok(mesh) => {
+                by_hash.entry(geometry_hash(&mesh)).or_default().push(file);
+                dedup.meshes += 1;
+            }
+            Err(_) => dedup.skipped.push(file),
+        }
+    }
+    dedup.groups = by_hash
+        .into_iter()
+        .filter(|(_, files)| files.len() > 1)
+        .map(|(hash, files)| DuplicateGroup {
+            canonical: files.iter().filter_map(|file| asset_id(file)).min(),
+            hash,
+            files,
+        })
+        .collect();
+    Ok(dedup)
+}
+
+impl MeshDedup {
+    // duplicate id -> the id it's the same as
+    pub fn aliases(&self) -> BTreeMap<u64, u64> {
+        let mut aliases = BTreeMap::new();
+        for group in &self.groups {
+            let Some(canonical) = group.canonical else { continue };
+            for id in group.files.iter().filter_map(|file| asset_id(file)) {
+                if id != canonical {
+                    aliases.insert(id, canonical);
+                }
+            }
+        }
+        aliases
+    }
+
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for group in &self.groups {
+            let _ = writeln!(out, "{}: {}", &group.hash[..16], group.files.join(", "));
+        }
+        let duplicates: usize = self.groups.iter().map(|group| group.files.len() - 1).sum();
+        let _ = writeln!(
+            out,
+            "{} mesh(es), {} duplicate(s) in {} group(s), {} file(s) skipped",
+            self.meshes,
+            duplicates,
+            self.groups.len(),
+            self.skipped.len()
+        );
+        out
+    }
+}
+
+pub fn aliases_to_json(aliases: &BTreeMap<u64, u64>) -> Result<String, Box<dyn Error>> {
+    Ok(serde_json::to_string_pretty(aliases)?)
+}
+
+pub fn aliases_from_json(data: &str) -> Result<BTreeMap<u64, u64>, Box<dyn Error>> {
+    let aliases: BTreeMap<u64, u64> =
+        serde_json::from_str(data).map_err(|e| format!("couldn't read the asset aliases: {}", e))?;
+    if let Some((id, _)) = aliases.iter().find(|(_, canonical)| aliases.contains_key(canonical)) {
+        return Err(format!("asset {} is aliased to an id that's aliased itself", id).into());
+    }
+    Ok(aliases)
+}
@@ -0,0 +1,226 @@
+// every mesh a place uses, converted to one filemesh version and written to a directory
+//
+// meshes come from SpecialMesh/FileMesh/MeshPart MeshIds, asset ids through an AssetSource and
+// rbxasset:// paths from a content dir. with textures on, the TextureId (TextureID on a
+// MeshPart, or its SurfaceAppearance's ColorMap) each mesh is used with is copied next to it.
+// manifest.json says which textures go with which mesh and what couldn't be exported, since a
+// downgraded mesh is no use when nobody can tell which texture it had. with placeholders on,
+// each mesh is written as its bounding box instead, for stand-ins in big place downgrades.
+use crate::asset_source::AssetSource;
+use crate::content::{asset_path_from_uri, resolve};
+use crate::content_uri::ContentUri;
+use crate::path::path_of;
+use crate::placeholder::placeholder_mesh;
+use crate::{filemesh, serialize_mesh, RobloxMeshVersion};
+use rbx_dom_weak::{Instance, WeakDom};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::Path;
+
+const MANIFEST_NAME: &str = "manifest.json";
+const MESH_EXTENSIONS: [&str; 2] = ["mesh", ""];
+const TEXTURE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", ""];
+
+pub struct MeshSources<'a> {
+    pub assets: Option<&'a AssetSource>,
+    pub content_dir: Option<&'a Path>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ExportedTexture {
+    pub source: String,
+    // relative to the output dir
+    pub file: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct ExportedMesh {
+    pub source: String,
+    pub file: String,
+    // the mesh's bounding box was written in its place
+    #[serde(default)]
+    pub placeholder: bool,
+    pub textures: Vec<ExportedTexture>,
+    pub used_by: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct MeshExport {
+    pub meshes: Vec<ExportedMesh>,
+    // uri -> why it wasn't exported
+    pub failed: BTreeMap<String, String>,
+}
+
+impl MeshExport {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (uri, reason) in &self.failed {
+            let _ = writeln!(out, "failed: {} ({})", uri, reason);
+        }
+        let textures: usize = self.meshes.iter().map(|mesh| mesh.textures.len()).sum();
+        let _ = writeln!(
+            out,
+            "{} mesh(es) exported with {} texture(s), {} failed",
+            self.meshes.len(),
+            textures,
+            self.failed.len()
+        );
+        out
+    }
+}
+
+#[derive(Default)]
+struct MeshUse {
+    textures: BTreeSet<String>,
+    used_by: Vec<String>,
+}
+
+fn uri_of(instance: &Instance, property: &str) -> Option<ContentUri> {
+    let uri = ContentUri::from_variant(instance.properties.get(&property.into())?)?;
+    (!uri.to_string().is_empty()).then_some(uri)
+}
+
+// the texture drawn on a mesh instance, if it has one
+fn texture_of(dom: &WeakDom, instance: &Instance) -> Option<ContentUri> {
+    if instance.class != "MeshPart" {
+        return uri_of(instance, "TextureId");
+    }
+    uri_of(instance, "TextureID").or_else(|| {
+        instance
+            .children()
+            .iter()
+            .filter_map(|&child| dom.get_by_ref(child))
+            .find(|child| child.class == "SurfaceAppearance")
+            .and_then(|appearance| uri_of(appearance, "ColorMap"))
+    })
+}
+
+fn mesh_uses(dom: &WeakDom, with_textures: bool) -> BTreeMap<String, MeshUse> {
+    let mut uses: BTreeMap<String, MeshUse> = BTreeMap::new();
+    for instance in dom.descendants() {
+        if !matches!(instance.class.as_str(), "SpecialMesh" | "FileMesh" | "MeshPart") {
+            continue;
+        }
+        let Some(mesh) = uri_of(instance, "MeshId") else { continue };
+        let entry = uses.entry(mesh.to_string()).or_default();
+        entry.used_by.push(path_of(dom, instance.referent()));
+        if with_textures
+            && let Some(texture) = texture_of(dom, instance)
+        {
+            entry.textures.insert(texture.to_string());
+        }
+    }
+    uses
+}
+
+fn fetch(uri: &ContentUri, sources: &MeshSources, extensions: &[&str]) -> Result<Vec<u8>, Box<dyn Error>> {
+    if let Some(id) = uri.asset_id() {
+        let assets = sources.assets.ok_or("it's an asset id and there's no asset source")?;
+        return assets.fetch(id, extensions);
+    }
+    if let ContentUri::Local(_) = uri {
+        let path = asset_path_from_uri(uri).ok_or("not a path inside the content dir")?;
+        let content_dir = sources.content_dir.ok_or("it's an rbxasset:// path and there's no content dir")?;
+        let file = resolve(content_dir, &path).ok_or_else(|| format!("no {} in {}", path, content_dir.display()))?;
+        return Ok(fs::read(file)?);
+    }
+    Err("only asset ids and rbxasset:// paths can be exported".into())
+}
+
+// a file name for the asset, its id or its content path with the slashes flattened
+fn file_stem(uri: &ContentUri) -> String {
+    if let Some(id) = uri.asset_id() {
+        return id.to_string();
+    }
+    let path = uri.local_path().unwrap_or_else(|| uri.to_string());
+    let path = path.rsplit_once('.').map_or(path.as_str(), |(stem, _)| stem);
+    path.replace(['/', ':', '?', '&', '='], "_")
+}
+
+fn texture_extension(data: &[u8]) -> &'static str {
+    if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        "png"
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        "jpg"
+    } else {
+        "bin"
+    }
+}
+
+pub fn export_meshes(
+    dom: &WeakDom,
+    out_dir: &Path,
+    version: RobloxMeshVersion,
+    sources: &MeshSources,
+    with_textures: bool,
+    placeholders: bool,
+) -> Result<MeshExport, Box<dyn Error>> {
+    fs::create_dir_all(out_dir)?;
+    let mut export = MeshExport::default();
+    // texture uri -> file it was written to, textures shared by meshes are copied once
+    let mut written_textures: BTreeMap<String, String> = BTreeMap::new();
+
+    for (mesh_uri, mesh_use) in mesh_uses(dom, with_textures) {
+        let uri = ContentUri::parse(&mesh_uri);
+        let converted = fetch(&uri, sources, &MESH_EXTENSIONS)
+            .and_then(|data| Ok(filemesh::parse_filemesh(&data)?))
+            .and_then(|mesh| if placeholders { Ok(placeholder_mesh(&mesh)?) } else { Ok(mesh) })
+            .and_then(|mesh| Ok(serialize_mesh(&mesh, version)?));
+        let bytes = match converted {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                export.failed.insert(mesh_uri, e.to_string());
+                continue;
+            }
+        };
+        let file = format!("{}.mesh", file_stem(&uri));
+        fs::write(out_dir.join(&file), bytes)?;
+
+        let mut textures = Vec::new();
+        for texture_uri in &mesh_use.textures {
+            if let Some(file) = written_textures.get(texture_uri) {
+                textures.push(ExportedTexture {
+                    source: texture_uri.clone(),
+                    file: file.clone(),
+                });
+                continue;
+            }
+            if export.failed.contains_key(texture_uri) {
+                continue;
+            }
+            let uri = ContentUri::parse(texture_uri);
+            let data = match fetch(&uri, sources, &TEXTURE_EXTENSIONS) {
+                Ok(data) => data,
+                Err(e) => {
+                    export.failed.insert(texture_uri.clone(), e.to_string());
+                    continue;
+                }
+            };
+            let extension = match uri.local_path() {
+                Some(path) => path.rsplit_once('.').map_or("bin".to_owned(), |(_, ext)| ext.to_ascii_lowercase()),
+                None => texture_extension(&data).to_owned(),
+            };
+            let texture_file = format!("{}.{}", file_stem(&uri), extension);
+            fs::write(out_dir.join(&texture_file), data)?;
+            written_textures.insert(texture_uri.clone(), texture_file.clone());
+            textures.push(ExportedTexture {
+                source: texture_uri.clone(),
+                file: texture_file,
+            });
+        }
+
+        export.meshes.push(ExportedMesh {
+            source: mesh_uri,
+            file,
+            placeholder: placeholders,
+            textures,
+            used_by: mesh_use.used_by,
+        });
+    }
+
+    fs::write(out_dir.join(MANIFEST_NAME), serde_json::to_string_pretty(&export)?)?;
+    Ok(export)
+}
@@ -0,0 +1,107 @@
+// what's in a filemesh, for going through meshes pulled out of asset dumps
+//
+// everything comes from parsing the whole file, lod levels included, so counts are of what's
+// stored rather than what a client draws (that's lod 0, see filemesh::parse_filemesh). vertex
+// colors count as there when some vertex isn't white, v1/v2 can't have any.
+use crate::error::Result;
+use crate::filemesh;
+use serde_json::json;
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+
+pub struct MeshInfo {
+    // "4.00", the header without "version "
+    pub version: String,
+    pub vertices: usize,
+    pub faces: usize,
+    // where each lod level starts and the last one ends, in faces. empty before v3
+    pub lod_offsets: Vec<u32>,
+    pub bones: usize,
+    // (controls, bones) of a dynamic head's poses
+    pub facs: Option<(usize, usize)>,
+    pub bounds: Option<([f32; 3], [f32; 3])>,
+    pub vertex_colors: bool,
+}
+
+pub fn mesh_info(data: &[u8]) -> Result<MeshInfo> {
+    let (header, _) = filemesh::read_header(data)?;
+    let version = header.trim_start_matches("version ").to_owned();
+    let (mesh, lod_offsets) = filemesh::parse_filemesh_all_lods(data)?;
+    let bounds = mesh.vertices.iter().fold(None, |bounds: Option<([f32; 3], [f32; 3])>, vertex| {
+        let (mut min, mut max) = bounds.unwrap_or((vertex.pos, vertex.pos));
+        for axis in 0..3 {
+            min[axis] = min[axis].min(vertex.pos[axis]);
+            max[axis] = max[axis].max(vertex.pos[axis]);
+        }
+        Some((min, max))
+    });
+    Ok(MeshInfo {
+        version,
+        vertices: mesh.vertices.len(),
+        faces: mesh.faces.len(),
+        bones: mesh.skin.as_ref().map_or(0, |skin| skin.bones.len()),
+        facs: mesh.facs.as_ref().map(|facs| (facs.control_names.len(), facs.bone_names.len())),
+        vertex_colors: mesh.has_vertex_colors(),
+        bounds,
+        lod_offsets,
+    })
+}
+
+impl MeshInfo {
+    pub fn lod_levels(&self) -> usize {
+        self.lod_offsets.len().saturating_sub(1).max(1)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "version: {}", self.version);
+        let _ = writeln!(out, "vertices: {}", self.vertices);
+        let _ = writeln!(out, "faces: {}", self.faces);
+        if self.lod_offsets.is_empty() {
+            let _ = writeln!(out, "lods: 1, the version has no lod offsets");
+        } else {
+            let offsets: Vec<String> = self.lod_offsets.iter().map(|offset| offset.to_string()).collect();
+            let _ = writeln!(out, "lods: {} (offsets {})", self.lod_levels(), offsets.join(", "));
+        }
+        let _ = writeln!(out, "bones: {}", self.bones);
+        match self.facs {
+            Some((controls, bones)) => {
+                let _ = writeln!(out, "facs: {} controls on {} bones", controls, bones);
+            }
+            None => {
+                let _ = writeln!(out, "facs: none");
+            }
+        }
+        match self.bounds {
+            Some((min, max)) => {
+                let size: Vec<f32> = (0..3).map(|axis| max[axis] - min[axis]).collect();
+                let _ = writeln!(
+                    out,
+                    "bounds: ({}, {}, {}) to ({}, {}, {}), {} x {} x {}",
+                    min[0], min[1], min[2], max[0], max[1], max[2], size[0], size[1], size[2]
+                );
+            }
+            None => {
+                let _ = writeln!(out, "bounds: none, there are no vertices");
+            }
+        }
+        let _ = writeln!(out, "vertex colors: {}", if self.vertex_colors { "yes" } else { "no" });
+        out
+    }
+
+    pub fn to_json(&self) -> std::result::Result<String, Box<dyn Error>> {
+        let facs = self.facs.map(|(controls, bones)| json!({ "controls": controls, "bones": bones }));
+        let bounds = self.bounds.map(|(min, max)| json!({ "min": min, "max": max }));
+        Ok(serde_json::to_string_pretty(&json!({
+            "version": self.version,
+            "vertices": self.vertices,
+            "faces": self.faces,
+            "lods": self.lod_levels(),
+            "lod_offsets": self.lod_offsets,
+            "bones": self.bones,
+            "facs": facs,
+            "bounds": bounds,
+            "vertex_colors": self.vertex_colors,
+        }))?)
+    }
+}
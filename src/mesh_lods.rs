@@ -0,0 +1,331 @@
+// simplified lod levels for a mesh, by quadric error edge collapses
+//
+// each level keeps collapsing edges until it has about half the faces of the one before. a vertex
+// is only ever collapsed onto one of its neighbours, never moved, so every level uses the mesh's
+// own vertices and the levels share one vertex buffer in the file like roblox's meshes do.
+// filemesh vertices are split at uv and normal seams, so they're welded by position for the
+// collapsing, and a corner that moves onto a neighbour takes the neighbour's copy from the same
+// side of the seam. open edges cost more to move so outlines keep their shape, and collapses
+// that would turn a face over or pinch the surface are skipped.
+use crate::filemesh::compact_vertices;
+use crate::mesh_types::IntermediateMesh;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet, BinaryHeap, HashMap};
+
+// how much more moving an open edge costs than moving across a surface
+const BOUNDARY_WEIGHT: f64 = 100.0;
+// a level that can't get below this much of the one before it is where it stops
+const MIN_REDUCTION: f64 = 0.9;
+
+// the plane equation products a2, ab, ac, ad, b2, bc, bd, c2, cd, d2
+type Quadric = [f64; 10];
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn length(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn plane_quadric(normal: [f64; 3], point: [f64; 3], weight: f64) -> Quadric {
+    let [a, b, c] = normal;
+    let d = -dot(normal, point);
+    [a * a, a * b, a * c, a * d, b * b, b * c, b * d, c * c, c * d, d * d].map(|value| value * weight)
+}
+
+fn add(q: &mut Quadric, other: &Quadric) {
+    for (value, other) in q.iter_mut().zip(other) {
+        *value += other;
+    }
+}
+
+fn error(q: &Quadric, p: [f64; 3]) -> f64 {
+    let [x, y, z] = p;
+    q[0] * x * x + 2.0 * q[1] * x * y + 2.0 * q[2] * x * z + 2.0 * q[3] * x + q[4] * y * y + 2.0 * q[5] * y * z
+        + 2.0 * q[6] * y
+        + q[7] * z * z
+        + 2.0 * q[8] * z
+        + q[9]
+}
+
+struct Face {
+    // welded vertex ids
+    welded: [usize; 3],
+    // the mesh's vertices
+    corners: [u32; 3],
+    alive: bool,
+}
+
+// moving `from` onto `to`, valid while neither vertex has changed since it was costed
+struct Collapse {
+    cost: f64,
+    from: usize,
+    to: usize,
+    stamps: (u32, u32),
+}
+
+impl PartialEq for Collapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Collapse {}
+
+impl PartialOrd for Collapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// cheapest first out of the max heap, ties by vertex so the same mesh always comes out the same
+impl Ord for Collapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| (other.from, other.to).cmp(&(self.from, self.to)))
+    }
+}
+
+struct Simplifier<'a> {
+    mesh: &'a IntermediateMesh,
+    positions: Vec<[f64; 3]>,
+    quadrics: Vec<Quadric>,
+    faces: Vec<Face>,
+    // welded id -> faces that have used it, dead ones included
+    vertex_faces: Vec<Vec<usize>>,
+    stamps: Vec<u32>,
+    removed: Vec<bool>,
+    heap: BinaryHeap<Collapse>,
+    alive: usize,
+}
+
+impl<'a> Simplifier<'a> {
+    fn new(mesh: &'a IntermediateMesh) -> Self {
+        let mut ids: HashMap<[u32; 3], usize> = HashMap::new();
+        let mut positions = Vec::new();
+        let welded_of: Vec<usize> = mesh
+            .vertices
+            .iter()
+            .map(|vertex| {
+                *ids.entry(vertex.pos.map(f32::to_bits)).or_insert_with(|| {
+                    positions.push(vertex.pos.map(|value| value as f64));
+                    positions.len() - 1
+                })
+            })
+            .collect();
+        let faces: Vec<Face> = mesh
+            .faces
+            .iter()
+            .map(|&corners| Face {
+                welded: corners.map(|corner| welded_of[corner as usize]),
+                corners,
+                alive: true,
+            })
+            .filter(|face| {
+                let [a, b, c] = face.welded;
+                a != b && b != c && a != c
+            })
+            .collect();
+
+        let mut quadrics = vec![[0.0; 10]; positions.len()];
+        let mut vertex_faces = vec![Vec::new(); positions.len()];
+        let mut edges: BTreeMap<(usize, usize), (usize, usize)> = BTreeMap::new();
+        for (index, face) in faces.iter().enumerate() {
+            let [p0, p1, p2] = face.welded.map(|id| positions[id]);
+            let normal = cross(sub(p1, p0), sub(p2, p0));
+            let area = length(normal);
+            if area > 0.0 {
+                let quadric = plane_quadric(normal.map(|value| value / area), p0, area / 2.0);
+                face.welded.iter().for_each(|&id| add(&mut quadrics[id], &quadric));
+            }
+            for (corner, &id) in face.welded.iter().enumerate() {
+                vertex_faces[id].push(index);
+                let next = face.welded[(corner + 1) % 3];
+                edges.entry((id.min(next), id.max(next))).or_insert((0, index)).0 += 1;
+            }
+        }
+        // a plane standing on each open edge keeps it from sliding sideways
+        for (&(a, b), &(count, face)) in &edges {
+            if count != 1 {
+                continue;
+            }
+            let [p0, p1, p2] = faces[face].welded.map(|id| positions[id]);
+            let face_normal = cross(sub(p1, p0), sub(p2, p0));
+            let edge = sub(positions[b], positions[a]);
+            let side = cross(edge, face_normal);
+            let side_length = length(side);
+            if side_length > 0.0 {
+                let quadric = plane_quadric(side.map(|value| value / side_length), positions[a], BOUNDARY_WEIGHT * length(edge));
+                add(&mut quadrics[a], &quadric);
+                add(&mut quadrics[b], &quadric);
+            }
+        }
+
+        let alive = faces.len();
+        let count = positions.len();
+        let mut simplifier = Self {
+            mesh,
+            positions,
+            quadrics,
+            faces,
+            vertex_faces,
+            stamps: vec![0; count],
+            removed: vec![false; count],
+            heap: BinaryHeap::new(),
+            alive,
+        };
+        for &(a, b) in edges.keys() {
+            simplifier.push_edge(a, b);
+        }
+        simplifier
+    }
+
+    fn alive_faces(&self, id: usize) -> impl Iterator<Item = usize> + '_ {
+        self.vertex_faces[id].iter().copied().filter(|&face| self.faces[face].alive)
+    }
+
+    fn neighbours(&self, id: usize) -> BTreeSet<usize> {
+        self.alive_faces(id)
+            .flat_map(|face| self.faces[face].welded)
+            .filter(|&other| other != id)
+            .collect()
+    }
+
+    fn cost(&self, from: usize, to: usize) -> f64 {
+        let mut quadric = self.quadrics[from];
+        add(&mut quadric, &self.quadrics[to]);
+        error(&quadric, self.positions[to])
+    }
+
+    // the cheaper way of collapsing the edge
+    fn push_edge(&mut self, a: usize, b: usize) {
+        let (from, to) = if self.cost(a, b) <= self.cost(b, a) { (a, b) } else { (b, a) };
+        self.heap.push(Collapse {
+            cost: self.cost(from, to),
+            from,
+            to,
+            stamps: (self.stamps[from], self.stamps[to]),
+        });
+    }
+
+    // the surface stays a surface and no face gets turned over
+    fn allowed(&self, from: usize, to: usize) -> bool {
+        let shared = self.alive_faces(from).filter(|&face| self.faces[face].welded.contains(&to)).count();
+        if self.neighbours(from).intersection(&self.neighbours(to)).count() > shared {
+            return false;
+        }
+        self.alive_faces(from)
+            .filter(|&face| !self.faces[face].welded.contains(&to))
+            .all(|face| {
+                let before = self.faces[face].welded.map(|id| self.positions[id]);
+                let after = self.faces[face].welded.map(|id| self.positions[if id == from { to } else { id }]);
+                let normal_before = cross(sub(before[1], before[0]), sub(before[2], before[0]));
+                let normal_after = cross(sub(after[1], after[0]), sub(after[2], after[0]));
+                dot(normal_before, normal_after) > 0.0
+            })
+    }
+
+    fn collapse(&mut self, from: usize, to: usize) {
+        // the copy of `to` each copy of `from` becomes, going by the faces across the edge
+        let mut corner_map: HashMap<u32, u32> = HashMap::new();
+        let mut to_corners: Vec<u32> = Vec::new();
+        for face in self.alive_faces(to).collect::<Vec<_>>() {
+            let face = &self.faces[face];
+            let to_corner = face.corners[face.welded.iter().position(|&id| id == to).unwrap_or(0)];
+            if !to_corners.contains(&to_corner) {
+                to_corners.push(to_corner);
+            }
+            if let Some(from_slot) = face.welded.iter().position(|&id| id == from) {
+                corner_map.entry(face.corners[from_slot]).or_insert(to_corner);
+            }
+        }
+        // copies off to the side of the edge take the one with the nearest uv
+        let mesh = self.mesh;
+        let vertices = &mesh.vertices;
+        let mut nearest = |corner: u32| {
+            *corner_map.entry(corner).or_insert_with(|| {
+                let uv = vertices[corner as usize].uv;
+                let distance = |other: &u32| {
+                    let other = vertices[*other as usize].uv;
+                    (other[0] - uv[0]).powi(2) + (other[1] - uv[1]).powi(2)
+                };
+                to_corners.iter().copied().min_by(|a, b| distance(a).total_cmp(&distance(b))).unwrap_or(corner)
+            })
+        };
+
+        for face in self.vertex_faces[from].clone() {
+            if !self.faces[face].alive {
+                continue;
+            }
+            if self.faces[face].welded.contains(&to) {
+                self.faces[face].alive = false;
+                self.alive -= 1;
+                continue;
+            }
+            let slot = self.faces[face].welded.iter().position(|&id| id == from).unwrap_or(0);
+            self.faces[face].welded[slot] = to;
+            self.faces[face].corners[slot] = nearest(self.faces[face].corners[slot]);
+            self.vertex_faces[to].push(face);
+        }
+        let quadric = self.quadrics[from];
+        add(&mut self.quadrics[to], &quadric);
+        self.removed[from] = true;
+        self.stamps[to] += 1;
+        for neighbour in self.neighbours(to) {
+            self.push_edge(to, neighbour);
+        }
+    }
+
+    // collapses until there are at most `target` faces or nothing left that can go
+    fn simplify(&mut self, target: usize) {
+        while self.alive > target {
+            let Some(collapse) = self.heap.pop() else { return };
+            let (from, to) = (collapse.from, collapse.to);
+            if self.removed[from] || self.removed[to] || collapse.stamps != (self.stamps[from], self.stamps[to]) {
+                continue;
+            }
+            if self.allowed(from, to) {
+                self.collapse(from, to);
+            }
+        }
+    }
+
+    fn faces(&self) -> Vec<[u32; 3]> {
+        self.faces.iter().filter(|face| face.alive).map(|face| face.corners).collect()
+    }
+}
+
+// up to `levels` simplified versions of the mesh's faces, lowest detail last, in the mesh's own
+// vertices. fewer when the mesh stops getting simpler
+pub fn generate_lods(mesh: &IntermediateMesh, levels: usize) -> Vec<Vec<[u32; 3]>> {
+    let mut simplifier = Simplifier::new(mesh);
+    let mut lods = Vec::new();
+    for _ in 0..levels {
+        let before = simplifier.alive;
+        simplifier.simplify(before / 2);
+        if simplifier.alive == 0 || simplifier.alive as f64 > before as f64 * MIN_REDUCTION {
+            break;
+        }
+        lods.push(simplifier.faces());
+    }
+    lods
+}
+
+// the mesh collapsed down to at most `target` faces, or as close as it gets, without the vertices
+// nothing uses anymore
+pub fn simplify(mesh: &IntermediateMesh, target: usize) -> IntermediateMesh {
+    let mut simplifier = Simplifier::new(mesh);
+    simplifier.simplify(target);
+    compact_vertices(mesh, &simplifier.faces())
+}
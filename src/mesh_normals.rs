@@ -0,0 +1,128 @@
+// vertex normals worked out from the faces, for objs that come without them or with broken ones
+//
+// each face's normal is weighted by its area, so slivers along an edge don't pull the shading
+// around. a corner of a face gets the sum of the faces around its position that are within the
+// smoothing angle of that face, like blender's auto smooth: below it the surface shades smooth,
+// past it the edge stays hard. vertices are split wherever their corners end up with different
+// normals, and copies left the same (split in the obj only by a bad normal) are merged back.
+// tangents are built on the normals, so they're generated again after.
+use crate::mesh_tangents::generate_tangents;
+use crate::mesh_topology::weld_vertices;
+use crate::mesh_types::IntermediateMesh;
+use std::collections::HashMap;
+
+// blender's auto smooth default
+pub const DEFAULT_SMOOTH_ANGLE: f32 = 30.0;
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalized(v: [f32; 3]) -> Option<[f32; 3]> {
+    let length = dot(v, v).sqrt();
+    (length > 1e-12 && length.is_finite()).then(|| v.map(|c| c / length))
+}
+
+// position, uv, color and new normal, plus the original vertex when it has to stay apart
+type VertexKey = ([u32; 3], [u32; 2], [u8; 4], [u32; 3], Option<u32>);
+
+pub struct NormalStats {
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+}
+
+// degrees, 180 shades everything smooth and 0 leaves every face flat
+pub fn recompute_normals(mesh: &mut IntermediateMesh, smooth_angle: f32) -> NormalStats {
+    let vertices_before = mesh.vertices.len();
+    let (vertex_ids, welded) = weld_vertices(mesh);
+    // twice the area long
+    let weighted: Vec<[f32; 3]> = mesh
+        .faces
+        .iter()
+        .map(|face| {
+            let [a, b, c] = face.map(|index| mesh.vertices[index as usize].pos);
+            cross(sub(b, a), sub(c, a))
+        })
+        .collect();
+    let unit: Vec<Option<[f32; 3]>> = weighted.iter().map(|&normal| normalized(normal)).collect();
+    let mut faces_at = vec![Vec::new(); welded.len()];
+    for (index, face) in mesh.faces.iter().enumerate() {
+        for &corner in face {
+            let id = vertex_ids[corner as usize];
+            if faces_at[id].last() != Some(&index) {
+                faces_at[id].push(index);
+            }
+        }
+    }
+    // a hair under, so faces exactly at the angle still smooth
+    let min_cos = smooth_angle.clamp(0.0, 180.0).to_radians().cos() - 1e-5;
+
+    // key -> the new vertex. skinned meshes keep every
+    // original apart, their copies can differ in weights
+    let skinned = mesh.skin.is_some();
+    let mut new_ids: HashMap<VertexKey, u32> = HashMap::new();
+    let mut sources: Vec<usize> = Vec::new();
+    let mut normals: Vec<[f32; 3]> = Vec::new();
+    let mut faces = Vec::with_capacity(mesh.faces.len());
+    for (index, face) in mesh.faces.iter().enumerate() {
+        let new_face = face.map(|corner| {
+            let vertex = &mesh.vertices[corner as usize];
+            let mut sum = [0.0; 3];
+            if let Some(own) = unit[index] {
+                for &other in &faces_at[vertex_ids[corner as usize]] {
+                    if unit[other].is_some_and(|normal| dot(normal, own) >= min_cos) {
+                        for axis in 0..3 {
+                            sum[axis] += weighted[other][axis];
+                        }
+                    }
+                }
+            }
+            // faces with no area keep what the vertex had
+            let normal = normalized(sum).unwrap_or(vertex.normal);
+            let key = (
+                vertex.pos.map(f32::to_bits),
+                vertex.uv.map(f32::to_bits),
+                vertex.color,
+                normal.map(f32::to_bits),
+                skinned.then_some(corner),
+            );
+            *new_ids.entry(key).or_insert_with(|| {
+                sources.push(corner as usize);
+                normals.push(normal);
+                (sources.len() - 1) as u32
+            })
+        });
+        faces.push(new_face);
+    }
+
+    mesh.vertices = sources
+        .iter()
+        .zip(&normals)
+        .map(|(&source, &normal)| {
+            let mut vertex = mesh.vertices[source];
+            vertex.normal = normal;
+            vertex
+        })
+        .collect();
+    mesh.faces = faces;
+    if let Some(skin) = &mut mesh.skin {
+        skin.joints = sources.iter().map(|&source| skin.joints.get(source).copied().unwrap_or_default()).collect();
+        skin.weights = sources
+            .iter()
+            .map(|&source| skin.weights.get(source).copied().unwrap_or([1.0, 0.0, 0.0, 0.0]))
+            .collect();
+    }
+    generate_tangents(mesh);
+    NormalStats {
+        vertices_before,
+        vertices_after: mesh.vertices.len(),
+    }
+}
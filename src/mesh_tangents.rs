@@ -0,0 +1,72 @@
+// per vertex tangents for normal mapped meshes
+//
+// surface appearance normal maps are read in tangent space, so a mesh written with the default
+// tangent every vertex gets lights its normal map from the wrong side. each face's tangent is
+// the direction u grows across it, from its corners' positions and uvs, summed onto its corners
+// weighted by the face's area and then made perpendicular to each vertex's normal, the same
+// basis mikktspace ends up at on meshes without mirrored uv islands sharing vertices. the sign
+// in w says which way the bitangent goes, it's cross(normal, tangent) * w pointing up the
+// texture, opengl style like roblox's normal maps. vertices split at uv seams (which obj and
+// gltf import already do) get their own tangents.
+use crate::mesh_types::{DEFAULT_TANGENT, IntermediateMesh};
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalized(v: [f32; 3]) -> Option<[f32; 3]> {
+    let length = dot(v, v).sqrt();
+    (length > 1e-12 && length.is_finite()).then(|| v.map(|c| c / length))
+}
+
+// any direction along the surface, for vertices whose faces have no usable uvs
+fn perpendicular(normal: [f32; 3]) -> [f32; 3] {
+    let other = if normal[1].abs() < 0.9 { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] };
+    normalized(cross(other, normal)).unwrap_or([1.0, 0.0, 0.0])
+}
+
+pub fn generate_tangents(mesh: &mut IntermediateMesh) {
+    let mut tangents = vec![[0.0f32; 3]; mesh.vertices.len()];
+    let mut bitangents = vec![[0.0f32; 3]; mesh.vertices.len()];
+    for face in &mesh.faces {
+        let [a, b, c] = face.map(|index| &mesh.vertices[index as usize]);
+        let (edge1, edge2) = (sub(b.pos, a.pos), sub(c.pos, a.pos));
+        // uvs are stored top left, the bitangent goes up the texture
+        let (du1, dv1) = (b.uv[0] - a.uv[0], a.uv[1] - b.uv[1]);
+        let (du2, dv2) = (c.uv[0] - a.uv[0], a.uv[1] - c.uv[1]);
+        let determinant = du1 * dv2 - du2 * dv1;
+        if determinant.abs() < 1e-12 {
+            continue;
+        }
+        // scaled by the uv area, so with it the sums are weighted by the face's area
+        let area = dot(cross(edge1, edge2), cross(edge1, edge2)).sqrt() / determinant.abs();
+        let scale = area / determinant;
+        let tangent = [0, 1, 2].map(|axis| (edge1[axis] * dv2 - edge2[axis] * dv1) * scale);
+        let bitangent = [0, 1, 2].map(|axis| (edge2[axis] * du1 - edge1[axis] * du2) * scale);
+        for index in face.map(|index| index as usize) {
+            for axis in 0..3 {
+                tangents[index][axis] += tangent[axis];
+                bitangents[index][axis] += bitangent[axis];
+            }
+        }
+    }
+
+    for ((vertex, tangent), bitangent) in mesh.vertices.iter_mut().zip(tangents).zip(bitangents) {
+        let Some(normal) = normalized(vertex.normal) else {
+            vertex.tangent = DEFAULT_TANGENT;
+            continue;
+        };
+        let along = dot(normal, tangent);
+        let tangent = normalized(sub(tangent, normal.map(|c| c * along))).unwrap_or_else(|| perpendicular(normal));
+        let sign = if dot(cross(normal, tangent), bitangent) < 0.0 { -1.0 } else { 1.0 };
+        vertex.tangent = [tangent[0], tangent[1], tangent[2], sign];
+    }
+}
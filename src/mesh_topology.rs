@@ -0,0 +1,256 @@
+// edges, holes and pieces of a mesh
+//
+// filemeshes split vertices wherever the uv or normal changes, so vertices are welded by exact
+// position first, otherwise every uv seam would look like a hole. an edge used by one face is on
+// a boundary, by more than two it's non-manifold. boundary edges chained together are the holes.
+// old clients draw meshes single sided and build collision from the hull, so holes show as
+// missing faces from behind and non-manifold bits are where collision goes wrong.
+use crate::mesh_types::IntermediateMesh;
+use std::collections::HashMap;
+use std::fmt::Write as FmtWrite;
+
+pub struct BoundaryLoop {
+    // welded vertex ids in order, following the winding of the faces next to it
+    pub vertices: Vec<usize>,
+    pub perimeter: f32,
+    // false for the open chains left around non-manifold vertices
+    pub closed: bool,
+}
+
+pub struct Topology {
+    // welded id -> one of the mesh's vertices at that position
+    pub welded: Vec<u32>,
+    pub edges: usize,
+    pub non_manifold_edges: usize,
+    pub boundary_edges: usize,
+    pub holes: Vec<BoundaryLoop>,
+    pub components: usize,
+}
+
+fn find_root(parent: &mut [usize], mut index: usize) -> usize {
+    while parent[index] != index {
+        parent[index] = parent[parent[index]];
+        index = parent[index];
+    }
+    index
+}
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn distance(a: [f32; 3], b: [f32; 3]) -> f32 {
+    dot(sub(a, b), sub(a, b)).sqrt()
+}
+
+// vertex index -> welded id, and welded id -> a vertex with that position
+pub fn weld_vertices(mesh: &IntermediateMesh) -> (Vec<usize>, Vec<u32>) {
+    let mut ids = HashMap::new();
+    let mut welded = Vec::new();
+    let vertex_ids = mesh
+        .vertices
+        .iter()
+        .enumerate()
+        .map(|(index, vertex)| {
+            *ids.entry(vertex.pos.map(f32::to_bits)).or_insert_with(|| {
+                welded.push(index as u32);
+                welded.len() - 1
+            })
+        })
+        .collect();
+    (vertex_ids, welded)
+}
+
+pub fn analyze(mesh: &IntermediateMesh) -> Topology {
+    let (vertex_ids, welded) = weld_vertices(mesh);
+    let faces: Vec<[usize; 3]> = mesh
+        .faces
+        .iter()
+        .filter(|face| face.iter().all(|&index| (index as usize) < vertex_ids.len()))
+        .map(|face| face.map(|index| vertex_ids[index as usize]))
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect();
+
+    // undirected edge -> faces using it, and every directed edge a face has
+    let mut edge_faces: HashMap<(usize, usize), usize> = HashMap::new();
+    let mut directed: HashMap<(usize, usize), usize> = HashMap::new();
+    for face in &faces {
+        for corner in 0..3 {
+            let (a, b) = (face[corner], face[(corner + 1) % 3]);
+            *edge_faces.entry((a.min(b), a.max(b))).or_default() += 1;
+            *directed.entry((a, b)).or_default() += 1;
+        }
+    }
+    let non_manifold_edges = edge_faces.values().filter(|&&count| count > 2).count();
+
+    // a boundary edge runs the way its one face winds, start vertex -> the ends
+    let mut outgoing: HashMap<usize, Vec<usize>> = HashMap::new();
+    let mut boundary_edges = 0;
+    for (&(a, b), _) in directed.iter().filter(|((a, b), _)| edge_faces[&(*a.min(b), *a.max(b))] == 1) {
+        outgoing.entry(a).or_default().push(b);
+        boundary_edges += 1;
+    }
+    let position = |id: usize| mesh.vertices[welded[id] as usize].pos;
+    let mut starts: Vec<usize> = outgoing.keys().copied().collect();
+    starts.sort_unstable();
+    let mut holes = Vec::new();
+    for start in starts {
+        while let Some(mut next) = outgoing.get_mut(&start).and_then(Vec::pop) {
+            let mut vertices = vec![start];
+            let mut perimeter = distance(position(start), position(next));
+            let mut closed = true;
+            while next != start {
+                vertices.push(next);
+                let Some(after) = outgoing.get_mut(&next).and_then(Vec::pop) else {
+                    closed = false;
+                    break;
+                };
+                perimeter += distance(position(next), position(after));
+                next = after;
+            }
+            // an open chain only happens around non-manifold vertices, it's still a hole
+            holes.push(BoundaryLoop { vertices, perimeter, closed });
+        }
+    }
+
+    let mut parent: Vec<usize> = (0..welded.len()).collect();
+    for face in &faces {
+        for &other in &face[1..] {
+            let (a, b) = (find_root(&mut parent, face[0]), find_root(&mut parent, other));
+            parent[a] = b;
+        }
+    }
+    let mut roots: Vec<usize> = faces.iter().map(|face| find_root(&mut parent, face[0])).collect();
+    roots.sort_unstable();
+    roots.dedup();
+
+    Topology {
+        welded,
+        edges: edge_faces.len(),
+        non_manifold_edges,
+        boundary_edges,
+        holes,
+        components: roots.len(),
+    }
+}
+
+// holes listed by perimeter before the rest are only counted
+const HOLES_SHOWN: usize = 10;
+
+impl Topology {
+    pub fn is_watertight(&self) -> bool {
+        self.boundary_edges == 0 && self.non_manifold_edges == 0
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "welded vertices: {}", self.welded.len());
+        let _ = writeln!(out, "edges: {}", self.edges);
+        let _ = writeln!(out, "non-manifold edges: {}", self.non_manifold_edges);
+        let _ = writeln!(out, "boundary edges: {}", self.boundary_edges);
+        let mut perimeters: Vec<f32> = self.holes.iter().map(|hole| hole.perimeter).collect();
+        perimeters.sort_by(f32::total_cmp);
+        let mut listed: Vec<String> = perimeters.iter().take(HOLES_SHOWN).map(|p| format!("{:.3}", p)).collect();
+        if perimeters.len() > HOLES_SHOWN {
+            listed.push(format!("and {} more", perimeters.len() - HOLES_SHOWN));
+        }
+        if listed.is_empty() {
+            let _ = writeln!(out, "holes: 0");
+        } else {
+            let _ = writeln!(out, "holes: {} (perimeters {})", self.holes.len(), listed.join(", "));
+        }
+        let _ = writeln!(out, "components: {}", self.components);
+        let _ = writeln!(out, "watertight: {}", if self.is_watertight() { "yes" } else { "no" });
+        out
+    }
+}
+
+pub struct HoleFill {
+    pub filled: usize,
+    pub faces_added: usize,
+    // holes with a perimeter over the limit, and open chains, which have no inside to fill
+    pub skipped: usize,
+}
+
+// ear clips a polygon given as positions, winding kept. corners are relative to the polygon.
+// the normal is newell's, which copes with the loop not being flat. when no ear is left (the
+// projection folds over itself) the rest is fanned, a bad cap beats a hole
+fn triangulate(points: &[[f32; 3]]) -> Vec<[usize; 3]> {
+    let mut normal = [0.0; 3];
+    for (index, &a) in points.iter().enumerate() {
+        let b = points[(index + 1) % points.len()];
+        normal[0] += (a[1] - b[1]) * (a[2] + b[2]);
+        normal[1] += (a[2] - b[2]) * (a[0] + b[0]);
+        normal[2] += (a[0] - b[0]) * (a[1] + b[1]);
+    }
+    let turns_left = |a: usize, b: usize, c: usize| {
+        dot(cross(sub(points[b], points[a]), sub(points[c], points[b])), normal) > 0.0
+    };
+    let inside = |p: usize, [a, b, c]: [usize; 3]| {
+        [(a, b), (b, c), (c, a)]
+            .iter()
+            .all(|&(from, to)| dot(cross(sub(points[to], points[from]), sub(points[p], points[from])), normal) >= 0.0)
+    };
+
+    let mut remaining: Vec<usize> = (0..points.len()).collect();
+    let mut triangles = Vec::new();
+    while remaining.len() > 3 {
+        let count = remaining.len();
+        let ear = (0..count).find(|&corner| {
+            let triangle = [
+                remaining[(corner + count - 1) % count],
+                remaining[corner],
+                remaining[(corner + 1) % count],
+            ];
+            turns_left(triangle[0], triangle[1], triangle[2])
+                && !remaining
+                    .iter()
+                    .any(|&other| !triangle.contains(&other) && inside(other, triangle))
+        });
+        let Some(corner) = ear else { break };
+        triangles.push([
+            remaining[(corner + count - 1) % count],
+            remaining[corner],
+            remaining[(corner + 1) % count],
+        ]);
+        remaining.remove(corner);
+    }
+    for corner in 1..remaining.len() - 1 {
+        triangles.push([remaining[0], remaining[corner], remaining[corner + 1]]);
+    }
+    triangles
+}
+
+// caps closed holes with a perimeter up to max_perimeter. the new faces reuse a vertex already at
+// each corner, so no vertices are added and the cap takes the normals and uvs of its edge
+pub fn fill_holes(mesh: &mut IntermediateMesh, max_perimeter: f32) -> HoleFill {
+    let topology = analyze(mesh);
+    let mut fill = HoleFill {
+        filled: 0,
+        faces_added: 0,
+        skipped: 0,
+    };
+    for hole in &topology.holes {
+        if !hole.closed || hole.perimeter > max_perimeter {
+            fill.skipped += 1;
+            continue;
+        }
+        // the loop runs the way the faces around it wind, the cap has to go the other way
+        let corners: Vec<u32> = hole.vertices.iter().rev().map(|&id| topology.welded[id]).collect();
+        let points: Vec<[f32; 3]> = corners.iter().map(|&index| mesh.vertices[index as usize].pos).collect();
+        for [a, b, c] in triangulate(&points) {
+            mesh.faces.push([corners[a], corners[b], corners[c]]);
+            fill.faces_added += 1;
+        }
+        fill.filled += 1;
+    }
+    fill
+}
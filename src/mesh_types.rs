@@ -1,16 +1,108 @@
 #![allow(non_snake_case)]
 #![allow(dead_code)]
 
+use serde_json::{Map, Value, json};
+use std::error::Error;
+
 #[derive(Debug, Clone, Copy)]
 pub struct IntermediateVertex {
     pub pos: [f32; 3],
     pub normal: [f32; 3],
     pub uv: [f32; 2],
+    // rgba, white for formats that don't carry it
+    pub color: [u8; 4],
+    // xyz along u, w the sign of the bitangent, see mesh_tangents.rs
+    pub tangent: [f32; 4],
 }
 
+pub const WHITE: [u8; 4] = [255, 255, 255, 255];
+// what roblox writes when it has nothing better, also what every vertex got before tangents
+pub const DEFAULT_TANGENT: [f32; 4] = [0.0, 0.0, -1.0, 1.0];
+
 pub struct IntermediateMesh {
     pub vertices: Vec<IntermediateVertex>,
     pub faces: Vec<[u32; 3]>,
+    // only v4+ meshes (and skinned gltf) have one
+    pub skin: Option<MeshSkin>,
+    // dynamic head poses, only written to v5
+    pub facs: Option<MeshFacs>,
+}
+
+impl IntermediateMesh {
+    // whether there's any color worth writing out, an all white mesh is the same as none
+    pub fn has_vertex_colors(&self) -> bool {
+        self.vertices.iter().any(|vertex| vertex.color != WHITE)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct MeshBone {
+    pub name: String,
+    pub parent: Option<usize>,
+    pub lod_parent: u16,
+    pub culling: f32,
+    // mesh space, the rotation is row major like a CFrame
+    pub rotation: [f32; 9],
+    pub position: [f32; 3],
+}
+
+#[derive(Debug, Clone)]
+pub struct MeshSkin {
+    pub bones: Vec<MeshBone>,
+    // one entry per vertex, joints index into bones (not subset local like in the file) and
+    // the weights add up to 1
+    pub joints: Vec<[u16; 4]>,
+    pub weights: Vec<[f32; 4]>,
+}
+
+// each control (JawDrop, EyesLookLeft, ...) moves the face bones by some amount. the transforms
+// are bone x control matrices, row major, position in studs and rotation in degrees in the
+// bone's own space
+#[derive(Debug, Clone, Default)]
+pub struct MeshFacs {
+    pub bone_names: Vec<String>,
+    // base controls first, then correctives
+    pub control_names: Vec<String>,
+    // control indices combined by each two/three pose corrective, which are also controls
+    pub two_pose_correctives: Vec<[u16; 2]>,
+    pub three_pose_correctives: Vec<[u16; 3]>,
+    // px, py, pz, rx, ry, rz
+    pub transforms: [Vec<f32>; 6],
+}
+
+impl MeshFacs {
+    // for looking at what a dynamic head does: every control with the bones it moves, zeros
+    // left out, and the correctives by control name
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        let cols = self.control_names.len();
+        let name = |index: u16| self.control_names.get(index as usize).cloned().unwrap_or_else(|| index.to_string());
+        let controls: Vec<Value> = self
+            .control_names
+            .iter()
+            .enumerate()
+            .map(|(control, control_name)| {
+                let mut bones = Map::new();
+                for (bone, bone_name) in self.bone_names.iter().enumerate() {
+                    let [px, py, pz, rx, ry, rz] = std::array::from_fn(|channel| {
+                        self.transforms[channel].get(bone * cols + control).copied().unwrap_or_default()
+                    });
+                    if [px, py, pz, rx, ry, rz].iter().all(|&value| value == 0.0) {
+                        continue;
+                    }
+                    bones.insert(bone_name.clone(), json!({ "position": [px, py, pz], "rotation": [rx, ry, rz] }));
+                }
+                json!({ "name": control_name, "bones": bones })
+            })
+            .collect();
+        let two_pose: Vec<Value> = self.two_pose_correctives.iter().map(|pair| json!(pair.map(name))).collect();
+        let three_pose: Vec<Value> = self.three_pose_correctives.iter().map(|triple| json!(triple.map(name))).collect();
+        Ok(serde_json::to_string_pretty(&json!({
+            "bones": self.bone_names,
+            "controls": controls,
+            "two_pose_correctives": two_pose,
+            "three_pose_correctives": three_pose,
+        }))?)
+    }
 }
 
 #[repr(C, packed)]
@@ -23,6 +115,12 @@ pub struct FileMeshVertex {
     pub r: u8,   pub g: u8,   pub b: u8,  pub a: u8,
 }
 
+// the tangent fields of a vertex, rounded to signed bytes
+pub fn file_tangent(tangent: [f32; 4]) -> FileMeshVertex {
+    let [tx, ty, tz, ts] = tangent.map(|value| (value * 127.0).round().clamp(-127.0, 127.0) as i8);
+    FileMeshVertex { tx, ty, tz, ts, ..Default::default() }
+}
+
 impl Default for FileMeshVertex {
     fn default() -> Self {
         Self {
@@ -72,6 +170,37 @@ pub struct FileMeshHeaderV3 {
     pub numFaces: u32,
 }
 
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy)]
+pub struct FileMeshEnvelope {
+    pub bones: [u8; 4],
+    pub weights: [u8; 4],
+}
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy)]
+pub struct FileMeshBone {
+    pub boneNameIndex: u32,
+    pub parentIndex: u16,
+    pub lodParentIndex: u16,
+    pub culling: f32,
+    pub rotation: [f32; 9],
+    pub position: [f32; 3],
+}
+
+pub const MAX_SUBSET_BONES: usize = 26;
+
+#[repr(C, packed)]
+#[derive(Debug, Clone, Copy)]
+pub struct FileMeshSubset {
+    pub facesBegin: u32,
+    pub facesLength: u32,
+    pub vertsBegin: u32,
+    pub vertsLength: u32,
+    pub numBoneIndices: u32,
+    pub boneIndices: [u16; MAX_SUBSET_BONES],
+}
+
 #[repr(C, packed)]
 pub struct FileMeshHeaderV4 {
     pub sizeof_FileMeshHeaderV4: u16,
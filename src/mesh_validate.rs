@@ -0,0 +1,126 @@
+// whether a converted mesh would get through an upload, checked before it's written
+//
+// the current rules are the ones the mesh importer applies today: at most 20000 triangles, no
+// side past 2048 studs, every number finite and uvs in 0-1. an old client takes less, roughly:
+//
+//   before 2016   5000 triangles, parts up to 512 studs
+//   2016-2020     10000 triangles, parts up to 2048 studs
+//
+// and only the mesh versions it reads, see TargetClient::mesh_version. the dates are as rough as
+// MESH_VERSION_YEARS, they're for catching a mesh that's way over rather than one that's close.
+// every problem comes back with the first vertex or number it's about, so it can be found.
+use crate::mesh_types::{IntermediateMesh, IntermediateVertex};
+use crate::{RobloxMeshVersion, TargetClient};
+use clap::ValueEnum;
+
+const MAX_TRIANGLES: usize = 20_000;
+const MAX_SIZE: f32 = 2048.0;
+// how far past 0-1 a uv can be before it counts, exporters leave 1.0000001 and the like
+const UV_TOLERANCE: f32 = 1e-4;
+
+type Field = fn(&IntermediateVertex) -> &[f32];
+
+struct Limits {
+    triangles: usize,
+    size: f32,
+}
+
+fn legacy_limits(client: TargetClient) -> Limits {
+    match client.year() {
+        ..2016 => Limits { triangles: 5_000, size: 512.0 },
+        _ => Limits { triangles: 10_000, size: 2048.0 },
+    }
+}
+
+fn version_name(version: RobloxMeshVersion) -> String {
+    version.to_possible_value().map_or_else(String::new, |value| value.get_name().to_owned())
+}
+
+fn check_limits(mesh: &IntermediateMesh, limits: &Limits, who: &str, problems: &mut Vec<String>) {
+    if mesh.faces.len() > limits.triangles {
+        problems.push(format!("{} triangles, {} take at most {}", mesh.faces.len(), who, limits.triangles));
+    }
+    let Some((min, max)) = bounds(mesh) else { return };
+    for (axis, name) in ["x", "y", "z"].iter().enumerate() {
+        let size = max[axis] - min[axis];
+        if size > limits.size {
+            problems.push(format!("{} studs along {}, {} take at most {}", size, name, who, limits.size));
+        }
+    }
+}
+
+// of the finite positions, non-finite ones are reported on their own
+fn bounds(mesh: &IntermediateMesh) -> Option<([f32; 3], [f32; 3])> {
+    mesh.vertices
+        .iter()
+        .filter(|vertex| vertex.pos.iter().all(|c| c.is_finite()))
+        .fold(None, |bounds: Option<([f32; 3], [f32; 3])>, vertex| {
+            let (mut min, mut max) = bounds.unwrap_or((vertex.pos, vertex.pos));
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex.pos[axis]);
+                max[axis] = max[axis].max(vertex.pos[axis]);
+            }
+            Some((min, max))
+        })
+}
+
+// how many vertices fail a test, and the first of them
+fn count_vertices(mesh: &IntermediateMesh, test: impl Fn(usize) -> bool) -> Option<(usize, usize)> {
+    let mut failing = (0..mesh.vertices.len()).filter(|&index| test(index));
+    let first = failing.next()?;
+    Some((failing.count() + 1, first))
+}
+
+// every reason the mesh wouldn't upload, none when it would. version is what it's written as,
+// client an old client it's also meant for
+pub fn validate_upload(
+    mesh: &IntermediateMesh,
+    version: Option<RobloxMeshVersion>,
+    client: Option<TargetClient>,
+) -> Vec<String> {
+    let mut problems = Vec::new();
+    if mesh.faces.is_empty() {
+        problems.push("the mesh has no triangles".to_owned());
+    }
+    let vertices = &mesh.vertices;
+    let fields: [(&str, Field); 3] = [
+        ("position", |vertex| &vertex.pos),
+        ("normal", |vertex| &vertex.normal),
+        ("uv", |vertex| &vertex.uv),
+    ];
+    for (what, field) in fields {
+        if let Some((count, first)) = count_vertices(mesh, |i| field(&vertices[i]).iter().any(|c| !c.is_finite())) {
+            problems.push(format!(
+                "{} vertices have a non-finite {}, the first is vertex {} with {:?}",
+                count,
+                what,
+                first,
+                field(&vertices[first])
+            ));
+        }
+    }
+    let outside = |c: f32| c.is_finite() && !(-UV_TOLERANCE..=1.0 + UV_TOLERANCE).contains(&c);
+    if let Some((count, first)) = count_vertices(mesh, |i| vertices[i].uv.iter().any(|&c| outside(c))) {
+        let [u, v] = vertices[first].uv;
+        problems.push(format!(
+            "{} vertices have uvs outside 0-1, the first is vertex {} at ({}, {})",
+            count, first, u, v
+        ));
+    }
+    check_limits(mesh, &Limits { triangles: MAX_TRIANGLES, size: MAX_SIZE }, "uploads", &mut problems);
+
+    if let Some(client) = client {
+        let who = format!("{} clients", client.year());
+        check_limits(mesh, &legacy_limits(client), &who, &mut problems);
+        let newest = client.mesh_version();
+        if let Some(version) = version.filter(|&version| version as u8 > newest as u8) {
+            problems.push(format!(
+                "it's written as {}, {} read {} at newest",
+                version_name(version),
+                who,
+                version_name(newest)
+            ));
+        }
+    }
+    problems
+}
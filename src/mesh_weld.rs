@@ -0,0 +1,109 @@
+// vertices at the same spot merged into one, for meshes split apart more than they need to be
+//
+// obj import makes a vertex per index per object, so seams between objects and files exported
+// with every face unwelded come out with copies nothing tells apart. two vertices are merged when
+// they're within epsilon of each other and their uvs, normals, colors (and bone weights on a
+// skinned mesh) match closely enough that the shading can't change. the first of them is kept as
+// it is. positions go in a grid of epsilon sized cells, so only the cells around a vertex get
+// searched. faces that end up with two corners on one vertex are dropped.
+use crate::mesh_types::{IntermediateMesh, IntermediateVertex};
+use std::collections::HashMap;
+
+// normals at most about 2.5 degrees apart
+const MIN_NORMAL_DOT: f32 = 0.999;
+const UV_EPSILON: f32 = 1e-4;
+const WEIGHT_EPSILON: f32 = 1e-3;
+
+pub struct WeldStats {
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+    pub faces_dropped: usize,
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn distance_squared(a: [f32; 3], b: [f32; 3]) -> f32 {
+    let d = [a[0] - b[0], a[1] - b[1], a[2] - b[2]];
+    dot(d, d)
+}
+
+fn cell_of(pos: [f32; 3], epsilon: f32) -> [i64; 3] {
+    if epsilon <= 0.0 {
+        return pos.map(|c| c.to_bits() as i64);
+    }
+    pos.map(|c| (c / epsilon).floor() as i64)
+}
+
+fn compatible(a: &IntermediateVertex, b: &IntermediateVertex, epsilon: f32) -> bool {
+    distance_squared(a.pos, b.pos) <= epsilon * epsilon
+        && (a.uv[0] - b.uv[0]).abs() <= UV_EPSILON
+        && (a.uv[1] - b.uv[1]).abs() <= UV_EPSILON
+        && dot(a.normal, b.normal) >= MIN_NORMAL_DOT
+        && a.color == b.color
+}
+
+// epsilon in studs, 0 merges exact copies only
+pub fn weld(mesh: &mut IntermediateMesh, epsilon: f32) -> WeldStats {
+    let vertices_before = mesh.vertices.len();
+    let faces_before = mesh.faces.len();
+    let mut cells: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+    let mut kept: Vec<usize> = Vec::new();
+    let mut remap = Vec::with_capacity(mesh.vertices.len());
+    for (index, vertex) in mesh.vertices.iter().enumerate() {
+        let cell = cell_of(vertex.pos, epsilon);
+        let same_skin = |other: usize| {
+            mesh.skin.as_ref().is_none_or(|skin| {
+                skin.joints.get(index) == skin.joints.get(other)
+                    && match (skin.weights.get(index), skin.weights.get(other)) {
+                        (Some(a), Some(b)) => a.iter().zip(b).all(|(a, b)| (a - b).abs() <= WEIGHT_EPSILON),
+                        (a, b) => a.is_none() && b.is_none(),
+                    }
+            })
+        };
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let near = [cell[0].wrapping_add(dx), cell[1].wrapping_add(dy), cell[2].wrapping_add(dz)];
+                    let Some(candidates) = cells.get(&near) else { continue };
+                    for &candidate in candidates {
+                        let source = kept[candidate as usize];
+                        if compatible(vertex, &mesh.vertices[source], epsilon) && same_skin(source) {
+                            found = Some(candidate);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+        let id = found.unwrap_or_else(|| {
+            kept.push(index);
+            let id = (kept.len() - 1) as u32;
+            cells.entry(cell).or_default().push(id);
+            id
+        });
+        remap.push(id);
+    }
+
+    mesh.faces = mesh
+        .faces
+        .iter()
+        .map(|face| face.map(|index| remap[index as usize]))
+        .filter(|[a, b, c]| a != b && b != c && a != c)
+        .collect();
+    mesh.vertices = kept.iter().map(|&source| mesh.vertices[source]).collect();
+    if let Some(skin) = &mut mesh.skin {
+        skin.joints = kept.iter().map(|&source| skin.joints.get(source).copied().unwrap_or_default()).collect();
+        skin.weights = kept
+            .iter()
+            .map(|&source| skin.weights.get(source).copied().unwrap_or([1.0, 0.0, 0.0, 0.0]))
+            .collect();
+    }
+    WeldStats {
+        vertices_before,
+        vertices_after: mesh.vertices.len(),
+        faces_dropped: faces_before - mesh.faces.len(),
+    }
+}
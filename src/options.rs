@@ -0,0 +1,519 @@
+// everything fix_place can do, set by name instead of a long list of positional bools
+//
+//   let options = FixPlaceOptions::new()
+//       .preset(Preset::Client2013)
+//       .output_format(OutputFormat::Binary)
+//       .folders_to_models(true);
+//   let out = fix_place(&data, options)?;
+use crate::asset_urls::{AssetUrlConfig, AssetUrlFormats};
+use crate::baseplate::Baseplate;
+use crate::binary_compat::BinaryCompat;
+use crate::dom_limits::DomLimits;
+use crate::asset_source::AssetSource;
+use crate::mappings::InstanceMappings;
+use crate::package_links::PackageVersion;
+use crate::pass_scope::PassScopes;
+use crate::passes;
+use crate::policy::FailRule;
+use crate::pipeline::{AggregatingLogObserver, ConversionObserver, PlacePass, Pipeline};
+use crate::presets::Preset;
+use crate::tags::TagConversion;
+use crate::thumbnail::ThumbnailCamera;
+use crate::unique_ids::UniqueIdHandling;
+use crate::world_space::RebaseOrigin;
+use crate::xml_compat::XmlCompat;
+use chrono::NaiveDate;
+use rbx_dom_weak::Ustr;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+pub const DEFAULT_ASSET_URL_FORMAT: &str = "http://www.roblox.com/asset/?id=";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputFormat {
+    // whatever the input was
+    #[default]
+    SameAsInput,
+    Xml,
+    Binary,
+}
+
+pub struct FixPlaceOptions {
+    pub(crate) output_format: OutputFormat,
+    pub(crate) folders_to_models: bool,
+    convert_meshparts: bool,
+    legacy_size_grid: bool,
+    shape_fallbacks: Option<u32>,
+    texture_tiling: Option<u32>,
+    spawn_fixups: Option<u32>,
+    replication_flags: Option<u32>,
+    tool_fixups: Option<u32>,
+    gear_dir: Option<PathBuf>,
+    resolve_packages: bool,
+    package_source: Option<AssetSource>,
+    package_version: PackageVersion,
+    inline_inserted_assets: Option<AssetSource>,
+    inject_leaderstats: bool,
+    unique_ids: Option<UniqueIdHandling>,
+    ensure_baseplate: Option<Baseplate>,
+    anchor_floating: Option<f32>,
+    rebase_origin: Option<RebaseOrigin>,
+    fail_if: Vec<FailRule>,
+    convert_assetid_to_url: bool,
+    asset_url_format: String,
+    asset_url_config: AssetUrlConfig,
+    asset_aliases: BTreeMap<u64, u64>,
+    mappings: InstanceMappings,
+    strip_classes: Vec<Ustr>,
+    pub(crate) preset: Option<Preset>,
+    pub(crate) conversion_log: bool,
+    tag_conversion: Option<TagConversion>,
+    asset_cutoff_date: Option<NaiveDate>,
+    pub(crate) xml_compat: Option<XmlCompat>,
+    pub(crate) binary_compat: Option<BinaryCompat>,
+    pub(crate) expand_shared_strings: bool,
+    pub(crate) verify: bool,
+    pub(crate) cache_dir: Option<PathBuf>,
+    pub(crate) parse_threads: usize,
+    pub(crate) dom_limits: DomLimits,
+    pub(crate) mem_stats: bool,
+    dedup_shared_strings: Option<usize>,
+    stable_output: bool,
+    thumbnail_camera: Option<ThumbnailCamera>,
+    pass_scopes: PassScopes,
+    extra_passes: Pipeline,
+    pub(crate) observer: Box<dyn ConversionObserver + Send>,
+}
+
+impl Default for FixPlaceOptions {
+    fn default() -> Self {
+        Self {
+            output_format: OutputFormat::default(),
+            folders_to_models: false,
+            convert_meshparts: false,
+            legacy_size_grid: false,
+            shape_fallbacks: None,
+            texture_tiling: None,
+            spawn_fixups: None,
+            replication_flags: None,
+            tool_fixups: None,
+            gear_dir: None,
+            resolve_packages: false,
+            package_source: None,
+            package_version: PackageVersion::Latest,
+            inline_inserted_assets: None,
+            inject_leaderstats: false,
+            unique_ids: None,
+            ensure_baseplate: None,
+            anchor_floating: None,
+            rebase_origin: None,
+            fail_if: Vec::new(),
+            convert_assetid_to_url: false,
+            asset_url_format: DEFAULT_ASSET_URL_FORMAT.to_owned(),
+            asset_url_config: AssetUrlConfig::default(),
+            asset_aliases: BTreeMap::new(),
+            mappings: InstanceMappings::default(),
+            strip_classes: Vec::new(),
+            preset: None,
+            conversion_log: false,
+            tag_conversion: None,
+            asset_cutoff_date: None,
+            xml_compat: None,
+            binary_compat: None,
+            expand_shared_strings: false,
+            verify: false,
+            cache_dir: None,
+            parse_threads: 1,
+            dom_limits: DomLimits::default(),
+            mem_stats: false,
+            dedup_shared_strings: None,
+            stable_output: false,
+            thumbnail_camera: None,
+            pass_scopes: PassScopes::new(),
+            extra_passes: Pipeline::new(),
+            observer: Box::new(AggregatingLogObserver::default()),
+        }
+    }
+}
+
+impl FixPlaceOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn output_format(mut self, format: OutputFormat) -> Self {
+        self.output_format = format;
+        self
+    }
+
+    pub fn folders_to_models(mut self, enabled: bool) -> Self {
+        self.folders_to_models = enabled;
+        self
+    }
+
+    pub fn convert_meshparts(mut self, enabled: bool) -> Self {
+        self.convert_meshparts = enabled;
+        self
+    }
+
+    // snaps parts to the 1.2/0.4 brick grid and sets their FormFactor to match
+    pub fn legacy_size_grid(mut self, enabled: bool) -> Self {
+        self.legacy_size_grid = enabled;
+        self
+    }
+
+    // year of the client the place is going to, shapes it doesn't have get rebuilt from older ones
+    pub fn shape_fallbacks(mut self, year: Option<u32>) -> Self {
+        self.shape_fallbacks = year;
+        self
+    }
+
+    // year of the target client, texture tiling it can't show gets dropped or turned into decals
+    pub fn texture_tiling(mut self, year: Option<u32>) -> Self {
+        self.texture_tiling = year;
+        self
+    }
+
+    // year of the target client, spawns get set up the way it picks them and teams without one
+    // get a pad
+    pub fn spawn_fixups(mut self, year: Option<u32>) -> Self {
+        self.spawn_fixups = year;
+        self
+    }
+
+    // year of the target client, FilteringEnabled and streaming get set the way it expects
+    pub fn replication_flags(mut self, year: Option<u32>) -> Self {
+        self.replication_flags = year;
+        self
+    }
+
+    // year of the target client, tools get turned into what it supports
+    pub fn tool_fixups(mut self, year: Option<u32>) -> Self {
+        self.tool_fixups = year;
+        self
+    }
+
+    // <id>.rbxm/.rbxmx files for gear scripts load by id, only used with tool_fixups
+    pub fn gear_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.gear_dir = dir;
+        self
+    }
+
+    // removes PackageLinks so packages stay as the plain models saved in the place
+    pub fn resolve_packages(mut self, enabled: bool) -> Self {
+        self.resolve_packages = enabled;
+        self
+    }
+
+    // where packages are fetched from to be expanded before unlinking, see package_links. only
+    // used with resolve_packages
+    pub fn package_source(mut self, source: Option<AssetSource>) -> Self {
+        self.package_source = source;
+        self
+    }
+
+    // the latest version of each package, or the one its link was saved with
+    pub fn package_version(mut self, version: PackageVersion) -> Self {
+        self.package_version = version;
+        self
+    }
+
+    // where models loaded with InsertService:LoadAsset(id) come from, see inserted_assets
+    pub fn inline_inserted_assets(mut self, source: Option<AssetSource>) -> Self {
+        self.inline_inserted_assets = source;
+        self
+    }
+
+    // adds a classic leaderboard script making the stats the game's own scripts did
+    pub fn inject_leaderstats(mut self, enabled: bool) -> Self {
+        self.inject_leaderstats = enabled;
+        self
+    }
+
+    // strip UniqueId/HistoryId for old targets, or give merged in duplicates new ones
+    pub fn unique_ids(mut self, handling: Option<UniqueIdHandling>) -> Self {
+        self.unique_ids = handling;
+        self
+    }
+
+    // a baseplate under the spawns when the place has no floor there, see baseplate
+    pub fn ensure_baseplate(mut self, baseplate: Option<Baseplate>) -> Self {
+        self.ensure_baseplate = baseplate;
+        self
+    }
+
+    // anchors unanchored parts with nothing this many studs under them, see floating
+    pub fn anchor_floating(mut self, within: Option<f32>) -> Self {
+        self.anchor_floating = within;
+        self
+    }
+
+    // moves the map so this point, or its middle, is at 0,0,0, see world_space
+    pub fn rebase_origin(mut self, origin: Option<RebaseOrigin>) -> Self {
+        self.rebase_origin = origin;
+        self
+    }
+
+    // rules the finished place has to pass, the conversion fails otherwise
+    pub fn fail_if(mut self, rules: Vec<FailRule>) -> Self {
+        self.fail_if = rules;
+        self
+    }
+
+    pub fn convert_assetid_to_url(mut self, enabled: bool) -> Self {
+        self.convert_assetid_to_url = enabled;
+        self
+    }
+
+    // a prefix for the id, or a template with {id}, {type} and {hash}
+    pub fn asset_url_format(mut self, format: impl Into<String>) -> Self {
+        self.asset_url_format = format.into();
+        self
+    }
+
+    // per asset type formats, see asset_urls
+    pub fn asset_url_config(mut self, config: AssetUrlConfig) -> Self {
+        self.asset_url_config = config;
+        self
+    }
+
+    // duplicate asset id -> the one to use instead, what mesh-dedup writes
+    pub fn asset_aliases(mut self, aliases: BTreeMap<u64, u64>) -> Self {
+        self.asset_aliases = aliases;
+        self
+    }
+
+    // replaces the mappings and strip list with the preset's
+    pub fn preset(mut self, preset: Preset) -> Self {
+        let data = preset.load();
+        self.mappings = data.mappings;
+        self.strip_classes = data.strip.iter().map(|class| Ustr::from(class.as_str())).collect();
+        self.preset = Some(preset);
+        self
+    }
+
+    pub fn mappings(mut self, mappings: InstanceMappings) -> Self {
+        self.mappings = mappings;
+        self
+    }
+
+    // rules from here win over the ones already set (e.g. from a preset) per class
+    pub fn extend_mappings(mut self, mappings: InstanceMappings) -> Self {
+        self.mappings.extend(mappings);
+        self
+    }
+
+    pub fn strip_classes(mut self, classes: Vec<Ustr>) -> Self {
+        self.strip_classes = classes;
+        self
+    }
+
+    pub fn tag_conversion(mut self, mode: Option<TagConversion>) -> Self {
+        self.tag_conversion = mode;
+        self
+    }
+
+    pub fn asset_cutoff_date(mut self, cutoff: Option<NaiveDate>) -> Self {
+        self.asset_cutoff_date = cutoff;
+        self
+    }
+
+    pub fn xml_compat(mut self, era: Option<XmlCompat>) -> Self {
+        self.xml_compat = era;
+        self
+    }
+
+    // binary output only, fails the conversion if the place needs a newer binary format
+    pub fn binary_compat(mut self, era: Option<BinaryCompat>) -> Self {
+        self.binary_compat = era;
+        self
+    }
+
+    // also makes binary output write shared strings inline
+    pub fn expand_shared_strings(mut self, enabled: bool) -> Self {
+        self.expand_shared_strings = enabled;
+        self
+    }
+
+    // read the output back after writing it and fail if instances or key properties got lost
+    pub fn verify(mut self, enabled: bool) -> Self {
+        self.verify = enabled;
+        self
+    }
+
+    // parsed inputs are cached here by file hash, see dom_cache
+    pub fn cache_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.cache_dir = dir;
+        self
+    }
+
+    // more than one decompresses the chunks of binary input in parallel
+    pub fn parse_threads(mut self, threads: usize) -> Self {
+        self.parse_threads = threads;
+        self
+    }
+
+    // how deep and big the input can be before it's refused, see dom_limits
+    pub fn dom_limits(mut self, limits: DomLimits) -> Self {
+        self.dom_limits = limits;
+        self
+    }
+
+    // count allocations per stage into RunReport::mem_stats, needs mem_stats::CountingAllocator
+    // installed as the global allocator to count anything past rss and dom size
+    pub fn mem_stats(mut self, enabled: bool) -> Self {
+        self.mem_stats = enabled;
+        self
+    }
+
+    pub fn dedup_shared_strings(mut self, min_bytes: Option<usize>) -> Self {
+        self.dedup_shared_strings = min_bytes;
+        self
+    }
+
+    // a _ConversionLog in the workspace saying how the place was made, see conversion_log
+    pub fn conversion_log(mut self, enabled: bool) -> Self {
+        self.conversion_log = enabled;
+        self
+    }
+
+    pub fn stable_output(mut self, enabled: bool) -> Self {
+        self.stable_output = enabled;
+        self
+    }
+
+    pub fn thumbnail_camera(mut self, placement: Option<ThumbnailCamera>) -> Self {
+        self.thumbnail_camera = placement;
+        self
+    }
+
+    // pass name -> the instances it may touch, see pass_scope
+    pub fn pass_scopes(mut self, scopes: PassScopes) -> Self {
+        self.pass_scopes = scopes;
+        self
+    }
+
+    // custom passes run after the built in conversions, before shared strings and sorting
+    pub fn with_pass(mut self, pass: impl PlacePass + 'static) -> Self {
+        self.extra_passes.push(pass);
+        self
+    }
+
+    // gets told about every conversion and warning instead of them being printed
+    pub fn observer(mut self, observer: impl ConversionObserver + Send + 'static) -> Self {
+        self.observer = Box::new(observer);
+        self
+    }
+
+    // the passes these options turn into, in the order fix_place runs them. moves the mappings
+    // and custom passes out, the output settings stay put
+    pub(crate) fn take_pipeline(&mut self) -> Pipeline {
+        let mut pipeline = Pipeline::new();
+        // first, so the expanded and inlined models get every other conversion too
+        if self.resolve_packages {
+            pipeline.push(passes::ResolvePackages {
+                source: self.package_source.take(),
+                version: self.package_version,
+            });
+        }
+        if let Some(source) = self.inline_inserted_assets.take() {
+            pipeline.push(passes::InlineInsertedAssets { source });
+        }
+        if let Some(cutoff) = self.asset_cutoff_date {
+            pipeline.push(passes::LateAssetReport { cutoff });
+        }
+        pipeline.push(passes::StripClasses {
+            classes: std::mem::take(&mut self.strip_classes),
+        });
+        if let Some(mode) = self.tag_conversion {
+            pipeline.push(passes::ConvertTags { mode });
+        }
+        pipeline.push(passes::ApplyMappings {
+            mappings: std::mem::take(&mut self.mappings),
+        });
+        if self.convert_meshparts {
+            pipeline.push(passes::MeshPartsToSpecialMeshes {
+                year: self.preset.map(Preset::year),
+            });
+        }
+        if self.folders_to_models {
+            pipeline.push(passes::FoldersToModels);
+        }
+        if let Some(year) = self.shape_fallbacks {
+            pipeline.push(passes::PartShapeFallbacks { year });
+        }
+        if self.legacy_size_grid {
+            pipeline.push(passes::LegacySizeGrid);
+        }
+        pipeline.push(passes::LegacyClassFixups);
+        pipeline.push(passes::TextSizeToFontSize);
+        if let Some(year) = self.texture_tiling {
+            pipeline.push(passes::TextureTiling { year });
+        }
+        if let Some(year) = self.spawn_fixups {
+            pipeline.push(passes::SpawnFixups { year });
+        }
+        if let Some(year) = self.replication_flags {
+            pipeline.push(passes::ReplicationFlags { year });
+        }
+        if let Some(year) = self.tool_fixups {
+            pipeline.push(passes::ToolFixups {
+                year,
+                gear_dir: self.gear_dir.take(),
+            });
+        }
+        if self.inject_leaderstats {
+            pipeline.push(passes::InjectLeaderstats);
+        }
+        // after spawns are fixed up and strip-classes took out what isn't a floor anymore
+        if let Some(baseplate) = self.ensure_baseplate {
+            pipeline.push(passes::EnsureBaseplate { baseplate });
+        }
+        // with the baseplate in, it's something to stand on
+        if let Some(within) = self.anchor_floating {
+            pipeline.push(passes::AnchorFloating { within });
+        }
+        // after everything that adds parts, before the thumbnail camera is placed
+        if let Some(origin) = self.rebase_origin {
+            pipeline.push(passes::Rebase { origin });
+        }
+        // after everything that merges models in
+        if let Some(handling) = self.unique_ids {
+            pipeline.push(passes::UniqueIds { handling });
+        }
+        // before the urls are made, so they're made from the kept ids
+        if !self.asset_aliases.is_empty() {
+            pipeline.push(passes::AliasAssetIds {
+                aliases: std::mem::take(&mut self.asset_aliases),
+            });
+        }
+        if self.convert_assetid_to_url {
+            pipeline.push(passes::AssetIdsToUrls {
+                formats: AssetUrlFormats::new(
+                    self.asset_url_format.clone(),
+                    std::mem::take(&mut self.asset_url_config),
+                ),
+            });
+        }
+        if let Some(placement) = self.thumbnail_camera {
+            pipeline.push(passes::SetThumbnailCamera { placement });
+        }
+        pipeline.extend(std::mem::take(&mut self.extra_passes));
+        if self.expand_shared_strings {
+            pipeline.push(passes::ExpandSharedStrings);
+        }
+        if let Some(min_bytes) = self.dedup_shared_strings {
+            pipeline.push(passes::DedupSharedStrings { min_bytes });
+        }
+        if self.stable_output {
+            pipeline.push(passes::SortChildren);
+        }
+        if !self.fail_if.is_empty() {
+            pipeline.push(passes::FailIf {
+                rules: std::mem::take(&mut self.fail_if),
+            });
+        }
+        pipeline.set_scopes(std::mem::take(&mut self.pass_scopes));
+        pipeline
+    }
+}
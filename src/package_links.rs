@@ -0,0 +1,167 @@
+// packages, models kept in sync with a published asset through a PackageLink child
+//
+// the link's PackageId is an asset older clients and offline servers can't ask about, so links
+// go and the package root stays as a plain model with the copy that was saved in the place. with
+// a source the root's contents are swapped for the published version first, the latest or the
+// one the link is pinned to, and moved so its pivot is where the saved copy's was. the saved copy
+// stays when the package can't be had. packages inside packages get expanded too, up to
+// MAX_NESTING deep so a package containing itself can't go on forever.
+use crate::asset_source::AssetSource;
+use crate::content_uri::ContentUri;
+use crate::pipeline::PassContext;
+use crate::world_space;
+use clap::ValueEnum;
+use rbx_dom_weak::WeakDom;
+use rbx_dom_weak::types::{CFrame, Ref, Vector3};
+use rbx_types::Variant;
+use std::collections::BTreeMap;
+
+const LINK_CLASS: &str = "PackageLink";
+const MODEL_EXTENSIONS: [&str; 2] = ["rbxm", "rbxmx"];
+const MAX_NESTING: usize = 8;
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PackageVersion {
+    #[default]
+    Latest,
+    // the version the link was saved with, the latest when it doesn't have one
+    Pinned,
+}
+
+struct Link {
+    root: Ref,
+    id: Option<u64>,
+    version: Option<u64>,
+}
+
+fn read_link(dom: &WeakDom, referent: Ref) -> Option<Link> {
+    let instance = dom.get_by_ref(referent)?;
+    let id = instance
+        .properties
+        .get(&"PackageId".into())
+        .and_then(ContentUri::from_variant)
+        .and_then(|uri| uri.asset_id());
+    let version = ["VersionIdSerialize", "VersionNumber"]
+        .iter()
+        .find_map(|name| match instance.properties.get(&(*name).into()) {
+            Some(Variant::Int64(version)) if *version > 0 => Some(*version as u64),
+            _ => None,
+        });
+    Some(Link {
+        root: instance.parent(),
+        id,
+        version,
+    })
+}
+
+// where a model or part is, for lining up the published version with the saved one
+fn pivot(dom: &WeakDom, referent: Ref) -> Option<CFrame> {
+    let instance = dom.get_by_ref(referent)?;
+    match instance.properties.get(&"WorldPivotData".into()) {
+        Some(Variant::OptionalCFrame(pivot)) => *pivot,
+        _ => match instance.properties.get(&"CFrame".into()) {
+            Some(Variant::CFrame(cframe)) => Some(*cframe),
+            _ => None,
+        },
+    }
+}
+
+// swaps the root's children for the published version's, the count of instances put in
+fn expand(dom: &mut WeakDom, root: Ref, package: &WeakDom, name: &str, ctx: &mut PassContext) -> Result<usize, String> {
+    let [published] = package.root().children() else {
+        return Err(format!("{} instances at the top, a package has one", package.root().children().len()));
+    };
+    let published = *published;
+    let offset = match (pivot(dom, root), pivot(package, published)) {
+        (Some(saved), Some(new)) => {
+            if saved.orientation != new.orientation {
+                ctx.warn(format!("package '{}' is turned differently from its published version, it's only moved to match", name));
+            }
+            Some(Vector3::new(
+                saved.position.x - new.position.x,
+                saved.position.y - new.position.y,
+                saved.position.z - new.position.z,
+            ))
+        }
+        _ => None,
+    };
+
+    let old_children = dom.get_by_ref(root).map(|instance| instance.children().to_vec()).unwrap_or_default();
+    for child in old_children {
+        dom.destroy(child);
+    }
+    let new_children: Vec<Ref> = package
+        .get_by_ref(published)
+        .map(|instance| instance.children().to_vec())
+        .unwrap_or_default();
+    let mut count = 0;
+    for child in new_children {
+        // the published copy's own link, the one being resolved
+        if package.get_by_ref(child).is_some_and(|instance| instance.class == LINK_CLASS) {
+            continue;
+        }
+        let copy = package.clone_into_external(child, dom);
+        dom.transfer_within(copy, root);
+        if let Some(offset) = offset {
+            world_space::translate(dom, copy, offset);
+        }
+        count += dom.descendants_of(copy).count();
+    }
+    Ok(count)
+}
+
+type PackageCache = BTreeMap<(u64, Option<u64>), Result<WeakDom, String>>;
+
+fn fetch_package<'a>(cache: &'a mut PackageCache, source: &AssetSource, id: u64, version: Option<u64>) -> &'a Result<WeakDom, String> {
+    cache.entry((id, version)).or_insert_with(|| {
+        let data = source.fetch_version(id, version, &MODEL_EXTENSIONS).map_err(|e| e.to_string())?;
+        crate::load_place(&data).map_err(|e| format!("couldn't read it: {}", e))
+    })
+}
+
+pub fn resolve_package_links(dom: &mut WeakDom, source: Option<&AssetSource>, version: PackageVersion, ctx: &mut PassContext) {
+    let mut cache = PackageCache::new();
+    for nesting in 0..=MAX_NESTING {
+        let links: Vec<Ref> = dom
+            .descendants()
+            .filter(|instance| instance.class == LINK_CLASS)
+            .map(|instance| instance.referent())
+            .filter(|&referent| ctx.in_scope(dom, referent))
+            .collect();
+        if links.is_empty() {
+            return;
+        }
+        // the last round only unlinks
+        let source = source.filter(|_| nesting < MAX_NESTING);
+        for referent in links {
+            // gone with a package expanded before it
+            let Some(link) = read_link(dom, referent) else { continue };
+            let name = dom.get_by_ref(link.root).map(|root| root.name.to_string()).unwrap_or_default();
+            let id_text = link.id.map_or_else(|| "without an id".to_owned(), |id| id.to_string());
+            let wanted = match version {
+                PackageVersion::Latest => None,
+                PackageVersion::Pinned => link.version,
+            };
+            let (Some(source), Some(id)) = (source, link.id) else {
+                dom.destroy(referent);
+                ctx.converted(link.root, format!("unlinked package {} from '{}', the saved copy stays", id_text, name));
+                continue;
+            };
+            let expanded = match fetch_package(&mut cache, source, id, wanted) {
+                Ok(package) => expand(dom, link.root, package, &name, ctx),
+                Err(e) => Err(e.clone()),
+            };
+            match expanded {
+                Ok(count) => {
+                    let which = wanted.map_or_else(|| "latest".to_owned(), |version| format!("version {}", version));
+                    ctx.converted(link.root, format!("expanded package {} into '{}' ({}, {} instances)", id, name, which, count));
+                }
+                Err(e) => {
+                    dom.destroy(referent);
+                    ctx.warn(format!("package {} in '{}' couldn't be expanded, the saved copy stays: {}", id, name, e));
+                }
+            }
+        }
+    }
+    ctx.warn(format!("packages nested more than {} deep were unlinked without being expanded", MAX_NESTING));
+}
@@ -0,0 +1,68 @@
+// which instances a pass is allowed to touch
+//
+// blanket passes are too blunt for places that mix runtime data with map organisation, like
+// folders in ReplicatedStorage that scripts index as models next to folders in Workspace that
+// only keep the map tidy. a scopes file restricts passes by class, keyed by pass name:
+//
+//   {
+//     "folders-to-models": { "under": ["ReplicatedStorage"] },
+//     "strip-classes": { "not_under": ["StarterGui"] },
+//     "instance-mappings": { "exclude_classes": ["UnionOperation"] }
+//   }
+//
+// classes and exclude_classes are about the instance itself, under and not_under about the
+// classes of its ancestors. empty lists don't restrict anything. only passes that pick
+// instances one at a time can be scoped, see PlacePass::scoped.
+use rbx_dom_weak::WeakDom;
+use rbx_dom_weak::types::Ref;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::error::Error;
+
+#[derive(Deserialize, Clone, Default, Debug)]
+#[serde(default, deny_unknown_fields)]
+pub struct PassScope {
+    pub classes: Vec<String>,
+    pub exclude_classes: Vec<String>,
+    // some ancestor has to be one of these
+    pub under: Vec<String>,
+    // and none of these
+    pub not_under: Vec<String>,
+}
+
+// pass name -> its scope
+pub type PassScopes = BTreeMap<String, PassScope>;
+
+fn listed(list: &[String], class: &str) -> bool {
+    list.iter().any(|listed| listed == class)
+}
+
+impl PassScope {
+    pub fn matches(&self, dom: &WeakDom, referent: Ref) -> bool {
+        let Some(instance) = dom.get_by_ref(referent) else { return false };
+        let class = instance.class.as_str();
+        if !self.classes.is_empty() && !listed(&self.classes, class) {
+            return false;
+        }
+        if listed(&self.exclude_classes, class) {
+            return false;
+        }
+        if self.under.is_empty() && self.not_under.is_empty() {
+            return true;
+        }
+        let mut inside = self.under.is_empty();
+        let mut parent = instance.parent();
+        while let Some(ancestor) = dom.get_by_ref(parent) {
+            if listed(&self.not_under, &ancestor.class) {
+                return false;
+            }
+            inside |= listed(&self.under, &ancestor.class);
+            parent = ancestor.parent();
+        }
+        inside
+    }
+}
+
+pub fn scopes_from_json(data: &str) -> Result<PassScopes, Box<dyn Error>> {
+    Ok(serde_json::from_str(data).map_err(|e| format!("couldn't read the pass scopes: {}", e))?)
+}
@@ -0,0 +1,1143 @@
+// the built in passes fix_place is assembled from, in the order it runs them
+use crate::anim;
+use crate::asset_era;
+use crate::asset_source::AssetSource;
+use crate::asset_urls::{self, AssetUrlFormats};
+use crate::baseplate::{self, Baseplate};
+use crate::content_uri::{self, ContentUri};
+use crate::floating;
+use crate::gltf::Transform;
+use crate::inserted_assets;
+use crate::leaderstats;
+use crate::legacy_parts;
+use crate::mappings::InstanceMappings;
+use crate::package_links::{self, PackageVersion};
+use crate::pipeline::{PassContext, PassResult, PlacePass};
+use crate::policy::{self, FailRule};
+use crate::prepare_legacy::{self, LocalizeOptions};
+use crate::replication;
+use crate::shared_strings;
+use crate::tags::{self, TagConversion};
+use crate::thumbnail::{self, ThumbnailCamera};
+use crate::tools;
+use crate::unique_ids::{self, UniqueIdHandling};
+use crate::universe;
+use crate::place_transform::{self, PlaceTransform};
+use crate::world_space::{self, RebaseOrigin};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use rbx_dom_weak::types::{BrickColor, CFrame, Enum, Matrix3, Ref, Vector3};
+use rbx_dom_weak::{Instance, InstanceBuilder, Ustr, WeakDom};
+use rbx_types::{Content, Variant};
+
+// the instances the pass is scoped to, all of them without a scope
+fn all_refs(dom: &WeakDom, ctx: &PassContext) -> Vec<Ref> {
+    dom.descendants()
+        .map(|instance| instance.referent())
+        .filter(|&referent| ctx.in_scope(dom, referent))
+        .collect()
+}
+
+fn service(dom: &WeakDom, class: &str) -> Option<Ref> {
+    dom.root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == class))
+}
+
+// PackageLinks removed, with the packages swapped for their published version when there's a source
+pub struct ResolvePackages {
+    pub source: Option<AssetSource>,
+    pub version: PackageVersion,
+}
+
+impl PlacePass for ResolvePackages {
+    fn name(&self) -> &str {
+        "resolve-packages"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        package_links::resolve_package_links(dom, self.source.as_ref(), self.version, ctx);
+        Ok(())
+    }
+}
+
+// models scripts load by id through InsertService, copied into ServerStorage
+pub struct InlineInsertedAssets {
+    pub source: AssetSource,
+}
+
+impl PlacePass for InlineInsertedAssets {
+    fn name(&self) -> &str {
+        "inline-inserted-assets"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        inserted_assets::inline_inserted_assets(dom, &self.source, ctx);
+        Ok(())
+    }
+}
+
+// everything in world space moved so the origin is somewhere else
+pub struct Rebase {
+    pub origin: RebaseOrigin,
+}
+
+impl PlacePass for Rebase {
+    fn name(&self) -> &str {
+        "rebase-origin"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        world_space::rebase_origin(dom, self.origin, ctx);
+        Ok(())
+    }
+}
+
+// the whole place scaled, turned and mirrored around a point
+pub struct TransformPlace {
+    pub transform: PlaceTransform,
+}
+
+impl PlacePass for TransformPlace {
+    fn name(&self) -> &str {
+        "place-transform"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        place_transform::transform_place(dom, &self.transform, ctx);
+        Ok(())
+    }
+}
+
+pub struct LateAssetReport {
+    pub cutoff: NaiveDate,
+}
+
+impl PlacePass for LateAssetReport {
+    fn name(&self) -> &str {
+        "late-asset-report"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        asset_era::report_late_assets(dom, self.cutoff, ctx);
+        Ok(())
+    }
+}
+
+pub struct StripClasses {
+    pub classes: Vec<Ustr>,
+}
+
+impl PlacePass for StripClasses {
+    fn name(&self) -> &str {
+        "strip-classes"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        if self.classes.is_empty() {
+            return Ok(());
+        }
+        let to_strip: Vec<_> = dom
+            .descendants()
+            .filter(|instance| self.classes.contains(&instance.class))
+            .map(|instance| instance.referent())
+            .filter(|&referent| ctx.in_scope(dom, referent))
+            .collect();
+        for referent in to_strip {
+            // an ancestor may already have been stripped
+            if let Some(instance) = dom.get_by_ref(referent) {
+                ctx.converted(referent, format!("stripped {} '{}'", instance.class, instance.name));
+                dom.destroy(referent);
+            }
+        }
+        Ok(())
+    }
+}
+
+pub struct ConvertTags {
+    pub mode: TagConversion,
+}
+
+impl PlacePass for ConvertTags {
+    fn name(&self) -> &str {
+        "convert-tags"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        tags::convert_tags(dom, self.mode, ctx);
+        Ok(())
+    }
+}
+
+pub struct ApplyMappings {
+    pub mappings: InstanceMappings,
+}
+
+impl PlacePass for ApplyMappings {
+    fn name(&self) -> &str {
+        "instance-mappings"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let mut mapped_children: Vec<(Ref, Vec<InstanceBuilder>)> = Vec::new();
+        let mut welds = Vec::new();
+        let mut boxed: BTreeMap<String, usize> = BTreeMap::new();
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            let Some(rule) = self.mappings.find(instance) else { continue };
+            let old_class = instance.class;
+            let children = rule.apply(instance, ctx);
+            ctx.converted(
+                referent,
+                format!("mapped instance '{}' from {} to {}", instance.name, old_class, instance.class),
+            );
+            if old_class == "WeldConstraint" && instance.class == "Weld" {
+                welds.push(referent);
+            }
+            if (old_class == "UnionOperation" || old_class == "NegateOperation") && instance.class == "Part" {
+                *boxed.entry(old_class.to_string()).or_default() += 1;
+            }
+            if !children.is_empty() {
+                mapped_children.push((referent, children));
+            }
+        }
+        for (class, count) in boxed {
+            ctx.warn(format!(
+                "{} {}(s) mapped to Part, their geometry is lost and they show up as boxes of the same size",
+                count, class
+            ));
+        }
+        for referent in welds {
+            weld_offsets(dom, referent, ctx);
+        }
+        // inserted afterwards so rules don't run on the children they just added
+        for (parent_ref, children) in mapped_children {
+            for child in children {
+                dom.insert(parent_ref, child);
+            }
+        }
+        Ok(())
+    }
+}
+
+fn part_cframe(dom: &WeakDom, weld: &Instance, property: &str) -> Option<CFrame> {
+    let Some(Variant::Ref(part)) = weld.properties.get(&property.into()) else { return None };
+    match dom.get_by_ref(*part)?.properties.get(&"CFrame".into()) {
+        Some(Variant::CFrame(cframe)) => Some(*cframe),
+        _ => None,
+    }
+}
+
+// a WeldConstraint holds its parts wherever they are, a Weld puts Part1 at Part0 * C0 * C1:Inverse(),
+// so one mapped from the other needs C0 = Part0:Inverse() * Part1 and C1 = identity to keep them put
+fn weld_offsets(dom: &mut WeakDom, referent: Ref, ctx: &mut PassContext) {
+    let Some(weld) = dom.get_by_ref(referent) else { return };
+    let (Some(part0), Some(part1)) = (part_cframe(dom, weld, "Part0"), part_cframe(dom, weld, "Part1")) else {
+        ctx.warn(format!("weld '{}' is missing a part, its C0 and C1 are left unset", weld.name));
+        return;
+    };
+    let c0 = anim::cframe_of(&anim::transform_of(&part0).inverse().then(&anim::transform_of(&part1)));
+    let c1 = anim::cframe_of(&Transform::IDENTITY);
+    let Some(weld) = dom.get_by_ref_mut(referent) else { return };
+    weld.properties.insert("C0".into(), Variant::CFrame(c0));
+    weld.properties.insert("C1".into(), Variant::CFrame(c1));
+}
+
+// year of the target client, from the preset. a box that weighs what the mesh did needs
+// CustomPhysicalProperties, which clients before it don't read, so for those it isn't set
+pub struct MeshPartsToSpecialMeshes {
+    pub year: Option<u32>,
+}
+
+impl PlacePass for MeshPartsToSpecialMeshes {
+    fn name(&self) -> &str {
+        "meshparts-to-specialmeshes"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let custom_physics = self.year.is_none_or(|year| year >= legacy_parts::CUSTOM_PHYSICAL_PROPERTIES_YEAR);
+        let mut unmatched_mass = 0;
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            if instance.class != "MeshPart" {
+                continue;
+            }
+            let initial_size = match instance.properties.get(&"InitialSize".into()) {
+                Some(Variant::Vector3(v)) => *v,
+                _ => {
+                    ctx.skipped(referent, format!(
+                        "meshpart '{}' missing initialsize property, skipping conversion",
+                        instance.name
+                    ));
+                    continue;
+                }
+            };
+            let size = match instance.properties.get(&"Size".into()) {
+                Some(Variant::Vector3(v)) => *v,
+                _ => {
+                    ctx.skipped(referent, format!(
+                        "meshpart '{}' missing size property, skipping conversion",
+                        instance.name
+                    ));
+                    continue;
+                }
+            };
+            if initial_size.x == 0.0 || initial_size.y == 0.0 || initial_size.z == 0.0 {
+                ctx.skipped(referent, format!(
+                    "meshpart '{}' has zero initialsize, skipping conversion",
+                    instance.name
+                ));
+                continue;
+            }
+            let scale = Vector3 {
+                x: size.x / initial_size.x,
+                y: size.y / initial_size.y,
+                z: size.z / initial_size.z,
+            };
+            instance.class = "Part".into();
+            // the mesh scale above comes from the real size, so clamping doesn't change the look
+            let legacy_size = legacy_parts::clamp_size(size);
+            instance.properties.insert("Size".into(), Variant::Vector3(legacy_size));
+            legacy_parts::set_form_factor(instance, legacy_parts::FORM_FACTOR_CUSTOM);
+            if let Some(physics) = legacy_parts::box_physical_properties(instance) {
+                if custom_physics {
+                    instance
+                        .properties
+                        .insert("CustomPhysicalProperties".into(), Variant::PhysicalProperties(physics));
+                } else {
+                    unmatched_mass += 1;
+                }
+            }
+            let mesh_id = instance
+                .properties
+                .get(&"MeshId".into())
+                .cloned()
+                .unwrap_or_else(|| Variant::Content(Content::from_uri(String::new())));
+            let instance_name = instance.name.clone();
+            let special_mesh = InstanceBuilder::new("SpecialMesh")
+                .with_name("Mesh")
+                .with_property("Scale", Variant::Vector3(scale))
+                .with_property("MeshType", Variant::Enum(Enum::from_u32(5)))
+                .with_property("MeshId", mesh_id);
+            dom.insert(referent, special_mesh);
+            let mut message = format!(
+                "converted meshpart '{}' -> part + specialmesh scale=({}, {}, {})",
+                instance_name, scale.x, scale.y, scale.z
+            );
+            if legacy_size != size {
+                message += &format!(", size clamped to ({}, {}, {})", legacy_size.x, legacy_size.y, legacy_size.z);
+            }
+            ctx.converted(referent, message);
+        }
+        if unmatched_mass > 0 {
+            ctx.warn(format!(
+                "{} converted meshpart(s) weigh more as boxes than they did, {} clients don't read \
+                 CustomPhysicalProperties so their mass can't be matched",
+                unmatched_mass,
+                self.year.unwrap_or_default()
+            ));
+        }
+        Ok(())
+    }
+}
+
+pub struct FoldersToModels;
+
+impl PlacePass for FoldersToModels {
+    fn name(&self) -> &str {
+        "folders-to-models"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            if instance.class == "Folder" {
+                instance.class = "Model".into();
+                ctx.converted(referent, format!("converted folder '{}' to model", instance.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+// rebuilds part shapes the target year doesn't have out of the closest thing it does
+pub struct PartShapeFallbacks {
+    pub year: u32,
+}
+
+impl PlacePass for PartShapeFallbacks {
+    fn name(&self) -> &str {
+        "part-shape-fallbacks"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let shape_key = Ustr::from("Shape");
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+
+            // Part.Shape wedges go back to the classes they used to be
+            if instance.class == "Part" && self.year < legacy_parts::PART_SHAPE_WEDGE_YEAR {
+                let shape = match instance.properties.get(&shape_key) {
+                    Some(Variant::Enum(shape)) => shape.to_u32(),
+                    _ => 0,
+                };
+                let class = match shape {
+                    legacy_parts::PART_TYPE_WEDGE => Some("WedgePart"),
+                    legacy_parts::PART_TYPE_CORNER_WEDGE => Some("CornerWedgePart"),
+                    _ => None,
+                };
+                if let Some(class) = class {
+                    instance.class = class.into();
+                    instance.properties.remove(&shape_key);
+                    ctx.converted(referent, format!("converted shaped part '{}' to {}", instance.name, class));
+                }
+            }
+
+            if instance.class == "CornerWedgePart" && self.year < legacy_parts::CORNER_WEDGE_PART_YEAR {
+                // nothing old has the corner shape, a wedge mesh at least keeps the slope
+                instance.class = "Part".into();
+                legacy_parts::set_form_factor(instance, legacy_parts::FORM_FACTOR_CUSTOM);
+                let size = match instance.properties.get(&"Size".into()) {
+                    Some(Variant::Vector3(size)) => *size,
+                    _ => Vector3::new(2.0, 2.0, 2.0),
+                };
+                let name = instance.name.clone();
+                let mesh = InstanceBuilder::new("SpecialMesh")
+                    .with_name("Mesh")
+                    .with_property("MeshType", Variant::Enum(Enum::from_u32(legacy_parts::MESH_TYPE_WEDGE)))
+                    .with_property("Scale", Variant::Vector3(Vector3::new(1.0, 1.0, 1.0)));
+                dom.insert(referent, mesh);
+                ctx.converted(referent, format!("converted cornerwedgepart '{}' to part + wedge specialmesh", name));
+                ctx.warn(format!(
+                    "cornerwedgepart '{}' ({}, {}, {}) is a plain wedge in {}, it only keeps one of its slopes",
+                    name, size.x, size.y, size.z, self.year
+                ));
+                continue;
+            }
+
+            if instance.class == "TrussPart" && self.year < legacy_parts::TRUSS_STYLE_YEAR {
+                // TrussPart keeps it as lowercase "style", "Style" is just the scripting alias
+                let style_key = Ustr::from("style");
+                if let Some(Variant::Enum(style)) = instance.properties.remove(&style_key)
+                    && style.to_u32() != 0
+                {
+                    ctx.converted(
+                        referent,
+                        format!("dropped truss style on '{}', it'll have alternating supports", instance.name),
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// parts old clients size by FormFactor, TrussPart is always 2x2x2 so it's left alone
+const GRID_PART_CLASSES: [&str; 7] = [
+    "Part",
+    "WedgePart",
+    "Seat",
+    "VehicleSeat",
+    "SpawnLocation",
+    "SkateboardPlatform",
+    "FlagStand",
+];
+
+// clients from before FormFactor Custom resize everything to the brick grid on load, doing it here
+// means the build at least comes out the way it'll look there and says by how much it moved
+pub struct LegacySizeGrid;
+
+impl PlacePass for LegacySizeGrid {
+    fn name(&self) -> &str {
+        "legacy-size-grid"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let mut snapped = 0;
+        let mut total_drift = 0.0f32;
+        let mut max_drift = (0.0f32, String::new());
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            if !GRID_PART_CLASSES.contains(&instance.class.as_str()) {
+                continue;
+            }
+            let Some(Variant::Vector3(size)) = instance.properties.get(&"Size".into()).cloned() else {
+                continue;
+            };
+            let (grid_size, form_factor) = legacy_parts::snap_size(size);
+            legacy_parts::set_form_factor(instance, form_factor);
+            instance.properties.insert("Size".into(), Variant::Vector3(grid_size));
+            let mut moved = 0.0;
+            if let Some(Variant::CFrame(cframe)) = instance.properties.get(&"CFrame".into()).cloned() {
+                let grid_cframe = legacy_parts::snap_position(cframe, grid_size);
+                moved = distance(cframe.position, grid_cframe.position);
+                instance.properties.insert("CFrame".into(), Variant::CFrame(grid_cframe));
+            }
+            let drift = distance(size, grid_size) + moved;
+            if drift < 1e-4 {
+                continue;
+            }
+            snapped += 1;
+            total_drift += drift;
+            if drift > max_drift.0 {
+                max_drift = (drift, instance.name.to_string());
+            }
+            ctx.converted(
+                referent,
+                format!(
+                    "snapped '{}' to the grid: size ({}, {}, {}) -> ({}, {}, {}), moved {:.3}",
+                    instance.name, size.x, size.y, size.z, grid_size.x, grid_size.y, grid_size.z, moved
+                ),
+            );
+        }
+        if snapped > 0 {
+            ctx.info(format!(
+                "snapped {} parts to the legacy grid, {:.3} studs of drift in total, most on '{}' ({:.3})",
+                snapped, total_drift, max_drift.1, max_drift.0
+            ));
+        }
+        Ok(())
+    }
+}
+
+fn distance(a: Vector3, b: Vector3) -> f32 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2) + (a.z - b.z).powi(2)).sqrt()
+}
+
+// classes old clients error on
+pub struct LegacyClassFixups;
+
+impl PlacePass for LegacyClassFixups {
+    fn name(&self) -> &str {
+        "legacy-class-fixups"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            if instance.class == "KeyframeSequence" {
+                instance.class = "Part".into();
+                ctx.converted(
+                    referent,
+                    format!("converted keyframesequence '{}' to part to avoid errors in old clients", instance.name),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+const LEGACY_FONT_SIZE_OPTIONS: [(i64, u32); 10] = [
+    (8, 0), (9, 1), (10, 2), (11, 3), (12, 4),
+    (14, 5), (18, 6), (24, 7), (36, 8), (48, 9),
+];
+const FONT_SIZE_COMPATIBILITY: [(u32, u32); 5] = [(10, 7), (11, 8), (12, 9), (13, 9), (14, 9)];
+
+fn font_size_name_from_value(value: u32) -> &'static str {
+    match value {
+        0 => "Size8", 1 => "Size9", 2 => "Size10", 3 => "Size11", 4 => "Size12",
+        5 => "Size14", 6 => "Size18", 7 => "Size24", 8 => "Size36", 9 => "Size48",
+        10 => "Size28", 11 => "Size32", 12 => "Size42", 13 => "Size60", 14 => "Size96",
+        _ => "Unknown",
+    }
+}
+
+fn normalize_font_size_value(value: u32) -> u32 {
+    FONT_SIZE_COMPATIBILITY
+        .iter()
+        .find(|&&(modern, _)| value == modern)
+        .map_or(value, |&(_, legacy)| legacy)
+}
+
+fn font_enum_from_text_size(text_size: i64) -> u32 {
+    LEGACY_FONT_SIZE_OPTIONS
+        .iter()
+        .min_by_key(|&&(size, _)| (text_size - size).abs())
+        .map(|&(_, enum_val)| enum_val)
+        .unwrap_or(0)
+}
+
+pub struct TextSizeToFontSize;
+
+impl PlacePass for TextSizeToFontSize {
+    fn name(&self) -> &str {
+        "textsize-to-fontsize"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let text_size_key: Ustr = "TextSize".into();
+        let font_size_key: Ustr = "FontSize".into();
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            let Some(prop_value) = instance.properties.get(&text_size_key) else { continue };
+            let text_size = match prop_value {
+                Variant::Int64(val) => *val,
+                Variant::Int32(val) => *val as i64,
+                Variant::Float32(val) => *val as i64,
+                Variant::Float64(val) => *val as i64,
+                _ => {
+                    ctx.skipped(referent, format!(
+                        "textsize on '{}' has unexpected type: {:?}",
+                        instance.name, prop_value
+                    ));
+                    continue;
+                }
+            };
+            let enum_value = normalize_font_size_value(font_enum_from_text_size(text_size));
+            instance
+                .properties
+                .insert(font_size_key, Variant::Enum(Enum::from_u32(enum_value)));
+            instance.properties.remove(&text_size_key);
+            ctx.converted(
+                referent,
+                format!(
+                    "converted TextSize {} on '{}' to FontSize {}",
+                    text_size,
+                    instance.name,
+                    font_size_name_from_value(enum_value)
+                ),
+            );
+        }
+        Ok(())
+    }
+}
+
+// roughly when Texture showed up and when it got OffsetStudsU/V
+const TEXTURE_YEAR: u32 = 2010;
+const TEXTURE_OFFSET_YEAR: u32 = 2017;
+
+fn float_prop(instance: &Instance, name: &str) -> Option<f32> {
+    match instance.properties.get(&name.into()) {
+        Some(Variant::Float32(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+// width and height of the part face a decal or texture sits on
+fn face_size(dom: &WeakDom, instance: &Instance) -> Option<(f32, f32)> {
+    let size = match dom.get_by_ref(instance.parent())?.properties.get(&"Size".into()) {
+        Some(Variant::Vector3(size)) => *size,
+        _ => return None,
+    };
+    let face = match instance.properties.get(&"Face".into()) {
+        Some(Variant::Enum(face)) => face.to_u32(),
+        _ => 5,
+    };
+    Some(match face {
+        // Right, Left
+        0 | 3 => (size.z, size.y),
+        // Top, Bottom
+        1 | 4 => (size.x, size.z),
+        // Back, Front
+        _ => (size.x, size.y),
+    })
+}
+
+// old clients either don't know about texture offsets or don't have textures at all, and a
+// texture that turns into a decal gets one copy of its image stretched over the whole face
+pub struct TextureTiling {
+    pub year: u32,
+}
+
+impl PlacePass for TextureTiling {
+    fn name(&self) -> &str {
+        "texture-tiling"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref(referent) else { continue };
+            if instance.class != "Texture" {
+                continue;
+            }
+            let tile = (
+                float_prop(instance, "StudsPerTileU").unwrap_or(2.0),
+                float_prop(instance, "StudsPerTileV").unwrap_or(2.0),
+            );
+            let offset = (
+                float_prop(instance, "OffsetStudsU").unwrap_or(0.0),
+                float_prop(instance, "OffsetStudsV").unwrap_or(0.0),
+            );
+            let face = face_size(dom, instance);
+            let name = instance.name.to_string();
+
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            if self.year < TEXTURE_OFFSET_YEAR {
+                instance.properties.remove(&"OffsetStudsU".into());
+                instance.properties.remove(&"OffsetStudsV".into());
+                // whole tiles of offset look the same as none
+                let shift = |offset: f32, tile: f32| if tile > 0.0 { offset.rem_euclid(tile) } else { offset };
+                let (u, v) = (shift(offset.0, tile.0), shift(offset.1, tile.1));
+                if u.abs() > 1e-3 || v.abs() > 1e-3 {
+                    ctx.converted(
+                        referent,
+                        format!("dropped offset on texture '{}', its tiles shift by ({}, {}) studs", name, u, v),
+                    );
+                }
+            }
+
+            if self.year < TEXTURE_YEAR {
+                instance.class = "Decal".into();
+                instance.properties.remove(&"StudsPerTileU".into());
+                instance.properties.remove(&"StudsPerTileV".into());
+                ctx.converted(referent, format!("converted texture '{}' to decal", name));
+                if let Some((width, height)) = face
+                    && tile.0 > 0.0
+                    && tile.1 > 0.0
+                {
+                    let (across, down) = (width / tile.0, height / tile.1);
+                    if across > 1.01 || down > 1.01 {
+                        ctx.warn(format!(
+                            "texture '{}' tiled {:.1}x{:.1} times, as a decal it's one image stretched over the face",
+                            name, across, down
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// roughly when SpawnLocation got AllowTeamChangeOnTouch and Enabled
+const SPAWN_TEAM_CHANGE_YEAR: u32 = 2009;
+const SPAWN_ENABLED_YEAR: u32 = 2016;
+const SPAWN_PAD_SIZE: Vector3 = Vector3 { x: 6.0, y: 1.0, z: 6.0 };
+
+fn bool_prop(instance: &Instance, name: &str) -> Option<bool> {
+    match instance.properties.get(&name.into()) {
+        Some(Variant::Bool(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+// old clients pick spawns purely by TeamColor and Neutral, lots of newer games leave that to
+// scripts (or disabled spawns) and end up with everyone on one pad or no pad at all
+pub struct SpawnFixups {
+    pub year: u32,
+}
+
+impl PlacePass for SpawnFixups {
+    fn name(&self) -> &str {
+        "spawn-fixups"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let mut team_spawns = Vec::new();
+        let mut first_spawn = None;
+        // not scoped, teams without a spawn get one so every spawn has to be seen
+        for referent in all_refs(dom, ctx) {
+            let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+            if instance.class != "SpawnLocation" {
+                continue;
+            }
+
+            if self.year < SPAWN_ENABLED_YEAR && bool_prop(instance, "Enabled") == Some(false) {
+                // nothing turns a spawn off before Enabled, a plain part keeps it looking the same
+                instance.class = "Part".into();
+                instance.properties.remove(&"Enabled".into());
+                ctx.converted(referent, format!("converted disabled spawnlocation '{}' to part", instance.name));
+                continue;
+            }
+
+            if let Some(Variant::Int32(duration)) = instance.properties.get(&"Duration".into())
+                && *duration < 0
+            {
+                instance.properties.insert("Duration".into(), Variant::Int32(0));
+                ctx.converted(referent, format!("set negative forcefield duration on '{}' to 0", instance.name));
+            }
+
+            if self.year < SPAWN_TEAM_CHANGE_YEAR
+                && instance.properties.remove(&"AllowTeamChangeOnTouch".into()) == Some(Variant::Bool(true))
+            {
+                ctx.warn(format!("spawnlocation '{}' changes teams on touch, nothing does that in {}", instance.name, self.year));
+            }
+
+            if bool_prop(instance, "Neutral") == Some(false)
+                && let Some(Variant::BrickColor(color)) = instance.properties.get(&"TeamColor".into())
+            {
+                team_spawns.push(*color);
+            }
+            if first_spawn.is_none()
+                && let Some(Variant::CFrame(cframe)) = instance.properties.get(&"CFrame".into())
+            {
+                first_spawn = Some(cframe.position);
+            }
+        }
+
+        let Some(teams) = service(dom, "Teams") else { return Ok(()) };
+        let Some(workspace) = service(dom, "Workspace") else { return Ok(()) };
+        let teams: Vec<(String, BrickColor)> = dom
+            .descendants_of(teams)
+            .filter(|instance| instance.class == "Team")
+            .filter_map(|team| match team.properties.get(&"TeamColor".into()) {
+                Some(Variant::BrickColor(color)) => Some((team.name.to_string(), *color)),
+                _ => None,
+            })
+            .collect();
+
+        // a row of pads next to the first spawn, so they're at least somewhere sensible
+        let origin = first_spawn.unwrap_or(Vector3::new(0.0, 0.5, 0.0));
+        let mut placed = 0;
+        for (team, color) in teams {
+            if team_spawns.contains(&color) {
+                continue;
+            }
+            placed += 1;
+            let position = Vector3::new(origin.x + placed as f32 * (SPAWN_PAD_SIZE.x + 2.0), origin.y, origin.z);
+            let pad = InstanceBuilder::new("SpawnLocation")
+                .with_name(format!("{} Spawn", team))
+                .with_property("Size", Variant::Vector3(SPAWN_PAD_SIZE))
+                .with_property("CFrame", Variant::CFrame(CFrame::new(position, Matrix3::identity())))
+                .with_property("Anchored", Variant::Bool(true))
+                // Color3uint8 is stripped for pre-2016 xml, BrickColor is what those clients read
+                .with_property("Color", Variant::Color3uint8(color.to_color3uint8()))
+                .with_property("BrickColor", Variant::BrickColor(color))
+                .with_property("TeamColor", Variant::BrickColor(color))
+                .with_property("Neutral", Variant::Bool(false));
+            let referent = dom.insert(workspace, pad);
+            team_spawns.push(color);
+            ctx.converted(referent, format!("added a {} spawn pad for team '{}'", color, team));
+        }
+        if placed > 0 {
+            ctx.warn(format!(
+                "added {} team spawn pads, the game probably picked teams in scripts, check where they ended up",
+                placed
+            ));
+        }
+        Ok(())
+    }
+}
+
+// a baseplate under the spawns when nothing else is under them
+pub struct EnsureBaseplate {
+    pub baseplate: Baseplate,
+}
+
+impl PlacePass for EnsureBaseplate {
+    fn name(&self) -> &str {
+        "ensure-baseplate"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        baseplate::ensure_baseplate(dom, &self.baseplate, ctx);
+        Ok(())
+    }
+}
+
+// unanchored parts with nothing under them get anchored where they are
+pub struct AnchorFloating {
+    pub within: f32,
+}
+
+impl PlacePass for AnchorFloating {
+    fn name(&self) -> &str {
+        "anchor-floating"
+    }
+
+    fn scoped(&self) -> bool {
+        true
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        floating::anchor_floating(dom, self.within, ctx);
+        Ok(())
+    }
+}
+
+// FilteringEnabled and streaming settings the target year expects
+pub struct ReplicationFlags {
+    pub year: u32,
+}
+
+impl PlacePass for ReplicationFlags {
+    fn name(&self) -> &str {
+        "replication-flags"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        replication::apply_replication_flags(dom, self.year, ctx);
+        Ok(())
+    }
+}
+
+// Tool properties and setups the target year doesn't have, plus starter gear from a local dir
+pub struct ToolFixups {
+    pub year: u32,
+    pub gear_dir: Option<PathBuf>,
+}
+
+impl PlacePass for ToolFixups {
+    fn name(&self) -> &str {
+        "tool-fixups"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        tools::apply_tool_fixups(dom, self.year, self.gear_dir.as_deref(), ctx);
+        Ok(())
+    }
+}
+
+// old place ids to the new ones of a universe being converted together, see universe
+pub struct RewritePlaceIds {
+    pub ids: BTreeMap<u64, u64>,
+}
+
+impl PlacePass for RewritePlaceIds {
+    fn name(&self) -> &str {
+        "rewrite-place-ids"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        universe::rewrite_place_ids(dom, &self.ids, ctx);
+        Ok(())
+    }
+}
+
+pub struct InjectLeaderstats;
+
+impl PlacePass for InjectLeaderstats {
+    fn name(&self) -> &str {
+        "inject-leaderstats"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        leaderstats::inject_leaderstats(dom, ctx);
+        Ok(())
+    }
+}
+
+// UniqueId/HistoryId stripped for old targets, or duplicates given new ids, see unique_ids
+pub struct UniqueIds {
+    pub handling: UniqueIdHandling,
+}
+
+impl PlacePass for UniqueIds {
+    fn name(&self) -> &str {
+        "unique-ids"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        match self.handling {
+            UniqueIdHandling::Strip => {
+                let (properties, instances) = unique_ids::strip_unique_ids(dom);
+                ctx.info(format!("stripped {} UniqueId/HistoryId value(s) from {} instance(s)", properties, instances));
+            }
+            UniqueIdHandling::Regenerate => {
+                for (referent, old_id, new_id) in unique_ids::regenerate_duplicate_ids(dom) {
+                    let name = dom.get_by_ref(referent).map_or_else(String::new, |instance| instance.name.to_string());
+                    ctx.converted(referent, format!("'{}' had UniqueId {} twice, gave it {}", name, old_id, new_id));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// duplicate asset ids to the one kept, see mesh_dedup
+pub struct AliasAssetIds {
+    pub aliases: BTreeMap<u64, u64>,
+}
+
+impl PlacePass for AliasAssetIds {
+    fn name(&self) -> &str {
+        "alias-asset-ids"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let changes = content_uri::rewrite_content(dom, |_, uri| {
+            let canonical = self.aliases.get(&uri.asset_id()?)?;
+            Some(uri.with_asset_id(*canonical))
+        });
+        for (referent, changed) in changes {
+            let name = dom.get_by_ref(referent).map_or_else(String::new, |instance| instance.name.to_string());
+            let changed: Vec<_> = changed
+                .iter()
+                .map(|(prop_name, uri)| format!("'{}' to {}", prop_name, uri))
+                .collect();
+            ctx.converted(referent, format!("aliased asset ids on '{}', changed {}", name, changed.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+pub struct AssetIdsToUrls {
+    pub formats: AssetUrlFormats,
+}
+
+impl PlacePass for AssetIdsToUrls {
+    fn name(&self) -> &str {
+        "assetids-to-urls"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let changes = content_uri::rewrite_content(dom, |prop_name, uri| match uri {
+            ContentUri::AssetId(id) => {
+                let url = self.formats.url(&id.to_string(), asset_urls::asset_type(prop_name));
+                Some(ContentUri::parse(&url))
+            }
+            _ => None,
+        });
+        for (referent, changed) in changes {
+            let name = dom.get_by_ref(referent).map_or_else(String::new, |instance| instance.name.to_string());
+            let changed: Vec<_> = changed
+                .iter()
+                .map(|(prop_name, url)| format!("'{}' to {}", prop_name, url))
+                .collect();
+            ctx.converted(referent, format!("converted asset IDs on '{}', changed {}", name, changed.join(", ")));
+        }
+        Ok(())
+    }
+}
+
+// meshes, textures and sounds referenced by id copied into a content dir, see prepare_legacy
+pub struct LocalizeAssets {
+    pub options: LocalizeOptions,
+}
+
+impl PlacePass for LocalizeAssets {
+    fn name(&self) -> &str {
+        "localize-assets"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        prepare_legacy::localize_assets(dom, &self.options, ctx)
+    }
+}
+
+pub struct SetThumbnailCamera {
+    pub placement: ThumbnailCamera,
+}
+
+impl PlacePass for SetThumbnailCamera {
+    fn name(&self) -> &str {
+        "thumbnail-camera"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        thumbnail::set_thumbnail_camera(dom, self.placement, ctx);
+        Ok(())
+    }
+}
+
+pub struct ExpandSharedStrings;
+
+impl PlacePass for ExpandSharedStrings {
+    fn name(&self) -> &str {
+        "expand-shared-strings"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let stats = shared_strings::expand_shared_strings(dom);
+        ctx.info(format!(
+            "expanded {} shared string value(s) ({} unique), output grows by ~{} bytes",
+            stats.values, stats.unique, stats.bytes
+        ));
+        Ok(())
+    }
+}
+
+pub struct DedupSharedStrings {
+    pub min_bytes: usize,
+}
+
+impl PlacePass for DedupSharedStrings {
+    fn name(&self) -> &str {
+        "dedup-shared-strings"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult {
+        let stats = shared_strings::dedup_shared_strings(dom, self.min_bytes);
+        ctx.info(format!(
+            "deduplicated {} value(s) into {} shared string(s), saving ~{} bytes",
+            stats.values, stats.unique, stats.bytes
+        ));
+        Ok(())
+    }
+}
+
+// both writers already emit properties sorted by name, so only child order is left to the
+// input file and whatever order conversions inserted things in
+pub struct SortChildren;
+
+impl PlacePass for SortChildren {
+    fn name(&self) -> &str {
+        "sort-children"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, _ctx: &mut PassContext) -> PassResult {
+        let parents: Vec<_> = dom
+            .descendants()
+            .filter(|instance| instance.children().len() > 1)
+            .map(|instance| instance.referent())
+            .collect();
+        for parent in parents {
+            dom.sort_children_by(parent, |a, b| {
+                a.class.as_str().cmp(b.class.as_str()).then_with(|| a.name.cmp(&b.name))
+            });
+        }
+        Ok(())
+    }
+}
+
+// runs last, fails the conversion if anything in the finished place breaks a --fail-if rule
+pub struct FailIf {
+    pub rules: Vec<FailRule>,
+}
+
+impl PlacePass for FailIf {
+    fn name(&self) -> &str {
+        "fail-if"
+    }
+
+    fn apply(&self, dom: &mut WeakDom, _ctx: &mut PassContext) -> PassResult {
+        let violations = policy::check(dom, &self.rules);
+        if violations.is_empty() {
+            return Ok(());
+        }
+        Err(policy::describe(&violations).into())
+    }
+}
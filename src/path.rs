@@ -0,0 +1,232 @@
+// instance paths, the one syntax everything that names an instance reads and prints
+//
+//   Workspace.Map.Spawn            names separated by dots
+//   game.Workspace["My Part"]      names with dots, brackets, quotes or odd spacing go in ["..."],
+//                                  \" and \\ escape inside the quotes. a leading game (or /) is the root
+//   Workspace.*.Handle             * is any one child, Spawn* any child whose name starts with Spawn
+//   Workspace.**.Script            ** is any number of levels, including none
+//   ..                             the parent, for paths relative to somewhere other than the root
+//
+// quoted names are always literal, so ["*"] is a child actually called *. paths are printed
+// relative to the root without the game prefix, quoting only the names that need it.
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+const ROOT_NAME: &str = "game";
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Segment {
+    Name(String),
+    // a bare name with * in it
+    Pattern(String),
+    AnyDepth,
+    Parent,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstancePath {
+    pub absolute: bool,
+    pub segments: Vec<Segment>,
+}
+
+fn needs_quotes(name: &str) -> bool {
+    name.is_empty()
+        || name == ROOT_NAME
+        || name.trim() != name
+        || name.contains(['.', '[', ']', '"', '\\', '*', '/'])
+}
+
+fn quote(name: &str) -> String {
+    format!("[\"{}\"]", name.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+// * matches any run of characters, everything else matches itself
+fn glob_matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = name.strip_prefix(first) else { return false };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else { return rest.is_empty() };
+    for part in middle {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+// reads one ["..."] starting just after the [, returns the name and what follows the ]
+fn parse_quoted(input: &str) -> Result<(String, &str), String> {
+    let body = input
+        .strip_prefix('"')
+        .ok_or_else(|| format!("expected \" after [ in '[{}'", input))?;
+    let mut name = String::new();
+    let mut chars = body.char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, escaped)) => name.push(escaped),
+                None => break,
+            },
+            '"' => {
+                let rest = body[index + 1..]
+                    .strip_prefix(']')
+                    .ok_or_else(|| format!("expected ] after \"{}\"", name))?;
+                return Ok((name, rest));
+            }
+            c => name.push(c),
+        }
+    }
+    Err(format!("unterminated name \"{}", name))
+}
+
+impl FromStr for InstancePath {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut rest = s.trim();
+        let mut absolute = false;
+        if let Some(after) = rest.strip_prefix('/') {
+            absolute = true;
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix(ROOT_NAME)
+            && (after.is_empty() || after.starts_with(['.', '[']))
+        {
+            absolute = true;
+            rest = after.strip_prefix('.').unwrap_or(after);
+        }
+
+        let mut segments = Vec::new();
+        while !rest.is_empty() {
+            if let Some(after) = rest.strip_prefix("..") {
+                segments.push(Segment::Parent);
+                rest = after.strip_prefix('.').unwrap_or(after);
+                continue;
+            }
+            if let Some(after) = rest.strip_prefix('[') {
+                let (name, after) = parse_quoted(after)?;
+                segments.push(Segment::Name(name));
+                rest = after;
+            } else {
+                let end = rest.find(['.', '[']).unwrap_or(rest.len());
+                let name = &rest[..end];
+                if name.is_empty() {
+                    return Err(format!("empty name in '{}', quote it as [\"\"]", s));
+                }
+                if name.contains([']', '"']) {
+                    return Err(format!("'{}' needs quoting, e.g. {}", name, quote(name)));
+                }
+                segments.push(match name {
+                    "**" => Segment::AnyDepth,
+                    name if name.contains('*') => Segment::Pattern(name.to_owned()),
+                    name => Segment::Name(name.to_owned()),
+                });
+                rest = &rest[end..];
+            }
+            // a dot between names, a [ can follow a name directly
+            if let Some(after) = rest.strip_prefix('.') {
+                if after.is_empty() {
+                    return Err(format!("'{}' ends with a dot", s));
+                }
+                if !after.starts_with('.') {
+                    rest = after;
+                }
+            } else if !rest.is_empty() && !rest.starts_with('[') {
+                return Err(format!("expected . or [ before '{}'", rest));
+            }
+        }
+        Ok(Self { absolute, segments })
+    }
+}
+
+impl fmt::Display for InstancePath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        if self.absolute {
+            f.write_str(ROOT_NAME)?;
+            first = false;
+        }
+        for segment in &self.segments {
+            let text = match segment {
+                Segment::Name(name) if needs_quotes(name) => {
+                    f.write_str(&quote(name))?;
+                    first = false;
+                    continue;
+                }
+                Segment::Name(name) | Segment::Pattern(name) => name.as_str(),
+                Segment::AnyDepth => "**",
+                Segment::Parent => "..",
+            };
+            if !first {
+                f.write_str(".")?;
+            }
+            f.write_str(text)?;
+            first = false;
+        }
+        Ok(())
+    }
+}
+
+impl InstancePath {
+    pub fn has_wildcards(&self) -> bool {
+        self.segments
+            .iter()
+            .any(|segment| matches!(segment, Segment::Pattern(_) | Segment::AnyDepth))
+    }
+
+    // every instance the path matches, starting at `from` unless it's absolute
+    pub fn resolve(&self, dom: &WeakDom, from: Ref) -> Vec<Ref> {
+        let mut current = vec![if self.absolute { dom.root_ref() } else { from }];
+        for segment in &self.segments {
+            let mut next = Vec::new();
+            let mut seen = HashSet::new();
+            for &referent in &current {
+                let Some(instance) = dom.get_by_ref(referent) else { continue };
+                let found: Vec<Ref> = match segment {
+                    Segment::Parent if instance.parent().is_some() => vec![instance.parent()],
+                    Segment::Parent => vec![referent],
+                    Segment::AnyDepth => std::iter::once(referent)
+                        .chain(dom.descendants_of(referent).map(|descendant| descendant.referent()))
+                        .collect(),
+                    Segment::Name(name) => children_where(dom, instance.children(), |child| child == name),
+                    Segment::Pattern(pattern) => children_where(dom, instance.children(), |child| glob_matches(pattern, child)),
+                };
+                next.extend(found.into_iter().filter(|found| seen.insert(*found)));
+            }
+            current = next;
+        }
+        current
+    }
+
+    // the single instance the path names, an error when it matches none or several
+    pub fn resolve_one(&self, dom: &WeakDom, from: Ref) -> Result<Ref, String> {
+        match self.resolve(dom, from).as_slice() {
+            [referent] => Ok(*referent),
+            [] => Err(format!("no instance at '{}'", self)),
+            many => Err(format!("'{}' matches {} instances, expected one", self, many.len())),
+        }
+    }
+}
+
+fn children_where(dom: &WeakDom, children: &[Ref], keep: impl Fn(&str) -> bool) -> Vec<Ref> {
+    children
+        .iter()
+        .copied()
+        .filter(|&child| dom.get_by_ref(child).is_some_and(|instance| keep(instance.name.as_str())))
+        .collect()
+}
+
+// the path of an instance from the root, e.g. Workspace["My Part"].Mesh
+pub fn path_of(dom: &WeakDom, referent: Ref) -> String {
+    let mut segments: Vec<Segment> = dom
+        .ancestors_of(referent)
+        .filter(|instance| instance.referent() != dom.root_ref())
+        .map(|instance| Segment::Name(instance.name.to_string()))
+        .collect();
+    segments.reverse();
+    InstancePath { absolute: false, segments }.to_string()
+}
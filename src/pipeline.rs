@@ -0,0 +1,394 @@
+// pluggable place transformations
+//
+// every conversion fix_place does is a PlacePass run in order by a Pipeline. downstream crates
+// can write their own passes and mix them with the built in ones from `passes`:
+//
+//   let pipeline = Pipeline::new()
+//       .with_pass(passes::StripClasses { classes: vec!["Sky".into()] })
+//       .with_pass(MyPass)
+//       .with_pass(passes::FoldersToModels);
+//   let report = pipeline.run(&mut dom)?;
+//
+// progress goes to a ConversionObserver. `run` logs to stdout like the cli always has, GUIs and
+// other embedders pass their own to `run_observed` (or FixPlaceOptions::observer) to show
+// progress and collect warnings without scraping output.
+use crate::mem_stats::{self, MemStats};
+use crate::pass_scope::{PassScope, PassScopes};
+use crate::verify::VerifySummary;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{Ustr, WeakDom};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::error::Error;
+use std::fmt::Write;
+
+pub type PassResult = Result<(), Box<dyn Error>>;
+
+// every hook does nothing by default, implement the ones you care about
+pub trait ConversionObserver {
+    fn on_instance_converted(&mut self, _pass: &str, _referent: Ref, _message: &str) {}
+    fn on_warning(&mut self, _pass: &str, _message: &str) {}
+    // summaries and other notes that aren't about a single instance
+    fn on_info(&mut self, _pass: &str, _message: &str) {}
+    fn on_pass_complete(&mut self, _pass: &str, _changed: usize) {}
+}
+
+// the default, prints what used to be hardcoded printlns
+pub struct LogObserver;
+
+impl ConversionObserver for LogObserver {
+    fn on_instance_converted(&mut self, _pass: &str, _referent: Ref, message: &str) {
+        println!("[legacy_place::convert] {}", message);
+    }
+
+    fn on_warning(&mut self, _pass: &str, message: &str) {
+        println!("[legacy_place::convert] {}", message);
+    }
+
+    fn on_info(&mut self, _pass: &str, message: &str) {
+        println!("[legacy_place::convert] {}", message);
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClassCounts {
+    pub modified: usize,
+    pub skipped: usize,
+    pub removed: usize,
+}
+
+// keyed by (pass name, class the instance had when the pass started)
+pub type ClassStats = BTreeMap<(String, String), ClassCounts>;
+
+// how many warnings of one kind get printed per pass before the rest are only counted
+const WARNINGS_SHOWN: usize = 3;
+
+// a warning with the instance names and numbers taken out, so "meshpart 'A' has zero
+// initialsize" and "meshpart 'B' has zero initialsize" count as the same thing
+fn warning_kind(message: &str) -> String {
+    let mut kind = String::with_capacity(message.len());
+    let mut in_quote = false;
+    let mut last_digit = false;
+    for c in message.chars() {
+        if c == '\'' {
+            if !in_quote {
+                kind.push_str("'_'");
+            }
+            in_quote = !in_quote;
+        } else if !in_quote {
+            if c.is_ascii_digit() {
+                if !last_digit {
+                    kind.push('#');
+                }
+            } else {
+                kind.push(c);
+            }
+        }
+        last_digit = c.is_ascii_digit();
+    }
+    kind
+}
+
+// LogObserver, except a warning that keeps repeating is printed a few times and then summed up
+// at the end of the pass. the default for fix_place
+#[derive(Default)]
+pub struct AggregatingLogObserver {
+    // kind -> times seen this pass, in the order first seen
+    seen: Vec<(String, usize)>,
+}
+
+impl ConversionObserver for AggregatingLogObserver {
+    fn on_instance_converted(&mut self, pass: &str, referent: Ref, message: &str) {
+        LogObserver.on_instance_converted(pass, referent, message);
+    }
+
+    fn on_warning(&mut self, pass: &str, message: &str) {
+        let kind = warning_kind(message);
+        let index = match self.seen.iter().position(|(seen, _)| *seen == kind) {
+            Some(index) => index,
+            None => {
+                self.seen.push((kind, 0));
+                self.seen.len() - 1
+            }
+        };
+        self.seen[index].1 += 1;
+        if self.seen[index].1 <= WARNINGS_SHOWN {
+            LogObserver.on_warning(pass, message);
+        }
+    }
+
+    fn on_info(&mut self, pass: &str, message: &str) {
+        LogObserver.on_info(pass, message);
+    }
+
+    fn on_pass_complete(&mut self, _pass: &str, _changed: usize) {
+        for (kind, count) in self.seen.drain(..) {
+            if count > WARNINGS_SHOWN {
+                println!(
+                    "[legacy_place::convert] ...and {} more like \"{}\"",
+                    count - WARNINGS_SHOWN,
+                    kind
+                );
+            }
+        }
+    }
+}
+
+// what a finished run left behind
+#[derive(Default, Debug)]
+pub struct RunReport {
+    pub warnings: Vec<String>,
+    // (pass name, instances changed)
+    pub changes: Vec<(String, usize)>,
+    pub class_stats: ClassStats,
+    // only with mem_stats enabled
+    pub mem_stats: Option<MemStats>,
+    // only with verify on, what reading the output back found
+    pub verify: Option<VerifySummary>,
+}
+
+impl RunReport {
+    // a warning from after the passes, while writing the output. goes to the observer and the
+    // report like a pass's would, under the stage's name
+    pub fn warn(&mut self, observer: &mut dyn ConversionObserver, stage: &str, message: impl Into<String>) {
+        let message = message.into();
+        observer.on_warning(stage, &message);
+        self.warnings.push(message);
+    }
+
+    // warnings, the verify summary and the stats table, what --report writes
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{} warning(s)", self.warnings.len());
+        for warning in &self.warnings {
+            let _ = writeln!(out, "  {}", warning);
+        }
+        if let Some(verify) = &self.verify {
+            let _ = writeln!(out, "{}", verify.to_text());
+        }
+        out.push('\n');
+        out.push_str(&self.stats_table());
+        out
+    }
+
+    // one row per pass and class, in the order the passes ran
+    pub fn stats_table(&self) -> String {
+        let order = |pass: &str| self.changes.iter().position(|(name, _)| name == pass).unwrap_or(usize::MAX);
+        let mut rows: Vec<_> = self.class_stats.iter().collect();
+        rows.sort_by_key(|((pass, class), _)| (order(pass), class.clone()));
+        if rows.is_empty() {
+            return String::from("no instances were changed\n");
+        }
+        let pass_width = rows.iter().map(|((pass, _), _)| pass.len()).max().unwrap_or(0).max(4);
+        let class_width = rows.iter().map(|((_, class), _)| class.len()).max().unwrap_or(0).max(5);
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "{:<pass_width$}  {:<class_width$}  {:>8}  {:>7}  {:>7}",
+            "pass", "class", "modified", "skipped", "removed"
+        );
+        for ((pass, class), counts) in rows {
+            let _ = writeln!(
+                out,
+                "{:<pass_width$}  {:<class_width$}  {:>8}  {:>7}  {:>7}",
+                pass, class, counts.modified, counts.skipped, counts.removed
+            );
+        }
+        out
+    }
+}
+
+// state shared by the passes of one run. passes report what they changed and anything odd
+// they ran into here instead of failing the whole conversion
+pub struct PassContext<'a> {
+    pub warnings: Vec<String>,
+    // (pass name, instances changed)
+    pub changes: Vec<(String, usize)>,
+    current_pass: String,
+    current_changes: usize,
+    // instances the current pass reported, for the per class stats
+    current_converted: Vec<Ref>,
+    current_skipped: Vec<Ref>,
+    current_scope: Option<PassScope>,
+    observer: &'a mut dyn ConversionObserver,
+}
+
+impl<'a> PassContext<'a> {
+    pub fn new(observer: &'a mut dyn ConversionObserver) -> Self {
+        Self {
+            warnings: Vec::new(),
+            changes: Vec::new(),
+            current_pass: String::new(),
+            current_changes: 0,
+            current_converted: Vec::new(),
+            current_skipped: Vec::new(),
+            current_scope: None,
+            observer,
+        }
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        self.observer.on_warning(&self.current_pass, &message);
+        self.warnings.push(message);
+    }
+
+    pub fn info(&mut self, message: impl AsRef<str>) {
+        self.observer.on_info(&self.current_pass, message.as_ref());
+    }
+
+    // counts the instance as changed and tells the observer what happened to it
+    pub fn converted(&mut self, referent: Ref, message: impl AsRef<str>) {
+        self.current_changes += 1;
+        self.current_converted.push(referent);
+        self.observer.on_instance_converted(&self.current_pass, referent, message.as_ref());
+    }
+
+    // a warning about an instance the pass had to leave alone
+    pub fn skipped(&mut self, referent: Ref, message: impl Into<String>) {
+        self.current_skipped.push(referent);
+        self.warn(message);
+    }
+
+    pub fn record_change(&mut self) {
+        self.current_changes += 1;
+    }
+
+    // false for instances the current pass's scope leaves out, see pass_scope
+    pub fn in_scope(&self, dom: &WeakDom, referent: Ref) -> bool {
+        self.current_scope.as_ref().is_none_or(|scope| scope.matches(dom, referent))
+    }
+}
+
+pub trait PlacePass {
+    fn name(&self) -> &str;
+    fn apply(&self, dom: &mut WeakDom, ctx: &mut PassContext) -> PassResult;
+    // whether apply checks PassContext::in_scope, giving a scope to a pass that doesn't fails the run
+    fn scoped(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Default)]
+pub struct Pipeline {
+    passes: Vec<Box<dyn PlacePass>>,
+    scopes: PassScopes,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_pass(mut self, pass: impl PlacePass + 'static) -> Self {
+        self.push(pass);
+        self
+    }
+
+    pub fn push(&mut self, pass: impl PlacePass + 'static) {
+        self.passes.push(Box::new(pass));
+    }
+
+    // appends all of another pipeline's passes
+    pub fn extend(&mut self, other: Pipeline) {
+        self.passes.extend(other.passes);
+        self.scopes.extend(other.scopes);
+    }
+
+    // restricts passes to some of the instances, keyed by pass name
+    pub fn with_scopes(mut self, scopes: PassScopes) -> Self {
+        self.set_scopes(scopes);
+        self
+    }
+
+    pub fn set_scopes(&mut self, scopes: PassScopes) {
+        self.scopes.extend(scopes);
+    }
+
+    pub fn pass_names(&self) -> impl Iterator<Item = &str> {
+        self.passes.iter().map(|pass| pass.name())
+    }
+
+    pub fn run(&self, dom: &mut WeakDom) -> Result<RunReport, Box<dyn Error>> {
+        self.run_observed(dom, &mut LogObserver)
+    }
+
+    pub fn run_observed(
+        &self,
+        dom: &mut WeakDom,
+        observer: &mut dyn ConversionObserver,
+    ) -> Result<RunReport, Box<dyn Error>> {
+        let mut ctx = PassContext::new(observer);
+        for name in self.scopes.keys() {
+            match self.passes.iter().find(|pass| pass.name() == name) {
+                Some(pass) if !pass.scoped() => {
+                    return Err(format!("pass '{}' works on the whole place and can't be scoped", name).into());
+                }
+                Some(_) => {}
+                None => ctx.warn(format!("there's a scope for '{}' but that pass isn't running", name)),
+            }
+        }
+        let mut class_stats = ClassStats::new();
+        let mut mem_stats = mem_stats::enabled().then(MemStats::default);
+        // every instance's class as of the start of the current pass, so a converted MeshPart is
+        // counted as a MeshPart. taken once and kept up to date from what the passes report,
+        // rather than walking the whole place again before every pass
+        let mut classes: HashMap<Ref, Ustr> = dom
+            .descendants()
+            .map(|instance| (instance.referent(), instance.class))
+            .collect();
+        for pass in &self.passes {
+            ctx.current_pass = pass.name().to_owned();
+            ctx.current_changes = 0;
+            ctx.current_scope = self.scopes.get(pass.name()).cloned();
+            let allocs_before = mem_stats::snapshot();
+            pass.apply(dom, &mut ctx)
+                .map_err(|e| format!("pass '{}' failed: {}", pass.name(), e))?;
+            if let Some(stats) = mem_stats.as_mut() {
+                stats.passes.push((pass.name().to_owned(), mem_stats::snapshot().since(&allocs_before)));
+            }
+            let changed = ctx.current_changes;
+            ctx.observer.on_pass_complete(pass.name(), changed);
+            ctx.changes.push((pass.name().to_owned(), changed));
+
+            // instances added by the pass aren't in classes, they count under the class they have now
+            let key = |classes: &HashMap<Ref, Ustr>, referent: Ref| {
+                let class = classes
+                    .get(&referent)
+                    .copied()
+                    .or_else(|| dom.get_by_ref(referent).map(|instance| instance.class))
+                    .map_or_else(|| String::from("?"), |class| class.to_string());
+                (pass.name().to_owned(), class)
+            };
+            let converted = std::mem::take(&mut ctx.current_converted);
+            let mut seen = HashSet::new();
+            for &referent in &converted {
+                if seen.insert(referent) && dom.get_by_ref(referent).is_some() {
+                    class_stats.entry(key(&classes, referent)).or_default().modified += 1;
+                }
+            }
+            for referent in std::mem::take(&mut ctx.current_skipped) {
+                class_stats.entry(key(&classes, referent)).or_default().skipped += 1;
+            }
+            classes.retain(|&referent, class| {
+                let kept = dom.get_by_ref(referent).is_some();
+                if !kept {
+                    class_stats.entry((pass.name().to_owned(), class.to_string())).or_default().removed += 1;
+                }
+                kept
+            });
+            // what the next pass starts from. only reported instances can have a new class
+            for referent in seen {
+                if let Some(instance) = dom.get_by_ref(referent) {
+                    classes.insert(referent, instance.class);
+                }
+            }
+        }
+        Ok(RunReport {
+            warnings: ctx.warnings,
+            changes: ctx.changes,
+            class_stats,
+            mem_stats,
+            verify: None,
+        })
+    }
+}
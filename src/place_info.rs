@@ -0,0 +1,285 @@
+// what a place file holds and roughly which studio saved it, read from the file itself
+//
+// nothing here goes through rbx_binary or rbx_xml, so it works on files they won't open and sees
+// property names as they were serialized rather than after rbx_dom renames them. the era is the
+// newest thing the file uses: classes, serialized property names, property types and, for binary
+// files, chunks and compression. a file can't be older than what's in it, so it's a lower bound.
+// a new place that only uses old things looks old, which is fine for picking a preset since the
+// preset only has to handle what's there.
+use crate::binary_compat::{self, SHARED_STRING_YEAR};
+use crate::dom_limits::{self, DomLimits};
+use crate::legacy_parts::CORNER_WEDGE_PART_YEAR;
+use crate::presets::Preset;
+use crate::rbxl_chunks::{self, Compression};
+use crate::xml_compat;
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::io::Cursor;
+use xml::reader::{EventReader, XmlEvent};
+
+// classes and serialized property names, with roughly when studio started saving them
+const CLASS_INTRODUCED: [(&str, u32); 22] = [
+    ("CornerWedgePart", CORNER_WEDGE_PART_YEAR),
+    ("ModuleScript", 2013),
+    ("SurfaceGui", 2013),
+    ("Folder", 2014),
+    ("UnionOperation", 2014),
+    ("NegateOperation", 2014),
+    ("ParticleEmitter", 2014),
+    ("Attachment", 2015),
+    ("MeshPart", 2016),
+    ("Accessory", 2016),
+    ("RopeConstraint", 2016),
+    ("HingeConstraint", 2016),
+    ("Beam", 2017),
+    ("UIListLayout", 2017),
+    ("PackageLink", 2018),
+    ("HumanoidDescription", 2018),
+    ("UICorner", 2020),
+    ("SurfaceAppearance", 2020),
+    ("WrapLayer", 2021),
+    ("MaterialVariant", 2022),
+    ("TextChatService", 2022),
+    ("Highlight", 2022),
+];
+
+const PROPERTY_INTRODUCED: [(&str, u32); 11] = [
+    ("CustomPhysicalProperties", 2015),
+    ("SmoothGrid", 2015),
+    ("Color3uint8", 2016),
+    ("LevelOfDetail", 2019),
+    ("AttributesSerialize", 2020),
+    ("WorldPivotData", 2021),
+    ("UniqueId", 2021),
+    ("HistoryId", 2021),
+    ("FontFace", 2022),
+    ("MaterialVariantSerialized", 2022),
+    ("Capabilities", 2023),
+];
+
+// binary places themselves, and chunks after the original INST/PROP/PRNT/END
+const BINARY_YEAR: u32 = 2013;
+const CHUNK_INTRODUCED: [(&str, u32); 3] = [("META", 2017), ("SSTR", SHARED_STRING_YEAR), ("SIGN", 2021)];
+const ZSTD_YEAR: u32 = 2022;
+// evidence lines shown under the era
+const EVIDENCE_SHOWN: usize = 8;
+const CLASSES_SHOWN: usize = 10;
+
+fn year_of(table: &[(&str, u32)], name: &str) -> Option<u32> {
+    table.iter().find(|(entry, _)| *entry == name).map(|&(_, year)| year)
+}
+
+#[derive(Default)]
+pub struct PlaceInfo {
+    pub binary: bool,
+    // the header version for binary files, the root's version attribute for xml
+    pub version: String,
+    pub instances: usize,
+    pub classes: BTreeMap<String, usize>,
+    pub meta: Vec<(String, String)>,
+    // serialized property names, types, chunks and compression, each with the year it dates
+    // the file to. only things newer than the format itself are kept
+    pub features: BTreeMap<String, u32>,
+    // where reading stopped early, what came before is still counted
+    pub error: Option<String>,
+}
+
+fn scan_binary(data: &[u8]) -> Result<PlaceInfo, Box<dyn Error>> {
+    let header = rbxl_chunks::read_file_header(data)?;
+    let mut info = PlaceInfo {
+        binary: true,
+        version: header.version.to_string(),
+        instances: header.num_instances as usize,
+        ..Default::default()
+    };
+    info.features.insert("binary format".to_owned(), BINARY_YEAR);
+    for chunk in rbxl_chunks::chunks(data) {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                info.error = Some(e.to_string());
+                break;
+            }
+        };
+        if chunk.compression == Compression::Zstd {
+            info.features.insert("zstd compressed chunks".to_owned(), ZSTD_YEAR);
+        }
+        let name = chunk.name_str();
+        if let Some(year) = year_of(&CHUNK_INTRODUCED, &name) {
+            info.features.insert(format!("{} chunk", name), year);
+        }
+        match name.as_str() {
+            "INST" => {
+                let inst = rbxl_chunks::parse_inst(&chunk.data)?;
+                *info.classes.entry(inst.class_name).or_default() += inst.referents.len();
+            }
+            "PROP" => {
+                let prop = rbxl_chunks::parse_prop_header(&chunk.data)?;
+                if let Some(year) = year_of(&PROPERTY_INTRODUCED, &prop.prop_name) {
+                    info.features.insert(format!("property {}", prop.prop_name), year);
+                }
+                let year = binary_compat::type_year(prop.type_id);
+                if year > BINARY_YEAR {
+                    info.features.insert(format!("type {}", rbxl_chunks::type_name(prop.type_id)), year);
+                }
+            }
+            "META" => info.meta = rbxl_chunks::parse_meta(&chunk.data)?,
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+fn scan_xml(data: &[u8]) -> Result<PlaceInfo, Box<dyn Error>> {
+    // xml-rs copies its namespace stack for every element, a deep enough file takes forever
+    dom_limits::check_xml_nesting(data, &DomLimits::default())?;
+    let mut info = PlaceInfo::default();
+    // element names from the root down
+    let mut stack: Vec<String> = Vec::new();
+    let mut meta_key: Option<String> = None;
+    for event in EventReader::new(Cursor::new(data)) {
+        let event = match event {
+            Ok(event) => event,
+            Err(e) => {
+                info.error = Some(e.to_string());
+                break;
+            }
+        };
+        match event {
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let attribute = |key: &str| {
+                    attributes
+                        .iter()
+                        .find(|attribute| attribute.name.local_name == key)
+                        .map(|attribute| attribute.value.clone())
+                };
+                let tag = name.local_name;
+                match tag.as_str() {
+                    "roblox" if stack.is_empty() => info.version = attribute("version").unwrap_or_default(),
+                    "Item" => {
+                        info.instances += 1;
+                        *info.classes.entry(attribute("class").unwrap_or_default()).or_default() += 1;
+                    }
+                    "Meta" => {
+                        let year = year_of(&CHUNK_INTRODUCED, "META").unwrap_or_default();
+                        info.features.insert("Meta elements".to_owned(), year);
+                        meta_key = attribute("name");
+                    }
+                    "SharedStrings" => {
+                        info.features.insert("SharedStrings section".to_owned(), SHARED_STRING_YEAR);
+                    }
+                    _ if stack.last().is_some_and(|parent| parent == "Properties") => {
+                        if let Some(property) = attribute("name")
+                            && let Some(year) = year_of(&PROPERTY_INTRODUCED, &property)
+                        {
+                            info.features.insert(format!("property {}", property), year);
+                        }
+                        if let Some(year) = xml_compat::type_year(&tag) {
+                            info.features.insert(format!("type {}", tag), year);
+                        }
+                    }
+                    _ => {}
+                }
+                stack.push(tag);
+            }
+            XmlEvent::Characters(text) => {
+                if let Some(key) = meta_key.take() {
+                    info.meta.push((key, text));
+                }
+            }
+            XmlEvent::EndElement { .. } => {
+                meta_key = None;
+                stack.pop();
+            }
+            _ => {}
+        }
+    }
+    Ok(info)
+}
+
+pub fn place_info(data: &[u8]) -> Result<PlaceInfo, Box<dyn Error>> {
+    if crate::is_binary_rbxl(data) {
+        scan_binary(data)
+    } else {
+        scan_xml(data)
+    }
+}
+
+// the oldest client preset that reads a file from `year`, or the newest preset when none does
+fn preset_for_year(year: u32) -> Preset {
+    match year {
+        year if year > 2013 => Preset::Client2016,
+        year if year > 2011 => Preset::Client2013,
+        _ => Preset::Client2011,
+    }
+}
+
+pub struct Era {
+    // none when nothing in the file dates it
+    pub year: Option<u32>,
+    // (year, what), newest first
+    pub evidence: Vec<(u32, String)>,
+    pub preset: Preset,
+}
+
+impl PlaceInfo {
+    pub fn detect_era(&self) -> Era {
+        let mut evidence: Vec<(u32, String)> = self
+            .classes
+            .keys()
+            .filter_map(|class| year_of(&CLASS_INTRODUCED, class).map(|year| (year, format!("class {}", class))))
+            .chain(self.features.iter().map(|(what, &year)| (year, what.clone())))
+            .collect();
+        evidence.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        let year = evidence.first().map(|&(year, _)| year);
+        let preset = preset_for_year(year.unwrap_or_default());
+        Era { year, evidence, preset }
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let format = if self.binary { "binary" } else { "xml" };
+        let _ = writeln!(out, "format: {}, version {}", format, self.version);
+        let _ = writeln!(out, "instances: {} of {} classes", self.instances, self.classes.len());
+        for (key, value) in &self.meta {
+            let _ = writeln!(out, "meta: {}={}", key, value);
+        }
+        let mut classes: Vec<(&String, &usize)> = self.classes.iter().collect();
+        classes.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        for (class, count) in classes.iter().take(CLASSES_SHOWN) {
+            let _ = writeln!(out, "  {:>8}  {}", count, class);
+        }
+        if let Some(error) = &self.error {
+            let _ = writeln!(out, "stopped reading early: {}", error);
+        }
+        out
+    }
+}
+
+impl Era {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        match self.year {
+            Some(year) => {
+                let _ = writeln!(out, "era: {} or later", year);
+            }
+            None => {
+                let _ = writeln!(out, "era: unknown, nothing in it is newer than 2008");
+            }
+        }
+        for (year, what) in self.evidence.iter().take(EVIDENCE_SHOWN) {
+            let _ = writeln!(out, "  {}  {}", year, what);
+        }
+        if self.evidence.len() > EVIDENCE_SHOWN {
+            let _ = writeln!(out, "  ...and {} more", self.evidence.len() - EVIDENCE_SHOWN);
+        }
+        let name = self.preset.to_possible_value().map(|value| value.get_name().to_owned()).unwrap_or_default();
+        let _ = writeln!(out, "suggested preset: {}", name);
+        if self.year.is_some_and(|year| year > 2016) {
+            let _ = writeln!(out, "  newer than every preset's client, expect a lot to be converted or dropped");
+        }
+        out
+    }
+}
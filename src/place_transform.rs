@@ -0,0 +1,254 @@
+// a whole place scaled, turned or mirrored, for building variants of a map
+//
+// everything in world space (see world_space.rs) goes through the same transform around one
+// point: mirrored first, then turned around the vertical axis, then scaled. what's relative to a
+// part (attachments, joint C0/C1, pivot offsets, mesh offsets) only gets scaled and mirrored,
+// the part's turn carries it. part sizes, file mesh scales and texture tiling are scaled with it.
+//
+// a mirrored rotation can't be stored in a CFrame, so mirrored parts are also flipped across
+// their own x axis, which blocks, wedges, cylinders and spheres look the same after. their left
+// and right surfaces, and decals and textures on those faces, swap sides to stay where they
+// were. meshes, unions and corner wedges aren't symmetric like that and come out the wrong way
+// round, they're warned about. decals aren't mirrored, so text on them stays readable.
+use crate::pipeline::PassContext;
+use crate::world_space::{self, RebaseOrigin, is_a};
+use clap::ValueEnum;
+use rbx_dom_weak::WeakDom;
+use rbx_dom_weak::types::{CFrame, Enum, Matrix3, Ref, Vector3};
+use rbx_types::Variant;
+
+// NormalId
+const RIGHT: u32 = 0;
+const LEFT: u32 = 3;
+// SpecialMesh MeshType, the only one whose Scale isn't relative to the part
+const FILE_MESH: u32 = 5;
+// every Left* surface property has a Right* one
+const SIDE_SURFACES: [&str; 4] = ["Surface", "SurfaceInput", "ParamA", "ParamB"];
+// what mirroring gets wrong
+const ASYMMETRIC: [&str; 3] = ["MeshPart", "PartOperation", "CornerWedgePart"];
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MirrorAxis {
+    // x becomes -x
+    X,
+    // z becomes -z
+    Z,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PlaceTransform {
+    pub scale: f32,
+    // degrees around the vertical axis, the way CFrame.Angles(0, angle, 0) turns
+    pub rotate: f32,
+    pub mirror: Option<MirrorAxis>,
+    pub around: RebaseOrigin,
+}
+
+impl PlaceTransform {
+    pub fn is_identity(&self) -> bool {
+        self.scale == 1.0 && self.rotate % 360.0 == 0.0 && self.mirror.is_none()
+    }
+}
+
+fn multiply(a: &Matrix3, b: &Matrix3) -> Matrix3 {
+    let row = |r: Vector3| {
+        Vector3::new(
+            r.x * b.x.x + r.y * b.y.x + r.z * b.z.x,
+            r.x * b.x.y + r.y * b.y.y + r.z * b.z.y,
+            r.x * b.x.z + r.y * b.y.z + r.z * b.z.z,
+        )
+    };
+    Matrix3::new(row(a.x), row(a.y), row(a.z))
+}
+
+fn apply(m: &Matrix3, v: Vector3) -> Vector3 {
+    let dot = |r: Vector3| r.x * v.x + r.y * v.y + r.z * v.z;
+    Vector3::new(dot(m.x), dot(m.y), dot(m.z))
+}
+
+fn flip(axis: Option<MirrorAxis>) -> Matrix3 {
+    let (x, z) = match axis {
+        Some(MirrorAxis::X) => (-1.0, 1.0),
+        Some(MirrorAxis::Z) => (1.0, -1.0),
+        None => (1.0, 1.0),
+    };
+    Matrix3::new(Vector3::new(x, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0), Vector3::new(0.0, 0.0, z))
+}
+
+struct Transform {
+    around: Vector3,
+    scale: f32,
+    // turn and mirror, what world space directions go through
+    world: Matrix3,
+    // the flip across a part's own x axis that makes a mirrored rotation a rotation again
+    local: Matrix3,
+    mirrored: bool,
+}
+
+impl Transform {
+    fn point(&self, p: Vector3) -> Vector3 {
+        let offset = apply(&self.world, Vector3::new(p.x - self.around.x, p.y - self.around.y, p.z - self.around.z));
+        Vector3::new(
+            self.around.x + offset.x * self.scale,
+            self.around.y + offset.y * self.scale,
+            self.around.z + offset.z * self.scale,
+        )
+    }
+
+    fn world_cframe(&self, cframe: &CFrame) -> CFrame {
+        let orientation = multiply(&multiply(&self.world, &cframe.orientation), &self.local);
+        CFrame::new(self.point(cframe.position), orientation)
+    }
+
+    // a vector in a part's own space
+    fn offset(&self, v: Vector3) -> Vector3 {
+        let v = apply(&self.local, v);
+        Vector3::new(v.x * self.scale, v.y * self.scale, v.z * self.scale)
+    }
+
+    fn local_cframe(&self, cframe: &CFrame) -> CFrame {
+        let orientation = multiply(&multiply(&self.local, &cframe.orientation), &self.local);
+        CFrame::new(self.offset(cframe.position), orientation)
+    }
+}
+
+fn scaled(v: Vector3, scale: f32) -> Vector3 {
+    Vector3::new(v.x * scale, v.y * scale, v.z * scale)
+}
+
+// cframes held relative to a part, or an attachment's parent
+fn is_local_cframe(class: &str, property: &str) -> bool {
+    match property {
+        "C0" | "C1" => is_a(class, "JointInstance"),
+        "CFrame" => is_a(class, "Attachment"),
+        "PivotOffset" => is_a(class, "BasePart"),
+        _ => false,
+    }
+}
+
+// the number of values changed
+fn transform_instance(dom: &mut WeakDom, referent: Ref, transform: &Transform) -> usize {
+    let Some(instance) = dom.get_by_ref_mut(referent) else { return 0 };
+    let class = instance.class;
+    let file_mesh = matches!(instance.properties.get(&"MeshType".into()), Some(Variant::Enum(mesh_type)) if mesh_type.to_u32() == FILE_MESH);
+    let mut count = 0;
+    for (name, value) in instance.properties.iter_mut() {
+        let name = name.as_str();
+        let changed = match value {
+            Variant::CFrame(cframe) if world_space::world_space(&class, name) => {
+                *cframe = transform.world_cframe(cframe);
+                true
+            }
+            Variant::OptionalCFrame(Some(cframe)) if world_space::world_space(&class, name) => {
+                *cframe = transform.world_cframe(cframe);
+                true
+            }
+            Variant::Vector3(position) if world_space::world_space(&class, name) => {
+                *position = transform.point(*position);
+                true
+            }
+            Variant::CFrame(cframe) if is_local_cframe(&class, name) => {
+                *cframe = transform.local_cframe(cframe);
+                true
+            }
+            Variant::Vector3(size) if name == "Size" && class != "Terrain" && is_a(&class, "BasePart") => {
+                *size = scaled(*size, transform.scale);
+                true
+            }
+            Variant::Vector3(offset) if name == "Offset" && is_a(&class, "DataModelMesh") => {
+                *offset = transform.offset(*offset);
+                true
+            }
+            // a file mesh's scale is against the mesh, the other kinds are against the part
+            Variant::Vector3(scale) if name == "Scale" && file_mesh => {
+                *scale = scaled(*scale, transform.scale);
+                true
+            }
+            Variant::Float32(studs)
+                if matches!(name, "StudsPerTileU" | "StudsPerTileV" | "OffsetStudsU" | "OffsetStudsV") && is_a(&class, "Texture") =>
+            {
+                *studs *= transform.scale;
+                true
+            }
+            Variant::Enum(face) if name == "Face" && transform.mirrored && matches!(face.to_u32(), RIGHT | LEFT) => {
+                *face = Enum::from_u32(RIGHT + LEFT - face.to_u32());
+                true
+            }
+            _ => false,
+        };
+        count += changed as usize;
+    }
+    if transform.mirrored && is_a(&class, "BasePart") {
+        for suffix in SIDE_SURFACES {
+            let (left, right) = (format!("Left{}", suffix), format!("Right{}", suffix));
+            let left_value = instance.properties.remove(&left.as_str().into());
+            let right_value = instance.properties.remove(&right.as_str().into());
+            count += (left_value != right_value) as usize;
+            if let Some(value) = left_value {
+                instance.properties.insert(right.as_str().into(), value);
+            }
+            if let Some(value) = right_value {
+                instance.properties.insert(left.as_str().into(), value);
+            }
+        }
+    }
+    count
+}
+
+pub fn transform_place(dom: &mut WeakDom, place_transform: &PlaceTransform, ctx: &mut PassContext) {
+    if place_transform.is_identity() {
+        return;
+    }
+    let Some(around) = world_space::origin_point(dom, place_transform.around) else {
+        ctx.warn("no parts in the workspace, there's no middle to transform around");
+        return;
+    };
+    let (sin, cos) = place_transform.rotate.to_radians().sin_cos();
+    // right angles exactly, so a grid built map stays on its grid
+    let (sin, cos) = if place_transform.rotate % 90.0 == 0.0 { (sin.round(), cos.round()) } else { (sin, cos) };
+    let turn = Matrix3::new(Vector3::new(cos, 0.0, sin), Vector3::new(0.0, 1.0, 0.0), Vector3::new(-sin, 0.0, cos));
+    let mirrored = place_transform.mirror.is_some();
+    let transform = Transform {
+        around,
+        scale: place_transform.scale,
+        world: multiply(&turn, &flip(place_transform.mirror)),
+        local: flip(place_transform.mirror.map(|_| MirrorAxis::X)),
+        mirrored,
+    };
+
+    // the whole place like rebase-origin, models in storage are saved where they go in the world
+    let refs: Vec<Ref> = dom.descendants().map(|instance| instance.referent()).collect();
+    let count: usize = refs.into_iter().map(|referent| transform_instance(dom, referent, &transform)).sum();
+    ctx.info(format!(
+        "changed {} values, scaled by {}, turned {} degrees{} around ({}, {}, {})",
+        count,
+        place_transform.scale,
+        place_transform.rotate,
+        match place_transform.mirror {
+            Some(MirrorAxis::X) => ", mirrored on x",
+            Some(MirrorAxis::Z) => ", mirrored on z",
+            None => "",
+        },
+        around.x,
+        around.y,
+        around.z
+    ));
+    if count > 0 {
+        ctx.record_change();
+    }
+
+    if mirrored {
+        let asymmetric = dom.descendants().filter(|instance| ASYMMETRIC.contains(&instance.class.as_str())).count()
+            + dom
+                .descendants()
+                .filter(|instance| matches!(instance.properties.get(&"MeshType".into()), Some(Variant::Enum(mesh_type)) if mesh_type.to_u32() == FILE_MESH))
+                .count();
+        if asymmetric > 0 {
+            ctx.warn(format!(
+                "{} meshes, unions and corner wedges can't be mirrored, they're in place but the wrong way round",
+                asymmetric
+            ));
+        }
+    }
+    world_space::warn_fixed_positions(dom, ctx);
+}
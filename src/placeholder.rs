@@ -0,0 +1,144 @@
+// stand-in meshes, the oriented bounding box of the real one
+//
+// distant props and meshes that didn't convert well can be swapped for their box, which is
+// 8 vertices and 12 triangles however big the original was. the box is along the mesh's
+// principal axes (the covariance eigenvectors of its vertices), so long thin things rotated in
+// the mesh don't get a box much bigger than they are. uvs map the box's two longest sides to
+// 0..1, which puts a texture on it roughly where it was.
+use crate::error::{ConversionError, Result};
+use crate::mesh_types::{DEFAULT_TANGENT, IntermediateMesh, IntermediateVertex, WHITE};
+
+fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+fn normalized(a: [f32; 3]) -> [f32; 3] {
+    let length = dot(a, a).sqrt();
+    if length > 0.0 { a.map(|c| c / length) } else { [0.0, 1.0, 0.0] }
+}
+
+// eigenvectors of a symmetric 3x3 matrix by jacobi rotations, as the columns' rows
+fn eigenvectors(mut m: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..32 {
+        let (mut p, mut q) = (0, 1);
+        for (i, j) in [(0, 2), (1, 2)] {
+            if m[i][j].abs() > m[p][q].abs() {
+                (p, q) = (i, j);
+            }
+        }
+        if m[p][q].abs() < 1e-12 {
+            break;
+        }
+        let theta = (m[q][q] - m[p][p]) / (2.0 * m[p][q]);
+        let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+        let (c, s) = (1.0 / (t * t + 1.0).sqrt(), t / (t * t + 1.0).sqrt());
+        for row in &mut m {
+            let (mkp, mkq) = (row[p], row[q]);
+            row[p] = c * mkp - s * mkq;
+            row[q] = s * mkp + c * mkq;
+        }
+        let (row_p, row_q) = (m[p], m[q]);
+        m[p] = [0, 1, 2].map(|k| c * row_p[k] - s * row_q[k]);
+        m[q] = [0, 1, 2].map(|k| s * row_p[k] + c * row_q[k]);
+        for row in &mut v {
+            let (vp, vq) = (row[p], row[q]);
+            row[p] = c * vp - s * vq;
+            row[q] = s * vp + c * vq;
+        }
+    }
+    [0, 1, 2].map(|column| [v[0][column], v[1][column], v[2][column]])
+}
+
+// center, right handed unit axes and half extents along them
+pub struct OrientedBox {
+    pub center: [f32; 3],
+    pub axes: [[f32; 3]; 3],
+    pub half_extents: [f32; 3],
+}
+
+pub fn oriented_box(mesh: &IntermediateMesh) -> Result<OrientedBox> {
+    if mesh.vertices.is_empty() {
+        return Err(ConversionError::NoMeshData);
+    }
+    let count = mesh.vertices.len() as f64;
+    let mut mean = [0.0f64; 3];
+    for vertex in &mesh.vertices {
+        for (sum, c) in mean.iter_mut().zip(vertex.pos) {
+            *sum += c as f64 / count;
+        }
+    }
+    let mut covariance = [[0.0f64; 3]; 3];
+    for vertex in &mesh.vertices {
+        let d = [0, 1, 2].map(|axis| vertex.pos[axis] as f64 - mean[axis]);
+        for i in 0..3 {
+            for j in 0..3 {
+                covariance[i][j] += d[i] * d[j] / count;
+            }
+        }
+    }
+    let vectors = eigenvectors(covariance).map(|vector| normalized(vector.map(|c| c as f32)));
+    let axes = [vectors[0], vectors[1], normalized(cross(vectors[0], vectors[1]))];
+
+    let mut low = [f32::INFINITY; 3];
+    let mut high = [f32::NEG_INFINITY; 3];
+    for vertex in &mesh.vertices {
+        for (axis, direction) in axes.iter().enumerate() {
+            let along = dot(vertex.pos, *direction);
+            low[axis] = low[axis].min(along);
+            high[axis] = high[axis].max(along);
+        }
+    }
+    let middle = [0, 1, 2].map(|axis| (low[axis] + high[axis]) / 2.0);
+    let center = [0, 1, 2].map(|c| (0..3).map(|axis| axes[axis][c] * middle[axis]).sum());
+    Ok(OrientedBox {
+        center,
+        axes,
+        half_extents: [0, 1, 2].map(|axis| (high[axis] - low[axis]) / 2.0),
+    })
+}
+
+// corner index bits: 1 = +axis 0, 2 = +axis 1, 4 = +axis 2. counter clockwise from outside
+const BOX_FACES: [[u32; 3]; 12] = [
+    [0, 6, 2], [0, 4, 6], // -0
+    [1, 7, 5], [1, 3, 7], // +0
+    [0, 5, 4], [0, 1, 5], // -1
+    [2, 7, 3], [2, 6, 7], // +1
+    [0, 3, 1], [0, 2, 3], // -2
+    [4, 7, 6], [4, 5, 7], // +2
+];
+
+pub fn placeholder_mesh(mesh: &IntermediateMesh) -> Result<IntermediateMesh> {
+    let bounds = oriented_box(mesh)?;
+    // u and v run along the two longest sides
+    let mut by_size = [0, 1, 2];
+    by_size.sort_by(|&a, &b| bounds.half_extents[b].total_cmp(&bounds.half_extents[a]));
+    let vertices = (0..8)
+        .map(|corner| {
+            let signs = [0, 1, 2].map(|axis| if corner & (1 << axis) != 0 { 1.0 } else { -1.0 });
+            let offset: [f32; 3] = [0, 1, 2].map(|c| {
+                (0..3)
+                    .map(|axis| bounds.axes[axis][c] * bounds.half_extents[axis] * signs[axis])
+                    .sum()
+            });
+            let normal = normalized([0, 1, 2].map(|c| (0..3).map(|axis| bounds.axes[axis][c] * signs[axis]).sum()));
+            IntermediateVertex {
+                pos: [0, 1, 2].map(|c| bounds.center[c] + offset[c]),
+                normal,
+                uv: [(signs[by_size[0]] + 1.0) / 2.0, (1.0 - signs[by_size[1]]) / 2.0],
+                color: WHITE,
+                tangent: DEFAULT_TANGENT,
+            }
+        })
+        .collect();
+    Ok(IntermediateMesh {
+        vertices,
+        faces: BOX_FACES.to_vec(),
+        skin: None,
+        facs: None,
+    })
+}
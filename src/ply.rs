@@ -0,0 +1,351 @@
+// .ply in and out, the simple format that keeps per vertex color
+//
+// ascii and binary little endian are read, with any element and property types; only vertex and
+// face are used. vertices take x/y/z, nx/ny/nz, s/t (or u/v, texture_u/texture_v) and
+// red/green/blue/alpha, as bytes or as 0-1 floats. faces are vertex_indices (or vertex_index)
+// lists, fanned into triangles. v is flipped like obj's is. a ply without normals gets them
+// worked out the way obj import does. writing always gives every property, colors as bytes.
+use crate::error::{ConversionError, Result};
+use crate::importer::{ImportCleanup, clean_faces};
+use crate::mesh_normals::{DEFAULT_SMOOTH_ANGLE, recompute_normals};
+use crate::mesh_tangents::generate_tangents;
+use crate::mesh_types::{DEFAULT_TANGENT, IntermediateMesh, IntermediateVertex};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use std::fmt::Write as FmtWrite;
+use std::io::{Cursor, Write};
+
+fn ply_err(message: impl Into<String>) -> ConversionError {
+    ConversionError::Unsupported(format!("ply: {}", message.into()))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+}
+
+#[derive(Clone, Copy)]
+enum Scalar {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Self::I8,
+            "uchar" | "uint8" => Self::U8,
+            "short" | "int16" => Self::I16,
+            "ushort" | "uint16" => Self::U16,
+            "int" | "int32" => Self::I32,
+            "uint" | "uint32" => Self::U32,
+            "float" | "float32" => Self::F32,
+            "double" | "float64" => Self::F64,
+            _ => return Err(ply_err(format!("unknown property type '{}'", name))),
+        })
+    }
+
+    fn is_float(self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+}
+
+enum Property {
+    Scalar { name: String, kind: Scalar },
+    List { name: String, count: Scalar, item: Scalar },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Self::Scalar { name, .. } | Self::List { name, .. } => name,
+        }
+    }
+}
+
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+    // where the body starts
+    length: usize,
+}
+
+fn read_header(data: &[u8]) -> Result<Header> {
+    let end = data
+        .windows(b"end_header".len())
+        .position(|window| window == b"end_header")
+        .ok_or_else(|| ply_err("no end_header"))?;
+    let length = data[end..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(data.len(), |newline| end + newline + 1);
+    let text = std::str::from_utf8(&data[..end]).map_err(|_| ply_err("the header isn't text"))?;
+    let mut lines = text.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(ply_err("doesn't start with 'ply'"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", "ascii", ..] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", ..] => format = Some(Format::BinaryLittleEndian),
+            ["format", other, ..] => return Err(ply_err(format!("{} isn't supported, only ascii and binary_little_endian", other))),
+            ["element", name, count] => elements.push(Element {
+                name: (*name).to_owned(),
+                count: count.parse().map_err(|_| ply_err(format!("bad count for element {}", name)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements.last_mut().ok_or_else(|| ply_err("property before any element"))?;
+                element.properties.push(Property::List {
+                    name: (*name).to_owned(),
+                    count: Scalar::parse(count)?,
+                    item: Scalar::parse(item)?,
+                });
+            }
+            ["property", kind, name] => {
+                let element = elements.last_mut().ok_or_else(|| ply_err("property before any element"))?;
+                element.properties.push(Property::Scalar {
+                    name: (*name).to_owned(),
+                    kind: Scalar::parse(kind)?,
+                });
+            }
+            _ => {}
+        }
+    }
+    let format = format.ok_or_else(|| ply_err("no format line"))?;
+    Ok(Header { format, elements, length })
+}
+
+// values come out as f64 whatever they're stored as, which holds every int type exactly
+struct Body<'a> {
+    format: Format,
+    cursor: Cursor<&'a [u8]>,
+    words: std::str::SplitAsciiWhitespace<'a>,
+}
+
+impl<'a> Body<'a> {
+    fn new(format: Format, body: &'a [u8]) -> Result<Self> {
+        let text = match format {
+            Format::Ascii => std::str::from_utf8(body).map_err(|_| ply_err("ascii body isn't text"))?,
+            Format::BinaryLittleEndian => "",
+        };
+        Ok(Self {
+            format,
+            cursor: Cursor::new(body),
+            words: text.split_ascii_whitespace(),
+        })
+    }
+
+    fn read(&mut self, kind: Scalar) -> Result<f64> {
+        if self.format == Format::Ascii {
+            let word = self.words.next().ok_or_else(|| ply_err("the file ends early"))?;
+            return word.parse().map_err(|_| ply_err(format!("'{}' isn't a number", word)));
+        }
+        let cursor = &mut self.cursor;
+        Ok(match kind {
+            Scalar::I8 => cursor.read_i8()? as f64,
+            Scalar::U8 => cursor.read_u8()? as f64,
+            Scalar::I16 => cursor.read_i16::<LittleEndian>()? as f64,
+            Scalar::U16 => cursor.read_u16::<LittleEndian>()? as f64,
+            Scalar::I32 => cursor.read_i32::<LittleEndian>()? as f64,
+            Scalar::U32 => cursor.read_u32::<LittleEndian>()? as f64,
+            Scalar::F32 => cursor.read_f32::<LittleEndian>()? as f64,
+            Scalar::F64 => cursor.read_f64::<LittleEndian>()?,
+        })
+    }
+}
+
+fn position_of(element: &Element, names: &[&str]) -> Option<usize> {
+    element.properties.iter().position(|property| names.contains(&property.name()))
+}
+
+fn read_vertices(element: &Element, body: &mut Body) -> Result<(Vec<IntermediateVertex>, bool)> {
+    let slot = |names: &[&str]| position_of(element, names);
+    let [x, y, z] = [slot(&["x"]), slot(&["y"]), slot(&["z"])];
+    let normal = [slot(&["nx"]), slot(&["ny"]), slot(&["nz"])];
+    let uv = [slot(&["s", "u", "texture_u"]), slot(&["t", "v", "texture_v"])];
+    let color = [slot(&["red", "r"]), slot(&["green", "g"]), slot(&["blue", "b"]), slot(&["alpha", "a"])];
+    let (Some(x), Some(y), Some(z)) = (x, y, z) else {
+        return Err(ply_err("vertices have no x, y and z"));
+    };
+    let has_normals = normal.iter().all(Option::is_some);
+    let mut values = vec![0.0; element.properties.len()];
+    let mut vertices = Vec::with_capacity(element.count.min(body.cursor.get_ref().len()));
+    for _ in 0..element.count {
+        for (value, property) in values.iter_mut().zip(&element.properties) {
+            *value = match property {
+                Property::Scalar { kind, .. } => body.read(*kind)?,
+                // nothing uses a list on a vertex, it's read past
+                Property::List { count, item, .. } => {
+                    let count = body.read(*count)? as usize;
+                    for _ in 0..count {
+                        body.read(*item)?;
+                    }
+                    0.0
+                }
+            };
+        }
+        let get = |slot: Option<usize>, default: f64| slot.map_or(default, |slot| values[slot]);
+        let channel = |slot: Option<usize>| match slot.map(|slot| &element.properties[slot]) {
+            Some(Property::Scalar { kind, .. }) if kind.is_float() => (values[slot.unwrap_or(0)].clamp(0.0, 1.0) * 255.0).round() as u8,
+            Some(_) => values[slot.unwrap_or(0)].clamp(0.0, 255.0) as u8,
+            None => 255,
+        };
+        vertices.push(IntermediateVertex {
+            pos: [values[x] as f32, values[y] as f32, values[z] as f32],
+            normal: if has_normals { normal.map(|slot| get(slot, 0.0) as f32) } else { [0.0, 1.0, 0.0] },
+            uv: [get(uv[0], 0.0) as f32, 1.0 - get(uv[1], 0.0) as f32],
+            color: color.map(channel),
+            tangent: DEFAULT_TANGENT,
+        });
+    }
+    Ok((vertices, has_normals))
+}
+
+fn read_faces(element: &Element, body: &mut Body, vertex_count: usize, faces: &mut Vec<[u32; 3]>) -> Result<()> {
+    let indices_slot = position_of(element, &["vertex_indices", "vertex_index"]);
+    let mut corners = Vec::new();
+    for _ in 0..element.count {
+        for (slot, property) in element.properties.iter().enumerate() {
+            match property {
+                Property::Scalar { kind, .. } => {
+                    body.read(*kind)?;
+                }
+                Property::List { count, item, .. } => {
+                    let count = body.read(*count)? as usize;
+                    corners.clear();
+                    for _ in 0..count {
+                        corners.push(body.read(*item)?);
+                    }
+                    if Some(slot) != indices_slot {
+                        continue;
+                    }
+                    if let Some(bad) = corners.iter().find(|&&index| index < 0.0 || index as usize >= vertex_count) {
+                        return Err(ply_err(format!("a face uses vertex {} which isn't there", bad)));
+                    }
+                    for i in 1..corners.len().saturating_sub(1) {
+                        faces.push([corners[0] as u32, corners[i] as u32, corners[i + 1] as u32]);
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// skips an element that isn't vertices or faces
+fn skip_element(element: &Element, body: &mut Body) -> Result<()> {
+    for _ in 0..element.count {
+        for property in &element.properties {
+            match property {
+                Property::Scalar { kind, .. } => {
+                    body.read(*kind)?;
+                }
+                Property::List { count, item, .. } => {
+                    let count = body.read(*count)? as usize;
+                    for _ in 0..count {
+                        body.read(*item)?;
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// with what was dropped or worked out, like obj import's
+pub fn ply_to_intermediate(data: &[u8]) -> Result<(IntermediateMesh, ImportCleanup)> {
+    let header = read_header(data)?;
+    let mut body = Body::new(header.format, &data[header.length..])?;
+    let mut vertices = None;
+    let mut faces = Vec::new();
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => vertices = Some(read_vertices(element, &mut body)?),
+            "face" => {
+                let count = vertices.as_ref().map_or(0, |(vertices, _): &(Vec<IntermediateVertex>, bool)| vertices.len());
+                read_faces(element, &mut body, count, &mut faces)?;
+            }
+            _ => skip_element(element, &mut body)?,
+        }
+    }
+    let (vertices, has_normals) = vertices.ok_or_else(|| ply_err("no vertex element"))?;
+    let mut mesh = IntermediateMesh {
+        vertices,
+        faces,
+        skin: None,
+        facs: None,
+    };
+    let mut cleanup = clean_faces(&mut mesh);
+    if mesh.faces.is_empty() {
+        return Err(ConversionError::NoMeshData);
+    }
+    if has_normals {
+        generate_tangents(&mut mesh);
+    } else {
+        cleanup.missing_normals = mesh.vertices.len();
+        recompute_normals(&mut mesh, DEFAULT_SMOOTH_ANGLE);
+    }
+    Ok((mesh, cleanup))
+}
+
+pub fn mesh_to_ply(mesh: &IntermediateMesh, binary: bool) -> Result<Vec<u8>> {
+    let mut header = String::new();
+    let format = if binary { "binary_little_endian" } else { "ascii" };
+    let fmt_err = |_| ply_err("couldn't write the header");
+    writeln!(header, "ply\nformat {} 1.0\ncomment written by {}", format, env!("CARGO_PKG_NAME")).map_err(fmt_err)?;
+    writeln!(header, "element vertex {}", mesh.vertices.len()).map_err(fmt_err)?;
+    for name in ["x", "y", "z", "nx", "ny", "nz", "s", "t"] {
+        writeln!(header, "property float {}", name).map_err(fmt_err)?;
+    }
+    for name in ["red", "green", "blue", "alpha"] {
+        writeln!(header, "property uchar {}", name).map_err(fmt_err)?;
+    }
+    writeln!(header, "element face {}", mesh.faces.len()).map_err(fmt_err)?;
+    writeln!(header, "property list uchar uint vertex_indices\nend_header").map_err(fmt_err)?;
+
+    let mut out = header.into_bytes();
+    for vertex in &mesh.vertices {
+        let floats = [
+            vertex.pos[0], vertex.pos[1], vertex.pos[2],
+            vertex.normal[0], vertex.normal[1], vertex.normal[2],
+            vertex.uv[0], 1.0 - vertex.uv[1],
+        ];
+        if binary {
+            for value in floats {
+                out.write_f32::<LittleEndian>(value)?;
+            }
+            out.write_all(&vertex.color)?;
+        } else {
+            let floats: Vec<String> = floats.iter().map(|value| value.to_string()).collect();
+            let [r, g, b, a] = vertex.color;
+            writeln!(out, "{} {} {} {} {}", floats.join(" "), r, g, b, a)?;
+        }
+    }
+    for face in &mesh.faces {
+        if binary {
+            out.write_u8(3)?;
+            for &index in face {
+                out.write_u32::<LittleEndian>(index)?;
+            }
+        } else {
+            writeln!(out, "3 {} {} {}", face[0], face[1], face[2])?;
+        }
+    }
+    Ok(out)
+}
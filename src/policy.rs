@@ -0,0 +1,106 @@
+// --fail-if rules, checked against the place once every pass has run
+//
+//   class=LocalScript        any LocalScript left
+//   source=loadstring        any script whose Source contains "loadstring"
+//   source:Script=getfenv    same, but only Scripts
+//   path=Workspace.**.Tool   anything the instance path matches, see path.rs
+//
+// source matching is plain substring matching. a place that breaks a rule fails the whole conversion
+// so nothing gets written and the cli exits non-zero.
+use crate::path::{path_of, InstancePath};
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{Instance, WeakDom};
+use rbx_types::Variant;
+use std::collections::HashSet;
+use std::fmt;
+use std::str::FromStr;
+
+// violations listed in the error before the rest are only counted
+const VIOLATIONS_SHOWN: usize = 10;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum FailRule {
+    Class(String),
+    Source { class: Option<String>, text: String },
+    Path(InstancePath),
+}
+
+impl FromStr for FailRule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((kind, value)) = s.split_once('=') else {
+            return Err(format!("expected class=NAME, source=TEXT, source:CLASS=TEXT or path=PATH, got '{}'", s));
+        };
+        if value.is_empty() {
+            return Err(format!("nothing to match in '{}'", s));
+        }
+        match kind.trim().split_once(':') {
+            None if kind.trim() == "class" => Ok(Self::Class(value.trim().to_owned())),
+            None if kind.trim() == "source" => Ok(Self::Source { class: None, text: value.to_owned() }),
+            None if kind.trim() == "path" => Ok(Self::Path(value.parse()?)),
+            Some(("source", class)) if !class.is_empty() => Ok(Self::Source {
+                class: Some(class.to_owned()),
+                text: value.to_owned(),
+            }),
+            _ => Err(format!("unknown rule '{}', expected class, source, source:CLASS or path", kind)),
+        }
+    }
+}
+
+impl fmt::Display for FailRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Class(class) => write!(f, "class={}", class),
+            Self::Source { class: None, text } => write!(f, "source={}", text),
+            Self::Source { class: Some(class), text } => write!(f, "source:{}={}", class, text),
+            Self::Path(path) => write!(f, "path={}", path),
+        }
+    }
+}
+
+impl FailRule {
+    // `resolved` is what the rule's path matches, paths are resolved once up front
+    fn matches(&self, instance: &Instance, resolved: &HashSet<Ref>) -> bool {
+        match self {
+            Self::Class(class) => instance.class == class.as_str(),
+            Self::Source { class, text } => {
+                class.as_ref().is_none_or(|class| instance.class == class.as_str())
+                    && matches!(instance.properties.get(&"Source".into()), Some(Variant::String(source)) if source.contains(text.as_str()))
+            }
+            Self::Path(_) => resolved.contains(&instance.referent()),
+        }
+    }
+}
+
+// every (rule, instance path) the place breaks
+pub fn check(dom: &WeakDom, rules: &[FailRule]) -> Vec<(String, String)> {
+    let resolved: Vec<HashSet<Ref>> = rules
+        .iter()
+        .map(|rule| match rule {
+            FailRule::Path(path) => path.resolve(dom, dom.root_ref()).into_iter().collect(),
+            _ => HashSet::new(),
+        })
+        .collect();
+    let mut violations = Vec::new();
+    for instance in dom.descendants() {
+        for (rule, resolved) in rules.iter().zip(&resolved) {
+            if rule.matches(instance, resolved) {
+                violations.push((rule.to_string(), path_of(dom, instance.referent())));
+            }
+        }
+    }
+    violations
+}
+
+pub fn describe(violations: &[(String, String)]) -> String {
+    let mut listed: Vec<String> = violations
+        .iter()
+        .take(VIOLATIONS_SHOWN)
+        .map(|(rule, path)| format!("{} breaks {}", path, rule))
+        .collect();
+    if violations.len() > VIOLATIONS_SHOWN {
+        listed.push(format!("and {} more", violations.len() - VIOLATIONS_SHOWN));
+    }
+    format!("{} --fail-if violation(s): {}", violations.len(), listed.join(", "))
+}
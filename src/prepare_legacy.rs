@@ -0,0 +1,265 @@
+// prepare-legacy, a place and everything it loads by id made ready for an old client in one go
+//
+// fix-place runs with the given options (a preset at least) plus a localize-assets pass, which
+// fetches every mesh, texture and sound the place references by id and writes it into a content
+// folder the client can load it from with rbxasset://:
+//
+//   out_dir/<stem>.rbxl(x)                       the fixed place
+//   out_dir/content/localized/meshes/<id>.mesh   converted to the target mesh version
+//   out_dir/content/localized/textures/<id>.png  png or jpg, shrunk past max_texture_size
+//   out_dir/content/localized/sounds/<id>.ogg    ogg vorbis, mp3 or wav as they were
+//   out_dir/content-manifest.json                sha256 of every file, see content.rs
+//
+// the content folder goes over the client's own. with a launcher given the place and assets go
+// where it loads them from instead, see launcher.rs. there's no audio decoder in here, so opus and
+// anything else old clients can't play is left pointing at its id with a warning, as are gifs.
+// with the client's content dir given, every rbxasset:// reference is checked to be in one or
+// the other.
+use crate::asset_source::AssetSource;
+use crate::asset_urls;
+use crate::content::{self, ContentReport};
+use crate::content_uri::{self, ContentUri};
+use crate::launcher::{AssetLayout, Launcher, CONTENT_LAYOUT};
+use crate::pipeline::{PassContext, PassResult, RunReport};
+use crate::{filemesh, passes, serialize_mesh, sniff, FixPlaceOptions, RobloxMeshVersion};
+use image::ImageFormat;
+use rbx_dom_weak::WeakDom;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+pub const DEFAULT_MAX_TEXTURE_SIZE: u32 = 1024;
+const MANIFEST_NAME: &str = "content-manifest.json";
+const MESH_EXTENSIONS: [&str; 2] = ["mesh", ""];
+const TEXTURE_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", ""];
+const SOUND_EXTENSIONS: [&str; 4] = ["ogg", "mp3", "wav", ""];
+
+pub struct LocalizeOptions {
+    pub source: AssetSource,
+    // the package dir being filled, the layout says where under it assets go
+    pub out_dir: PathBuf,
+    pub layout: &'static AssetLayout,
+    pub mesh_version: RobloxMeshVersion,
+    // textures bigger than this on either side are scaled down to fit
+    pub max_texture_size: u32,
+}
+
+#[derive(Default)]
+struct LocalizeCounts {
+    meshes: usize,
+    textures: usize,
+    resized: usize,
+    sounds: usize,
+    failed: usize,
+}
+
+// what a file written for an asset is, the folder it goes in and its bytes
+struct Localized {
+    kind: &'static str,
+    extension: &'static str,
+    data: Vec<u8>,
+    resized: bool,
+}
+
+fn shrink_texture(data: &[u8], max_size: u32) -> Result<Option<Vec<u8>>, Box<dyn Error>> {
+    let image = image::load_from_memory(data)?;
+    if image.width() <= max_size && image.height() <= max_size {
+        return Ok(None);
+    }
+    let resized = image.resize(max_size, max_size, image::imageops::FilterType::Triangle);
+    let mut out = Cursor::new(Vec::new());
+    resized.write_to(&mut out, ImageFormat::Png)?;
+    Ok(Some(out.into_inner()))
+}
+
+// the asset made into something the client reads, from the kind of property it was in
+fn convert_asset(data: Vec<u8>, asset_type: &str, options: &LocalizeOptions) -> Result<Localized, Box<dyn Error>> {
+    // assets are sometimes served still compressed
+    let data = if data.starts_with(&sniff::ZSTD_MAGIC) { zstd::stream::decode_all(data.as_slice())? } else { data };
+    let sniffed = sniff::sniff(&data);
+    match (asset_type, sniffed.extension) {
+        ("mesh", "mesh") => {
+            let mesh = filemesh::parse_filemesh(&data)?;
+            let data = serialize_mesh(&mesh, options.mesh_version)?;
+            Ok(Localized { kind: "meshes", extension: "mesh", data, resized: false })
+        }
+        ("texture", "png" | "jpg") => match shrink_texture(&data, options.max_texture_size)? {
+            Some(data) => Ok(Localized { kind: "textures", extension: "png", data, resized: true }),
+            None => Ok(Localized { kind: "textures", extension: sniffed.extension, data, resized: false }),
+        },
+        ("sound", _) if sniffed.kind == "ogg opus" => Err("it's opus, which old clients can't play".into()),
+        ("sound", "ogg" | "mp3" | "wav") => {
+            Ok(Localized { kind: "sounds", extension: sniffed.extension, data, resized: false })
+        }
+        _ => Err(format!("expected a {}, it's {}", asset_type, sniffed.kind).into()),
+    }
+}
+
+fn localize_asset(id: u64, asset_type: &str, options: &LocalizeOptions) -> Result<(Localized, String), Box<dyn Error>> {
+    let extensions: &[&str] = match asset_type {
+        "mesh" => &MESH_EXTENSIONS,
+        "texture" => &TEXTURE_EXTENSIONS,
+        _ => &SOUND_EXTENSIONS,
+    };
+    let data = options.source.fetch(id, extensions)?;
+    let localized = convert_asset(data, asset_type, options)?;
+    let relative = format!("{}/{}.{}", options.layout.folder(localized.kind), id, localized.extension);
+    Ok((localized, relative))
+}
+
+// every mesh, texture and sound referenced by id written into the content dir and pointed at
+// with rbxasset://. an asset that can't be fetched or converted keeps its id and gets a warning
+pub fn localize_assets(dom: &mut WeakDom, options: &LocalizeOptions, ctx: &mut PassContext) -> PassResult {
+    let mut counts = LocalizeCounts::default();
+    // id -> where it went, or None when it failed, so every asset is fetched and warned about once
+    let mut done: HashMap<u64, Option<ContentUri>> = HashMap::new();
+    let mut write_error = None;
+    let changes = content_uri::rewrite_content(dom, |prop_name, uri| {
+        let asset_type = asset_urls::asset_type(prop_name);
+        if !matches!(asset_type, "mesh" | "texture" | "sound") || write_error.is_some() {
+            return None;
+        }
+        let id = uri.asset_id()?;
+        if let Some(localized) = done.get(&id) {
+            return localized.clone();
+        }
+        let localized = match localize_asset(id, asset_type, options) {
+            Ok((localized, relative)) => {
+                let path = options.out_dir.join(options.layout.asset_dir).join(&relative);
+                let written = path.parent().map_or(Ok(()), fs::create_dir_all).and_then(|_| fs::write(&path, &localized.data));
+                if let Err(e) = written {
+                    write_error = Some(format!("couldn't write {}: {}", path.display(), e));
+                    return None;
+                }
+                match localized.kind {
+                    "meshes" => counts.meshes += 1,
+                    "textures" => counts.textures += 1,
+                    _ => counts.sounds += 1,
+                }
+                if localized.resized {
+                    counts.resized += 1;
+                }
+                Some(ContentUri::Local(format!("{}/{}", options.layout.url_base, relative)))
+            }
+            Err(e) => {
+                counts.failed += 1;
+                ctx.warn(format!("couldn't localize {} {} ({}), it keeps its id", asset_type, id, e));
+                None
+            }
+        };
+        done.insert(id, localized.clone());
+        localized
+    });
+    if let Some(e) = write_error {
+        return Err(e.into());
+    }
+    for (referent, changed) in changes {
+        let name = dom.get_by_ref(referent).map_or_else(String::new, |instance| instance.name.to_string());
+        let changed: Vec<_> = changed
+            .iter()
+            .map(|(prop_name, uri)| format!("'{}' to {}", prop_name, uri))
+            .collect();
+        ctx.converted(referent, format!("localized assets on '{}', changed {}", name, changed.join(", ")));
+    }
+    ctx.info(format!(
+        "localized {} mesh(es), {} texture(s) ({} scaled down) and {} sound(s), {} failed",
+        counts.meshes, counts.textures, counts.resized, counts.sounds, counts.failed
+    ));
+    Ok(())
+}
+
+pub struct PrepareReport {
+    pub place: PathBuf,
+    pub content_dir: PathBuf,
+    pub manifest: PathBuf,
+    pub files: usize,
+    pub run: RunReport,
+    // only with the client's content dir to check against
+    pub content: Option<ContentReport>,
+}
+
+impl PrepareReport {
+    pub fn is_ok(&self) -> bool {
+        self.content.as_ref().is_none_or(ContentReport::is_ok)
+    }
+
+    // the fix-place report, then the content folder and whether it's complete
+    pub fn to_text(&self) -> String {
+        let mut out = self.run.to_text();
+        let _ = writeln!(out);
+        let _ = writeln!(out, "place written to {}", self.place.display());
+        let _ = writeln!(
+            out,
+            "{} file(s) in {}, hashes in {}",
+            self.files,
+            self.content_dir.display(),
+            self.manifest.display()
+        );
+        match &self.content {
+            Some(content) => out.push_str(&content.to_text()),
+            None => {
+                let _ = writeln!(out, "rbxasset:// references weren't checked, give the client's content dir for that");
+            }
+        }
+        out
+    }
+}
+
+// fix-place, localizing and packaging, see the top of the file. options are the fix-place
+// options to run with, the localize pass is added to them
+#[allow(clippy::too_many_arguments)]
+pub fn prepare_legacy(
+    input: &Path,
+    out_dir: &Path,
+    options: FixPlaceOptions,
+    source: AssetSource,
+    mesh_version: RobloxMeshVersion,
+    max_texture_size: u32,
+    client_content: Option<&Path>,
+    launcher: Option<Launcher>,
+) -> Result<PrepareReport, Box<dyn Error>> {
+    let data = fs::read(input).map_err(|e| format!("{}: {}", input.display(), e))?;
+    let layout = launcher.map_or(&CONTENT_LAYOUT, Launcher::layout);
+    let content_dir = out_dir.join(layout.content_dir);
+    fs::create_dir_all(&content_dir)?;
+    let localize = LocalizeOptions {
+        source,
+        out_dir: out_dir.to_path_buf(),
+        layout,
+        mesh_version,
+        max_texture_size,
+    };
+    let (output, run) = crate::fix_place_with_report(&data, options.with_pass(passes::LocalizeAssets { options: localize }))?;
+
+    let extension = if crate::is_binary_rbxl(&output) { "rbxl" } else { "rbxlx" };
+    let stem = input.file_stem().unwrap_or(input.as_os_str());
+    let place_dir = out_dir.join(layout.place_dir);
+    fs::create_dir_all(&place_dir)?;
+    let place = place_dir.join(stem).with_extension(extension);
+    fs::write(&place, &output)?;
+
+    let manifest = out_dir.join(MANIFEST_NAME);
+    let files = content::write_manifest(&content_dir, &manifest)?;
+
+    let content = match client_content {
+        Some(client_content) => {
+            let dom = crate::load_place(&output)?;
+            let mut report = content::verify_content(&content_dir, &dom, None);
+            // whatever the package doesn't have has to come with the client
+            report.missing.retain(|path, _| content::resolve(client_content, path).is_none());
+            Some(report)
+        }
+        None => None,
+    };
+    Ok(PrepareReport {
+        place,
+        content_dir,
+        manifest,
+        files,
+        run,
+        content,
+    })
+}
@@ -0,0 +1,53 @@
+// built-in conversion presets, the data lives in src/presets/*.json and is compiled in
+use crate::mappings::InstanceMappings;
+use clap::ValueEnum;
+use serde::Deserialize;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Preset {
+    #[value(name = "2011")]
+    Client2011,
+    #[value(name = "2013")]
+    Client2013,
+    #[value(name = "2016")]
+    Client2016,
+    Finobe,
+    Novetus,
+}
+
+#[derive(Deserialize)]
+pub struct PresetData {
+    pub description: String,
+    #[serde(default)]
+    pub mappings: InstanceMappings,
+    #[serde(default)]
+    pub strip: Vec<String>,
+}
+
+impl Preset {
+    fn source(self) -> &'static str {
+        match self {
+            Preset::Client2011 => include_str!("presets/2011.json"),
+            Preset::Client2013 => include_str!("presets/2013.json"),
+            Preset::Client2016 => include_str!("presets/2016.json"),
+            Preset::Finobe => include_str!("presets/finobe.json"),
+            Preset::Novetus => include_str!("presets/novetus.json"),
+        }
+    }
+
+    // the year of the clients the preset is for
+    pub fn year(self) -> u32 {
+        match self {
+            Preset::Client2011 => 2011,
+            Preset::Client2013 => 2013,
+            Preset::Client2016 | Preset::Finobe => 2016,
+            Preset::Novetus => 2012,
+        }
+    }
+
+    pub fn load(self) -> PresetData {
+        // the preset files are part of the binary, so a parse failure is a bug in the data
+        serde_json::from_str(self.source())
+            .unwrap_or_else(|e| panic!("built-in preset {:?} is invalid: {}", self, e))
+    }
+}
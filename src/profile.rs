@@ -0,0 +1,150 @@
+// rough per-property size profile of a place
+//
+// sizes are estimated from the values themselves, roughly what the binary format stores before
+// compression. it won't match the file size exactly but it's good enough to see that the place
+// is 80% union MeshData and decide what to strip for a legacy host's upload limit.
+use crate::path::path_of;
+use rbx_dom_weak::WeakDom;
+use rbx_types::{SharedString, Variant};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as FmtWrite;
+
+pub struct PlaceProfile {
+    pub total_bytes: usize,
+    pub instance_count: usize,
+    // (class.property, bytes, value count)
+    pub properties: Vec<(String, usize, usize)>,
+    // (instance path, class, bytes)
+    pub instances: Vec<(String, String, usize)>,
+    pub shared_string_bytes: usize,
+    pub shared_string_count: usize,
+}
+
+fn value_size(value: &Variant, seen_shared: &mut HashSet<SharedString>) -> usize {
+    match value {
+        Variant::String(s) => 4 + s.len(),
+        Variant::BinaryString(bytes) => 4 + AsRef::<[u8]>::as_ref(bytes).len(),
+        Variant::Content(content) => 4 + content.as_uri().map_or(0, str::len),
+        Variant::ContentId(content_id) => 4 + content_id.as_str().len(),
+        Variant::Tags(tags) => 4 + tags.encode().len(),
+        Variant::Attributes(attributes) => {
+            let mut buf = Vec::new();
+            attributes.to_writer(&mut buf).map_or(0, |_| 4 + buf.len())
+        }
+        // the data is stored once in the SSTR chunk, every use after that is an index
+        Variant::SharedString(shared) => {
+            if seen_shared.insert(shared.clone()) {
+                4 + shared.data().len()
+            } else {
+                4
+            }
+        }
+        Variant::Bool(_) => 1,
+        Variant::Int32(_) | Variant::Float32(_) | Variant::Enum(_) | Variant::BrickColor(_) | Variant::Ref(_) => 4,
+        Variant::Int64(_) | Variant::Float64(_) => 8,
+        Variant::Vector2(_) | Variant::UDim(_) => 8,
+        Variant::Vector3(_) | Variant::Color3(_) => 12,
+        Variant::UDim2(_) => 16,
+        Variant::CFrame(_) => 48,
+        Variant::NumberSequence(seq) => 4 + seq.keypoints.len() * 12,
+        Variant::ColorSequence(seq) => 4 + seq.keypoints.len() * 20,
+        // everything else is small and fixed size, close enough
+        _ => 8,
+    }
+}
+
+pub fn profile_place(dom: &WeakDom) -> PlaceProfile {
+    let mut seen_shared = HashSet::new();
+    let mut by_property: HashMap<String, (usize, usize)> = HashMap::new();
+    let mut instances = Vec::new();
+    let mut total_bytes = 0;
+    let mut shared_string_bytes = 0;
+
+    for instance in dom.descendants().skip(1) {
+        // class id, referent, name and parent entry
+        let mut instance_bytes = 4 + 4 + instance.name.len() + 8;
+        for (prop_name, value) in &instance.properties {
+            let first_use = matches!(value, Variant::SharedString(shared) if !seen_shared.contains(shared));
+            let size = value_size(value, &mut seen_shared);
+            if first_use {
+                shared_string_bytes += size;
+            }
+            instance_bytes += size;
+            let entry = by_property
+                .entry(format!("{}.{}", instance.class, prop_name))
+                .or_default();
+            entry.0 += size;
+            entry.1 += 1;
+        }
+        total_bytes += instance_bytes;
+        instances.push((instance.referent(), instance.class.to_string(), instance_bytes));
+    }
+
+    let mut properties: Vec<_> = by_property
+        .into_iter()
+        .map(|(name, (bytes, count))| (name, bytes, count))
+        .collect();
+    properties.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    instances.sort_by_key(|&(_, _, bytes)| std::cmp::Reverse(bytes));
+
+    PlaceProfile {
+        total_bytes,
+        instance_count: instances.len(),
+        properties,
+        instances: instances
+            .into_iter()
+            .map(|(referent, class, bytes)| (path_of(dom, referent), class, bytes))
+            .collect(),
+        shared_string_bytes,
+        shared_string_count: seen_shared.len(),
+    }
+}
+
+fn percent(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        part as f64 * 100.0 / total as f64
+    }
+}
+
+impl PlaceProfile {
+    pub fn to_text(&self, top: usize) -> String {
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            "estimated size: {} bytes across {} instances (uncompressed)",
+            self.total_bytes, self.instance_count
+        );
+        let _ = writeln!(
+            out,
+            "shared strings: {} unique, {} bytes ({:.1}%)",
+            self.shared_string_count,
+            self.shared_string_bytes,
+            percent(self.shared_string_bytes, self.total_bytes)
+        );
+        let _ = writeln!(out, "\ntop properties:");
+        for (name, bytes, count) in self.properties.iter().take(top) {
+            let _ = writeln!(
+                out,
+                "  {:>12} bytes {:>5.1}%  {} ({} values)",
+                bytes,
+                percent(*bytes, self.total_bytes),
+                name,
+                count
+            );
+        }
+        let _ = writeln!(out, "\ntop instances:");
+        for (path, class, bytes) in self.instances.iter().take(top) {
+            let _ = writeln!(
+                out,
+                "  {:>12} bytes {:>5.1}%  {} ({})",
+                bytes,
+                percent(*bytes, self.total_bytes),
+                path,
+                class
+            );
+        }
+        out
+    }
+}
@@ -0,0 +1,231 @@
+// python bindings, built with `maturin build --features python`
+//
+//   import roblox_utils
+//   mesh = roblox_utils.obj_to_filemesh(open("a.obj", "rb").read(), "v2_00")
+//   place = roblox_utils.parse_place(data)          # nested dicts
+//   info = roblox_utils.place_info(data)            # same report as the http api
+//   out = roblox_utils.fix_place(data, preset="2013", force_binary=True)
+//   out, report = roblox_utils.fix_place_with_report(data, preset="2013", verify=True)
+//
+// fix_place takes the same options as the http api / daemon sidecars. failures raise
+// roblox_utils.ConversionError, bad options raise ValueError.
+use crate::dom_limits::{DEFAULT_MAX_DEPTH, DomLimits};
+use crate::serve::{self, HttpError, Options, Reply, Upload};
+use crate::RobloxMeshVersion;
+use clap::ValueEnum;
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyBytes, PyDict, PyList};
+use pyo3::IntoPyObjectExt;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+use rbx_types::Variant;
+use serde_json::Value;
+
+create_exception!(roblox_utils, ConversionError, PyException);
+
+fn to_py_err(error: HttpError) -> PyErr {
+    let HttpError(status, message) = error;
+    if status == 400 {
+        PyValueError::new_err(message)
+    } else {
+        ConversionError::new_err(message)
+    }
+}
+
+fn conversion_err(error: impl std::fmt::Display) -> PyErr {
+    ConversionError::new_err(error.to_string())
+}
+
+fn mesh_version(version: &str) -> PyResult<RobloxMeshVersion> {
+    RobloxMeshVersion::from_str(version, true)
+        .map_err(|_| PyValueError::new_err(format!("invalid mesh version: {}", version)))
+}
+
+fn run(action: &str, data: &[u8], options: Options) -> PyResult<Reply> {
+    let upload = Upload {
+        file: data.to_vec(),
+        filename: None,
+        options,
+    };
+    serve::run_action(action, &upload).map_err(to_py_err)
+}
+
+fn json_to_py<'py>(py: Python<'py>, value: &Value) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        Value::Null => Ok(py.None().into_bound(py)),
+        Value::Bool(b) => b.into_bound_py_any(py),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into_bound_py_any(py),
+            None => n.as_f64().unwrap_or_default().into_bound_py_any(py),
+        },
+        Value::String(s) => s.into_bound_py_any(py),
+        Value::Array(items) => {
+            let list = PyList::empty(py);
+            for item in items {
+                list.append(json_to_py(py, item)?)?;
+            }
+            Ok(list.into_any())
+        }
+        Value::Object(map) => {
+            let dict = PyDict::new(py);
+            for (key, item) in map {
+                dict.set_item(key, json_to_py(py, item)?)?;
+            }
+            Ok(dict.into_any())
+        }
+    }
+}
+
+fn variant_to_py<'py>(py: Python<'py>, value: &Variant) -> PyResult<Bound<'py, PyAny>> {
+    match value {
+        Variant::Bool(b) => b.into_bound_py_any(py),
+        Variant::Int32(i) => i.into_bound_py_any(py),
+        Variant::Int64(i) => i.into_bound_py_any(py),
+        Variant::Float32(f) => f.into_bound_py_any(py),
+        Variant::Float64(f) => f.into_bound_py_any(py),
+        Variant::String(s) => s.into_bound_py_any(py),
+        Variant::BinaryString(bytes) => PyBytes::new(py, bytes.as_ref()).into_bound_py_any(py),
+        Variant::SharedString(shared) => PyBytes::new(py, shared.data()).into_bound_py_any(py),
+        Variant::Content(content) => content.as_uri().unwrap_or_default().into_bound_py_any(py),
+        Variant::ContentId(content_id) => content_id.as_str().into_bound_py_any(py),
+        Variant::Enum(e) => e.to_u32().into_bound_py_any(py),
+        Variant::BrickColor(color) => (*color as u16).into_bound_py_any(py),
+        Variant::Vector2(v) => (v.x, v.y).into_bound_py_any(py),
+        Variant::Vector3(v) => (v.x, v.y, v.z).into_bound_py_any(py),
+        Variant::Color3(c) => (c.r, c.g, c.b).into_bound_py_any(py),
+        Variant::Color3uint8(c) => (c.r, c.g, c.b).into_bound_py_any(py),
+        Variant::CFrame(cf) => {
+            let (p, o) = (cf.position, cf.orientation);
+            vec![
+                p.x, p.y, p.z, o.x.x, o.x.y, o.x.z, o.y.x, o.y.y, o.y.z, o.z.x, o.z.y, o.z.z,
+            ]
+            .into_bound_py_any(py)
+        }
+        Variant::Ref(referent) if referent.is_none() => Ok(py.None().into_bound(py)),
+        Variant::Ref(referent) => referent.to_string().into_bound_py_any(py),
+        Variant::Tags(tags) => tags.iter().collect::<Vec<_>>().into_bound_py_any(py),
+        // anything more exotic is handed over as its debug representation
+        other => format!("{:?}", other).into_bound_py_any(py),
+    }
+}
+
+fn instance_to_py<'py>(py: Python<'py>, dom: &WeakDom, referent: Ref) -> PyResult<Bound<'py, PyDict>> {
+    let dict = PyDict::new(py);
+    let Some(instance) = dom.get_by_ref(referent) else { return Ok(dict) };
+    dict.set_item("name", &instance.name)?;
+    dict.set_item("class", instance.class.as_str())?;
+    dict.set_item("referent", referent.to_string())?;
+    let properties = PyDict::new(py);
+    for (name, value) in &instance.properties {
+        properties.set_item(name.as_str(), variant_to_py(py, value)?)?;
+    }
+    dict.set_item("properties", properties)?;
+    let children = PyList::empty(py);
+    for &child in instance.children() {
+        children.append(instance_to_py(py, dom, child)?)?;
+    }
+    dict.set_item("children", children)?;
+    Ok(dict)
+}
+
+#[pyfunction]
+fn obj_to_filemesh<'py>(py: Python<'py>, data: &[u8], version: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let bytes = crate::convert_obj_to_filemesh(data, mesh_version(version)?).map_err(conversion_err)?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+#[pyfunction]
+fn filemesh_to_obj<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+    let bytes = crate::convert_filemesh_to_obj(data).map_err(conversion_err)?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+#[pyfunction]
+fn filemesh_to_gltf<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyBytes>> {
+    let bytes = crate::convert_filemesh_to_gltf(data).map_err(conversion_err)?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+#[pyfunction]
+fn gltf_to_filemesh<'py>(py: Python<'py>, data: &[u8], version: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let bytes = crate::convert_gltf_to_filemesh(data, mesh_version(version)?).map_err(conversion_err)?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+#[pyfunction]
+fn filemesh_to_filemesh<'py>(py: Python<'py>, data: &[u8], version: &str) -> PyResult<Bound<'py, PyBytes>> {
+    let bytes = crate::convert_filemesh_to_filemesh(data, mesh_version(version)?).map_err(conversion_err)?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+// the root is the DataModel, its children are the services. the dicts nest as deep as the
+// place does, so max_depth is checked on the way in, see dom_limits
+#[pyfunction]
+#[pyo3(signature = (data, max_depth = DEFAULT_MAX_DEPTH))]
+fn parse_place<'py>(py: Python<'py>, data: &[u8], max_depth: usize) -> PyResult<Bound<'py, PyDict>> {
+    let limits = DomLimits { max_depth, ..DomLimits::default() };
+    let dom = crate::load_place_with_limits(data, 1, &limits).map_err(conversion_err)?;
+    instance_to_py(py, &dom, dom.root_ref())
+}
+
+#[pyfunction]
+fn place_info<'py>(py: Python<'py>, data: &[u8]) -> PyResult<Bound<'py, PyAny>> {
+    match run("place-info", data, Options::new())? {
+        Reply::Json(value) => json_to_py(py, &value),
+        Reply::File { .. } => Err(ConversionError::new_err("place-info returned a file")),
+    }
+}
+
+fn run_fix_place(data: &[u8], options: Option<&Bound<'_, PyDict>>) -> PyResult<(Vec<u8>, Value)> {
+    let mut string_options = Options::new();
+    for (key, value) in options.into_iter().flatten() {
+        if value.is_none() {
+            continue;
+        }
+        string_options.insert(key.extract::<String>()?, value.str()?.to_string());
+    }
+    match run("fix-place", data, string_options)? {
+        Reply::File { bytes, report, .. } => Ok((bytes, report.unwrap_or_default())),
+        Reply::Json(_) => Err(ConversionError::new_err("fix-place returned a report instead of a file")),
+    }
+}
+
+#[pyfunction]
+#[pyo3(signature = (data, **options))]
+fn fix_place<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<Bound<'py, PyBytes>> {
+    let (bytes, _) = run_fix_place(data, options)?;
+    Ok(PyBytes::new(py, &bytes))
+}
+
+// fix_place, plus { "warnings": [...], "verify": "..." or None }
+#[pyfunction]
+#[pyo3(signature = (data, **options))]
+fn fix_place_with_report<'py>(
+    py: Python<'py>,
+    data: &[u8],
+    options: Option<&Bound<'py, PyDict>>,
+) -> PyResult<(Bound<'py, PyBytes>, Bound<'py, PyAny>)> {
+    let (bytes, report) = run_fix_place(data, options)?;
+    Ok((PyBytes::new(py, &bytes), json_to_py(py, &report)?))
+}
+
+#[pymodule]
+fn roblox_utils(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add("ConversionError", m.py().get_type::<ConversionError>())?;
+    m.add_function(wrap_pyfunction!(obj_to_filemesh, m)?)?;
+    m.add_function(wrap_pyfunction!(filemesh_to_obj, m)?)?;
+    m.add_function(wrap_pyfunction!(filemesh_to_gltf, m)?)?;
+    m.add_function(wrap_pyfunction!(gltf_to_filemesh, m)?)?;
+    m.add_function(wrap_pyfunction!(filemesh_to_filemesh, m)?)?;
+    m.add_function(wrap_pyfunction!(parse_place, m)?)?;
+    m.add_function(wrap_pyfunction!(place_info, m)?)?;
+    m.add_function(wrap_pyfunction!(fix_place, m)?)?;
+    m.add_function(wrap_pyfunction!(fix_place_with_report, m)?)?;
+    Ok(())
+}
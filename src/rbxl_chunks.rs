@@ -0,0 +1,457 @@
+// low-level reader for the chunks of a binary place/model, independent of rbx_binary so it
+// can look at files that rbx_binary refuses to open
+// https://dom.rojo.space/binary
+use crate::error::{ConversionError, Result};
+use byteorder::{LittleEndian, ReadBytesExt};
+use std::fmt::Write as FmtWrite;
+use std::io::{Cursor, Read};
+
+const FILE_MAGIC: &[u8] = b"<roblox!\x89\xff\r\n\x1a\n";
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+pub const FILE_HEADER_SIZE: usize = 32;
+const CHUNK_HEADER_SIZE: usize = 16;
+// the most a compressed byte can stand for, a header claiming more is damaged and isn't allocated
+const MAX_LZ4_RATIO: usize = 255;
+// an rle block, a few bytes standing for 128 KiB
+const MAX_ZSTD_RATIO: usize = 32 * 1024;
+
+pub struct FileHeader {
+    pub version: u16,
+    pub num_types: u32,
+    pub num_instances: u32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Lz4,
+    Zstd,
+}
+
+pub struct RawChunk {
+    pub name: [u8; 4],
+    pub offset: usize,
+    pub compressed_len: u32,
+    pub len: u32,
+    pub compression: Compression,
+    pub data: Vec<u8>,
+}
+
+impl RawChunk {
+    pub fn name_str(&self) -> String {
+        String::from_utf8_lossy(&self.name).trim_end_matches('\0').to_string()
+    }
+}
+
+pub fn read_file_header(bytes: &[u8]) -> Result<FileHeader> {
+    if !bytes.starts_with(FILE_MAGIC) {
+        return Err(chunk_err("missing binary file signature"));
+    }
+    if bytes.len() < FILE_HEADER_SIZE {
+        return Err(chunk_err("file header is truncated"));
+    }
+    let mut cursor = Cursor::new(&bytes[FILE_MAGIC.len()..]);
+    Ok(FileHeader {
+        version: cursor.read_u16::<LittleEndian>()?,
+        num_types: cursor.read_u32::<LittleEndian>()?,
+        num_instances: cursor.read_u32::<LittleEndian>()?,
+    })
+}
+
+// walks the chunks after the file header, stopping after END or at the first chunk that
+// can't be read. the error is returned as the last item so callers can keep what came before
+pub struct ChunkIter<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    done: bool,
+}
+
+pub fn chunks(bytes: &[u8]) -> ChunkIter<'_> {
+    chunks_from(bytes, FILE_HEADER_SIZE)
+}
+
+pub fn chunks_from(bytes: &[u8], offset: usize) -> ChunkIter<'_> {
+    ChunkIter {
+        bytes,
+        offset,
+        done: false,
+    }
+}
+
+impl Iterator for ChunkIter<'_> {
+    type Item = Result<RawChunk>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.offset >= self.bytes.len() {
+            return None;
+        }
+        let result = self.read_chunk();
+        match &result {
+            Ok(chunk) if &chunk.name == b"END\0" => self.done = true,
+            Ok(_) => {}
+            Err(_) => self.done = true,
+        }
+        Some(result)
+    }
+}
+
+impl ChunkIter<'_> {
+    // after an error this is the offset of the chunk that failed
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn read_chunk(&mut self) -> Result<RawChunk> {
+        let offset = self.offset;
+        let header = self
+            .bytes
+            .get(offset..offset + CHUNK_HEADER_SIZE)
+            .ok_or_else(|| chunk_err(format!("chunk header at offset {} is truncated", offset)))?;
+
+        let mut cursor = Cursor::new(header);
+        let mut name = [0u8; 4];
+        cursor.read_exact(&mut name)?;
+        let compressed_len = cursor.read_u32::<LittleEndian>()?;
+        let len = cursor.read_u32::<LittleEndian>()?;
+
+        let stored_len = if compressed_len == 0 { len } else { compressed_len } as usize;
+        let body_start = offset + CHUNK_HEADER_SIZE;
+        let body = self.bytes.get(body_start..body_start + stored_len).ok_or_else(|| {
+            chunk_err(format!(
+                "chunk {} at offset {} needs {} bytes but only {} remain",
+                String::from_utf8_lossy(&name),
+                offset,
+                stored_len,
+                self.bytes.len() - body_start
+            ))
+        })?;
+
+        let zstd = body.starts_with(ZSTD_MAGIC);
+        let max_len = if zstd {
+            // the frame says how big it is when it was written with its size
+            match zstd::zstd_safe::get_frame_content_size(body) {
+                Ok(Some(size)) => size.min((stored_len * MAX_ZSTD_RATIO) as u64) as usize,
+                _ => stored_len * MAX_ZSTD_RATIO,
+            }
+        } else {
+            stored_len * MAX_LZ4_RATIO
+        };
+        if compressed_len != 0 && len as usize > max_len {
+            return Err(chunk_err(format!(
+                "chunk {} at offset {} claims {} bytes uncompressed, more than its {} compressed bytes can hold",
+                String::from_utf8_lossy(&name),
+                offset,
+                len,
+                stored_len
+            )));
+        }
+
+        let (compression, data) = if compressed_len == 0 {
+            (Compression::None, body.to_vec())
+        } else if zstd {
+            let data = zstd::bulk::decompress(body, len as usize)
+                .map_err(|e| chunk_err(format!("zstd chunk at offset {}: {}", offset, e)))?;
+            (Compression::Zstd, data)
+        } else {
+            let data = lz4_flex::block::decompress(body, len as usize)
+                .map_err(|e| chunk_err(format!("lz4 chunk at offset {}: {}", offset, e)))?;
+            (Compression::Lz4, data)
+        };
+
+        self.offset = body_start + stored_len;
+        Ok(RawChunk {
+            name,
+            offset,
+            compressed_len,
+            len,
+            compression,
+            data,
+        })
+    }
+}
+
+pub fn type_name(type_id: u8) -> &'static str {
+    match type_id {
+        0x01 => "String",
+        0x02 => "Bool",
+        0x03 => "Int32",
+        0x04 => "Float32",
+        0x05 => "Float64",
+        0x06 => "UDim",
+        0x07 => "UDim2",
+        0x08 => "Ray",
+        0x09 => "Faces",
+        0x0A => "Axes",
+        0x0B => "BrickColor",
+        0x0C => "Color3",
+        0x0D => "Vector2",
+        0x0E => "Vector3",
+        0x0F => "Vector2int16",
+        0x10 => "CFrame",
+        0x11 => "Quaternion",
+        0x12 => "Enum",
+        0x13 => "Ref",
+        0x14 => "Vector3int16",
+        0x15 => "NumberSequence",
+        0x16 => "ColorSequence",
+        0x17 => "NumberRange",
+        0x18 => "Rect",
+        0x19 => "PhysicalProperties",
+        0x1A => "Color3uint8",
+        0x1B => "Int64",
+        0x1C => "SharedString",
+        0x1D => "Bytecode",
+        0x1E => "OptionalCFrame",
+        0x1F => "UniqueId",
+        0x20 => "Font",
+        0x21 => "SecurityCapabilities",
+        0x22 => "Content",
+        _ => "Unknown",
+    }
+}
+
+pub fn read_string(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+    let len = cursor.read_u32::<LittleEndian>()? as usize;
+    let remaining = cursor.get_ref().len().saturating_sub(cursor.position() as usize);
+    if len > remaining {
+        return Err(chunk_err("string length runs past the end of the chunk"));
+    }
+    let mut buf = vec![0u8; len];
+    cursor.read_exact(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+// roblox's interleaved, zigzag encoded, delta accumulated referent arrays
+pub fn read_referents(cursor: &mut Cursor<&[u8]>, count: usize) -> Result<Vec<i32>> {
+    let remaining = cursor.get_ref().len().saturating_sub(cursor.position() as usize);
+    if count * 4 > remaining {
+        return Err(chunk_err("referent array runs past the end of the chunk"));
+    }
+    let mut raw = vec![0u8; count * 4];
+    cursor.read_exact(&mut raw)?;
+    let mut referents = Vec::with_capacity(count);
+    let mut last = 0i32;
+    for i in 0..count {
+        let value = u32::from_be_bytes([raw[i], raw[i + count], raw[i + count * 2], raw[i + count * 3]]);
+        let decoded = ((value >> 1) as i32) ^ -((value & 1) as i32);
+        last = last.wrapping_add(decoded);
+        referents.push(last);
+    }
+    Ok(referents)
+}
+
+pub fn write_referents(out: &mut Vec<u8>, referents: &[i32]) {
+    let count = referents.len();
+    let mut encoded = Vec::with_capacity(count);
+    let mut last = 0i32;
+    for &referent in referents {
+        let delta = referent.wrapping_sub(last);
+        last = referent;
+        encoded.push(((delta << 1) ^ (delta >> 31)) as u32);
+    }
+    let start = out.len();
+    out.resize(start + count * 4, 0);
+    for (i, value) in encoded.iter().enumerate() {
+        for (byte_index, byte) in value.to_be_bytes().iter().enumerate() {
+            out[start + i + count * byte_index] = *byte;
+        }
+    }
+}
+
+// writes an uncompressed chunk
+pub fn write_chunk(out: &mut Vec<u8>, name: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(name);
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(&0u32.to_le_bytes());
+    out.extend_from_slice(data);
+}
+
+pub fn write_file_header(out: &mut Vec<u8>, num_types: u32, num_instances: u32) {
+    out.extend_from_slice(FILE_MAGIC);
+    out.extend_from_slice(&0u16.to_le_bytes());
+    out.extend_from_slice(&num_types.to_le_bytes());
+    out.extend_from_slice(&num_instances.to_le_bytes());
+    out.extend_from_slice(&[0u8; 8]);
+}
+
+pub struct InstChunk {
+    pub class_id: u32,
+    pub class_name: String,
+    pub is_service: bool,
+    pub referents: Vec<i32>,
+}
+
+pub fn parse_inst(data: &[u8]) -> Result<InstChunk> {
+    let mut cursor = Cursor::new(data);
+    let class_id = cursor.read_u32::<LittleEndian>()?;
+    let class_name = read_string(&mut cursor)?;
+    let is_service = cursor.read_u8()? != 0;
+    let count = cursor.read_u32::<LittleEndian>()? as usize;
+    let referents = read_referents(&mut cursor, count)?;
+    Ok(InstChunk {
+        class_id,
+        class_name,
+        is_service,
+        referents,
+    })
+}
+
+pub struct PropChunkHeader {
+    pub class_id: u32,
+    pub prop_name: String,
+    pub type_id: u8,
+    pub values_offset: usize,
+}
+
+pub fn parse_prop_header(data: &[u8]) -> Result<PropChunkHeader> {
+    let mut cursor = Cursor::new(data);
+    let class_id = cursor.read_u32::<LittleEndian>()?;
+    let prop_name = read_string(&mut cursor)?;
+    let type_id = cursor.read_u8()?;
+    Ok(PropChunkHeader {
+        class_id,
+        prop_name,
+        type_id,
+        values_offset: cursor.position() as usize,
+    })
+}
+
+pub struct PrntChunk {
+    pub version: u8,
+    pub children: Vec<i32>,
+    pub parents: Vec<i32>,
+}
+
+pub fn parse_prnt(data: &[u8]) -> Result<PrntChunk> {
+    let mut cursor = Cursor::new(data);
+    let version = cursor.read_u8()?;
+    let count = cursor.read_u32::<LittleEndian>()? as usize;
+    let children = read_referents(&mut cursor, count)?;
+    let parents = read_referents(&mut cursor, count)?;
+    Ok(PrntChunk {
+        version,
+        children,
+        parents,
+    })
+}
+
+pub fn parse_meta(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let mut cursor = Cursor::new(data);
+    let count = cursor.read_u32::<LittleEndian>()?;
+    let mut entries = Vec::new();
+    for _ in 0..count {
+        let key = read_string(&mut cursor)?;
+        let value = read_string(&mut cursor)?;
+        entries.push((key, value));
+    }
+    Ok(entries)
+}
+
+// (version, count)
+pub fn parse_sstr_header(data: &[u8]) -> Result<(u32, u32)> {
+    let mut cursor = Cursor::new(data);
+    Ok((cursor.read_u32::<LittleEndian>()?, cursor.read_u32::<LittleEndian>()?))
+}
+
+pub fn inspect(bytes: &[u8]) -> Result<String> {
+    let header = read_file_header(bytes)?;
+    let mut out = String::new();
+    let mut class_names = std::collections::HashMap::new();
+    let mut chunk_totals: Vec<(String, usize, u64, u64)> = Vec::new();
+
+    let _ = writeln!(
+        out,
+        "header: version {}, {} types, {} instances",
+        header.version, header.num_types, header.num_instances
+    );
+
+    for chunk in chunks(bytes) {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(e) => {
+                let _ = writeln!(out, "error: {}", e);
+                break;
+            }
+        };
+        let name = chunk.name_str();
+        let detail = match name.as_str() {
+            "INST" => parse_inst(&chunk.data).map(|inst| {
+                class_names.insert(inst.class_id, inst.class_name.clone());
+                format!(
+                    "class {} '{}'{}, {} instances",
+                    inst.class_id,
+                    inst.class_name,
+                    if inst.is_service { " (service)" } else { "" },
+                    inst.referents.len()
+                )
+            }),
+            "PROP" => parse_prop_header(&chunk.data).map(|prop| {
+                format!(
+                    "class '{}' property '{}' type {} (0x{:02X}), {} value bytes",
+                    class_names
+                        .get(&prop.class_id)
+                        .map_or_else(|| prop.class_id.to_string(), String::clone),
+                    prop.prop_name,
+                    type_name(prop.type_id),
+                    prop.type_id,
+                    chunk.data.len() - prop.values_offset
+                )
+            }),
+            "PRNT" => parse_prnt(&chunk.data)
+                .map(|prnt| {
+                    format!(
+                        "version {}, {} links, {} top level",
+                        prnt.version,
+                        prnt.children.len(),
+                        prnt.parents.iter().filter(|&&parent| parent == -1).count()
+                    )
+                }),
+            "META" => parse_meta(&chunk.data).map(|entries| {
+                entries
+                    .iter()
+                    .map(|(k, v)| format!("{}={}", k, v))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            }),
+            "SSTR" => parse_sstr_header(&chunk.data)
+                .map(|(version, count)| format!("version {}, {} shared strings", version, count)),
+            _ => Ok(String::new()),
+        };
+        let detail = detail.unwrap_or_else(|e| format!("unreadable: {}", e));
+
+        let _ = writeln!(
+            out,
+            "{:>10}  {:<4}  {:?} {} -> {} bytes  {}",
+            chunk.offset,
+            name,
+            chunk.compression,
+            if chunk.compressed_len == 0 { chunk.len } else { chunk.compressed_len },
+            chunk.len,
+            detail
+        );
+
+        match chunk_totals.iter_mut().find(|(n, ..)| *n == name) {
+            Some(total) => {
+                total.1 += 1;
+                total.2 += chunk.compressed_len as u64;
+                total.3 += chunk.len as u64;
+            }
+            None => chunk_totals.push((name, 1, chunk.compressed_len as u64, chunk.len as u64)),
+        }
+    }
+
+    let _ = writeln!(out, "totals:");
+    for (name, count, compressed, len) in chunk_totals {
+        let _ = writeln!(
+            out,
+            "  {:<4}  {:>6} chunks  {:>12} compressed  {:>12} uncompressed",
+            name, count, compressed, len
+        );
+    }
+    Ok(out)
+}
+
+pub fn chunk_err(message: impl Into<String>) -> ConversionError {
+    ConversionError::BinaryPlaceParse(message.into())
+}
@@ -0,0 +1,318 @@
+// best-effort recovery of truncated or partially corrupt binary places
+//
+// the readable chunks are collected, anything that points at data we lost is dropped, a
+// fresh PRNT chunk is synthesized (the original is usually the first casualty of
+// truncation since it's written last) and the result is handed to rbx_binary.
+//
+// a garbled PRNT can make instances each other's parents. nothing in such a loop reaches the
+// root, so rbx_binary would quietly leave all of it out. each loop is cut at one instance,
+// which goes in with the orphans.
+use crate::dom_limits::{self, DomLimits};
+use crate::error::{ConversionError, Result};
+use crate::rbxl_chunks::{self, RawChunk};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write as FmtWrite;
+use std::io::Cursor;
+use std::panic::{self, AssertUnwindSafe};
+
+const KNOWN_CHUNKS: [&[u8; 4]; 6] = [b"META", b"SSTR", b"INST", b"PROP", b"PRNT", b"END\0"];
+const ORPHAN_FOLDER_NAME: &str = "RecoveredOrphans";
+
+#[derive(Default)]
+pub struct DamageReport {
+    pub file_len: usize,
+    pub chunks_read: usize,
+    pub damaged_regions: Vec<(usize, String)>,
+    pub bytes_skipped: usize,
+    pub missing_end: bool,
+    pub missing_prnt: bool,
+    pub dropped_props: Vec<String>,
+    pub declared_instances: u32,
+    pub recovered_instances: usize,
+    pub orphaned_instances: usize,
+    pub parent_loops: usize,
+}
+
+impl DamageReport {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "file size: {} bytes", self.file_len);
+        let _ = writeln!(out, "readable chunks: {}", self.chunks_read);
+        for (offset, message) in &self.damaged_regions {
+            let _ = writeln!(out, "damaged at offset {}: {}", offset, message);
+        }
+        let _ = writeln!(out, "bytes skipped: {}", self.bytes_skipped);
+        if self.missing_end {
+            let _ = writeln!(out, "END chunk missing, file is truncated");
+        }
+        if self.missing_prnt {
+            let _ = writeln!(out, "PRNT chunk missing, hierarchy could not be restored");
+        }
+        if self.parent_loops > 0 {
+            let _ = writeln!(out, "parent loops cut: {}, each at one instance moved into {}", self.parent_loops, ORPHAN_FOLDER_NAME);
+        }
+        for prop in &self.dropped_props {
+            let _ = writeln!(out, "dropped property chunk: {}", prop);
+        }
+        let _ = writeln!(
+            out,
+            "instances recovered: {} of {} declared ({} orphaned into {})",
+            self.recovered_instances, self.declared_instances, self.orphaned_instances, ORPHAN_FOLDER_NAME
+        );
+        out
+    }
+}
+
+struct ReadableChunks {
+    meta: Option<RawChunk>,
+    sstr: Option<RawChunk>,
+    inst: Vec<(RawChunk, rbxl_chunks::InstChunk)>,
+    prop: Vec<(RawChunk, rbxl_chunks::PropChunkHeader)>,
+    prnt: Option<rbxl_chunks::PrntChunk>,
+}
+
+fn find_next_chunk(bytes: &[u8], from: usize) -> Option<usize> {
+    let last = bytes.len().checked_sub(4)?;
+    (from..=last).find(|&offset| {
+        KNOWN_CHUNKS
+            .iter()
+            .any(|name| &bytes[offset..offset + 4] == name.as_slice())
+    })
+}
+
+fn collect_chunks(bytes: &[u8], report: &mut DamageReport) -> ReadableChunks {
+    let mut readable = ReadableChunks {
+        meta: None,
+        sstr: None,
+        inst: Vec::new(),
+        prop: Vec::new(),
+        prnt: None,
+    };
+    let mut saw_end = false;
+    let mut offset = rbxl_chunks::FILE_HEADER_SIZE;
+
+    while offset < bytes.len() && !saw_end {
+        let mut iter = rbxl_chunks::chunks_from(bytes, offset);
+        let mut failure = None;
+        for chunk in iter.by_ref() {
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    failure = Some(e.to_string());
+                    break;
+                }
+            };
+            report.chunks_read += 1;
+            match &chunk.name {
+                b"META" => readable.meta = Some(chunk),
+                b"SSTR" => readable.sstr = Some(chunk),
+                b"INST" => match rbxl_chunks::parse_inst(&chunk.data) {
+                    Ok(inst) => readable.inst.push((chunk, inst)),
+                    Err(e) => report.damaged_regions.push((chunk.offset, e.to_string())),
+                },
+                b"PROP" => match rbxl_chunks::parse_prop_header(&chunk.data) {
+                    Ok(prop) => readable.prop.push((chunk, prop)),
+                    Err(e) => report.damaged_regions.push((chunk.offset, e.to_string())),
+                },
+                b"PRNT" => match rbxl_chunks::parse_prnt(&chunk.data) {
+                    Ok(prnt) => readable.prnt = Some(prnt),
+                    Err(e) => report.damaged_regions.push((chunk.offset, e.to_string())),
+                },
+                b"END\0" => saw_end = true,
+                _ => {}
+            }
+        }
+        let Some(message) = failure else { break };
+        let bad_offset = iter.offset();
+        report.damaged_regions.push((bad_offset, message));
+        match find_next_chunk(bytes, bad_offset + 1) {
+            Some(next) => {
+                report.bytes_skipped += next - bad_offset;
+                offset = next;
+            }
+            None => {
+                report.bytes_skipped += bytes.len() - bad_offset;
+                break;
+            }
+        }
+    }
+
+    report.missing_end = !saw_end;
+    report.missing_prnt = readable.prnt.is_none();
+    readable
+}
+
+fn known_referents(readable: &ReadableChunks) -> HashSet<i32> {
+    readable
+        .inst
+        .iter()
+        .flat_map(|(_, inst)| inst.referents.iter().copied())
+        .collect()
+}
+
+// parents from the original PRNT where both ends survived, everything else goes top level.
+// also returns how many loops were cut
+fn parent_links(readable: &ReadableChunks, known: &HashSet<i32>) -> (HashMap<i32, i32>, usize) {
+    let mut parent_of: HashMap<i32, i32> = HashMap::new();
+    if let Some(prnt) = &readable.prnt {
+        for (&child, &parent) in prnt.children.iter().zip(&prnt.parents) {
+            if known.contains(&child) && (parent == -1 || known.contains(&parent)) {
+                parent_of.insert(child, parent);
+            }
+        }
+    }
+    let mut starts: Vec<i32> = parent_of.keys().copied().collect();
+    starts.sort_unstable();
+    let mut done: HashSet<i32> = HashSet::new();
+    let mut loops = 0;
+    for start in starts {
+        let mut path = HashSet::new();
+        let mut current = start;
+        while !done.contains(&current) {
+            if !path.insert(current) {
+                // back where this walk has been, `current` closes the loop
+                parent_of.remove(&current);
+                loops += 1;
+                break;
+            }
+            match parent_of.get(&current) {
+                Some(&parent) if parent != -1 => current = parent,
+                _ => break,
+            }
+        }
+        done.extend(path);
+    }
+    (parent_of, loops)
+}
+
+// returns the rebuilt file and how many top level instances were top level in the original;
+// orphans are written after those so they end up last among the root's children
+fn build_file(
+    readable: &ReadableChunks,
+    parent_of: &HashMap<i32, i32>,
+    props: &[&(RawChunk, rbxl_chunks::PropChunkHeader)],
+) -> (Vec<u8>, usize) {
+    let known = known_referents(readable);
+    let mut children = Vec::with_capacity(known.len());
+    let mut parents = Vec::with_capacity(known.len());
+    let mut orphans = Vec::new();
+    for (_, inst) in &readable.inst {
+        for &referent in &inst.referents {
+            match parent_of.get(&referent) {
+                Some(&parent) => {
+                    children.push(referent);
+                    parents.push(parent);
+                }
+                None => orphans.push(referent),
+            }
+        }
+    }
+    let genuine_roots = parents.iter().filter(|&&parent| parent == -1).count();
+    parents.extend(std::iter::repeat_n(-1, orphans.len()));
+    children.extend(orphans);
+
+    let mut out = Vec::new();
+    rbxl_chunks::write_file_header(&mut out, readable.inst.len() as u32, known.len() as u32);
+    if let Some(meta) = &readable.meta {
+        rbxl_chunks::write_chunk(&mut out, b"META", &meta.data);
+    }
+    if let Some(sstr) = &readable.sstr {
+        rbxl_chunks::write_chunk(&mut out, b"SSTR", &sstr.data);
+    }
+    for (chunk, _) in &readable.inst {
+        rbxl_chunks::write_chunk(&mut out, b"INST", &chunk.data);
+    }
+    for (chunk, _) in props {
+        rbxl_chunks::write_chunk(&mut out, b"PROP", &chunk.data);
+    }
+    let mut prnt = vec![0u8];
+    prnt.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    rbxl_chunks::write_referents(&mut prnt, &children);
+    rbxl_chunks::write_referents(&mut prnt, &parents);
+    rbxl_chunks::write_chunk(&mut out, b"PRNT", &prnt);
+    rbxl_chunks::write_chunk(&mut out, b"END\0", b"</roblox>");
+    (out, genuine_roots)
+}
+
+// rbx_binary panics on some malformed input, treat that the same as an error. the panic hook is
+// left alone, it's process wide and other threads may be running, so the message still prints
+fn try_decode(bytes: &[u8]) -> Option<WeakDom> {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| rbx_binary::from_reader(Cursor::new(bytes))));
+    result.ok().and_then(|decoded| decoded.ok())
+}
+
+pub fn recover_place(bytes: &[u8], limits: &DomLimits) -> Result<(WeakDom, DamageReport)> {
+    let header = rbxl_chunks::read_file_header(bytes)?;
+    let mut report = DamageReport {
+        file_len: bytes.len(),
+        declared_instances: header.num_instances,
+        ..Default::default()
+    };
+    let readable = collect_chunks(bytes, &mut report);
+
+    let class_ids: HashSet<u32> = readable.inst.iter().map(|(_, inst)| inst.class_id).collect();
+    let class_names: HashMap<u32, &str> = readable
+        .inst
+        .iter()
+        .map(|(_, inst)| (inst.class_id, inst.class_name.as_str()))
+        .collect();
+    let mut props: Vec<_> = readable
+        .prop
+        .iter()
+        .filter(|(_, prop)| class_ids.contains(&prop.class_id))
+        .collect();
+
+    let (parent_of, parent_loops) = parent_links(&readable, &known_referents(&readable));
+    report.parent_loops = parent_loops;
+    let (file, genuine_roots) = build_file(&readable, &parent_of, &props);
+    let mut dom = try_decode(&file);
+    if dom.is_none() {
+        // find the property chunks rbx_binary chokes on by trying them one at a time
+        props.retain(|entry| {
+            let ok = try_decode(&build_file(&readable, &parent_of, &[*entry]).0).is_some();
+            if !ok {
+                let (_, prop) = entry;
+                report.dropped_props.push(format!(
+                    "{}.{}",
+                    class_names.get(&prop.class_id).copied().unwrap_or("?"),
+                    prop.prop_name
+                ));
+            }
+            ok
+        });
+        dom = try_decode(&build_file(&readable, &parent_of, &props).0);
+    }
+    let mut dom = dom.ok_or_else(|| rbxl_chunks::chunk_err("no decodable instances could be recovered"))?;
+
+    // services stay at the top level even when their PRNT entry was lost
+    let orphans: Vec<_> = dom.root().children()[genuine_roots..]
+        .iter()
+        .copied()
+        .filter(|&referent| {
+            dom.get_by_ref(referent)
+                .is_some_and(|instance| !is_service_class(&readable, instance.class.as_str()))
+        })
+        .collect();
+    if !orphans.is_empty() {
+        let root_ref = dom.root_ref();
+        let folder = dom.insert(root_ref, InstanceBuilder::new("Folder").with_name(ORPHAN_FOLDER_NAME));
+        for &orphan in &orphans {
+            dom.transfer_within(orphan, folder);
+        }
+    }
+
+    // a chain too deep to write out is better refused here than as a stack overflow in rbx_xml
+    dom_limits::check_dom(&dom, limits).map_err(|e| ConversionError::BinaryPlaceParse(format!("recovered place: {}", e)))?;
+
+    report.orphaned_instances = orphans.len();
+    report.recovered_instances = dom.descendants().count() - 1 - usize::from(!orphans.is_empty());
+    Ok((dom, report))
+}
+
+fn is_service_class(readable: &ReadableChunks, class: &str) -> bool {
+    readable
+        .inst
+        .iter()
+        .any(|(_, inst)| inst.is_service && inst.class_name == class)
+}
@@ -0,0 +1,129 @@
+// a box cut out of a place: the parts in it and the models they're in, as a model file
+//
+// giant maps are more parts than old clients can take, so they get split into district sized
+// pieces. a part is in the box when its world space bounds touch it. a part that's in a model
+// brings the whole model (the nearest one up from it), so a house on the edge isn't cut in half,
+// and the same house can end up in both pieces. the folders and models above what's taken are
+// copied without their other children, so it goes back where it was when inserted.
+use crate::world_space::{has_terrain, is_a, part_bounds, service};
+use rbx_dom_weak::types::{Ref, Vector3};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_types::Variant;
+use std::collections::{HashMap, HashSet};
+
+// "x,y,z"
+pub fn parse_point(s: &str) -> Result<Vector3, String> {
+    let numbers: Vec<f32> = s
+        .split(',')
+        .map(|part| part.trim().parse::<f32>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("expected x,y,z, got '{}'", s))?;
+    match numbers[..] {
+        [x, y, z] => Ok(Vector3::new(x, y, z)),
+        _ => Err(format!("expected 3 numbers for x,y,z, got {}", numbers.len())),
+    }
+}
+
+pub struct Region {
+    pub dom: WeakDom,
+    // parts touching the box
+    pub parts: usize,
+    pub models: usize,
+    // everything in the model file, models bring parts from outside the box
+    pub total_parts: usize,
+    pub terrain: bool,
+}
+
+// the nearest model between a part and the workspace, or the part itself
+fn unit_of(dom: &WeakDom, part: Ref, workspace: Ref) -> Ref {
+    let mut current = dom.get_by_ref(part).map_or(Ref::none(), |instance| instance.parent());
+    while current != workspace {
+        let Some(instance) = dom.get_by_ref(current) else { break };
+        if is_a(&instance.class, "Model") {
+            return current;
+        }
+        current = instance.parent();
+    }
+    part
+}
+
+// the folders and models from the workspace down to `referent`, outermost first
+pub(crate) fn containers(dom: &WeakDom, referent: Ref, workspace: Ref) -> Vec<Ref> {
+    let mut chain = Vec::new();
+    let mut current = dom.get_by_ref(referent).map_or(Ref::none(), |instance| instance.parent());
+    while current != workspace {
+        let Some(instance) = dom.get_by_ref(current) else { break };
+        chain.push(current);
+        current = instance.parent();
+    }
+    chain.reverse();
+    chain
+}
+
+// what the parts bring along, each once and none inside another one
+pub(crate) fn units(dom: &WeakDom, parts: &[Ref], workspace: Ref) -> Vec<Ref> {
+    let mut units = Vec::new();
+    let mut seen = HashSet::new();
+    for &part in parts {
+        let unit = unit_of(dom, part, workspace);
+        if seen.insert(unit) {
+            units.push(unit);
+        }
+    }
+    // a model inside another one that's taken comes with it
+    units.retain(|&unit| !containers(dom, unit, workspace).iter().any(|container| seen.contains(container)));
+    units
+}
+
+pub fn extract_region(dom: &WeakDom, a: Vector3, b: Vector3) -> Result<Region, String> {
+    let min = Vector3::new(a.x.min(b.x), a.y.min(b.y), a.z.min(b.z));
+    let max = Vector3::new(a.x.max(b.x), a.y.max(b.y), a.z.max(b.z));
+    let workspace = service(dom, "Workspace").ok_or("the place has no workspace")?;
+
+    let inside: Vec<Ref> = dom
+        .descendants_of(workspace)
+        .filter(|instance| instance.class != "Terrain" && is_a(&instance.class, "BasePart"))
+        .filter(|instance| {
+            part_bounds(instance).is_some_and(|(low, high)| {
+                low.x <= max.x && high.x >= min.x && low.y <= max.y && high.y >= min.y && low.z <= max.z && high.z >= min.z
+            })
+        })
+        .map(|instance| instance.referent())
+        .collect();
+
+    let units = units(dom, &inside, workspace);
+    let mut out = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let out_root = out.root_ref();
+    let copies = dom.clone_multiple_into_external(&units, &mut out);
+    let mut copied_containers: HashMap<Ref, Ref> = HashMap::new();
+    for (&unit, &copy) in units.iter().zip(&copies) {
+        let mut parent = out_root;
+        for container in containers(dom, unit, workspace) {
+            parent = *copied_containers.entry(container).or_insert_with(|| {
+                let instance = dom.get_by_ref(container).expect("containers only holds instances in the dom");
+                // refs would point at things that weren't taken
+                let properties = instance
+                    .properties
+                    .iter()
+                    .filter(|(_, value)| !matches!(value, Variant::Ref(_)))
+                    .map(|(name, value)| (*name, value.clone()));
+                let builder = InstanceBuilder::new(instance.class).with_name(instance.name.as_str()).with_properties(properties);
+                out.insert(parent, builder)
+            });
+        }
+        out.transfer_within(copy, parent);
+    }
+
+    let models = units
+        .iter()
+        .filter(|&&unit| dom.get_by_ref(unit).is_some_and(|instance| is_a(&instance.class, "Model")))
+        .count();
+    let total_parts = out.descendants().filter(|instance| is_a(&instance.class, "BasePart")).count();
+    Ok(Region {
+        parts: inside.len(),
+        models,
+        total_parts,
+        terrain: has_terrain(dom),
+        dom: out,
+    })
+}
@@ -0,0 +1,179 @@
+// interactive editing of a loaded place
+//
+// paths are relative to the current instance in the syntax from path.rs, ".." goes up and "/"
+// (or game) goes back to the root. rm takes wildcards and removes everything they match. property values are parsed as json (true, 5, [1, 2, 3], {"type": ...}),
+// anything that isn't valid json is taken as a plain string.
+use crate::mappings::json_to_variant;
+use crate::path::{path_of, InstancePath};
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{Ustr, WeakDom};
+use serde_json::Value;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+const HELP: &str = "\
+commands:
+  ls                  list children of the current instance
+  cd <path>           move to a child path (Workspace.Map, Map[\"Spawn 1\"]), .. for the parent, / for the root
+  pwd                 print the current path
+  props               list properties of the current instance
+  set <prop> <value>  set a property, value is json or a plain string
+  unset <prop>        remove a property
+  rename <name>       rename the current instance
+  rm [path]           delete a child, or everything a wildcard path (Map.**.Script) matches, or the
+                      current instance and move to its parent
+  save <file>         write the place, .rbxlx/.rbxmx as xml, anything else binary
+  exit                leave without saving";
+
+struct Session {
+    dom: WeakDom,
+    current: Ref,
+    dirty: bool,
+}
+
+fn resolve(dom: &WeakDom, from: Ref, path: &str) -> Result<Ref, String> {
+    path.parse::<InstancePath>()?.resolve_one(dom, from)
+}
+
+fn current_path(dom: &WeakDom, referent: Ref) -> String {
+    format!("/{}", path_of(dom, referent))
+}
+
+impl Session {
+    fn run_command(&mut self, command: &str, arg: &str) -> Result<bool, Box<dyn Error>> {
+        match command {
+            "help" | "?" => println!("{}", HELP),
+            "exit" | "quit" => {
+                if self.dirty {
+                    println!("discarding unsaved changes");
+                }
+                return Ok(false);
+            }
+            "pwd" => println!("{}", current_path(&self.dom, self.current)),
+            "ls" => {
+                let target = if arg.is_empty() {
+                    self.current
+                } else {
+                    resolve(&self.dom, self.current, arg)?
+                };
+                let instance = self.dom.get_by_ref(target).ok_or("current instance is gone")?;
+                for &child in instance.children() {
+                    if let Some(child) = self.dom.get_by_ref(child) {
+                        println!(
+                            "  {:<24} {}{}",
+                            child.class.as_str(),
+                            child.name,
+                            if child.children().is_empty() { "" } else { "/" }
+                        );
+                    }
+                }
+            }
+            "cd" => {
+                let arg = if arg.is_empty() { "/" } else { arg };
+                self.current = resolve(&self.dom, self.current, arg)?;
+            }
+            "props" => {
+                let instance = self.dom.get_by_ref(self.current).ok_or("current instance is gone")?;
+                let mut props: Vec<_> = instance.properties.iter().collect();
+                props.sort_by_key(|(name, _)| name.as_str());
+                println!("  ClassName = {}", instance.class);
+                for (name, value) in props {
+                    println!("  {} = {:?}", name, value);
+                }
+            }
+            "set" => {
+                let (prop_name, raw) = arg.split_once(char::is_whitespace).ok_or("usage: set <prop> <value>")?;
+                let raw = raw.trim();
+                let json = serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_owned()));
+                let key = Ustr::from(prop_name);
+                let instance = self.dom.get_by_ref_mut(self.current).ok_or("current instance is gone")?;
+                let value = json_to_variant(&json, instance.properties.get(&key))
+                    .ok_or_else(|| format!("can't use '{}' as a value for {}", raw, prop_name))?;
+                instance.properties.insert(key, value);
+                self.dirty = true;
+            }
+            "unset" => {
+                let instance = self.dom.get_by_ref_mut(self.current).ok_or("current instance is gone")?;
+                if instance.properties.remove(&Ustr::from(arg)).is_none() {
+                    return Err(format!("'{}' has no property {}", instance.name, arg).into());
+                }
+                self.dirty = true;
+            }
+            "rename" => {
+                if self.current == self.dom.root_ref() {
+                    return Err("can't rename the root".into());
+                }
+                let instance = self.dom.get_by_ref_mut(self.current).ok_or("current instance is gone")?;
+                instance.name = arg.to_owned();
+                self.dirty = true;
+            }
+            "rm" => {
+                let targets = if arg.is_empty() {
+                    vec![self.current]
+                } else {
+                    arg.parse::<InstancePath>()?.resolve(&self.dom, self.current)
+                };
+                if targets.is_empty() {
+                    return Err(format!("no instance at '{}'", arg).into());
+                }
+                if targets.contains(&self.dom.root_ref()) {
+                    return Err("can't remove the root".into());
+                }
+                let mut removed = 0;
+                for target in targets {
+                    // an earlier match can take later ones with it
+                    if self.dom.get_by_ref(target).is_none() {
+                        continue;
+                    }
+                    // don't leave the cursor inside what's being deleted
+                    if self.dom.ancestors_of(self.current).any(|instance| instance.referent() == target) {
+                        self.current = self.dom.get_by_ref(target).map_or(self.dom.root_ref(), |instance| instance.parent());
+                    }
+                    self.dom.destroy(target);
+                    removed += 1;
+                }
+                if removed > 1 {
+                    println!("removed {} instances", removed);
+                }
+                self.dirty = true;
+            }
+            "save" => {
+                if arg.is_empty() {
+                    return Err("usage: save <file>".into());
+                }
+                crate::save_dom(&self.dom, Path::new(arg))?;
+                self.dirty = false;
+                println!("saved {}", arg);
+            }
+            "" => {}
+            other => println!("unknown command '{}', try help", other),
+        }
+        Ok(true)
+    }
+}
+
+pub fn run(dom: WeakDom) -> Result<(), Box<dyn Error>> {
+    let mut session = Session {
+        current: dom.root_ref(),
+        dom,
+        dirty: false,
+    };
+    println!("loaded {} instances, type help for commands", session.dom.descendants().count() - 1);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{}> ", current_path(&session.dom, session.current));
+        io::stdout().flush()?;
+        let Some(line) = lines.next() else { break };
+        let line = line?;
+        let line = line.trim();
+        let (command, arg) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match session.run_command(command, arg.trim()) {
+            Ok(true) => {}
+            Ok(false) => break,
+            Err(e) => println!("error: {}", e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,195 @@
+// FilteringEnabled and streaming settings for the client a place is going to
+//
+// before 2014 there was no FilteringEnabled (or RemoteEvents), every change a client made went
+// to everyone. from 2014 it was a saved Workspace setting that defaulted off, and from 2018 the
+// engine forces it on and stops saving it. a game only works in one of those models, so besides
+// setting the flag this looks through the scripts for signs the game was built for the other one.
+//
+// rbx_xml and rbx_binary skip FilteringEnabled since current files don't carry it, the xml output
+// gets it put back afterwards by write_filtering_enabled.
+use crate::pipeline::PassContext;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::{Ustr, WeakDom};
+use rbx_types::Variant;
+use std::io::Cursor;
+use xml::reader::{EventReader, XmlEvent};
+use xml::writer::{EmitterConfig, XmlEvent as WriteEvent};
+
+const FILTERING_ENABLED_YEAR: u32 = 2014;
+const FILTERING_FORCED_YEAR: u32 = 2018;
+const STREAMING_YEAR: u32 = 2013;
+// StreamingMinRadius, StreamingTargetRadius, StreamingPauseMode and friends
+const STREAMING_SETTINGS_YEAR: u32 = 2019;
+
+const REMOTE_CLASSES: [&str; 3] = ["RemoteEvent", "RemoteFunction", "UnreliableRemoteEvent"];
+const REMOTE_CALLS: [&str; 6] = [
+    "FireServer(",
+    "InvokeServer(",
+    "OnServerEvent",
+    "OnServerInvoke",
+    "FireClient(",
+    "FireAllClients(",
+];
+// a LocalScript touching these expects its changes to reach everyone
+const SHARED_STATE: [&str; 3] = ["leaderstats", "workspace", "Workspace"];
+const WRITES: [&str; 4] = [".Value =", ".Parent =", ":Destroy()", ":Remove()"];
+
+#[derive(Default)]
+struct ScriptModel {
+    // scripts calling remotes, plus RemoteEvent/RemoteFunction instances
+    remotes: usize,
+    // LocalScripts that change workspace or leaderstats directly
+    client_writes: usize,
+}
+
+fn script_model(dom: &WeakDom) -> ScriptModel {
+    let mut model = ScriptModel::default();
+    for instance in dom.descendants() {
+        if REMOTE_CLASSES.contains(&instance.class.as_str()) {
+            model.remotes += 1;
+        }
+        let Some(Variant::String(source)) = instance.properties.get(&"Source".into()) else { continue };
+        if REMOTE_CALLS.iter().any(|call| source.contains(call)) {
+            model.remotes += 1;
+        }
+        if instance.class == "LocalScript"
+            && SHARED_STATE.iter().any(|name| source.contains(name))
+            && WRITES.iter().any(|write| source.contains(write))
+        {
+            model.client_writes += 1;
+        }
+    }
+    model
+}
+
+fn workspace(dom: &WeakDom) -> Option<Ref> {
+    dom.root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == "Workspace"))
+}
+
+pub fn apply_replication_flags(dom: &mut WeakDom, year: u32, ctx: &mut PassContext) {
+    let model = script_model(dom);
+    let Some(workspace) = workspace(dom) else {
+        ctx.warn("no workspace, leaving replication settings alone");
+        return;
+    };
+    let Some(instance) = dom.get_by_ref_mut(workspace) else { return };
+    let filtering_key = Ustr::from("FilteringEnabled");
+
+    if year < FILTERING_ENABLED_YEAR {
+        instance.properties.remove(&filtering_key);
+        if model.remotes > 0 {
+            ctx.warn(format!(
+                "{} scripts/instances use remotes, which don't exist in {}, the game expects FilteringEnabled",
+                model.remotes, year
+            ));
+        }
+    } else if year < FILTERING_FORCED_YEAR {
+        let current = matches!(instance.properties.get(&filtering_key), Some(Variant::Bool(true)));
+        // remotes are the only way an FE game gets anything to the server, so they decide it
+        let filtering = current || model.remotes > 0;
+        instance.properties.insert(filtering_key, Variant::Bool(filtering));
+        ctx.info(format!("FilteringEnabled set to {} for {}", filtering, year));
+        if filtering && model.client_writes > 0 {
+            ctx.warn(format!(
+                "{} LocalScripts change workspace or leaderstats directly, with FilteringEnabled nobody else sees it",
+                model.client_writes
+            ));
+        }
+    } else {
+        instance.properties.insert(filtering_key, Variant::Bool(true));
+        if model.client_writes > 0 && model.remotes == 0 {
+            ctx.warn(format!(
+                "no remotes but {} LocalScripts change workspace or leaderstats, this looks like an experimental \
+                 mode game and {} forces FilteringEnabled",
+                model.client_writes, year
+            ));
+        }
+    }
+
+    let streaming_keys: Vec<Ustr> = instance
+        .properties
+        .keys()
+        .filter(|key| key.contains("Streaming") && key.as_str() != "StreamingEnabled")
+        .copied()
+        .collect();
+    if year < STREAMING_SETTINGS_YEAR {
+        for key in streaming_keys {
+            instance.properties.remove(&key);
+        }
+        let streaming_key = Ustr::from("StreamingEnabled");
+        if year < STREAMING_YEAR {
+            instance.properties.remove(&streaming_key);
+        } else if instance.properties.get(&streaming_key) == Some(&Variant::Bool(true)) {
+            // the old streaming had no radius or persistence settings and dropped parts the game
+            // expected to be there, not worth it
+            instance.properties.insert(streaming_key, Variant::Bool(false));
+            ctx.warn(format!("turned off StreamingEnabled, streaming in {} can't be configured like this place expects", year));
+        }
+    }
+}
+
+// the FilteringEnabled the dom's Workspace ends up with, None when there isn't one
+pub fn filtering_enabled(dom: &WeakDom) -> Option<bool> {
+    match dom.get_by_ref(workspace(dom)?)?.properties.get(&"FilteringEnabled".into()) {
+        Some(Variant::Bool(value)) => Some(*value),
+        _ => None,
+    }
+}
+
+type XmlResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// adds <bool name="FilteringEnabled"> to the Workspace of rbx_xml output
+pub fn write_filtering_enabled(xml_bytes: &[u8], value: bool) -> XmlResult<Vec<u8>> {
+    let mut output = Vec::new();
+    // whitespace is passed through untouched, so this is the only thing that changes
+    let mut writer = EmitterConfig::new()
+        .write_document_declaration(false)
+        .normalize_empty_elements(false)
+        .create_writer(&mut output);
+
+    // element depth, and the depth of the Workspace Item while inside it
+    let mut depth = 0usize;
+    let mut workspace_depth = None;
+    let mut written = false;
+    for event in EventReader::new(Cursor::new(xml_bytes)) {
+        let event = event?;
+        match &event {
+            XmlEvent::StartDocument { .. } => continue,
+            XmlEvent::Whitespace(text) => {
+                writer.write(WriteEvent::characters(text))?;
+                continue;
+            }
+            XmlEvent::StartElement { name, attributes, .. } => {
+                depth += 1;
+                let is_workspace = name.local_name == "Item"
+                    && attributes
+                        .iter()
+                        .any(|attr| attr.name.local_name == "class" && attr.value == "Workspace");
+                if is_workspace && workspace_depth.is_none() {
+                    workspace_depth = Some(depth);
+                }
+            }
+            XmlEvent::EndElement { name } => {
+                if !written && name.local_name == "Properties" && workspace_depth == Some(depth - 1) {
+                    writer.write(WriteEvent::start_element("bool").attr("name", "FilteringEnabled"))?;
+                    writer.write(WriteEvent::characters(if value { "true" } else { "false" }))?;
+                    writer.write(WriteEvent::end_element())?;
+                    written = true;
+                }
+                if workspace_depth == Some(depth) {
+                    workspace_depth = None;
+                }
+                depth -= 1;
+            }
+            _ => {}
+        }
+        if let Some(write_event) = event.as_writer_event() {
+            writer.write(write_event)?;
+        }
+    }
+    Ok(output)
+}
@@ -0,0 +1,180 @@
+// signed GETs from an s3 bucket, or anything speaking its api (minio, r2 and the like)
+//
+// requests are signed with aws signature v4 when AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY are
+// set (AWS_SESSION_TOKEN too, for temporary ones) and go out unsigned for a public bucket when
+// they aren't. the region is AWS_REGION or AWS_DEFAULT_REGION, us-east-1 without either. with
+// AWS_ENDPOINT_URL set, objects are at <endpoint>/<bucket>/<key>, otherwise at
+// https://<bucket>.s3.<region>.amazonaws.com/<key>. the body isn't hashed, only small GETs are
+// made and the signature covers the rest.
+//
+// a missing key is a 404 only when the caller may list the bucket. without that, public buckets
+// included, s3 answers 403 AccessDenied so as not to say whether the key exists, so that counts
+// as missing too and the next name gets tried.
+use crate::asset_source::{self, Download};
+use crate::content::sha256_hex;
+use chrono::Utc;
+use sha2::{Digest, Sha256};
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Write as FmtWrite};
+
+const DEFAULT_REGION: &str = "us-east-1";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+const BLOCK_SIZE: usize = 64;
+
+#[derive(Clone)]
+struct Credentials {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+}
+
+// the secrets stay out of any {:?}
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("access_key", &self.access_key)
+            .field("secret_key", &"<redacted>")
+            .field("session_token", &self.session_token.as_ref().map(|_| "<redacted>"))
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct S3Bucket {
+    pub bucket: String,
+    region: String,
+    endpoint: Option<String>,
+    credentials: Option<Credentials>,
+}
+
+fn is_missing(status: u16, body: &str) -> bool {
+    status == 404
+        || (status == 403 && ["<Code>AccessDenied</Code>", "<Code>NoSuchKey</Code>"].iter().any(|code| body.contains(code)))
+}
+
+fn env_var(name: &str) -> Option<String> {
+    env::var(name).ok().filter(|value| !value.is_empty())
+}
+
+fn hmac(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut block = [0u8; BLOCK_SIZE];
+    if key.len() > BLOCK_SIZE {
+        block[..32].copy_from_slice(&Sha256::digest(key));
+    } else {
+        block[..key.len()].copy_from_slice(key);
+    }
+    let mut inner = Sha256::new();
+    inner.update(block.map(|byte| byte ^ 0x36));
+    inner.update(message);
+    let mut outer = Sha256::new();
+    outer.update(block.map(|byte| byte ^ 0x5c));
+    outer.update(inner.finalize());
+    outer.finalize().to_vec()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+// every byte but the unreserved ones percent encoded, slashes kept between segments
+fn encode_path(path: &str) -> String {
+    let mut out = String::new();
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => out.push(byte as char),
+            _ => {
+                let _ = write!(out, "%{:02X}", byte);
+            }
+        }
+    }
+    out
+}
+
+impl S3Bucket {
+    // the bucket with the region, endpoint and credentials from the environment
+    pub fn from_env(bucket: &str) -> Self {
+        let credentials = match (env_var("AWS_ACCESS_KEY_ID"), env_var("AWS_SECRET_ACCESS_KEY")) {
+            (Some(access_key), Some(secret_key)) => Some(Credentials {
+                access_key,
+                secret_key,
+                session_token: env_var("AWS_SESSION_TOKEN"),
+            }),
+            _ => None,
+        };
+        Self {
+            bucket: bucket.to_owned(),
+            region: env_var("AWS_REGION")
+                .or_else(|| env_var("AWS_DEFAULT_REGION"))
+                .unwrap_or_else(|| DEFAULT_REGION.to_owned()),
+            endpoint: env_var("AWS_ENDPOINT_URL").map(|endpoint| endpoint.trim_end_matches('/').to_owned()),
+            credentials,
+        }
+    }
+
+    // (scheme and host, host, path) of an object
+    fn locate(&self, key: &str) -> (String, String, String) {
+        match &self.endpoint {
+            Some(endpoint) => {
+                let host = endpoint.split_once("://").map_or(endpoint.as_str(), |(_, host)| host).to_owned();
+                (endpoint.clone(), host, encode_path(&format!("/{}/{}", self.bucket, key)))
+            }
+            None => {
+                let host = format!("{}.s3.{}.amazonaws.com", self.bucket, self.region);
+                (format!("https://{}", host), host, encode_path(&format!("/{}", key)))
+            }
+        }
+    }
+
+    fn located(&self, download: Download, key: &str) -> Download {
+        Download {
+            location: format!("s3://{}/{}", self.bucket, key),
+            ..download
+        }
+    }
+
+    // None when there's no such object, or no access to it
+    pub fn get(&self, key: &str) -> Result<Option<Download>, Box<dyn Error>> {
+        let (base, host, path) = self.locate(key);
+        let url = format!("{}{}", base, path);
+        let Some(credentials) = &self.credentials else {
+            return Ok(asset_source::download_unless_missing(&url, &[], is_missing)?.map(|download| self.located(download, key)));
+        };
+
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let date = now.format("%Y%m%d").to_string();
+        let mut headers = vec![
+            ("host", host),
+            ("x-amz-content-sha256", UNSIGNED_PAYLOAD.to_owned()),
+            ("x-amz-date", amz_date.clone()),
+        ];
+        if let Some(token) = &credentials.session_token {
+            headers.push(("x-amz-security-token", token.clone()));
+        }
+        let canonical_headers: String = headers.iter().map(|(name, value)| format!("{}:{}\n", name, value.trim())).collect();
+        let signed_headers = headers.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+        let canonical_request = format!("GET\n{}\n\n{}\n{}\n{}", path, canonical_headers, signed_headers, UNSIGNED_PAYLOAD);
+        let scope = format!("{}/{}/s3/aws4_request", date, self.region);
+        let string_to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, sha256_hex(canonical_request.as_bytes()));
+
+        let key_date = hmac(format!("AWS4{}", credentials.secret_key).as_bytes(), date.as_bytes());
+        let key_region = hmac(&key_date, self.region.as_bytes());
+        let key_service = hmac(&key_region, b"s3");
+        let signing_key = hmac(&key_service, b"aws4_request");
+        let signature = hex(&hmac(&signing_key, string_to_sign.as_bytes()));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            credentials.access_key, scope, signed_headers, signature
+        );
+
+        // ureq sends host itself, from the url
+        let mut request_headers: Vec<(&str, &str)> =
+            headers.iter().filter(|(name, _)| *name != "host").map(|(name, value)| (*name, value.as_str())).collect();
+        request_headers.push(("Authorization", &authorization));
+        Ok(asset_source::download_unless_missing(&url, &request_headers, is_missing)?.map(|download| self.located(download, key)))
+    }
+}
@@ -1,6 +1,7 @@
-use crate::error::Result;
+use crate::error::{ConversionError, Result};
 use crate::mesh_types::*;
 use byteorder::{LittleEndian, WriteBytesExt};
+use std::collections::HashMap;
 use std::io::Write;
 
 pub enum V1Version {
@@ -8,29 +9,79 @@ pub enum V1Version {
     V1_01,
 }
 
+// how an ascii mesh is laid out. the default is what this tool has always written, legacy is
+// meant to be what roblox's own v1 exporter wrote, which old client mesh caches compare hashes
+// of. it hasn't been checked byte for byte against a file from that exporter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct V1Format {
+    // \r\n after the header and the face count
+    pub crlf: bool,
+    // numbers like msvc's printf %g (0.5, 1, -0.0975, 1.5e-005) instead of always six decimals
+    pub legacy_numbers: bool,
+}
+
+impl V1Format {
+    pub const LEGACY: Self = Self { crlf: true, legacy_numbers: true };
+}
+
+fn trim_zeros(number: &str) -> &str {
+    if number.contains('.') { number.trim_end_matches('0').trim_end_matches('.') } else { number }
+}
+
+// printf("%g") as the msvc runtimes before 2015 did it, six significant digits with the zeros
+// after them dropped and an exponent of at least three digits (1.5e-005 where glibc has 1.5e-05).
+// the float goes through a double first like it does in a printf call
+fn legacy_number(value: f32) -> String {
+    let value = value as f64;
+    if !value.is_finite() {
+        let text = if value.is_nan() { "1.#QNAN" } else { "1.#INF" };
+        return if value.is_sign_negative() { format!("-{}", text) } else { text.to_owned() };
+    }
+    if value == 0.0 {
+        return if value.is_sign_negative() { "-0".to_owned() } else { "0".to_owned() };
+    }
+    // rounded to six digits first, that's what decides whether it gets an exponent
+    let scientific = format!("{:.5e}", value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    if !(-4..6).contains(&exponent) {
+        format!("{}e{}{:03}", trim_zeros(mantissa), if exponent < 0 { '-' } else { '+' }, exponent.abs())
+    } else {
+        trim_zeros(&format!("{:.*}", (5 - exponent) as usize, value)).to_owned()
+    }
+}
+
 pub fn write_v1(mesh: &IntermediateMesh, version: V1Version) -> Result<Vec<u8>> {
+    write_v1_formatted(mesh, version, V1Format::default())
+}
+
+pub fn write_v1_formatted(mesh: &IntermediateMesh, version: V1Version, format: V1Format) -> Result<Vec<u8>> {
     let mut writer = Vec::new();
     let (header, scaler) = match version {
-        V1Version::V1_00 => ("version 1.00\n", 2.0f32),
-        V1Version::V1_01 => ("version 1.01\n", 1.0f32),
+        V1Version::V1_00 => ("version 1.00", 2.0f32),
+        V1Version::V1_01 => ("version 1.01", 1.0f32),
     };
+    let newline = if format.crlf { "\r\n" } else { "\n" };
+    let number = |value: f32| if format.legacy_numbers { legacy_number(value) } else { format!("{:.6}", value) };
 
-    write!(writer, "{}", header)?;
-    write!(writer, "{}\n", mesh.faces.len())?;
+    write!(writer, "{}{}", header, newline)?;
+    write!(writer, "{}{}", mesh.faces.len(), newline)?;
 
+    // all on one line, and nothing after the last face
     for face in &mesh.faces {
         for &vertex_index in face {
             let vertex = &mesh.vertices[vertex_index as usize];
             let p = vertex.pos;
             let n = vertex.normal;
-            let uv = vertex.uv;
+            // bottom left in the file, parse_v1 flips it back
+            let uv = [vertex.uv[0], 1.0 - vertex.uv[1]];
 
             write!(
                 writer,
-                "[{:.6},{:.6},{:.6}][{:.6},{:.6},{:.6}][{:.6},{:.6},{:.6}]",
-                p[0] * scaler, p[1] * scaler, p[2] * scaler,
-                n[0], n[1], n[2],
-                uv[0], uv[1], 0.0,
+                "[{},{},{}][{},{},{}][{},{},{}]",
+                number(p[0] * scaler), number(p[1] * scaler), number(p[2] * scaler),
+                number(n[0]), number(n[1]), number(n[2]),
+                number(uv[0]), number(uv[1]), number(0.0),
             )?;
         }
     }
@@ -40,7 +91,7 @@ pub fn write_v1(mesh: &IntermediateMesh, version: V1Version) -> Result<Vec<u8>>
 
 pub fn write_v2(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
     let mut writer = Vec::new();
-    write!(writer, "version 2.00\n")?;
+    writeln!(writer, "version 2.00")?;
 
     let num_verts = mesh.vertices.len() as u32;
     let num_faces = mesh.faces.len() as u32;
@@ -60,7 +111,8 @@ pub fn write_v2(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
             px: vertex.pos[0], py: vertex.pos[1], pz: vertex.pos[2],
             nx: vertex.normal[0], ny: vertex.normal[1], nz: vertex.normal[2],
             tu: vertex.uv[0], tv: vertex.uv[1],
-            ..Default::default()
+            r: vertex.color[0], g: vertex.color[1], b: vertex.color[2], a: vertex.color[3],
+            ..file_tangent(vertex.tangent)
         };
         writer.write_all(as_bytes(&file_vertex))?;
     }
@@ -73,19 +125,43 @@ pub fn write_v2(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
     Ok(writer)
 }
 
+// where each lod level starts and the last one ends. a mesh without lods gets the single 0 these
+// writers have always put there
+fn lod_offsets(mesh: &IntermediateMesh, lods: &[Vec<[u32; 3]>]) -> Vec<u32> {
+    if lods.is_empty() {
+        return vec![0];
+    }
+    let mut offsets = vec![0, mesh.faces.len() as u32];
+    for lod in lods {
+        offsets.push(offsets[offsets.len() - 1] + lod.len() as u32);
+    }
+    offsets
+}
+
+// 0 says there are no lods, clients only look at whether it's set
+fn lod_type(lods: &[Vec<[u32; 3]>]) -> u16 {
+    if lods.is_empty() { 0 } else { 2 }
+}
+
 pub fn write_v3(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
+    write_v3_with_lods(mesh, &[])
+}
+
+// lods are faces in the mesh's vertices, see mesh_lods.rs
+pub fn write_v3_with_lods(mesh: &IntermediateMesh, lods: &[Vec<[u32; 3]>]) -> Result<Vec<u8>> {
     let mut writer = Vec::new();
-    write!(writer, "version 3.00\n")?;
+    writeln!(writer, "version 3.00")?;
 
+    let offsets = lod_offsets(mesh, lods);
     let num_verts = mesh.vertices.len() as u32;
-    let num_faces = mesh.faces.len() as u32;
+    let num_faces = mesh.faces.len() as u32 + lods.iter().map(|lod| lod.len() as u32).sum::<u32>();
 
     let header = FileMeshHeaderV3 {
         sizeof_FileMeshHeaderV3: std::mem::size_of::<FileMeshHeaderV3>() as u16,
         sizeof_FileMeshVertex: std::mem::size_of::<FileMeshVertex>() as u8,
         sizeof_FileMeshFace: std::mem::size_of::<FileMeshFace>() as u8,
         sizeof_LodOffset: 4,
-        numLodOffsets: 1,
+        numLodOffsets: offsets.len() as u16,
         numVerts: num_verts,
         numFaces: num_faces,
     };
@@ -97,103 +173,302 @@ pub fn write_v3(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
             px: vertex.pos[0], py: vertex.pos[1], pz: vertex.pos[2],
             nx: vertex.normal[0], ny: vertex.normal[1], nz: vertex.normal[2],
             tu: vertex.uv[0], tv: vertex.uv[1],
-            ..Default::default()
+            r: vertex.color[0], g: vertex.color[1], b: vertex.color[2], a: vertex.color[3],
+            ..file_tangent(vertex.tangent)
         };
         writer.write_all(as_bytes(&file_vertex))?;
     }
 
-    for face in &mesh.faces {
+    for face in mesh.faces.iter().chain(lods.iter().flatten()) {
         let file_face = FileMeshFace { a: face[0], b: face[1], c: face[2] };
         writer.write_all(as_bytes(&file_face))?;
     }
 
-    writer.write_u32::<LittleEndian>(0)?;
+    for offset in offsets {
+        writer.write_u32::<LittleEndian>(offset)?;
+    }
 
     Ok(writer)
 }
 
+// what goes in a v4/v5 body. skinned meshes are split into subsets of at most MAX_SUBSET_BONES
+// bones each, with vertices shared between subsets copied so every subset has its own run
+struct MeshLayout {
+    vertices: Vec<IntermediateVertex>,
+    faces: Vec<[u32; 3]>,
+    envelopes: Vec<FileMeshEnvelope>,
+    bones: Vec<FileMeshBone>,
+    bone_names: Vec<u8>,
+    subsets: Vec<FileMeshSubset>,
+    lod_offsets: Vec<u32>,
+}
+
+impl MeshLayout {
+    fn new(mesh: &IntermediateMesh, lods: &[Vec<[u32; 3]>]) -> Result<Self> {
+        let Some(skin) = &mesh.skin else {
+            return Ok(Self {
+                vertices: mesh.vertices.clone(),
+                faces: mesh.faces.iter().chain(lods.iter().flatten()).copied().collect(),
+                envelopes: Vec::new(),
+                bones: Vec::new(),
+                bone_names: Vec::new(),
+                subsets: Vec::new(),
+                lod_offsets: lod_offsets(mesh, lods),
+            });
+        };
+        // subsets would need splitting per level
+        if !lods.is_empty() {
+            return Err(ConversionError::Unsupported("lods can't be generated for skinned meshes".to_owned()));
+        }
+        if skin.bones.len() >= u16::MAX as usize {
+            return Err(ConversionError::Unsupported(format!("too many bones ({})", skin.bones.len())));
+        }
+
+        let mut bone_names = Vec::new();
+        let mut bones = Vec::with_capacity(skin.bones.len());
+        for bone in &skin.bones {
+            bones.push(FileMeshBone {
+                boneNameIndex: bone_names.len() as u32,
+                parentIndex: bone.parent.map_or(0xFFFF, |parent| parent as u16),
+                lodParentIndex: bone.lod_parent,
+                culling: bone.culling,
+                rotation: bone.rotation,
+                position: bone.position,
+            });
+            bone_names.extend_from_slice(bone.name.as_bytes());
+            bone_names.push(0);
+        }
+
+        // bones actually pulling on a vertex
+        let vertex_bones = |vertex: u32| {
+            let (joints, weights) = (skin.joints[vertex as usize], skin.weights[vertex as usize]);
+            (0..4).filter(move |&slot| weights[slot] > 0.0).map(move |slot| joints[slot])
+        };
+
+        // greedily group faces, starting a new subset when one would go over the bone limit
+        let mut groups: Vec<(Vec<u16>, Vec<[u32; 3]>)> = vec![(Vec::new(), Vec::new())];
+        for face in &mesh.faces {
+            let mut face_bones: Vec<u16> = face.iter().flat_map(|&vertex| vertex_bones(vertex)).collect();
+            face_bones.sort_unstable();
+            face_bones.dedup();
+            let (group_bones, _) = groups.last().unwrap();
+            let new_bones = face_bones.iter().filter(|bone| !group_bones.contains(bone)).count();
+            if group_bones.len() + new_bones > MAX_SUBSET_BONES {
+                groups.push((Vec::new(), Vec::new()));
+            }
+            let (group_bones, group_faces) = groups.last_mut().unwrap();
+            for bone in face_bones {
+                if !group_bones.contains(&bone) {
+                    group_bones.push(bone);
+                }
+            }
+            group_faces.push(*face);
+        }
+
+        let mut layout = Self {
+            vertices: Vec::new(),
+            faces: Vec::new(),
+            envelopes: Vec::new(),
+            bones,
+            bone_names,
+            subsets: Vec::new(),
+            lod_offsets: lod_offsets(mesh, lods),
+        };
+        for (group_bones, group_faces) in groups.into_iter().filter(|(_, faces)| !faces.is_empty()) {
+            let verts_begin = layout.vertices.len();
+            let faces_begin = layout.faces.len();
+            let mut remap: HashMap<u32, u32> = HashMap::new();
+            for face in group_faces {
+                let face = face.map(|vertex| {
+                    *remap.entry(vertex).or_insert_with(|| {
+                        layout.vertices.push(mesh.vertices[vertex as usize]);
+                        layout.envelopes.push(envelope(
+                            skin.joints[vertex as usize],
+                            skin.weights[vertex as usize],
+                            &group_bones,
+                        ));
+                        (layout.vertices.len() - 1) as u32
+                    })
+                });
+                layout.faces.push(face);
+            }
+            let mut bone_indices = [0u16; MAX_SUBSET_BONES];
+            bone_indices[..group_bones.len()].copy_from_slice(&group_bones);
+            layout.subsets.push(FileMeshSubset {
+                facesBegin: faces_begin as u32,
+                facesLength: (layout.faces.len() - faces_begin) as u32,
+                vertsBegin: verts_begin as u32,
+                vertsLength: (layout.vertices.len() - verts_begin) as u32,
+                numBoneIndices: group_bones.len() as u32,
+                boneIndices: bone_indices,
+            });
+        }
+        Ok(layout)
+    }
+
+    fn write_body(&self, writer: &mut Vec<u8>) -> Result<()> {
+        for vertex in &self.vertices {
+            let file_vertex = FileMeshVertex {
+                px: vertex.pos[0], py: vertex.pos[1], pz: vertex.pos[2],
+                nx: vertex.normal[0], ny: vertex.normal[1], nz: vertex.normal[2],
+                tu: vertex.uv[0], tv: vertex.uv[1],
+                r: vertex.color[0], g: vertex.color[1], b: vertex.color[2], a: vertex.color[3],
+                ..file_tangent(vertex.tangent)
+            };
+            writer.write_all(as_bytes(&file_vertex))?;
+        }
+
+        for envelope in &self.envelopes {
+            writer.write_all(as_bytes(envelope))?;
+        }
+
+        for face in &self.faces {
+            let file_face = FileMeshFace { a: face[0], b: face[1], c: face[2] };
+            writer.write_all(as_bytes(&file_face))?;
+        }
+
+        for &offset in &self.lod_offsets {
+            writer.write_u32::<LittleEndian>(offset)?;
+        }
+
+        for bone in &self.bones {
+            writer.write_all(as_bytes(bone))?;
+        }
+        writer.write_all(&self.bone_names)?;
+        for subset in &self.subsets {
+            writer.write_all(as_bytes(subset))?;
+        }
+        Ok(())
+    }
+}
+
+// weights are stored as bytes that have to add up to 255, bone indices are into the subset
+fn envelope(joints: [u16; 4], weights: [f32; 4], subset_bones: &[u16]) -> FileMeshEnvelope {
+    let mut bones = [0u8; 4];
+    let mut bytes = [0u8; 4];
+    for slot in 0..4 {
+        if weights[slot] <= 0.0 {
+            continue;
+        }
+        bones[slot] = subset_bones.iter().position(|&bone| bone == joints[slot]).unwrap_or(0) as u8;
+        bytes[slot] = (weights[slot] * 255.0).round().clamp(0.0, 255.0) as u8;
+    }
+    // put any rounding error on the heaviest weight
+    let total: i32 = bytes.iter().map(|&b| b as i32).sum();
+    let heaviest = (0..4).max_by_key(|&slot| bytes[slot]).unwrap_or(0);
+    bytes[heaviest] = (bytes[heaviest] as i32 + 255 - total).clamp(0, 255) as u8;
+    FileMeshEnvelope { bones, weights: bytes }
+}
+
 pub fn write_v4(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
-    let mut writer = Vec::new();
-    write!(writer, "version 4.00\n")?;
+    write_v4_with_lods(mesh, &[])
+}
 
-    let num_verts = mesh.vertices.len() as u32;
-    let num_faces = mesh.faces.len() as u32;
+pub fn write_v4_with_lods(mesh: &IntermediateMesh, lods: &[Vec<[u32; 3]>]) -> Result<Vec<u8>> {
+    let mut writer = Vec::new();
+    writeln!(writer, "version 4.00")?;
 
+    let layout = MeshLayout::new(mesh, lods)?;
     let header = FileMeshHeaderV4 {
         sizeof_FileMeshHeaderV4: std::mem::size_of::<FileMeshHeaderV4>() as u16,
-        lodType: 0,
-        numVerts: num_verts,
-        numFaces: num_faces,
-        numLodOffsets: 1,
-        numBones: 0,
-        sizeof_boneNames: 0,
-        numSubsets: 0,
+        lodType: lod_type(lods),
+        numVerts: layout.vertices.len() as u32,
+        numFaces: layout.faces.len() as u32,
+        numLodOffsets: layout.lod_offsets.len() as u16,
+        numBones: layout.bones.len() as u16,
+        sizeof_boneNames: layout.bone_names.len() as u32,
+        numSubsets: layout.subsets.len() as u16,
         numHighQualityLODs: 1,
         unused: 0,
     };
-    
+
     writer.write_all(as_bytes(&header))?;
-    
-    for vertex in &mesh.vertices {
-        let file_vertex = FileMeshVertex {
-            px: vertex.pos[0], py: vertex.pos[1], pz: vertex.pos[2],
-            nx: vertex.normal[0], ny: vertex.normal[1], nz: vertex.normal[2],
-            tu: vertex.uv[0], tv: vertex.uv[1],
-            ..Default::default()
-        };
-        writer.write_all(as_bytes(&file_vertex))?;
+    layout.write_body(&mut writer)?;
+
+    Ok(writer)
+}
+
+fn name_buffer(names: &[String]) -> Vec<u8> {
+    let mut buffer = Vec::new();
+    for name in names {
+        buffer.extend_from_slice(name.as_bytes());
+        buffer.push(0);
     }
+    buffer
+}
 
-    for face in &mesh.faces {
-        let file_face = FileMeshFace { a: face[0], b: face[1], c: face[2] };
-        writer.write_all(as_bytes(&file_face))?;
+// facs block, format 1. matrices are always written quantized to u16 between their min and max
+fn write_facs(facs: &MeshFacs) -> Result<Vec<u8>> {
+    let rows = facs.bone_names.len();
+    let cols = facs.control_names.len();
+    if facs.transforms.iter().any(|channel| channel.len() != rows * cols) {
+        return Err(ConversionError::Unsupported("facs transforms don't match the bone and control counts".to_owned()));
     }
 
-    writer.write_u32::<LittleEndian>(0)?;
+    let mut transforms = Vec::new();
+    for channel in &facs.transforms {
+        let low = channel.iter().copied().fold(f32::INFINITY, f32::min).min(0.0);
+        let high = channel.iter().copied().fold(f32::NEG_INFINITY, f32::max).max(0.0);
+        let range = high - low;
+        transforms.write_u16::<LittleEndian>(2)?;
+        transforms.write_u32::<LittleEndian>(rows as u32)?;
+        transforms.write_u32::<LittleEndian>(cols as u32)?;
+        transforms.write_f32::<LittleEndian>(low)?;
+        transforms.write_f32::<LittleEndian>(high)?;
+        for &value in channel {
+            let q = if range > 0.0 { ((value - low) / range * u16::MAX as f32).round() } else { 0.0 };
+            transforms.write_u16::<LittleEndian>(q as u16)?;
+        }
+    }
 
+    let bone_names = name_buffer(&facs.bone_names);
+    let control_names = name_buffer(&facs.control_names);
+    let mut writer = Vec::new();
+    writer.write_u32::<LittleEndian>(bone_names.len() as u32)?;
+    writer.write_u32::<LittleEndian>(control_names.len() as u32)?;
+    writer.write_u64::<LittleEndian>(transforms.len() as u64)?;
+    writer.write_u32::<LittleEndian>((facs.two_pose_correctives.len() * 4) as u32)?;
+    writer.write_u32::<LittleEndian>((facs.three_pose_correctives.len() * 6) as u32)?;
+    writer.write_all(&bone_names)?;
+    writer.write_all(&control_names)?;
+    writer.write_all(&transforms)?;
+    for corrective in facs.two_pose_correctives.iter().flatten().chain(facs.three_pose_correctives.iter().flatten()) {
+        writer.write_u16::<LittleEndian>(*corrective)?;
+    }
     Ok(writer)
 }
 
 pub fn write_v5(mesh: &IntermediateMesh) -> Result<Vec<u8>> {
-    let mut writer = Vec::new();
-    write!(writer, "version 5.00\n")?;
+    write_v5_with_lods(mesh, &[])
+}
 
-    let num_verts = mesh.vertices.len() as u32;
-    let num_faces = mesh.faces.len() as u32;
+pub fn write_v5_with_lods(mesh: &IntermediateMesh, lods: &[Vec<[u32; 3]>]) -> Result<Vec<u8>> {
+    let mut writer = Vec::new();
+    writeln!(writer, "version 5.00")?;
 
+    let layout = MeshLayout::new(mesh, lods)?;
+    let facs = match &mesh.facs {
+        Some(facs) => write_facs(facs)?,
+        None => Vec::new(),
+    };
     let header = FileMeshHeaderV5 {
         sizeof_MeshHeader: std::mem::size_of::<FileMeshHeaderV5>() as u16,
-        lodType: 0,
-        numVerts: num_verts,
-        numFaces: num_faces,
-        numLodOffsets: 1,
-        numBones: 0,
-        sizeof_boneNameBuffer: 0,
-        numSubsets: 0,
+        lodType: lod_type(lods),
+        numVerts: layout.vertices.len() as u32,
+        numFaces: layout.faces.len() as u32,
+        numLodOffsets: layout.lod_offsets.len() as u16,
+        numBones: layout.bones.len() as u16,
+        sizeof_boneNameBuffer: layout.bone_names.len() as u32,
+        numSubsets: layout.subsets.len() as u16,
         numHighQualityLODs: 1,
         unusedPadding: 0,
-        facsDataFormat: 0,
-        facsDataSize: 0,
+        facsDataFormat: if facs.is_empty() { 0 } else { 1 },
+        facsDataSize: facs.len() as u32,
     };
-    
-    writer.write_all(as_bytes(&header))?;
 
-    for vertex in &mesh.vertices {
-        let file_vertex = FileMeshVertex {
-            px: vertex.pos[0], py: vertex.pos[1], pz: vertex.pos[2],
-            nx: vertex.normal[0], ny: vertex.normal[1], nz: vertex.normal[2],
-            tu: vertex.uv[0], tv: vertex.uv[1],
-            ..Default::default()
-        };
-        writer.write_all(as_bytes(&file_vertex))?;
-    }
-
-    for face in &mesh.faces {
-        let file_face = FileMeshFace { a: face[0], b: face[1], c: face[2] };
-        writer.write_all(as_bytes(&file_face))?;
-    }
-
-    writer.write_u32::<LittleEndian>(0)?;
+    writer.write_all(as_bytes(&header))?;
+    layout.write_body(&mut writer)?;
+    writer.write_all(&facs)?;
 
     Ok(writer)
 }
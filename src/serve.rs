@@ -0,0 +1,500 @@
+// small http front end for the conversions
+//
+//   GET  /health
+//   POST /mesh/obj-to-filemesh?version=v2_00
+//   POST /mesh/filemesh-to-obj
+//   POST /mesh/filemesh-to-gltf                     glb, skinned if the mesh is
+//   POST /mesh/gltf-to-filemesh?version=v4_00       glb or embedded gltf in, bones kept for v4/v5
+//   POST /mesh/filemesh-to-filemesh?version=v4_00
+//   POST /place/fix?preset=2013&force_xml=true      (same options as fix-place, snake_case),
+//                                                   X-Warnings and with verify=true X-Verify headers
+//   POST /place/info                                json report
+//
+// when started by the daemon with --bind there's also a job api backed by its inbox/outbox:
+//   POST /jobs?action=fix-place&preset=2013         queue a job, returns { "id": ... }
+//   GET  /jobs/<id>                                 status or the finished job's report
+//   GET  /jobs/<id>/output                          converted file
+//
+// the file is either the raw request body or the first file field of a multipart/form-data
+// upload. options come from the query string and/or the other form fields. errors are returned
+// as json { "error": "..." } with a 4xx/5xx status.
+use crate::asset_urls::AssetUrlConfig;
+use crate::baseplate::Baseplate;
+use crate::binary_compat::BinaryCompat;
+use crate::daemon::{self, JobDirs};
+use crate::mappings::InstanceMappings;
+use crate::policy::FailRule;
+use crate::presets::Preset;
+use crate::tags::TagConversion;
+use crate::thumbnail::ThumbnailCamera;
+use crate::unique_ids::UniqueIdHandling;
+use crate::verify::VerifySummary;
+use crate::world_space::RebaseOrigin;
+use crate::xml_compat::XmlCompat;
+use crate::{FixPlaceOptions, OutputFormat, RobloxMeshVersion, fix_place_with_report, load_place, pass_scope, profile};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::io::Read;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+use std::thread;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+pub type Options = HashMap<String, String>;
+
+pub struct Upload {
+    pub file: Vec<u8>,
+    pub filename: Option<String>,
+    pub options: Options,
+}
+
+pub enum Reply {
+    // report is the run's warnings and verify summary for fix-place, sent as headers
+    File { bytes: Vec<u8>, filename: String, report: Option<serde_json::Value> },
+    Json(serde_json::Value),
+}
+
+pub struct HttpError(pub u16, pub String);
+
+impl<E: Error> From<E> for HttpError {
+    fn from(e: E) -> Self {
+        HttpError(422, e.to_string())
+    }
+}
+
+fn bad_request(message: impl Into<String>) -> HttpError {
+    HttpError(400, message.into())
+}
+
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => out.push(b' '),
+            b'%' => match text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok()) {
+                Some(byte) => {
+                    out.push(byte);
+                    i += 2;
+                }
+                None => out.push(b'%'),
+            },
+            byte => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn parse_query(url: &str) -> (String, Options) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let options = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, "true"));
+            (percent_decode(key), percent_decode(value))
+        })
+        .collect();
+    (path.to_owned(), options)
+}
+
+fn find(haystack: &[u8], needle: &[u8], from: usize) -> Option<usize> {
+    haystack
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + from)
+}
+
+fn header_param<'a>(header: &'a str, name: &str) -> Option<&'a str> {
+    header.split(';').find_map(|part| {
+        let (key, value) = part.trim().split_once('=')?;
+        key.eq_ignore_ascii_case(name).then(|| value.trim_matches('"'))
+    })
+}
+
+// only what browsers and curl send: no nested multiparts, no transfer encodings
+fn parse_multipart(body: &[u8], boundary: &str, upload: &mut Upload) -> Result<(), HttpError> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let mut pos = find(body, &delimiter, 0).ok_or_else(|| bad_request("multipart boundary not found"))?;
+    let mut found_file = false;
+    loop {
+        pos += delimiter.len();
+        if body[pos..].starts_with(b"--") {
+            break;
+        }
+        let headers_end =
+            find(body, b"\r\n\r\n", pos).ok_or_else(|| bad_request("malformed multipart headers"))?;
+        let headers = String::from_utf8_lossy(&body[pos..headers_end]);
+        let content_start = headers_end + 4;
+        let next = find(body, &delimiter, content_start).ok_or_else(|| bad_request("unterminated multipart body"))?;
+        // the part's content ends with the CRLF that precedes the next delimiter
+        let content = &body[content_start..next.saturating_sub(2).max(content_start)];
+
+        let disposition = headers
+            .lines()
+            .find(|line| line.to_ascii_lowercase().starts_with("content-disposition:"))
+            .unwrap_or_default();
+        let name = header_param(disposition, "name").unwrap_or_default();
+        match header_param(disposition, "filename") {
+            Some(filename) if !found_file => {
+                upload.file = content.to_vec();
+                upload.filename = Some(filename.to_owned());
+                found_file = true;
+            }
+            Some(_) => {}
+            None => {
+                upload
+                    .options
+                    .insert(name.to_owned(), String::from_utf8_lossy(content).into_owned());
+            }
+        }
+        pos = next;
+    }
+    if !found_file {
+        return Err(bad_request("multipart upload has no file field"));
+    }
+    Ok(())
+}
+
+fn read_upload(request: &mut Request, max_upload: usize) -> Result<(String, Upload), HttpError> {
+    let (path, options) = parse_query(request.url());
+    let content_type = request
+        .headers()
+        .iter()
+        .find(|header| header.field.equiv("Content-Type"))
+        .map(|header| header.value.as_str().to_owned())
+        .unwrap_or_default();
+
+    let mut body = Vec::new();
+    request
+        .as_reader()
+        .take(max_upload as u64 + 1)
+        .read_to_end(&mut body)
+        .map_err(|e| HttpError(400, e.to_string()))?;
+    if body.len() > max_upload {
+        return Err(HttpError(413, format!("upload is larger than {} bytes", max_upload)));
+    }
+
+    let mut upload = Upload {
+        file: Vec::new(),
+        filename: None,
+        options,
+    };
+    if content_type.to_ascii_lowercase().starts_with("multipart/form-data") {
+        let boundary =
+            header_param(&content_type, "boundary").ok_or_else(|| bad_request("multipart upload without a boundary"))?;
+        parse_multipart(&body, boundary, &mut upload)?;
+    } else {
+        upload.file = body;
+    }
+    Ok((path, upload))
+}
+
+fn flag(options: &Options, name: &str) -> bool {
+    options
+        .get(name)
+        .is_some_and(|value| matches!(value.to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+}
+
+fn value_enum<T: ValueEnum>(options: &Options, name: &str) -> Result<Option<T>, HttpError> {
+    options
+        .get(name)
+        .map(|value| T::from_str(value, true).map_err(|_| bad_request(format!("invalid {}: {}", name, value))))
+        .transpose()
+}
+
+fn parsed<T: std::str::FromStr>(options: &Options, name: &str) -> Result<Option<T>, HttpError> {
+    options
+        .get(name)
+        .map(|value| value.parse().map_err(|_| bad_request(format!("invalid {}: {}", name, value))))
+        .transpose()
+}
+
+fn mesh_version(options: &Options) -> Result<RobloxMeshVersion, HttpError> {
+    value_enum(options, "version")?.ok_or_else(|| bad_request("missing version"))
+}
+
+fn output_name(upload: &Upload, extension: &str) -> String {
+    let stem = upload
+        .filename
+        .as_deref()
+        .and_then(|name| name.rsplit_once('.').map(|(stem, _)| stem).or(Some(name)))
+        .unwrap_or("output");
+    format!("{}.{}", stem, extension)
+}
+
+fn handle_fix_place(upload: &Upload) -> Result<Reply, HttpError> {
+    let options = &upload.options;
+    let output_format = if flag(options, "force_xml") {
+        OutputFormat::Xml
+    } else if flag(options, "force_binary") {
+        OutputFormat::Binary
+    } else {
+        OutputFormat::SameAsInput
+    };
+    let mut fix_options = FixPlaceOptions::new()
+        .output_format(output_format)
+        .folders_to_models(flag(options, "folders_to_models"))
+        .convert_meshparts(flag(options, "convert_meshparts"))
+        .legacy_size_grid(flag(options, "legacy_size_grid"))
+        .shape_fallbacks(parsed::<u32>(options, "shape_fallbacks")?)
+        .texture_tiling(parsed::<u32>(options, "texture_tiling")?)
+        .spawn_fixups(parsed::<u32>(options, "spawn_fixups")?)
+        .replication_flags(parsed::<u32>(options, "replication_flags")?)
+        .tool_fixups(parsed::<u32>(options, "tool_fixups")?)
+        .resolve_packages(flag(options, "resolve_packages"))
+        .inject_leaderstats(flag(options, "inject_leaderstats"))
+        .unique_ids(value_enum::<UniqueIdHandling>(options, "unique_ids")?)
+        .ensure_baseplate(if flag(options, "ensure_baseplate") {
+            Some(Baseplate::default())
+        } else {
+            parsed::<Baseplate>(options, "ensure_baseplate")?
+        })
+        .anchor_floating(if flag(options, "anchor_floating") {
+            Some(crate::floating::DEFAULT_WITHIN)
+        } else {
+            parsed::<f32>(options, "anchor_floating")?
+        })
+        .rebase_origin(parsed::<RebaseOrigin>(options, "rebase_origin")?)
+        .convert_assetid_to_url(flag(options, "convert_assetid_to_url"))
+        .tag_conversion(value_enum::<TagConversion>(options, "convert_tags")?)
+        .asset_cutoff_date(parsed::<NaiveDate>(options, "asset_cutoff_date")?)
+        .xml_compat(value_enum::<XmlCompat>(options, "xml_compat")?)
+        .binary_compat(value_enum::<BinaryCompat>(options, "binary_compat")?)
+        .expand_shared_strings(flag(options, "expand_shared_strings"))
+        .verify(flag(options, "verify"))
+        .dedup_shared_strings(parsed::<usize>(options, "dedup_shared_strings")?)
+        .stable_output(flag(options, "stable_output"))
+        .conversion_log(flag(options, "conversion_log"))
+        .thumbnail_camera(parsed::<ThumbnailCamera>(options, "thumbnail_camera")?);
+    if let Some(format) = options.get("asset_url_format") {
+        fix_options = fix_options.asset_url_format(format.as_str());
+    }
+    if let Some(json) = options.get("asset_url_config") {
+        let config = AssetUrlConfig::from_json(json).map_err(|e| bad_request(format!("invalid asset_url_config: {}", e)))?;
+        fix_options = fix_options.asset_url_config(config);
+    }
+    // one rule per line
+    if let Some(rules) = options.get("fail_if") {
+        let rules = rules
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.parse::<FailRule>().map_err(bad_request))
+            .collect::<Result<_, _>>()?;
+        fix_options = fix_options.fail_if(rules);
+    }
+    if let Some(preset) = value_enum::<Preset>(options, "preset")? {
+        fix_options = fix_options.preset(preset);
+    }
+    if let Some(json) = options.get("instance_mappings") {
+        fix_options = fix_options.extend_mappings(InstanceMappings::from_json(json)?);
+    }
+    if let Some(json) = options.get("pass_scopes") {
+        let scopes = pass_scope::scopes_from_json(json).map_err(|e| bad_request(e.to_string()))?;
+        fix_options = fix_options.pass_scopes(scopes);
+    }
+    let (output, run) = fix_place_with_report(&upload.file, fix_options)
+    .map_err(|e| HttpError(422, e.to_string()))?;
+    let extension = if crate::is_binary_rbxl(&output) { "rbxl" } else { "rbxlx" };
+    Ok(Reply::File {
+        filename: output_name(upload, extension),
+        bytes: output,
+        report: Some(json!({
+            "warnings": run.warnings,
+            "verify": run.verify.as_ref().map(VerifySummary::to_text),
+        })),
+    })
+}
+
+fn handle_place_info(upload: &Upload) -> Result<Reply, HttpError> {
+    let dom = load_place(&upload.file).map_err(|e| HttpError(422, e.to_string()))?;
+    let mut class_counts: HashMap<&str, usize> = HashMap::new();
+    for instance in dom.descendants().skip(1) {
+        *class_counts.entry(instance.class.as_str()).or_default() += 1;
+    }
+    let profile = profile::profile_place(&dom);
+    let top = 20;
+    Ok(Reply::Json(json!({
+        "format": if crate::is_binary_rbxl(&upload.file) { "binary" } else { "xml" },
+        "file_size": upload.file.len(),
+        "instance_count": profile.instance_count,
+        "estimated_size": profile.total_bytes,
+        "classes": class_counts,
+        "shared_strings": {
+            "count": profile.shared_string_count,
+            "bytes": profile.shared_string_bytes,
+        },
+        "top_properties": profile.properties.iter().take(top).map(|(name, bytes, count)| json!({
+            "property": name,
+            "bytes": bytes,
+            "values": count,
+        })).collect::<Vec<_>>(),
+        "top_instances": profile.instances.iter().take(top).map(|(path, class, bytes)| json!({
+            "path": path,
+            "class": class,
+            "bytes": bytes,
+        })).collect::<Vec<_>>(),
+    })))
+}
+
+// also used by the daemon, which picks the action from the file extension
+pub fn run_action(action: &str, upload: &Upload) -> Result<Reply, HttpError> {
+    match action {
+        "obj-to-filemesh" => Ok(Reply::File {
+            bytes: crate::convert_obj_to_filemesh(&upload.file, mesh_version(&upload.options)?)?,
+            filename: output_name(upload, "mesh"),
+            report: None,
+        }),
+        "filemesh-to-obj" => Ok(Reply::File {
+            bytes: crate::convert_filemesh_to_obj(&upload.file)?,
+            filename: output_name(upload, "obj"),
+            report: None,
+        }),
+        "filemesh-to-gltf" => Ok(Reply::File {
+            bytes: crate::convert_filemesh_to_gltf(&upload.file)?,
+            filename: output_name(upload, "glb"),
+            report: None,
+        }),
+        "gltf-to-filemesh" => Ok(Reply::File {
+            bytes: crate::convert_gltf_to_filemesh(&upload.file, mesh_version(&upload.options)?)?,
+            filename: output_name(upload, "mesh"),
+            report: None,
+        }),
+        "filemesh-to-filemesh" => Ok(Reply::File {
+            bytes: crate::convert_filemesh_to_filemesh(&upload.file, mesh_version(&upload.options)?)?,
+            filename: output_name(upload, "mesh"),
+            report: None,
+        }),
+        "fix-place" => handle_fix_place(upload),
+        "place-info" => handle_place_info(upload),
+        _ => Err(bad_request(format!("unknown action '{}'", action))),
+    }
+}
+
+fn route(method: &Method, path: &str, upload: &Upload, jobs: Option<&JobDirs>) -> Result<Reply, HttpError> {
+    let job_path = path.strip_prefix("/jobs/");
+    match (method, path) {
+        (Method::Get, "/health") => Ok(Reply::Json(json!({ "status": "ok" }))),
+        (Method::Post, "/mesh/obj-to-filemesh") => run_action("obj-to-filemesh", upload),
+        (Method::Post, "/mesh/filemesh-to-obj") => run_action("filemesh-to-obj", upload),
+        (Method::Post, "/mesh/filemesh-to-gltf") => run_action("filemesh-to-gltf", upload),
+        (Method::Post, "/mesh/gltf-to-filemesh") => run_action("gltf-to-filemesh", upload),
+        (Method::Post, "/mesh/filemesh-to-filemesh") => run_action("filemesh-to-filemesh", upload),
+        (Method::Post, "/place/fix") => run_action("fix-place", upload),
+        (Method::Post, "/place/info") => run_action("place-info", upload),
+        (Method::Post, "/jobs") if let Some(dirs) = jobs => {
+            let id = daemon::submit(dirs, upload).map_err(|e| HttpError(500, e.to_string()))?;
+            Ok(Reply::Json(json!({ "id": id })))
+        }
+        (Method::Get, _) if let (Some(dirs), Some(rest)) = (jobs, job_path) => match rest.strip_suffix("/output") {
+            Some(id) => daemon::job_output(dirs, id).ok_or_else(|| HttpError(404, format!("no output for job {}", id))),
+            None => daemon::job_status(dirs, rest)
+                .map(Reply::Json)
+                .ok_or_else(|| HttpError(404, format!("no job {}", rest))),
+        },
+        _ => Err(HttpError(404, format!("no route for {} {}", method, path))),
+    }
+}
+
+fn header(field: &str, value: &str) -> Header {
+    Header::from_bytes(field.as_bytes(), value.as_bytes()).expect("static header names are valid")
+}
+
+// header values can only be printable ascii
+fn to_ascii(text: &str) -> String {
+    text.chars()
+        .map(|c| if (c.is_ascii_graphic() || c == ' ') && c != '"' && c != '\\' { c } else { '_' })
+        .collect()
+}
+
+// the name comes from the upload, so it's reduced to printable ascii for filename= and sent in
+// full as utf-8 in filename* (rfc 5987) for clients that read it
+fn content_disposition(filename: &str) -> Result<Header, HttpError> {
+    let ascii = to_ascii(filename);
+    let mut encoded = String::new();
+    for byte in filename.bytes() {
+        if byte.is_ascii_alphanumeric() || b"!#$&+-.^_`|~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    let value = format!("attachment; filename=\"{}\"; filename*=UTF-8''{}", ascii, encoded);
+    Header::from_bytes(&b"Content-Disposition"[..], value.as_bytes())
+        .map_err(|_| HttpError(500, format!("couldn't send {:?} as a file name", filename)))
+}
+
+fn respond(request: Request, result: Result<Reply, HttpError>) {
+    let result = match result {
+        Ok(Reply::File { bytes, filename, report }) => content_disposition(&filename).map(|disposition| {
+            let mut response = Response::from_data(bytes)
+                .with_header(header("Content-Type", "application/octet-stream"))
+                .with_header(disposition);
+            // a fix-place run's warning count and verify summary, the warnings are in the server log
+            if let Some(report) = report {
+                let warnings = report["warnings"].as_array().map_or(0, Vec::len);
+                response.add_header(header("X-Warnings", &warnings.to_string()));
+                if let Some(verify) = report["verify"].as_str() {
+                    response.add_header(header("X-Verify", &to_ascii(verify)));
+                }
+            }
+            response
+        }),
+        Ok(Reply::Json(value)) => Ok(Response::from_data(value.to_string().into_bytes())
+            .with_header(header("Content-Type", "application/json"))),
+        Err(e) => Err(e),
+    };
+    let response = result.unwrap_or_else(|HttpError(status, message)| {
+        Response::from_data(json!({ "error": message }).to_string().into_bytes())
+            .with_status_code(status)
+            .with_header(header("Content-Type", "application/json"))
+    });
+    if let Err(e) = request.respond(response) {
+        println!("[legacy_place::serve] failed to send response: {}", e);
+    }
+}
+
+fn handle(mut request: Request, max_upload: usize, jobs: Option<&JobDirs>) {
+    let upload = match read_upload(&mut request, max_upload) {
+        Ok(upload) => upload,
+        Err(e) => return respond(request, Err(e)),
+    };
+    let (path, upload) = upload;
+    let method = request.method().clone();
+    println!(
+        "[legacy_place::serve] {} {} ({} bytes)",
+        method,
+        path,
+        upload.file.len()
+    );
+    // a converter panicking on a bad file shouldn't take the worker down with it
+    let result = panic::catch_unwind(AssertUnwindSafe(|| route(&method, &path, &upload, jobs)))
+        .unwrap_or_else(|_| Err(HttpError(500, "conversion panicked".to_owned())));
+    respond(request, result);
+}
+
+pub fn run(bind: &str, workers: usize, max_upload: usize, jobs: Option<Arc<JobDirs>>) -> Result<(), Box<dyn Error>> {
+    let server = Arc::new(Server::http(bind).map_err(|e| e.to_string())?);
+    println!("[legacy_place::serve] listening on http://{} with {} worker(s)", bind, workers);
+    let handles: Vec<_> = (0..workers.max(1))
+        .map(|_| {
+            let server = Arc::clone(&server);
+            let jobs = jobs.clone();
+            thread::spawn(move || {
+                while let Ok(request) = server.recv() {
+                    handle(request, max_upload, jobs.as_deref());
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        let _ = handle.join();
+    }
+    Ok(())
+}
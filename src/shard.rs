@@ -0,0 +1,211 @@
+// a map too big for one legacy server split into a grid of places
+//
+// the workspace's parts are cut into cols x rows cells, columns along x and rows along z. what
+// moves between shards is what extract-region takes, each part with the nearest model it's in,
+// and it goes to the one cell its middle is in so nothing ends up in two shards. everything else
+// (services, scripts, the folders the map is kept in, terrain, the camera) is in every shard.
+//
+// the manifest is a universe file, so the shards can go through universe fix together. the ids
+// in it are placeholders numbered from 1, the start place is the shard with the first spawn.
+use crate::region;
+use crate::world_space::{is_a, part_bounds, service};
+use rbx_dom_weak::types::{Ref, Vector3};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use serde_json::json;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+pub const MANIFEST_NAME: &str = "shards.json";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Grid {
+    pub cols: usize,
+    pub rows: usize,
+}
+
+impl FromStr for Grid {
+    type Err = String;
+
+    // "3x2", 3 along x and 2 along z
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parsed = s
+            .split_once(['x', 'X'])
+            .and_then(|(cols, rows)| Some((cols.trim().parse::<usize>().ok()?, rows.trim().parse::<usize>().ok()?)));
+        match parsed {
+            Some((cols, rows)) if cols > 0 && rows > 0 && cols * rows > 1 => Ok(Self { cols, rows }),
+            Some(_) => Err(format!("a {} grid is one shard or none, there's nothing to split", s)),
+            None => Err(format!("expected <cols>x<rows>, e.g. 3x2, got '{}'", s)),
+        }
+    }
+}
+
+pub struct Shard {
+    pub file: PathBuf,
+    pub col: usize,
+    pub row: usize,
+    // the cell, x and z
+    pub min: [f32; 2],
+    pub max: [f32; 2],
+    pub parts: usize,
+    pub spawns: usize,
+}
+
+pub struct ShardReport {
+    pub grid: Grid,
+    pub shards: Vec<Shard>,
+    pub start: usize,
+}
+
+// the box around everything under `referent`, itself included
+fn bounds(dom: &WeakDom, referent: Ref) -> Option<(Vector3, Vector3)> {
+    dom.descendants_of(referent)
+        .filter(|instance| is_a(&instance.class, "BasePart"))
+        .filter_map(part_bounds)
+        .reduce(|(min, max), (low, high)| {
+            (
+                Vector3::new(min.x.min(low.x), min.y.min(low.y), min.z.min(low.z)),
+                Vector3::new(max.x.max(high.x), max.y.max(high.y), max.z.max(high.z)),
+            )
+        })
+}
+
+pub fn shard_place(input: &[u8], dom: &mut WeakDom, grid: Grid, stem: &str, out_dir: &Path) -> Result<ShardReport, Box<dyn Error>> {
+    let workspace = service(dom, "Workspace").ok_or("the place has no workspace")?;
+    let parts: Vec<Ref> = dom
+        .descendants_of(workspace)
+        .filter(|instance| instance.class != "Terrain" && is_a(&instance.class, "BasePart"))
+        .map(|instance| instance.referent())
+        .collect();
+    let units: Vec<(Ref, Vector3)> = region::units(dom, &parts, workspace)
+        .into_iter()
+        .filter_map(|unit| {
+            let (low, high) = bounds(dom, unit)?;
+            Some((unit, Vector3::new((low.x + high.x) / 2.0, 0.0, (low.z + high.z) / 2.0)))
+        })
+        .collect();
+    let Some(map) = bounds(dom, workspace) else {
+        return Err("no parts in the workspace, there's no map to split".into());
+    };
+
+    let width = (map.1.x - map.0.x) / grid.cols as f32;
+    let depth = (map.1.z - map.0.z) / grid.rows as f32;
+    // the far edge goes in the last cell
+    let cell_of = |middle: Vector3| {
+        let col = ((middle.x - map.0.x) / width).floor() as usize;
+        let row = ((middle.z - map.0.z) / depth).floor() as usize;
+        col.min(grid.cols - 1) + row.min(grid.rows - 1) * grid.cols
+    };
+    let mut cells: Vec<Vec<Ref>> = vec![Vec::new(); grid.cols * grid.rows];
+    for &(unit, middle) in &units {
+        // a map that's a line along one axis has no width there
+        let middle = Vector3::new(
+            if width > 0.0 { middle.x } else { map.0.x },
+            0.0,
+            if depth > 0.0 { middle.z } else { map.0.z },
+        );
+        cells[cell_of(middle)].push(unit);
+    }
+
+    let first_spawn = dom
+        .descendants_of(workspace)
+        .find(|instance| instance.class == "SpawnLocation")
+        .map(|instance| instance.referent());
+    let start = first_spawn
+        .and_then(|spawn| {
+            cells
+                .iter()
+                .position(|cell| cell.iter().any(|&unit| dom.descendants_of(unit).any(|instance| instance.referent() == spawn)))
+        })
+        .unwrap_or(0);
+
+    // everything that moves waits in here while the other shards are written
+    let mut held = WeakDom::new(InstanceBuilder::new("DataModel"));
+    let held_root = held.root_ref();
+    let mut parents = HashMap::new();
+    for &unit in cells.iter().flatten() {
+        parents.insert(unit, dom.get_by_ref(unit).map_or(Ref::none(), |instance| instance.parent()));
+        dom.transfer(unit, &mut held, held_root);
+    }
+
+    let extension = if crate::is_binary_rbxl(input) { "rbxl" } else { "rbxlx" };
+    let mut shards = Vec::new();
+    for (index, cell) in cells.iter().enumerate() {
+        for &unit in cell {
+            held.transfer(unit, dom, parents[&unit]);
+        }
+        let in_cell = |class: &str| {
+            cell.iter()
+                .flat_map(|&unit| dom.descendants_of(unit))
+                .filter(|instance| is_a(&instance.class, class))
+                .count()
+        };
+        let (col, row) = (index % grid.cols, index / grid.cols);
+        let file = PathBuf::from(format!("{}_{}_{}.{}", stem, col, row, extension));
+        crate::save_dom(dom, &out_dir.join(&file))?;
+        shards.push(Shard {
+            col,
+            row,
+            min: [map.0.x + width * col as f32, map.0.z + depth * row as f32],
+            max: [map.0.x + width * (col + 1) as f32, map.0.z + depth * (row + 1) as f32],
+            parts: in_cell("BasePart"),
+            spawns: in_cell("SpawnLocation"),
+            file,
+        });
+        for &unit in cell {
+            dom.transfer(unit, &mut held, held_root);
+        }
+    }
+    Ok(ShardReport { grid, shards, start })
+}
+
+impl ShardReport {
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "{} shards ({}x{})", self.shards.len(), self.grid.cols, self.grid.rows);
+        for (index, shard) in self.shards.iter().enumerate() {
+            let _ = write!(
+                out,
+                "  {} x {}..{} z {}..{}: {} part(s), {} spawn(s)",
+                shard.file.display(),
+                shard.min[0],
+                shard.max[0],
+                shard.min[1],
+                shard.max[1],
+                shard.parts,
+                shard.spawns
+            );
+            if index == self.start {
+                let _ = write!(out, ", start place");
+            }
+            if shard.spawns == 0 {
+                let _ = write!(out, ", no spawn in it");
+            }
+            let _ = writeln!(out);
+        }
+        out
+    }
+
+    // a universe file with the shards' cells alongside
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        let places: Vec<_> = self
+            .shards
+            .iter()
+            .enumerate()
+            .map(|(index, shard)| {
+                json!({
+                    "file": shard.file,
+                    "id": index + 1,
+                    "start": index == self.start,
+                    "cell": [shard.col, shard.row],
+                    "min": shard.min,
+                    "max": shard.max,
+                    "parts": shard.parts,
+                })
+            })
+            .collect();
+        Ok(serde_json::to_string_pretty(&json!({ "places": places }))?)
+    }
+}
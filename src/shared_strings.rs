@@ -0,0 +1,89 @@
+// SharedString expansion and deduplication
+//
+// readers from before the SSTR chunk (and before the SharedStrings section in rbxlx) only know
+// inline binary strings, so expansion turns every SharedString back into a BinaryString. the
+// binary writer has to be told as well, see rbx_binary's Serializer::inline_shared_strings.
+//
+// deduplication goes the other way for modern output: BinaryString values that repeat (usually
+// union/mesh data from files that went through an old xml round trip) are turned into
+// SharedStrings so they're only stored once. only properties the format actually stores as
+// SharedString are touched; anything else would be written inline again by rbx_binary anyway.
+use rbx_dom_weak::{Ustr, WeakDom};
+use rbx_reflection::DataType;
+use rbx_types::{BinaryString, SharedString, Variant, VariantType};
+use std::collections::{HashMap, HashSet};
+
+#[derive(Default)]
+pub struct SharedStringStats {
+    pub values: usize,
+    pub unique: usize,
+    pub bytes: i64,
+}
+
+pub fn expand_shared_strings(dom: &mut WeakDom) -> SharedStringStats {
+    let refs: Vec<_> = dom.descendants().map(|instance| instance.referent()).collect();
+    let mut seen: HashSet<SharedString> = HashSet::new();
+    let mut stats = SharedStringStats::default();
+    for referent in refs {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        for value in instance.properties.values_mut() {
+            let Variant::SharedString(shared) = value else { continue };
+            let shared = shared.clone();
+            stats.values += 1;
+            stats.bytes += shared.data().len() as i64;
+            *value = Variant::BinaryString(BinaryString::from(shared.data().to_vec()));
+            // each unique string was already stored once, so that much isn't new
+            if seen.insert(shared.clone()) {
+                stats.unique += 1;
+                stats.bytes -= shared.data().len() as i64;
+            }
+        }
+    }
+    stats
+}
+
+// whether a BinaryString in this property can be written as a SharedString. only ones the
+// database declares as SharedString are, an unknown class or property keeps the type it has so
+// readers still see what they were written with
+fn stores_as_shared_string(class: Ustr, prop_name: Ustr) -> bool {
+    let Ok(database) = rbx_reflection_database::get() else { return false };
+    let Some(class_descriptor) = database.classes.get(class.as_str()) else { return false };
+    database
+        .superclasses_iter(class_descriptor)
+        .find_map(|class| class.properties.get(prop_name.as_str()))
+        .is_some_and(|property| matches!(property.data_type, DataType::Value(VariantType::SharedString)))
+}
+
+pub fn dedup_shared_strings(dom: &mut WeakDom, min_bytes: usize) -> SharedStringStats {
+    let mut candidates: HashMap<Vec<u8>, Vec<(rbx_dom_weak::types::Ref, Ustr)>> = HashMap::new();
+    for instance in dom.descendants() {
+        for (prop_name, value) in &instance.properties {
+            let Variant::BinaryString(bytes) = value else { continue };
+            let bytes: &[u8] = bytes.as_ref();
+            if bytes.len() < min_bytes || !stores_as_shared_string(instance.class, *prop_name) {
+                continue;
+            }
+            candidates
+                .entry(bytes.to_vec())
+                .or_default()
+                .push((instance.referent(), *prop_name));
+        }
+    }
+
+    let mut stats = SharedStringStats::default();
+    for (bytes, uses) in candidates {
+        if uses.len() < 2 {
+            continue;
+        }
+        stats.unique += 1;
+        stats.values += uses.len();
+        stats.bytes += ((uses.len() - 1) * bytes.len()) as i64;
+        let shared = SharedString::new(bytes);
+        for (referent, prop_name) in uses {
+            if let Some(instance) = dom.get_by_ref_mut(referent) {
+                instance.properties.insert(prop_name, Variant::SharedString(shared.clone()));
+            }
+        }
+    }
+    stats
+}
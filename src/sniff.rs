@@ -0,0 +1,240 @@
+// what a downloaded asset is, going by its bytes
+//
+// the cdn hands assets out without a name or extension, and the content type it sends is
+// usually just binary/octet-stream. this looks at magic numbers first, roblox's own formats
+// (meshes, binary and xml places/models), images, audio and compressed wrappers, and falls back
+// to telling lua from other text. each kind comes with the extension it should have and a few
+// details to tell copies apart.
+use crate::filemesh;
+use rbx_reflection::ClassTag;
+use std::io::Cursor;
+
+pub(crate) const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+// words that don't show up in much besides lua
+const LUA_HINTS: [&str; 8] = [
+    "local ", "function", "end\n", "then", "game:", "script.", "workspace", "Instance.new",
+];
+
+pub struct Sniffed {
+    pub kind: String,
+    // what to name the file, without the dot
+    pub extension: &'static str,
+    pub details: Vec<(String, String)>,
+}
+
+impl Sniffed {
+    fn new(kind: impl Into<String>, extension: &'static str) -> Self {
+        Self {
+            kind: kind.into(),
+            extension,
+            details: Vec::new(),
+        }
+    }
+
+    fn detail(mut self, key: &str, value: impl ToString) -> Self {
+        self.details.push((key.to_owned(), value.to_string()));
+        self
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = format!("{} (.{})\n", self.kind, self.extension);
+        for (key, value) in &self.details {
+            out.push_str(&format!("  {}: {}\n", key, value));
+        }
+        out
+    }
+}
+
+fn u16_le(data: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(at..at + 2)?.try_into().ok()?))
+}
+
+fn u32_le(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn sniff_mesh(data: &[u8]) -> Sniffed {
+    let header = data.split(|&b| b == b'\n').next().unwrap_or_default();
+    let version = String::from_utf8_lossy(header).trim().trim_start_matches("version ").to_owned();
+    let sniffed = Sniffed::new(format!("mesh v{}", version), "mesh");
+    let mesh = match filemesh::parse_filemesh(data) {
+        Ok(mesh) => mesh,
+        Err(e) => return sniffed.detail("error", e),
+    };
+    let mut sniffed = sniffed
+        .detail("vertices", mesh.vertices.len())
+        .detail("faces", mesh.faces.len());
+    if let Ok(lods) = filemesh::parse_filemesh_lods(data)
+        && lods.len() > 1
+    {
+        sniffed = sniffed.detail("lods", lods.len());
+    }
+    if let Some(skin) = &mesh.skin {
+        sniffed = sniffed.detail("bones", skin.bones.len());
+    }
+    if let Some(facs) = &mesh.facs {
+        sniffed = sniffed.detail("facs controls", facs.control_names.len());
+    }
+    sniffed
+}
+
+fn is_service(class: &str) -> bool {
+    rbx_reflection_database::get()
+        .ok()
+        .and_then(|database| database.classes.get(class))
+        .is_some_and(|descriptor| descriptor.tags.contains(&ClassTag::Service))
+}
+
+// places have services at the top, models don't
+fn sniff_dom(data: &[u8], binary: bool) -> Sniffed {
+    let dom = match crate::load_place(data) {
+        Ok(dom) => dom,
+        Err(e) => {
+            let (kind, extension) = if binary { ("binary model or place", "rbxm") } else { ("xml model or place", "rbxmx") };
+            return Sniffed::new(kind, extension).detail("error", e);
+        }
+    };
+    let roots: Vec<&str> = dom
+        .root()
+        .children()
+        .iter()
+        .filter_map(|&child| dom.get_by_ref(child))
+        .map(|instance| instance.class.as_str())
+        .collect();
+    let is_place = roots.iter().any(|class| is_service(class));
+    let (kind, extension) = match (binary, is_place) {
+        (true, true) => ("binary place", "rbxl"),
+        (true, false) => ("binary model", "rbxm"),
+        (false, true) => ("xml place", "rbxlx"),
+        (false, false) => ("xml model", "rbxmx"),
+    };
+    let mut sniffed = Sniffed::new(kind, extension).detail("instances", dom.descendants().count() - 1);
+    if !is_place {
+        let mut classes = roots.clone();
+        classes.sort_unstable();
+        classes.dedup();
+        sniffed = sniffed.detail("roots", classes.join(", "));
+    }
+    sniffed
+}
+
+fn sniff_image(data: &[u8], kind: &str, extension: &'static str) -> Sniffed {
+    let sniffed = Sniffed::new(kind, extension);
+    let dimensions = image::ImageReader::new(Cursor::new(data))
+        .with_guessed_format()
+        .ok()
+        .and_then(|reader| reader.into_dimensions().ok());
+    match dimensions {
+        Some((width, height)) => sniffed.detail("size", format!("{}x{}", width, height)),
+        None => sniffed.detail("error", "couldn't read the header"),
+    }
+}
+
+fn sniff_ogg(data: &[u8]) -> Sniffed {
+    // the first page's packet starts at 28 with one segment, which is the usual
+    let packet = data.get(28..).unwrap_or_default();
+    if packet.starts_with(b"\x01vorbis") {
+        let mut sniffed = Sniffed::new("ogg vorbis", "ogg");
+        if let (Some(&channels), Some(rate)) = (packet.get(11), u32_le(packet, 12)) {
+            sniffed = sniffed.detail("channels", channels).detail("sample rate", rate);
+        }
+        return sniffed;
+    }
+    if packet.starts_with(b"OpusHead") {
+        let mut sniffed = Sniffed::new("ogg opus", "ogg");
+        if let (Some(&channels), Some(rate)) = (packet.get(9), u32_le(packet, 12)) {
+            sniffed = sniffed.detail("channels", channels).detail("sample rate", rate);
+        }
+        return sniffed;
+    }
+    Sniffed::new("ogg", "ogg")
+}
+
+fn sniff_wav(data: &[u8]) -> Sniffed {
+    let mut sniffed = Sniffed::new("wav", "wav");
+    // fmt is nearly always the first chunk
+    if data.get(12..16) == Some(b"fmt ")
+        && let (Some(channels), Some(rate)) = (u16_le(data, 22), u32_le(data, 24))
+    {
+        sniffed = sniffed.detail("channels", channels).detail("sample rate", rate);
+    }
+    sniffed
+}
+
+fn sniff_text(text: &str) -> Sniffed {
+    let trimmed = text.trim_start();
+    if (trimmed.starts_with('{') || trimmed.starts_with('['))
+        && serde_json::from_str::<serde_json::Value>(trimmed).is_ok()
+    {
+        return Sniffed::new("json", "json");
+    }
+    // after the <?xml ?> line and any comments
+    if trimmed.starts_with("<?xml") && text.contains("<COLLADA") {
+        return Sniffed::new("collada", "dae");
+    }
+    let lines = text.lines().count();
+    let hints = LUA_HINTS.iter().filter(|hint| text.contains(*hint)).count();
+    if hints >= 2 || trimmed.starts_with("--") {
+        return Sniffed::new("lua", "lua").detail("lines", lines);
+    }
+    Sniffed::new("text", "txt").detail("lines", lines)
+}
+
+pub fn sniff(data: &[u8]) -> Sniffed {
+    sniff_bytes(data).detail("bytes", data.len())
+}
+
+fn sniff_bytes(data: &[u8]) -> Sniffed {
+    if data.starts_with(b"version ") {
+        sniff_mesh(data)
+    } else if data.starts_with(crate::imesh::IMESH_MAGIC) {
+        match crate::imesh::read_imesh(data) {
+            Ok(mesh) => Sniffed::new("intermediate mesh", "imesh")
+                .detail("vertices", mesh.vertices.len())
+                .detail("faces", mesh.faces.len()),
+            Err(e) => Sniffed::new("intermediate mesh", "imesh").detail("error", e),
+        }
+    } else if data.starts_with(b"ply\n") || data.starts_with(b"ply\r\n") {
+        Sniffed::new("ply", "ply")
+    } else if crate::is_binary_rbxl(data) {
+        sniff_dom(data, true)
+    } else if data.starts_with(b"\x89PNG\r\n\x1a\n") {
+        sniff_image(data, "png", "png")
+    } else if data.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        sniff_image(data, "jpeg", "jpg")
+    } else if data.starts_with(b"GIF87a") || data.starts_with(b"GIF89a") {
+        let sniffed = Sniffed::new("gif", "gif");
+        match (u16_le(data, 6), u16_le(data, 8)) {
+            (Some(width), Some(height)) => sniffed.detail("size", format!("{}x{}", width, height)),
+            _ => sniffed,
+        }
+    } else if data.starts_with(b"OggS") {
+        sniff_ogg(data)
+    } else if data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WAVE") {
+        sniff_wav(data)
+    } else if data.starts_with(b"ID3") || (data.len() > 1 && data[0] == 0xFF && data[1] & 0xE0 == 0xE0) {
+        Sniffed::new("mp3", "mp3")
+    } else if data.starts_with(&ZSTD_MAGIC) {
+        // assets are sometimes served still compressed, what's inside is what matters
+        match zstd::stream::decode_all(data) {
+            Ok(inner) => {
+                let sniffed = sniff_bytes(&inner).detail("decompressed bytes", inner.len());
+                let kind = format!("{}, zstd compressed", sniffed.kind);
+                Sniffed { kind, ..sniffed }
+            }
+            Err(e) => Sniffed::new("zstd", "zst").detail("error", e),
+        }
+    } else if data.starts_with(&[0x1F, 0x8B]) {
+        Sniffed::new("gzip", "gz")
+    } else {
+        let text = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+        match std::str::from_utf8(text) {
+            Ok(text) if text.trim_start().starts_with("<roblox") => sniff_dom(data, false),
+            Ok(text) => sniff_text(text),
+            Err(_) => {
+                let head: Vec<String> = data.iter().take(16).map(|byte| format!("{:02x}", byte)).collect();
+                Sniffed::new("unknown", "bin").detail("starts with", head.join(" "))
+            }
+        }
+    }
+}
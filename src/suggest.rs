@@ -0,0 +1,188 @@
+// fix-place options picked for a place and the client it's going to
+//
+// goes by place-info's class counts and era: the preset for the target year, the year based
+// passes for the classes they'd touch, and the output compat setting the format needs. what the
+// options lose on the way (stripped classes, mapped ones, property types the target can't read)
+// is listed next to them. the options come out as fix-place flags and as the json the http api
+// and daemon sidecars take, so the json can be dropped next to a place in the daemon's inbox as is.
+use crate::binary_compat::BinaryCompat;
+use crate::place_info::{Era, PlaceInfo};
+use crate::presets::Preset;
+use crate::xml_compat::XmlCompat;
+use clap::ValueEnum;
+use serde_json::{Map, Value};
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+
+// binary places from before this don't exist, older targets get xml
+const BINARY_YEAR: u32 = 2013;
+const MESH_PART_YEAR: u32 = 2016;
+const UNIQUE_ID_YEAR: u32 = 2021;
+// xml types after this are all read by clients since, see xml_compat
+const XML_COMPAT_NEEDED_BEFORE: u32 = 2020;
+const BINARY_COMPAT_NEEDED_BEFORE: u32 = 2024;
+
+// year based passes and the classes they do something to
+const YEAR_PASSES: [(&str, &[&str]); 5] = [
+    ("shape_fallbacks", &["Part", "TrussPart", "CornerWedgePart", "WedgePart"]),
+    ("texture_tiling", &["Texture"]),
+    ("spawn_fixups", &["SpawnLocation", "Team"]),
+    ("replication_flags", &["Workspace"]),
+    ("tool_fixups", &["Tool", "HopperBin"]),
+];
+
+pub struct Suggestion {
+    pub target: u32,
+    pub era: Era,
+    // (option, value, why) with the names the http api uses, flags are the same with dashes
+    pub options: Vec<(String, String, String)>,
+    pub lossy: Vec<String>,
+}
+
+// the newest preset the target can load everything from, it's safer to take out too much
+fn target_preset(target: u32) -> Preset {
+    match target {
+        target if target >= 2016 => Preset::Client2016,
+        target if target >= 2013 => Preset::Client2013,
+        _ => Preset::Client2011,
+    }
+}
+
+fn value_name<T: ValueEnum>(value: T) -> String {
+    value.to_possible_value().map(|value| value.get_name().to_owned()).unwrap_or_default()
+}
+
+pub fn suggest(info: &PlaceInfo, target: u32) -> Suggestion {
+    let era = info.detect_era();
+    let mut suggestion = Suggestion {
+        target,
+        options: Vec::new(),
+        lossy: Vec::new(),
+        era,
+    };
+    if suggestion.era.year.is_none_or(|year| year <= target) {
+        return suggestion;
+    }
+    let count = |class: &str| info.classes.get(class).copied().unwrap_or_default();
+
+    let preset = target_preset(target);
+    let data = preset.load();
+    suggestion.options.push(("preset".to_owned(), value_name(preset), data.description.clone()));
+    for class in &data.strip {
+        if count(class) > 0 {
+            suggestion.lossy.push(format!("{} {} removed", count(class), class));
+        }
+    }
+    for class in info.classes.keys() {
+        let Some(rules) = data.mappings.rules_for(class) else { continue };
+        let becomes = match rules {
+            [rule] => rule.class.clone().unwrap_or_else(|| class.clone()),
+            _ => "whatever the preset's rules pick".to_owned(),
+        };
+        suggestion.lossy.push(format!("{} {} become {}", count(class), class, becomes));
+    }
+
+    if target < MESH_PART_YEAR && count("MeshPart") > 0 {
+        let why = format!("{} MeshPart(s), the target has none", count("MeshPart"));
+        suggestion.options.push(("convert_meshparts".to_owned(), "true".to_owned(), why));
+        suggestion
+            .lossy
+            .push(format!("{} MeshPart(s) become Parts with SpecialMeshes, their collision is their box", count("MeshPart")));
+    }
+    for (pass, classes) in YEAR_PASSES {
+        let found: Vec<String> = classes
+            .iter()
+            .filter(|class| count(class) > 0)
+            .map(|class| format!("{} {}", count(class), class))
+            .collect();
+        if !found.is_empty() {
+            suggestion.options.push((pass.to_owned(), target.to_string(), found.join(", ")));
+        }
+    }
+    let unique_ids = ["property UniqueId", "property HistoryId"].iter().any(|what| info.features.contains_key(*what));
+    if target < UNIQUE_ID_YEAR && unique_ids {
+        let why = "UniqueId/HistoryId, which the target's parsers don't know".to_owned();
+        suggestion.options.push(("unique_ids".to_owned(), "strip".to_owned(), why));
+    }
+    let cutoff = format!("{}-12-31", target);
+    let why = "warns about sounds and animations uploaded after the target".to_owned();
+    suggestion.options.push(("asset_cutoff_date".to_owned(), cutoff, why));
+
+    // newer property types, which the compat settings drop (xml) or refuse (binary)
+    let late_types: Vec<&str> = info
+        .features
+        .iter()
+        .filter(|&(what, &year)| what.starts_with("type ") && year > target)
+        .map(|(what, _)| what.trim_start_matches("type "))
+        .collect();
+    let xml_output = !info.binary || target < BINARY_YEAR;
+    if info.binary && xml_output {
+        let why = "binary places are newer than the target".to_owned();
+        suggestion.options.push(("force_xml".to_owned(), "true".to_owned(), why));
+    }
+    if xml_output && target < XML_COMPAT_NEEDED_BEFORE {
+        let era = [XmlCompat::Era2016, XmlCompat::Era2012, XmlCompat::Era2008]
+            .into_iter()
+            .find(|era| value_name(*era).parse::<u32>().is_ok_and(|year| year <= target))
+            .unwrap_or(XmlCompat::Era2008);
+        let why = "writes the xml the way the target's era reads it".to_owned();
+        suggestion.options.push(("xml_compat".to_owned(), value_name(era), why));
+        if !late_types.is_empty() {
+            suggestion.lossy.push(format!("properties of type {} dropped", late_types.join(", ")));
+        }
+    }
+    if !xml_output && target < BINARY_COMPAT_NEEDED_BEFORE {
+        let era = [BinaryCompat::Era2022, BinaryCompat::Era2019, BinaryCompat::Era2016, BinaryCompat::Era2014]
+            .into_iter()
+            .find(|era| era.year() <= target)
+            .unwrap_or(BinaryCompat::Era2014);
+        let why = "fails the conversion instead of writing a file the target can't open".to_owned();
+        suggestion.options.push(("binary_compat".to_owned(), value_name(era), why));
+        if !late_types.is_empty() {
+            let lost = format!(
+                "properties of type {} make binary_compat fail if the preset doesn't remove them, use force_xml to drop them instead",
+                late_types.join(", ")
+            );
+            suggestion.lossy.push(lost);
+        }
+    }
+    suggestion
+}
+
+fn flag(name: &str, value: &str) -> String {
+    let flag = format!("--{}", name.replace('_', "-"));
+    if value == "true" { flag } else { format!("{} {}", flag, value) }
+}
+
+impl Suggestion {
+    pub fn to_text(&self) -> String {
+        let mut out = self.era.to_text();
+        if self.options.is_empty() {
+            let _ = writeln!(out, "nothing in the place is newer than {}, it can go as is", self.target);
+            return out;
+        }
+        let _ = writeln!(out, "\nfor a {} client:", self.target);
+        for (name, value, why) in &self.options {
+            let _ = writeln!(out, "  {:<32} {}", flag(name, value), why);
+        }
+        if !self.lossy.is_empty() {
+            let _ = writeln!(out, "\nlost on the way:");
+            for lost in &self.lossy {
+                let _ = writeln!(out, "  {}", lost);
+            }
+        }
+        let flags: Vec<String> = self.options.iter().map(|(name, value, _)| flag(name, value)).collect();
+        let _ = writeln!(out, "\nfix-place {}", flags.join(" "));
+        out
+    }
+
+    // what the http api takes and a daemon sidecar holds
+    pub fn to_json(&self) -> Result<String, Box<dyn Error>> {
+        let mut options = Map::new();
+        options.insert("action".to_owned(), Value::from("fix-place"));
+        for (name, value, _) in &self.options {
+            options.insert(name.clone(), Value::from(value.as_str()));
+        }
+        Ok(serde_json::to_string_pretty(&options)?)
+    }
+}
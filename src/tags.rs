@@ -0,0 +1,169 @@
+// CollectionService tag conversion
+//
+// old clients have no CollectionService tags, so tags can be turned into things scripts
+// of that era can see: child StringValues or suffixes on the instance name. the reverse
+// modes turn those back into real tags when upgrading a place.
+use crate::pipeline::PassContext;
+use clap::ValueEnum;
+use rbx_dom_weak::{InstanceBuilder, Ustr, WeakDom};
+use rbx_types::{Tags, Variant};
+
+pub const TAG_VALUE_NAME: &str = "CollectionServiceTag";
+// "Lava [#Kill #Hazard]". the brackets keep ordinary names with a # in them ("Door #1") from
+// being read as tags, only a name ending in a whole [#..] block is
+pub const TAG_SUFFIX_START: &str = " [#";
+pub const TAG_SUFFIX_SEPARATOR: &str = " #";
+pub const TAG_SUFFIX_END: &str = "]";
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TagConversion {
+    // one StringValue named CollectionServiceTag per tag, holding the tag name
+    Values,
+    // tags appended to the name, "Lava [#Kill #Hazard]"
+    NameSuffix,
+    FromValues,
+    FromNameSuffix,
+}
+
+pub fn convert_tags(dom: &mut WeakDom, mode: TagConversion, ctx: &mut PassContext) {
+    match mode {
+        TagConversion::Values => tags_to_values(dom, ctx),
+        TagConversion::NameSuffix => tags_to_name_suffix(dom, ctx),
+        TagConversion::FromValues => values_to_tags(dom, ctx),
+        TagConversion::FromNameSuffix => name_suffix_to_tags(dom, ctx),
+    }
+}
+
+fn tags_key() -> Ustr {
+    "Tags".into()
+}
+
+// some older files carry tags as a raw BinaryString instead of the Tags type
+fn read_tags(value: &Variant) -> Option<Vec<String>> {
+    match value {
+        Variant::Tags(tags) => Some(tags.iter().map(str::to_owned).collect()),
+        Variant::BinaryString(bytes) => {
+            Tags::decode(bytes.as_ref()).ok().map(|tags| tags.iter().map(str::to_owned).collect())
+        }
+        _ => None,
+    }
+}
+
+fn take_tags(dom: &mut WeakDom, ctx: &mut PassContext) -> Vec<(rbx_dom_weak::types::Ref, Vec<String>)> {
+    let refs: Vec<_> = dom.descendants().map(|instance| instance.referent()).collect();
+    let mut tagged = Vec::new();
+    for referent in refs {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        let Some(value) = instance.properties.remove(&tags_key()) else { continue };
+        match read_tags(&value) {
+            Some(tags) if !tags.is_empty() => tagged.push((referent, tags)),
+            Some(_) => {}
+            None => ctx.skipped(referent, format!("tags on '{}' have unexpected type: {:?}", instance.name, value)),
+        }
+    }
+    tagged
+}
+
+fn tags_to_values(dom: &mut WeakDom, ctx: &mut PassContext) {
+    for (referent, tags) in take_tags(dom, ctx) {
+        for tag in &tags {
+            dom.insert(
+                referent,
+                InstanceBuilder::new("StringValue")
+                    .with_name(TAG_VALUE_NAME)
+                    .with_property("Value", Variant::String(tag.clone())),
+            );
+        }
+        if let Some(instance) = dom.get_by_ref(referent) {
+            ctx.converted(
+                referent,
+                format!("converted {} tag(s) on '{}' to StringValues", tags.len(), instance.name),
+            );
+        }
+    }
+}
+
+fn tags_to_name_suffix(dom: &mut WeakDom, ctx: &mut PassContext) {
+    for (referent, tags) in take_tags(dom, ctx) {
+        if let Some(instance) = dom.get_by_ref_mut(referent) {
+            let unreadable: Vec<&String> = tags
+                .iter()
+                .filter(|tag| tag.contains(TAG_SUFFIX_SEPARATOR) || tag.contains(TAG_SUFFIX_END))
+                .collect();
+            if !unreadable.is_empty() {
+                ctx.warn(format!(
+                    "tags {:?} on '{}' have ' #' or ']' in them and won't come back intact from the name suffix",
+                    unreadable, instance.name
+                ));
+            }
+            instance.name = format!(
+                "{}{}{}{}",
+                instance.name,
+                TAG_SUFFIX_START,
+                tags.join(TAG_SUFFIX_SEPARATOR),
+                TAG_SUFFIX_END
+            );
+            ctx.converted(
+                referent,
+                format!("converted {} tag(s) to name suffix: '{}'", tags.len(), instance.name),
+            );
+        }
+    }
+}
+
+fn add_tags(dom: &mut WeakDom, referent: rbx_dom_weak::types::Ref, new_tags: Vec<String>) {
+    let Some(instance) = dom.get_by_ref_mut(referent) else { return };
+    let mut tags: Vec<String> = instance
+        .properties
+        .get(&tags_key())
+        .and_then(read_tags)
+        .unwrap_or_default();
+    for tag in new_tags {
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    instance.properties.insert(tags_key(), Variant::Tags(Tags::from(tags)));
+}
+
+fn values_to_tags(dom: &mut WeakDom, ctx: &mut PassContext) {
+    let tag_values: Vec<_> = dom
+        .descendants()
+        .filter(|instance| instance.class == "StringValue" && instance.name == TAG_VALUE_NAME)
+        .filter_map(|instance| match instance.properties.get(&"Value".into()) {
+            Some(Variant::String(tag)) => Some((instance.referent(), instance.parent(), tag.clone())),
+            _ => None,
+        })
+        .collect();
+
+    for (value_ref, parent_ref, tag) in tag_values {
+        dom.destroy(value_ref);
+        if let Some(parent) = dom.get_by_ref(parent_ref) {
+            ctx.converted(parent_ref, format!("restored tag '{}' on '{}'", tag, parent.name));
+        }
+        add_tags(dom, parent_ref, vec![tag]);
+    }
+}
+
+fn name_suffix_to_tags(dom: &mut WeakDom, ctx: &mut PassContext) {
+    let refs: Vec<_> = dom.descendants().map(|instance| instance.referent()).collect();
+    for referent in refs {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        let Some(block) = instance.name.strip_suffix(TAG_SUFFIX_END) else { continue };
+        let Some(marker) = block.rfind(TAG_SUFFIX_START) else { continue };
+        let tags: Vec<String> = block[marker + TAG_SUFFIX_START.len()..]
+            .split(TAG_SUFFIX_SEPARATOR)
+            .filter(|tag| !tag.is_empty())
+            .map(str::to_owned)
+            .collect();
+        if tags.is_empty() {
+            continue;
+        }
+        instance.name.truncate(marker);
+        ctx.converted(
+            referent,
+            format!("restored {} tag(s) from name suffix on '{}'", tags.len(), instance.name),
+        );
+        add_tags(dom, referent, tags);
+    }
+}
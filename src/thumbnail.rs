@@ -0,0 +1,199 @@
+// the camera studio and launchers render place previews from
+//
+// studio keeps it as a Camera named ThumbnailCamera under Workspace. places don't have anywhere
+// to store an actual image (neither format has a slot for one, the website thumbnail lives
+// outside the file), so the camera is all that can be embedded. converted archives often have
+// none or one pointing at where the map used to be before it was cut down.
+use crate::pipeline::PassContext;
+use rbx_dom_weak::types::{CFrame, Matrix3, Vector3};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+use rbx_types::Variant;
+use std::str::FromStr;
+
+pub const THUMBNAIL_CAMERA_NAME: &str = "ThumbnailCamera";
+const FIELD_OF_VIEW: f32 = 70.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThumbnailCamera {
+    // looks at the whole map from above at an angle
+    Auto,
+    // copies Workspace.CurrentCamera
+    Current,
+    // "x,y,z,lx,ly,lz", a position and the point to look at
+    LookAt(Vector3, Vector3),
+}
+
+impl FromStr for ThumbnailCamera {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "auto" => return Ok(Self::Auto),
+            "current" => return Ok(Self::Current),
+            _ => {}
+        }
+        let numbers: Vec<f32> = s
+            .split(',')
+            .map(|part| part.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("expected auto, current or x,y,z,lx,ly,lz, got '{}'", s))?;
+        match numbers[..] {
+            [x, y, z, lx, ly, lz] => Ok(Self::LookAt(Vector3::new(x, y, z), Vector3::new(lx, ly, lz))),
+            _ => Err(format!("expected 6 numbers for x,y,z,lx,ly,lz, got {}", numbers.len())),
+        }
+    }
+}
+
+fn sub(a: Vector3, b: Vector3) -> Vector3 {
+    Vector3::new(a.x - b.x, a.y - b.y, a.z - b.z)
+}
+
+fn cross(a: Vector3, b: Vector3) -> Vector3 {
+    Vector3::new(a.y * b.z - a.z * b.y, a.z * b.x - a.x * b.z, a.x * b.y - a.y * b.x)
+}
+
+fn normalize(v: Vector3) -> Vector3 {
+    let length = (v.x * v.x + v.y * v.y + v.z * v.z).sqrt();
+    if length == 0.0 {
+        return v;
+    }
+    Vector3::new(v.x / length, v.y / length, v.z / length)
+}
+
+// same as CFrame.new(position, target), columns are right, up and back
+pub fn look_at(position: Vector3, target: Vector3) -> CFrame {
+    let forward = normalize(sub(target, position));
+    let mut right = cross(forward, Vector3::new(0.0, 1.0, 0.0));
+    if right.x == 0.0 && right.y == 0.0 && right.z == 0.0 {
+        // looking straight up or down
+        right = Vector3::new(1.0, 0.0, 0.0);
+    }
+    let right = normalize(right);
+    let up = cross(right, forward);
+    let back = Vector3::new(-forward.x, -forward.y, -forward.z);
+    CFrame::new(
+        position,
+        Matrix3::new(
+            Vector3::new(right.x, up.x, back.x),
+            Vector3::new(right.y, up.y, back.y),
+            Vector3::new(right.z, up.z, back.z),
+        ),
+    )
+}
+
+// bounds of the parts under workspace, ignoring rotation. big places usually have a few parts
+// far out in the void (kill bricks, hidden storage) that would shrink the map to a dot, so with
+// enough parts the outermost few percent on each axis are left out
+pub(crate) fn workspace_bounds(dom: &WeakDom, workspace: rbx_dom_weak::types::Ref) -> Option<(Vector3, Vector3)> {
+    let mut lows: [Vec<f32>; 3] = Default::default();
+    let mut highs: [Vec<f32>; 3] = Default::default();
+    for instance in dom.descendants_of(workspace) {
+        let (Some(Variant::CFrame(cframe)), Some(Variant::Vector3(size))) = (
+            instance.properties.get(&"CFrame".into()),
+            instance.properties.get(&"Size".into()),
+        ) else {
+            continue;
+        };
+        let p = cframe.position;
+        for (axis, (center, extent)) in [(p.x, size.x), (p.y, size.y), (p.z, size.z)].into_iter().enumerate() {
+            lows[axis].push(center - extent / 2.0);
+            highs[axis].push(center + extent / 2.0);
+        }
+    }
+    let count = lows[0].len();
+    if count == 0 {
+        return None;
+    }
+    let trim = if count >= 20 { count / 20 } else { 0 };
+    let mut min = [0.0; 3];
+    let mut max = [0.0; 3];
+    for axis in 0..3 {
+        lows[axis].sort_by(f32::total_cmp);
+        highs[axis].sort_by(f32::total_cmp);
+        min[axis] = lows[axis][trim];
+        max[axis] = highs[axis][count - 1 - trim];
+    }
+    Some((Vector3::new(min[0], min[1], min[2]), Vector3::new(max[0], max[1], max[2])))
+}
+
+fn auto_camera(dom: &WeakDom, workspace: rbx_dom_weak::types::Ref) -> Option<(CFrame, Vector3)> {
+    let (min, max) = workspace_bounds(dom, workspace)?;
+    let center = Vector3::new((min.x + max.x) / 2.0, (min.y + max.y) / 2.0, (min.z + max.z) / 2.0);
+    let extent = sub(max, min);
+    let radius = (extent.x * extent.x + extent.y * extent.y + extent.z * extent.z).sqrt() / 2.0;
+    // far enough back that a sphere around the map fits the vertical field of view
+    let distance = radius.max(1.0) / (FIELD_OF_VIEW.to_radians() / 2.0).sin();
+    let direction = normalize(Vector3::new(1.0, 0.8, 1.0));
+    let position = Vector3::new(
+        center.x + direction.x * distance,
+        center.y + direction.y * distance,
+        center.z + direction.z * distance,
+    );
+    Some((look_at(position, center), center))
+}
+
+fn current_camera(dom: &WeakDom, workspace: rbx_dom_weak::types::Ref) -> Option<(CFrame, Vector3)> {
+    let Some(Variant::Ref(camera_ref)) = dom.get_by_ref(workspace)?.properties.get(&"CurrentCamera".into()) else {
+        return None;
+    };
+    let camera = dom.get_by_ref(*camera_ref)?;
+    let Some(Variant::CFrame(cframe)) = camera.properties.get(&"CFrame".into()) else { return None };
+    let focus = match camera.properties.get(&"Focus".into()) {
+        Some(Variant::CFrame(focus)) => focus.position,
+        _ => cframe.position,
+    };
+    Some((*cframe, focus))
+}
+
+pub fn set_thumbnail_camera(dom: &mut WeakDom, placement: ThumbnailCamera, ctx: &mut PassContext) {
+    let workspace = dom
+        .root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == "Workspace"));
+    let Some(workspace) = workspace else {
+        ctx.warn("no workspace, can't place a thumbnail camera");
+        return;
+    };
+    let camera = match placement {
+        ThumbnailCamera::Auto => auto_camera(dom, workspace),
+        ThumbnailCamera::Current => current_camera(dom, workspace),
+        ThumbnailCamera::LookAt(position, target) => Some((look_at(position, target), target)),
+    };
+    let Some((cframe, focus)) = camera else {
+        ctx.warn(format!("couldn't work out a {:?} thumbnail camera, leaving it alone", placement));
+        return;
+    };
+
+    let existing = dom.get_by_ref(workspace).and_then(|instance| {
+        instance.children().iter().copied().find(|&child| {
+            dom.get_by_ref(child)
+                .is_some_and(|child| child.class == "Camera" && child.name == THUMBNAIL_CAMERA_NAME)
+        })
+    });
+    let focus = CFrame::new(focus, Matrix3::identity());
+    let referent = match existing.and_then(|referent| dom.get_by_ref_mut(referent)) {
+        Some(camera) => {
+            camera.properties.insert("CFrame".into(), Variant::CFrame(cframe));
+            camera.properties.insert("Focus".into(), Variant::CFrame(focus));
+            camera.referent()
+        }
+        None => dom.insert(
+            workspace,
+            InstanceBuilder::new("Camera")
+                .with_name(THUMBNAIL_CAMERA_NAME)
+                .with_property("CFrame", Variant::CFrame(cframe))
+                .with_property("Focus", Variant::CFrame(focus))
+                .with_property("FieldOfView", Variant::Float32(FIELD_OF_VIEW)),
+        ),
+    };
+    let (p, f) = (cframe.position, focus.position);
+    ctx.converted(
+        referent,
+        format!(
+            "set thumbnail camera at ({}, {}, {}) looking at ({}, {}, {})",
+            p.x, p.y, p.z, f.x, f.y, f.z
+        ),
+    );
+}
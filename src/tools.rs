@@ -0,0 +1,182 @@
+// Tools for clients from before the modern tool setup
+//
+// old tools always have a Handle and take their grip from Tool.Grip, anything without a handle
+// was a HopperBin. newer tools can skip the handle (RequiresHandle = false), put the grip on a
+// RightGripAttachment inside the handle and carry properties old clients don't know.
+//
+// starter gear in newer games is often loaded by id from a script instead of sitting in
+// StarterPack. with a gear dir (files named <id>.rbxm or <id>.rbxmx) those get put in StarterPack
+// so they show up without the script.
+use crate::pipeline::PassContext;
+use rbx_dom_weak::types::{CFrame, Enum, Matrix3, Ref, Vector3};
+use rbx_dom_weak::{InstanceBuilder, Ustr, WeakDom};
+use rbx_types::Variant;
+use std::fs;
+use std::path::Path;
+
+const ATTACHMENT_YEAR: u32 = 2015;
+const REQUIRES_HANDLE_YEAR: u32 = 2013;
+const TOOLTIP_YEAR: u32 = 2014;
+const MANUAL_ACTIVATION_YEAR: u32 = 2014;
+
+const GRIP_ATTACHMENT: &str = "RightGripAttachment";
+// Tool properties a HopperBin doesn't have
+const TOOL_ONLY: [&str; 6] = ["Grip", "RequiresHandle", "CanBeDropped", "ToolTip", "Enabled", "ManualActivationOnly"];
+// events a HopperBin script has to get some other way (Selected, Deselected, mouse clicks)
+const TOOL_EVENTS: [&str; 3] = [".Equipped", ".Unequipped", ".Activated"];
+
+fn child_named(dom: &WeakDom, parent: Ref, name: &str) -> Option<Ref> {
+    dom.get_by_ref(parent)?
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.name == name))
+}
+
+// ids passed straight to LoadAsset, e.g. InsertService:LoadAsset(12345)
+fn loaded_asset_ids(dom: &WeakDom) -> Vec<u64> {
+    let mut ids = Vec::new();
+    for instance in dom.descendants() {
+        let Some(Variant::String(source)) = instance.properties.get(&"Source".into()) else { continue };
+        for (index, _) in source.match_indices("LoadAsset(") {
+            let rest = source[index + "LoadAsset(".len()..].trim_start();
+            let digits: String = rest.chars().take_while(char::is_ascii_digit).collect();
+            if let Ok(id) = digits.parse()
+                && !ids.contains(&id)
+            {
+                ids.push(id);
+            }
+        }
+    }
+    ids
+}
+
+fn localize_gear(dom: &mut WeakDom, gear_dir: &Path, ctx: &mut PassContext) {
+    let ids = loaded_asset_ids(dom);
+    if ids.is_empty() {
+        return;
+    }
+    let starter_pack = dom
+        .root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == "StarterPack"));
+    let starter_pack = match starter_pack {
+        Some(starter_pack) => starter_pack,
+        None => dom.insert(dom.root_ref(), InstanceBuilder::new("StarterPack").with_name("StarterPack")),
+    };
+    for id in ids {
+        let Some(path) = ["rbxm", "rbxmx"]
+            .iter()
+            .map(|ext| gear_dir.join(format!("{}.{}", id, ext)))
+            .find(|path| path.is_file())
+        else {
+            ctx.warn(format!("asset {} is loaded by a script but isn't in the gear dir", id));
+            continue;
+        };
+        let loaded = fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|data| crate::load_place(&data).map_err(|e| e.to_string()));
+        let model = match loaded {
+            Ok(model) => model,
+            Err(e) => {
+                ctx.warn(format!("couldn't load {}: {}", path.display(), e));
+                continue;
+            }
+        };
+        let tools: Vec<Ref> = model
+            .descendants()
+            .filter(|instance| instance.class == "Tool" || instance.class == "HopperBin")
+            .map(|instance| instance.referent())
+            .collect();
+        if tools.is_empty() {
+            ctx.warn(format!("{} has no tools in it, skipping", path.display()));
+            continue;
+        }
+        for tool in tools {
+            let copy = model.clone_into_external(tool, dom);
+            dom.transfer_within(copy, starter_pack);
+            let name = dom.get_by_ref(copy).map_or_else(String::new, |tool| tool.name.to_string());
+            ctx.converted(copy, format!("added gear '{}' from asset {} to starterpack", name, id));
+        }
+    }
+}
+
+pub fn apply_tool_fixups(dom: &mut WeakDom, year: u32, gear_dir: Option<&Path>, ctx: &mut PassContext) {
+    if let Some(gear_dir) = gear_dir {
+        localize_gear(dom, gear_dir, ctx);
+    }
+
+    let tools: Vec<Ref> = dom
+        .descendants()
+        .filter(|instance| instance.class == "Tool")
+        .map(|instance| instance.referent())
+        .collect();
+    for tool in tools {
+        // the grip attachment says where the hand goes on the handle, which is what Grip is
+        if let Some(handle) = child_named(dom, tool, "Handle")
+            && let Some(attachment) = child_named(dom, handle, GRIP_ATTACHMENT)
+        {
+            let grip = match dom.get_by_ref(attachment).and_then(|a| a.properties.get(&"CFrame".into())) {
+                Some(Variant::CFrame(cframe)) => Some(*cframe),
+                _ => None,
+            };
+            let Some(instance) = dom.get_by_ref_mut(tool) else { continue };
+            let identity = CFrame::new(Vector3::new(0.0, 0.0, 0.0), Matrix3::identity());
+            let unset = match instance.properties.get(&"Grip".into()) {
+                Some(Variant::CFrame(current)) => *current == identity,
+                _ => true,
+            };
+            if let Some(grip) = grip
+                && unset
+            {
+                instance.properties.insert("Grip".into(), Variant::CFrame(grip));
+                ctx.converted(tool, format!("set grip on tool '{}' from its {}", instance.name, GRIP_ATTACHMENT));
+            }
+            if year < ATTACHMENT_YEAR {
+                dom.destroy(attachment);
+            }
+        }
+
+        let Some(instance) = dom.get_by_ref_mut(tool) else { continue };
+        let name = instance.name.to_string();
+        if year < TOOLTIP_YEAR
+            && let Some(Variant::String(tip)) = instance.properties.remove(&"ToolTip".into())
+            && !tip.is_empty()
+        {
+            ctx.info(format!("dropped tooltip '{}' on tool '{}'", tip, name));
+        }
+        if year < MANUAL_ACTIVATION_YEAR
+            && instance.properties.remove(&"ManualActivationOnly".into()) == Some(Variant::Bool(true))
+        {
+            ctx.warn(format!("tool '{}' was manual activation only, clicking will activate it in {}", name, year));
+        }
+
+        let handleless = instance.properties.get(&"RequiresHandle".into()) == Some(&Variant::Bool(false));
+        if year < REQUIRES_HANDLE_YEAR && handleless {
+            instance.class = "HopperBin".into();
+            for property in TOOL_ONLY {
+                instance.properties.remove(&Ustr::from(property));
+            }
+            // BinType.Script, it does nothing by itself and leaves everything to the scripts
+            instance.properties.insert("BinType".into(), Variant::Enum(Enum::from_u32(0)));
+            ctx.converted(tool, format!("converted handleless tool '{}' to hopperbin", name));
+            let scripts = dom
+                .descendants_of(tool)
+                .filter(|script| match script.properties.get(&"Source".into()) {
+                    Some(Variant::String(source)) => TOOL_EVENTS.iter().any(|event| source.contains(event)),
+                    _ => false,
+                })
+                .count();
+            if scripts > 0 {
+                ctx.warn(format!(
+                    "{} scripts in '{}' use tool events, a hopperbin only has Selected and Deselected",
+                    scripts, name
+                ));
+            }
+        } else if year < REQUIRES_HANDLE_YEAR {
+            instance.properties.remove(&"RequiresHandle".into());
+        }
+    }
+}
@@ -0,0 +1,82 @@
+// UniqueId and HistoryId, the per instance ids studio has saved since 2021
+//
+// parsers from before then don't know the type, old xml readers trip over the element and old
+// binary ones refuse the whole PROP chunk, so places for old targets want them stripped. newer
+// targets keep them, but a UniqueId has to be unique within the place, and models merged in
+// (inserted assets, gear) can bring ids the place already has. later copies get a new id
+// derived from the old one and how many times it came up, so the same input always gives the
+// same output. HistoryId is shared by copies on purpose and is left as is.
+use clap::ValueEnum;
+use rbx_dom_weak::WeakDom;
+use rbx_dom_weak::types::Ref;
+use rbx_types::{UniqueId, Variant};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UniqueIdHandling {
+    // drop every UniqueId typed property
+    Strip,
+    // keep them and give duplicates new ones
+    Regenerate,
+}
+
+// properties removed and instances they were on
+pub fn strip_unique_ids(dom: &mut WeakDom) -> (usize, usize) {
+    let refs: Vec<_> = dom.descendants().map(|instance| instance.referent()).collect();
+    let (mut properties, mut instances) = (0, 0);
+    for referent in refs {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        let before = instance.properties.len();
+        instance.properties.retain(|_, value| !matches!(value, Variant::UniqueId(_)));
+        let removed = before - instance.properties.len();
+        if removed > 0 {
+            properties += removed;
+            instances += 1;
+        }
+    }
+    (properties, instances)
+}
+
+fn derived_id(id: UniqueId, copy: u32) -> UniqueId {
+    let mut hasher = Sha256::new();
+    hasher.update(id.to_string().as_bytes());
+    hasher.update(copy.to_le_bytes());
+    let digest = hasher.finalize();
+    let mut random = [0u8; 8];
+    random.copy_from_slice(&digest[..8]);
+    // always positive, like the ones studio makes
+    UniqueId::new(id.index(), id.time(), i64::from_le_bytes(random) & i64::MAX)
+}
+
+// instances whose UniqueId was already taken, with the id they had and the one they got.
+// nil ids are allowed to repeat and stay
+pub fn regenerate_duplicate_ids(dom: &mut WeakDom) -> Vec<(Ref, UniqueId, UniqueId)> {
+    let unique_id_key = "UniqueId".into();
+    let mut seen: HashSet<UniqueId> = dom
+        .descendants()
+        .filter_map(|instance| match instance.properties.get(&unique_id_key) {
+            Some(Variant::UniqueId(id)) => Some(*id),
+            _ => None,
+        })
+        .collect();
+    let refs: Vec<_> = dom.descendants().map(|instance| instance.referent()).collect();
+    let mut first_seen: HashSet<UniqueId> = HashSet::new();
+    let mut regenerated = Vec::new();
+    for referent in refs {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        let Some(Variant::UniqueId(id)) = instance.properties.get(&unique_id_key).cloned() else { continue };
+        if id.is_nil() || first_seen.insert(id) {
+            continue;
+        }
+        let mut copy = 1;
+        let mut new_id = derived_id(id, copy);
+        while !seen.insert(new_id) {
+            copy += 1;
+            new_id = derived_id(id, copy);
+        }
+        instance.properties.insert(unique_id_key, Variant::UniqueId(new_id));
+        regenerated.push((referent, id, new_id));
+    }
+    regenerated
+}
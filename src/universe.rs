@@ -0,0 +1,279 @@
+// games made of several places, converted together so teleports between them keep working
+//
+// a universe file lists the places, the id each had and optionally the id it gets where the
+// converted game will be hosted:
+//
+//   { "places": [
+//       { "file": "start.rbxl", "id": 1818, "new_id": 1, "start": true },
+//       { "file": "dungeon.rbxlx", "id": 24913208, "new_id": 2 } ] }
+//
+// files are relative to the universe file. every place gets the same fix-place options, and
+// numbers in scripts (and Int/NumberValues) equal to an old id are replaced with the new one.
+// place ids are long enough that a number matching one is taken to be it, which also catches
+// the ids kept in constants rather than passed to TeleportService directly. teleports to a
+// literal id that isn't in the universe are warned about, that place won't come along.
+use crate::pipeline::{PassContext, RunReport};
+use crate::FixPlaceOptions;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+use rbx_types::Variant;
+use serde::Deserialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt::Write as FmtWrite;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// TeleportService methods that take the place id first
+const TELEPORT_METHODS: [&str; 6] = [
+    "Teleport",
+    "TeleportAsync",
+    "TeleportToPlaceInstance",
+    "TeleportPartyAsync",
+    "TeleportToSpawnByName",
+    "TeleportToPrivateServer",
+];
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct UniversePlace {
+    pub file: PathBuf,
+    pub id: u64,
+    #[serde(default)]
+    pub new_id: Option<u64>,
+    #[serde(default)]
+    pub start: bool,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct Universe {
+    pub places: Vec<UniversePlace>,
+}
+
+impl Universe {
+    // reads a universe file, place files come back relative to the current dir
+    pub fn load(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let mut universe: Universe =
+            serde_json::from_str(&text).map_err(|e| format!("couldn't read {}: {}", path.display(), e))?;
+        let base = path.parent().unwrap_or(Path::new(""));
+        for place in &mut universe.places {
+            place.file = base.join(&place.file);
+        }
+
+        if universe.places.is_empty() {
+            return Err(format!("{} has no places", path.display()).into());
+        }
+        let starts = universe.places.iter().filter(|place| place.start).count();
+        if starts != 1 {
+            return Err(format!("{} needs exactly one start place, it has {}", path.display(), starts).into());
+        }
+        let mut ids = BTreeSet::new();
+        let mut new_ids = BTreeSet::new();
+        for place in &universe.places {
+            if !ids.insert(place.id) {
+                return Err(format!("place id {} is in {} twice", place.id, path.display()).into());
+            }
+            if let Some(new_id) = place.new_id
+                && !new_ids.insert(new_id)
+            {
+                return Err(format!("new_id {} is given to two places in {}", new_id, path.display()).into());
+            }
+        }
+        Ok(universe)
+    }
+
+    // old id -> new id for every place, the ones without a new_id keep theirs
+    pub fn id_map(&self) -> BTreeMap<u64, u64> {
+        self.places
+            .iter()
+            .map(|place| (place.id, place.new_id.unwrap_or(place.id)))
+            .collect()
+    }
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'.'
+}
+
+// (start, end, value) of the whole numbers in a script, not parts of names or decimals
+fn integer_literals(source: &str) -> Vec<(usize, usize, u64)> {
+    let bytes = source.as_bytes();
+    let mut literals = Vec::new();
+    let mut index = 0;
+    while index < bytes.len() {
+        if !bytes[index].is_ascii_digit() || (index > 0 && is_word_byte(bytes[index - 1])) {
+            index += 1;
+            continue;
+        }
+        let start = index;
+        while index < bytes.len() && bytes[index].is_ascii_digit() {
+            index += 1;
+        }
+        if index < bytes.len() && is_word_byte(bytes[index]) {
+            continue;
+        }
+        if let Ok(value) = source[start..index].parse() {
+            literals.push((start, index, value));
+        }
+    }
+    literals
+}
+
+// literal place ids passed to TeleportService, e.g. TeleportService:Teleport(1818, player)
+pub fn teleport_targets(source: &str) -> Vec<u64> {
+    let mut targets = Vec::new();
+    for method in TELEPORT_METHODS {
+        let call = format!(":{}(", method);
+        for (index, _) in source.match_indices(&call) {
+            let rest = source[index + call.len()..].trim_start();
+            let digits = &rest[..rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len())];
+            let after = rest[digits.len()..].trim_start();
+            if let Ok(id) = digits.parse()
+                && (after.starts_with(',') || after.starts_with(')'))
+                && !targets.contains(&id)
+            {
+                targets.push(id);
+            }
+        }
+    }
+    targets
+}
+
+// swaps old place ids for new ones, and warns about teleports to places outside the universe
+pub fn rewrite_place_ids(dom: &mut WeakDom, ids: &BTreeMap<u64, u64>, ctx: &mut PassContext) {
+    let refs: Vec<Ref> = dom.descendants().map(|instance| instance.referent()).collect();
+    for referent in refs {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        let name = instance.name.to_string();
+        if let Some(Variant::String(source)) = instance.properties.get_mut(&"Source".into()) {
+            for target in teleport_targets(source) {
+                if !ids.contains_key(&target) {
+                    ctx.warn(format!("'{}' teleports to place {}, which isn't in the universe", name, target));
+                }
+            }
+            let mut rewritten = 0;
+            for (start, end, value) in integer_literals(source).into_iter().rev() {
+                if let Some(&new_id) = ids.get(&value)
+                    && new_id != value
+                {
+                    source.replace_range(start..end, &new_id.to_string());
+                    rewritten += 1;
+                }
+            }
+            if rewritten > 0 {
+                ctx.converted(referent, format!("rewrote {} place ids in '{}'", rewritten, name));
+            }
+            continue;
+        }
+        // ids kept in IntValues and NumberValues
+        let new_value = match instance.properties.get(&"Value".into()) {
+            Some(Variant::Int64(value)) => u64::try_from(*value)
+                .ok()
+                .and_then(|value| ids.get(&value))
+                .map(|&new_id| Variant::Int64(new_id as i64)),
+            Some(Variant::Float64(value)) if value.fract() == 0.0 && *value > 0.0 => {
+                ids.get(&(*value as u64)).map(|&new_id| Variant::Float64(new_id as f64))
+            }
+            _ => None,
+        };
+        if let Some(new_value) = new_value
+            && instance.properties.get(&"Value".into()) != Some(&new_value)
+        {
+            instance.properties.insert("Value".into(), new_value);
+            ctx.converted(referent, format!("rewrote the place id in '{}'", name));
+        }
+    }
+}
+
+pub struct ConvertedPlace {
+    pub place: UniversePlace,
+    pub output: PathBuf,
+    pub report: RunReport,
+}
+
+pub struct UniverseReport {
+    pub places: Vec<ConvertedPlace>,
+}
+
+impl UniverseReport {
+    // one section per place, start place first
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        let warnings: usize = self.places.iter().map(|converted| converted.report.warnings.len()).sum();
+        let _ = writeln!(out, "{} places, {} warning(s)", self.places.len(), warnings);
+        for converted in &self.places {
+            let place = &converted.place;
+            let _ = writeln!(out);
+            let _ = write!(out, "== {} ({}", place.file.display(), place.id);
+            if let Some(new_id) = place.new_id {
+                let _ = write!(out, " -> {}", new_id);
+            }
+            if place.start {
+                let _ = write!(out, ", start place");
+            }
+            let _ = writeln!(out, ") -> {}", converted.output.display());
+            out.push_str(&converted.report.to_text());
+        }
+        out
+    }
+}
+
+// converts every place into out_dir with the options make_options builds, the start place first.
+// the first place that fails stops the whole thing, a universe with a place missing is no use
+pub fn fix_universe(
+    universe: &Universe,
+    out_dir: &Path,
+    mut make_options: impl FnMut() -> Result<FixPlaceOptions, Box<dyn Error>>,
+) -> Result<UniverseReport, Box<dyn Error>> {
+    fs::create_dir_all(out_dir)?;
+    let ids = universe.id_map();
+    let mut places = universe.places.clone();
+    places.sort_by_key(|place| !place.start);
+
+    let mut converted = Vec::new();
+    for place in places {
+        let input = fs::read(&place.file).map_err(|e| format!("{}: {}", place.file.display(), e))?;
+        let options = make_options()?.with_pass(crate::passes::RewritePlaceIds { ids: ids.clone() });
+        println!("[legacy_place::universe] converting {}", place.file.display());
+        let (output, report) =
+            crate::fix_place_with_report(&input, options).map_err(|e| format!("{}: {}", place.file.display(), e))?;
+        let extension = if crate::is_binary_rbxl(&output) { "rbxl" } else { "rbxlx" };
+        let stem = place.file.file_stem().unwrap_or(place.file.as_os_str());
+        let output_path = out_dir.join(stem).with_extension(extension);
+        fs::write(&output_path, output)?;
+        converted.push(ConvertedPlace {
+            place,
+            output: output_path,
+            report,
+        });
+    }
+    Ok(UniverseReport { places: converted })
+}
+
+pub struct TeleportTarget {
+    pub file: PathBuf,
+    pub place_id: u64,
+    pub in_universe: bool,
+}
+
+// every place a script in the universe teleports to by a literal id
+pub fn scan_teleports(universe: &Universe) -> Result<Vec<TeleportTarget>, Box<dyn Error>> {
+    let ids = universe.id_map();
+    let mut found = Vec::new();
+    for place in &universe.places {
+        let input = fs::read(&place.file).map_err(|e| format!("{}: {}", place.file.display(), e))?;
+        let dom = crate::load_place(&input).map_err(|e| format!("{}: {}", place.file.display(), e))?;
+        let mut targets = BTreeSet::new();
+        for instance in dom.descendants() {
+            if let Some(Variant::String(source)) = instance.properties.get(&"Source".into()) {
+                targets.extend(teleport_targets(source));
+            }
+        }
+        found.extend(targets.into_iter().map(|place_id| TeleportTarget {
+            file: place.file.clone(),
+            place_id,
+            in_universe: ids.contains_key(&place_id),
+        }));
+    }
+    Ok(found)
+}
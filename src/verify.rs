@@ -0,0 +1,186 @@
+// --verify, reads the written place back and checks it against the dom it was written from
+//
+// writers drop what the target format can't encode without saying anything (a property type the
+// binary format has no id for, a value xml_compat strips for an old era). the tree has to come
+// back identical, same classes and names in the same order. the properties a place can't work
+// without are compared too and any that changed fail the run, the rest only get counted.
+use crate::path::path_of;
+use rbx_dom_weak::types::Ref;
+use rbx_dom_weak::WeakDom;
+use rbx_types::Variant;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+
+const KEY_PROPERTIES: [&str; 26] = [
+    "Source",
+    "CFrame",
+    "Size",
+    "Anchored",
+    "CanCollide",
+    "Transparency",
+    "Color",
+    "BrickColor",
+    "Material",
+    "shape",
+    "MeshId",
+    "TextureId",
+    "Texture",
+    "SoundId",
+    "AnimationId",
+    "Image",
+    "Text",
+    "Value",
+    "Disabled",
+    "Enabled",
+    "Scale",
+    "Offset",
+    "Part0",
+    "Part1",
+    "C0",
+    "C1",
+];
+
+// problems listed in the error before the rest are only counted
+const PROBLEMS_SHOWN: usize = 10;
+
+#[derive(Default, Debug)]
+pub struct VerifySummary {
+    pub instances: usize,
+    pub properties: usize,
+    // class.property -> how many instances came back without it or with a different value
+    pub other_changes: BTreeMap<String, usize>,
+}
+
+impl VerifySummary {
+    pub fn to_text(&self) -> String {
+        let mut text = format!("output verified, {} instances and {} properties", self.instances, self.properties);
+        if !self.other_changes.is_empty() {
+            let changes: Vec<String> = self
+                .other_changes
+                .iter()
+                .map(|(property, count)| format!("{} ({})", property, count))
+                .collect();
+            text.push_str(&format!(", non-key properties that didn't survive: {}", changes.join(", ")));
+        }
+        text
+    }
+}
+
+// the xml reader drops text that's only whitespace, so a name or value of spaces comes back empty.
+// not worth failing over
+fn same_text(before: &str, after: &str) -> bool {
+    before == after || (before.trim().is_empty() && after.is_empty())
+}
+
+fn text_of(value: &Variant) -> Option<Cow<'_, str>> {
+    match value {
+        Variant::Content(content) => Some(Cow::Borrowed(content.as_uri().unwrap_or_default())),
+        Variant::ContentId(content_id) => Some(Cow::Borrowed(content_id.as_str())),
+        Variant::String(s) => Some(Cow::Borrowed(s)),
+        Variant::BinaryString(s) => Some(String::from_utf8_lossy(s.as_ref())),
+        _ => None,
+    }
+}
+
+fn same_value(before: &Variant, after: &Variant) -> bool {
+    match (before, after) {
+        (before, after) if before == after => true,
+        // int64 values come back as whatever width the property is declared with
+        (Variant::Int64(a), Variant::Int32(b)) | (Variant::Int32(b), Variant::Int64(a)) => *a == i64::from(*b),
+        (Variant::Float64(a), Variant::Float32(b)) | (Variant::Float32(b), Variant::Float64(a)) => *a == f64::from(*b),
+        (Variant::String(a), Variant::String(b)) => same_text(a, b),
+        // --binary-compat writes content as a plain string, which reads back as a string type
+        (Variant::Content(a), after) => match text_of(after) {
+            Some(b) => same_text(a.as_uri().unwrap_or_default(), &b),
+            None => false,
+        },
+        // and shared strings inline
+        (Variant::SharedString(a), Variant::BinaryString(b)) => a.data() == AsRef::<[u8]>::as_ref(b),
+        _ => false,
+    }
+}
+
+// pairs every instance with its copy in the reloaded dom, the first mismatch is returned as an error
+fn pair_instances(original: &WeakDom, reloaded: &WeakDom) -> Result<Vec<(Ref, Ref)>, String> {
+    let mut pairs = Vec::new();
+    let mut stack = vec![(original.root_ref(), reloaded.root_ref())];
+    while let Some((before, after)) = stack.pop() {
+        let (Some(before_instance), Some(after_instance)) = (original.get_by_ref(before), reloaded.get_by_ref(after)) else {
+            continue;
+        };
+        if before != original.root_ref()
+            && (before_instance.class != after_instance.class || !same_text(&before_instance.name, &after_instance.name))
+        {
+            return Err(format!(
+                "{} ({}) came back as {} '{}'",
+                path_of(original, before),
+                before_instance.class,
+                after_instance.class,
+                after_instance.name
+            ));
+        }
+        let (before_children, after_children) = (before_instance.children(), after_instance.children());
+        if before_children.len() != after_children.len() {
+            let path = if before == original.root_ref() { "game".to_owned() } else { path_of(original, before) };
+            return Err(format!(
+                "{} had {} children, came back with {}",
+                path,
+                before_children.len(),
+                after_children.len()
+            ));
+        }
+        pairs.push((before, after));
+        stack.extend(before_children.iter().copied().zip(after_children.iter().copied()).rev());
+    }
+    Ok(pairs)
+}
+
+pub fn verify_output(original: &WeakDom, output: &[u8]) -> Result<VerifySummary, Box<dyn Error>> {
+    let reloaded = crate::load_place(output).map_err(|e| format!("--verify couldn't read the output back: {}", e))?;
+    let pairs = pair_instances(original, &reloaded).map_err(|e| format!("--verify failed, {}", e))?;
+    let refs: HashMap<Ref, Ref> = pairs.iter().copied().collect();
+
+    let mut summary = VerifySummary {
+        instances: pairs.len() - 1,
+        ..VerifySummary::default()
+    };
+    let mut problems = Vec::new();
+    for &(before, after) in &pairs {
+        let (Some(before_instance), Some(after_instance)) = (original.get_by_ref(before), reloaded.get_by_ref(after)) else {
+            continue;
+        };
+        for (prop_name, value) in &before_instance.properties {
+            summary.properties += 1;
+            let survived = match (value, after_instance.properties.get(prop_name)) {
+                // referents are new in the reloaded dom, they have to point at the same instance
+                (Variant::Ref(target), Some(Variant::Ref(reloaded_target))) => {
+                    refs.get(target).copied().unwrap_or_else(Ref::none) == *reloaded_target
+                }
+                (value, Some(reloaded_value)) => same_value(value, reloaded_value),
+                (_, None) => false,
+            };
+            if survived {
+                continue;
+            }
+            if KEY_PROPERTIES.contains(&prop_name.as_str()) {
+                let how = if after_instance.properties.contains_key(prop_name) { "changed" } else { "was dropped" };
+                problems.push(format!("{}.{} {}", path_of(original, before), prop_name, how));
+            } else {
+                *summary
+                    .other_changes
+                    .entry(format!("{}.{}", before_instance.class, prop_name))
+                    .or_default() += 1;
+            }
+        }
+    }
+
+    if !problems.is_empty() {
+        let mut listed: Vec<String> = problems.iter().take(PROBLEMS_SHOWN).cloned().collect();
+        if problems.len() > PROBLEMS_SHOWN {
+            listed.push(format!("and {} more", problems.len() - PROBLEMS_SHOWN));
+        }
+        return Err(format!("--verify failed, {} key properties didn't survive: {}", problems.len(), listed.join(", ")).into());
+    }
+    Ok(summary)
+}
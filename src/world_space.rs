@@ -0,0 +1,185 @@
+// what in a place is positioned in world space, and moving it
+//
+// parts (CFrame), model pivots, cameras (CFrame and Focus) and the few legacy objects that hold a
+// world position themselves. attachments, welds, joints and pivot offsets are relative to their
+// part and move with it. terrain voxels and positions written into scripts can't be moved.
+//
+// very old clients jitter badly far from 0,0,0 since everything is single precision, so a map
+// built out at 20000 studs can be rebased: the given point, or the middle of the map, becomes
+// the origin and everything is moved by the same amount.
+use crate::pipeline::PassContext;
+use crate::thumbnail;
+use rbx_dom_weak::types::{Ref, Vector3};
+use rbx_dom_weak::{Instance, WeakDom};
+use rbx_types::Variant;
+use std::str::FromStr;
+
+// (class, property) for world space positions on things that aren't parts
+const WORLD_POSITIONS: [(&str, &str); 4] = [
+    ("Camera", "CFrame"),
+    ("Camera", "Focus"),
+    ("BodyPosition", "Position"),
+    ("Explosion", "Position"),
+];
+const TERRAIN_GRIDS: [&str; 2] = ["SmoothGrid", "ClusterGridV3"];
+// script code that builds positions, which might be world space ones
+const POSITION_CONSTRUCTORS: [&str; 2] = ["Vector3.new(", "CFrame.new("];
+
+pub(crate) fn is_a(class: &str, ancestor: &str) -> bool {
+    let Ok(database) = rbx_reflection_database::get() else { return class == ancestor };
+    match database.classes.get(class) {
+        Some(descriptor) => database.superclasses_iter(descriptor).any(|class| class.name == ancestor),
+        None => class == ancestor,
+    }
+}
+
+pub(crate) fn world_space(class: &str, property: &str) -> bool {
+    match property {
+        "WorldPivotData" => true,
+        "CFrame" if is_a(class, "BasePart") => true,
+        _ => WORLD_POSITIONS.iter().any(|&(world_class, name)| name == property && is_a(class, world_class)),
+    }
+}
+
+// the world space bounds of a part, rotation included, (min, max)
+pub(crate) fn part_bounds(instance: &Instance) -> Option<(Vector3, Vector3)> {
+    let (Some(Variant::CFrame(cframe)), Some(Variant::Vector3(size))) = (
+        instance.properties.get(&"CFrame".into()),
+        instance.properties.get(&"Size".into()),
+    ) else {
+        return None;
+    };
+    let half = Vector3::new(size.x / 2.0, size.y / 2.0, size.z / 2.0);
+    let extent = |row: Vector3| row.x.abs() * half.x + row.y.abs() * half.y + row.z.abs() * half.z;
+    let o = cframe.orientation;
+    let extent = Vector3::new(extent(o.x), extent(o.y), extent(o.z));
+    let p = cframe.position;
+    Some((
+        Vector3::new(p.x - extent.x, p.y - extent.y, p.z - extent.z),
+        Vector3::new(p.x + extent.x, p.y + extent.y, p.z + extent.z),
+    ))
+}
+
+fn moved(position: Vector3, delta: Vector3) -> Vector3 {
+    Vector3::new(position.x + delta.x, position.y + delta.y, position.z + delta.z)
+}
+
+// moves everything under `referent`, itself included, the number of values changed
+pub(crate) fn translate(dom: &mut WeakDom, referent: Ref, delta: Vector3) -> usize {
+    let refs: Vec<Ref> = dom.descendants_of(referent).map(|instance| instance.referent()).collect();
+    let mut count = 0;
+    for referent in refs {
+        let Some(instance) = dom.get_by_ref_mut(referent) else { continue };
+        let class = instance.class;
+        for (name, value) in instance.properties.iter_mut() {
+            if !world_space(&class, name) {
+                continue;
+            }
+            match value {
+                Variant::CFrame(cframe) | Variant::OptionalCFrame(Some(cframe)) => {
+                    cframe.position = moved(cframe.position, delta)
+                }
+                Variant::Vector3(position) => *position = moved(*position, delta),
+                _ => continue,
+            }
+            count += 1;
+        }
+    }
+    count
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RebaseOrigin {
+    // the middle of the map, left and right and front to back. heights stay as they are
+    Center,
+    // "x,y,z", the point that becomes 0,0,0
+    Point(Vector3),
+}
+
+impl FromStr for RebaseOrigin {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().eq_ignore_ascii_case("center") {
+            return Ok(Self::Center);
+        }
+        let numbers: Vec<f32> = s
+            .split(',')
+            .map(|part| part.trim().parse::<f32>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("expected center or x,y,z, got '{}'", s))?;
+        match numbers[..] {
+            [x, y, z] => Ok(Self::Point(Vector3::new(x, y, z))),
+            _ => Err(format!("expected 3 numbers for x,y,z, got {}", numbers.len())),
+        }
+    }
+}
+
+// smooth or the older cluster terrain with anything in it
+pub(crate) fn has_terrain(dom: &WeakDom) -> bool {
+    dom.descendants().any(|instance| {
+        instance.class == "Terrain"
+            && TERRAIN_GRIDS.iter().any(|grid| match instance.properties.get(&(*grid).into()) {
+                Some(Variant::BinaryString(data)) => !AsRef::<[u8]>::as_ref(data).is_empty(),
+                _ => false,
+            })
+    })
+}
+
+pub(crate) fn service(dom: &WeakDom, class: &str) -> Option<Ref> {
+    dom.root()
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|instance| instance.class == class))
+}
+
+// the point an origin stands for, none for the middle of a map without parts
+pub(crate) fn origin_point(dom: &WeakDom, origin: RebaseOrigin) -> Option<Vector3> {
+    match origin {
+        RebaseOrigin::Point(point) => Some(point),
+        RebaseOrigin::Center => {
+            let (min, max) = thumbnail::workspace_bounds(dom, service(dom, "Workspace")?)?;
+            Some(Vector3::new((min.x + max.x) / 2.0, 0.0, (min.z + max.z) / 2.0))
+        }
+    }
+}
+
+// what stays where it was when everything else is moved
+pub(crate) fn warn_fixed_positions(dom: &WeakDom, ctx: &mut PassContext) {
+    if has_terrain(dom) {
+        ctx.warn("terrain can't be moved, it's now out of place with the rest of the map");
+    }
+    let scripts = dom
+        .descendants()
+        .filter(|instance| match instance.properties.get(&"Source".into()) {
+            Some(Variant::String(source)) => POSITION_CONSTRUCTORS.iter().any(|call| source.contains(call)),
+            _ => false,
+        })
+        .count();
+    if scripts > 0 {
+        ctx.warn(format!(
+            "{} scripts build positions with Vector3.new/CFrame.new, the ones in world space weren't moved",
+            scripts
+        ));
+    }
+}
+
+pub fn rebase_origin(dom: &mut WeakDom, origin: RebaseOrigin, ctx: &mut PassContext) {
+    let Some(origin) = origin_point(dom, origin) else {
+        ctx.warn("no parts in the workspace, there's no middle to rebase on");
+        return;
+    };
+    if origin == Vector3::new(0.0, 0.0, 0.0) {
+        return;
+    }
+    // 0 - so an axis that isn't moved doesn't print as -0
+    let delta = Vector3::new(0.0 - origin.x, 0.0 - origin.y, 0.0 - origin.z);
+    // the whole place, models kept in storage get cloned into the world where they were saved
+    let count = translate(dom, dom.root_ref(), delta);
+    ctx.info(format!("moved {} positions by ({}, {}, {})", count, delta.x, delta.y, delta.z));
+    if count > 0 {
+        ctx.record_change();
+    }
+    warn_fixed_positions(dom, ctx);
+}
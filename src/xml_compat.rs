@@ -0,0 +1,266 @@
+// post-processing of rbx_xml output for older clients
+//
+// rbx_xml writes current rbxlx conventions. clients from around 2008-2012 choke on a few of
+// them, so this rewrites the document:
+//   - shared strings are inlined as BinaryString and the SharedStrings section is dropped
+//   - property types that didn't exist yet are removed entirely
+//   - Meta elements are dropped
+//   - 2008 era: ProtectedString sources are written as escaped text instead of CDATA,
+//     referents get the RBX prefix and the root element carries the old xsd attributes
+use clap::ValueEnum;
+use std::collections::HashMap;
+use std::io::Cursor;
+use xml::attribute::OwnedAttribute;
+use xml::reader::{EventReader, XmlEvent};
+use xml::writer::{EmitterConfig, XmlEvent as WriteEvent};
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum XmlCompat {
+    #[value(name = "2008")]
+    Era2008,
+    #[value(name = "2012")]
+    Era2012,
+    #[value(name = "2016")]
+    Era2016,
+}
+
+// xml property type tags and roughly when the engine started reading them
+const TYPE_INTRODUCED: [(&str, u32); 15] = [
+    ("Faces", 2009),
+    ("Axes", 2009),
+    ("BinaryString", 2010),
+    ("NumberSequence", 2014),
+    ("ColorSequence", 2014),
+    ("NumberRange", 2014),
+    ("Rect2D", 2014),
+    ("int64", 2015),
+    ("PhysicalProperties", 2015),
+    ("Color3uint8", 2016),
+    ("OptionalCoordinateFrame", 2020),
+    ("UniqueId", 2021),
+    ("Font", 2022),
+    ("SecurityCapabilities", 2023),
+    ("NetAssetRef", 2024),
+];
+
+impl XmlCompat {
+    fn year(self) -> u32 {
+        match self {
+            XmlCompat::Era2008 => 2008,
+            XmlCompat::Era2012 => 2012,
+            XmlCompat::Era2016 => 2016,
+        }
+    }
+
+    fn supports_type(self, type_name: &str) -> bool {
+        type_year(type_name).is_none_or(|year| year <= self.year())
+    }
+}
+
+// none for the types every client reads
+pub(crate) fn type_year(type_name: &str) -> Option<u32> {
+    TYPE_INTRODUCED
+        .iter()
+        .find(|(name, _)| *name == type_name)
+        .map(|&(_, year)| year)
+}
+
+type XmlResult<T> = std::result::Result<T, Box<dyn std::error::Error>>;
+
+// SharedStrings are written at the end of the document, so they are collected up front
+fn collect_shared_strings(xml_bytes: &[u8]) -> XmlResult<HashMap<String, String>> {
+    let mut shared = HashMap::new();
+    let mut current_md5: Option<String> = None;
+    let mut in_section = false;
+    for event in EventReader::new(Cursor::new(xml_bytes)) {
+        match event? {
+            XmlEvent::StartElement { name, attributes, .. } => match name.local_name.as_str() {
+                "SharedStrings" => in_section = true,
+                "SharedString" if in_section => {
+                    current_md5 = attribute(&attributes, "md5").map(str::to_owned);
+                }
+                _ => {}
+            },
+            XmlEvent::Characters(text) | XmlEvent::CData(text) => {
+                if let Some(md5) = &current_md5 {
+                    shared
+                        .entry(md5.clone())
+                        .or_insert_with(String::new)
+                        .push_str(text.trim());
+                }
+            }
+            XmlEvent::EndElement { name } => match name.local_name.as_str() {
+                "SharedStrings" => in_section = false,
+                "SharedString" => current_md5 = None,
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    Ok(shared)
+}
+
+fn attribute<'a>(attributes: &'a [OwnedAttribute], name: &str) -> Option<&'a str> {
+    attributes
+        .iter()
+        .find(|attr| attr.name.local_name == name)
+        .map(|attr| attr.value.as_str())
+}
+
+fn prefixed_referent(value: &str) -> String {
+    if value == "null" || value.starts_with("RBX") {
+        value.to_owned()
+    } else {
+        format!("RBX{}", value)
+    }
+}
+
+pub fn apply_xml_compat(xml_bytes: &[u8], era: XmlCompat) -> XmlResult<Vec<u8>> {
+    let shared_strings = collect_shared_strings(xml_bytes)?;
+    let old_conventions = era == XmlCompat::Era2008;
+
+    let mut output = Vec::new();
+    let mut writer = EmitterConfig::new()
+        .perform_indent(true)
+        .write_document_declaration(false)
+        .normalize_empty_elements(false)
+        .cdata_to_characters(old_conventions)
+        .create_writer(&mut output);
+
+    // depth of an element being skipped along with everything inside it
+    let mut skip_depth = 0usize;
+    let mut in_properties = false;
+    let mut in_ref = false;
+    // (property name, hash) of a SharedString property being inlined
+    let mut pending_shared: Option<(String, String)> = None;
+    // indentation is redone by the writer, so whitespace only survives as the whole value
+    // of an element, e.g. a Name of " "
+    let mut leaf_whitespace: Option<String> = None;
+    let mut inlined_count = 0usize;
+    let mut stripped: HashMap<String, usize> = HashMap::new();
+
+    for event in EventReader::new(Cursor::new(xml_bytes)) {
+        let event = event?;
+        if skip_depth > 0 {
+            match event {
+                XmlEvent::StartElement { .. } => skip_depth += 1,
+                XmlEvent::EndElement { .. } => skip_depth -= 1,
+                _ => {}
+            }
+            continue;
+        }
+
+        if let Some((prop_name, hash)) = &mut pending_shared {
+            match event {
+                XmlEvent::Characters(text) => hash.push_str(text.trim()),
+                XmlEvent::EndElement { .. } => {
+                    match shared_strings.get(hash.as_str()) {
+                        Some(data) => {
+                            writer.write(WriteEvent::start_element("BinaryString").attr("name", prop_name))?;
+                            writer.write(WriteEvent::characters(data))?;
+                            writer.write(WriteEvent::end_element())?;
+                            inlined_count += 1;
+                        }
+                        None => println!(
+                            "[legacy_place::xml_compat] shared string {} for '{}' is missing, dropping it",
+                            hash, prop_name
+                        ),
+                    }
+                    pending_shared = None;
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        let whitespace = leaf_whitespace.take();
+        match &event {
+            XmlEvent::StartDocument { .. } => continue,
+            XmlEvent::Whitespace(text) if whitespace.is_some() => leaf_whitespace = Some(text.clone()),
+            XmlEvent::Whitespace(_) => {}
+            XmlEvent::StartElement { name, attributes, .. } => {
+                let local = name.local_name.as_str();
+                if local == "SharedStrings" || local == "Meta" {
+                    skip_depth = 1;
+                    continue;
+                }
+                if in_properties && !era.supports_type(local) {
+                    *stripped.entry(local.to_owned()).or_default() += 1;
+                    skip_depth = 1;
+                    continue;
+                }
+                if in_properties && local == "SharedString" {
+                    if !era.supports_type("BinaryString") {
+                        *stripped.entry(local.to_owned()).or_default() += 1;
+                        skip_depth = 1;
+                        continue;
+                    }
+                    let prop_name = attribute(attributes, "name").unwrap_or_default().to_owned();
+                    pending_shared = Some((prop_name, String::new()));
+                    continue;
+                }
+                if local == "Properties" {
+                    in_properties = true;
+                }
+                in_ref = in_properties && local == "Ref";
+
+                let mut attributes = attributes.clone();
+                if old_conventions {
+                    for attr in attributes.iter_mut() {
+                        if name.local_name == "Item" && attr.name.local_name == "referent" {
+                            attr.value = prefixed_referent(&attr.value);
+                        }
+                    }
+                }
+                if old_conventions && local == "roblox" {
+                    writer.write(
+                        WriteEvent::start_element("roblox")
+                            .attr("xmlns:xmime", "http://www.w3.org/2005/05/xmlmime")
+                            .attr("xmlns:xsi", "http://www.w3.org/2001/XMLSchema-instance")
+                            .attr("xsi:noNamespaceSchemaLocation", "http://www.roblox.com/roblox.xsd")
+                            .attr("version", "4"),
+                    )?;
+                    continue;
+                }
+                let mut start = WriteEvent::start_element(name.borrow());
+                for attr in &attributes {
+                    start = start.attr(attr.name.borrow(), &attr.value);
+                }
+                writer.write(start)?;
+                leaf_whitespace = Some(String::new());
+            }
+            XmlEvent::Characters(text) if in_ref && old_conventions => {
+                writer.write(WriteEvent::characters(&prefixed_referent(text.trim())))?;
+            }
+            XmlEvent::EndElement { name } => {
+                if let Some(text) = whitespace.filter(|text| !text.is_empty()) {
+                    writer.write(WriteEvent::characters(&text))?;
+                }
+                if name.local_name == "Properties" {
+                    in_properties = false;
+                }
+                in_ref = false;
+                writer.write(WriteEvent::end_element())?;
+            }
+            other => {
+                if let Some(write_event) = other.as_writer_event() {
+                    writer.write(write_event)?;
+                }
+            }
+        }
+    }
+
+    if inlined_count > 0 {
+        println!("[legacy_place::xml_compat] inlined {} shared string(s)", inlined_count);
+    }
+    for (type_name, count) in stripped {
+        println!(
+            "[legacy_place::xml_compat] removed {} {} propert{} unsupported by {:?}",
+            count,
+            type_name,
+            if count == 1 { "y" } else { "ies" },
+            era
+        );
+    }
+    Ok(output)
+}